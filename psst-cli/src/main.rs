@@ -8,10 +8,26 @@ use psst_core::{
     connection::Credentials,
     error::Error,
     item_id::{ItemId, ItemIdType},
-    player::{item::PlaybackItem, PlaybackConfig, Player, PlayerCommand, PlayerEvent},
+    lastfm::LastFmClient,
+    player::{
+        item::{PlaybackItem, PlaybackTrim},
+        PlaybackConfig, Player, PlayerCommand, PlayerEvent,
+    },
+    scrobble::{
+        listenbrainz::ListenBrainzScrobbler, maloja::MalojaScrobbler, should_scrobble,
+        ScrobbleEntry, ScrobbleQueue, Scrobbler,
+    },
     session::{SessionConfig, SessionService},
 };
-use std::{env, io, io::BufRead, path::PathBuf, thread};
+use std::{
+    env, io,
+    io::BufRead,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+const CACHE_DIR: &str = "cache";
 
 fn main() {
     env_logger::init();
@@ -34,7 +50,7 @@ fn main() {
 
 fn start(track_id: &str, session: SessionService) -> Result<(), Error> {
     let cdn = Cdn::new(session.clone(), None)?;
-    let cache = Cache::new(PathBuf::from("cache"))?;
+    let cache = Cache::new(PathBuf::from(CACHE_DIR))?;
     let item_id = ItemId::from_base62(track_id, ItemIdType::Track).unwrap();
     play_item(
         session,
@@ -43,6 +59,8 @@ fn start(track_id: &str, session: SessionService) -> Result<(), Error> {
         PlaybackItem {
             item_id,
             norm_level: NormalizationLevel::Track,
+            trim: PlaybackTrim::default(),
+            start_position: Duration::default(),
         },
     )
 }
@@ -55,8 +73,9 @@ fn play_item(
 ) -> Result<(), Error> {
     let output = DefaultAudioOutput::open()?;
     let config = PlaybackConfig::default();
+    let scrobble_queue = create_scrobble_queue();
 
-    let mut player = Player::new(session, cdn, cache, config, &output);
+    let mut player = Player::new(session.clone(), cdn, cache, config, &output);
 
     let _ui_thread = thread::spawn({
         let player_sender = player.sender();
@@ -102,10 +121,72 @@ fn play_item(
         }
     });
 
+    let mut has_scrobbled = false;
     for event in player.receiver() {
+        match &event {
+            PlayerEvent::Playing { .. } => {
+                has_scrobbled = false;
+            }
+            PlayerEvent::Position { path, position }
+                if !has_scrobbled && should_scrobble(path.duration, *position) =>
+            {
+                if let Some(queue) = &scrobble_queue {
+                    match ScrobbleEntry::fetch(&session, path.item_id, *position) {
+                        Ok(entry) => queue.scrobble(entry),
+                        Err(err) => log::warn!("failed to fetch track for scrobbling: {err}"),
+                    }
+                }
+                has_scrobbled = true;
+            }
+            _ => {}
+        }
         player.handle(event);
     }
     output.sink().close();
 
     Ok(())
 }
+
+/// Scrobble to the services configured through environment variables:
+/// `LASTFM_API_KEY`, `LASTFM_API_SECRET`, `LASTFM_SESSION_KEY` and optionally
+/// `LASTFM_URL` for Last.fm,
+/// `LISTENBRAINZ_TOKEN` and optionally `LISTENBRAINZ_URL` for ListenBrainz,
+/// `MALOJA_URL` and `MALOJA_API_KEY` for Maloja.
+fn create_scrobble_queue() -> Option<ScrobbleQueue> {
+    let var = |name: &str| env::var(name).ok();
+    let mut scrobblers: Vec<Box<dyn Scrobbler>> = Vec::new();
+    if let (Some(api_key), Some(api_secret), Some(session_key)) = (
+        var("LASTFM_API_KEY"),
+        var("LASTFM_API_SECRET"),
+        var("LASTFM_SESSION_KEY"),
+    ) {
+        if let Ok(scrobbler) = LastFmClient::create_scrobbler(
+            var("LASTFM_URL").as_deref(),
+            Some(&api_key),
+            Some(&api_secret),
+            Some(&session_key),
+            None,
+        ) {
+            scrobblers.push(Box::new(scrobbler));
+        }
+    }
+    if let Some(token) = var("LISTENBRAINZ_TOKEN") {
+        scrobblers.push(Box::new(ListenBrainzScrobbler::new(
+            var("LISTENBRAINZ_URL").as_deref(),
+            &token,
+            None,
+        )));
+    }
+    if let (Some(url), Some(api_key)) = (var("MALOJA_URL"), var("MALOJA_API_KEY")) {
+        scrobblers.push(Box::new(MalojaScrobbler::new(&url, &api_key, None)));
+    }
+    if scrobblers.is_empty() {
+        return None;
+    }
+    let queue = ScrobbleQueue::new(
+        Path::new(CACHE_DIR).join("scrobbles"),
+        Box::new(|id| log::error!("{id} refused the credentials")),
+    );
+    queue.set_scrobblers(scrobblers);
+    Some(queue)
+}
@@ -0,0 +1,83 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use super::source::AudioSource;
+
+/// Target gain of a `FadingSource`, shared between the player and the audio
+/// thread.
+#[derive(Clone)]
+pub struct FadeGain(Arc<AtomicU32>);
+
+impl FadeGain {
+    pub fn new(gain: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(gain.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, gain: f32) {
+        self.0
+            .store(gain.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Scales the inner source by a gain factor.  Changes of the target gain are
+/// not applied at once, but ramped over at most one second, so the gain can be
+/// updated in coarse steps without audible clicks.
+pub struct FadingSource<S> {
+    source: S,
+    gain: FadeGain,
+    current: f32,
+    /// Largest gain change per frame.
+    step: f32,
+    channels: usize,
+}
+
+impl<S> FadingSource<S>
+where
+    S: AudioSource,
+{
+    pub fn new(source: S, gain: FadeGain) -> Self {
+        Self {
+            current: gain.get(),
+            step: 1.0 / source.sample_rate() as f32,
+            channels: source.channel_count(),
+            source,
+            gain,
+        }
+    }
+}
+
+impl<S> AudioSource for FadingSource<S>
+where
+    S: AudioSource,
+{
+    fn write(&mut self, output: &mut [f32]) -> usize {
+        let written = self.source.write(output);
+        let target = self.gain.get();
+        if self.current == target && target == 1.0 {
+            return written;
+        }
+        for frame in output[..written].chunks_mut(self.channels) {
+            if self.current < target {
+                self.current = (self.current + self.step).min(target);
+            } else if self.current > target {
+                self.current = (self.current - self.step).max(target);
+            }
+            frame.iter_mut().for_each(|s| *s *= self.current);
+        }
+        written
+    }
+
+    fn channel_count(&self) -> usize {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+}
@@ -1,7 +1,10 @@
 pub mod decode;
 pub mod decrypt;
+pub mod fade;
 pub mod normalize;
 pub mod output;
 pub mod probe;
 pub mod resample;
+pub mod silence;
 pub mod source;
+pub mod stretch;
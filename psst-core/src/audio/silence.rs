@@ -0,0 +1,191 @@
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::source::{AudioSource, SeekCount};
+
+/// Silence skipping switch shared between the player and the audio thread,
+/// together with a counter of the time that has been skipped over.
+#[derive(Clone)]
+pub struct SilenceSkip {
+    enabled: Arc<AtomicBool>,
+    skipped_micros: Arc<AtomicU64>,
+}
+
+impl SilenceSkip {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+            skipped_micros: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Return the time skipped since the last call and reset the counter.
+    pub fn take_skipped(&self) -> Duration {
+        Duration::from_micros(self.skipped_micros.swap(0, Ordering::Relaxed))
+    }
+
+    fn add_skipped(&self, micros: u64) {
+        self.skipped_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+}
+
+/// Shortens silent gaps in the inner source.  The signal is inspected in short
+/// blocks, a block is considered silent if its peak stays below
+/// `SILENCE_THRESHOLD`.  The first `MAX_GAP` of every silent run is kept, so
+/// speech keeps its natural pauses, and the rest is dropped.
+pub struct SilenceSkippingSource<S> {
+    source: S,
+    skip: SilenceSkip,
+    seeks: SeekCount,
+    /// Value of `seeks` the buffered samples belong to.
+    last_seek: u64,
+    channels: usize,
+    sample_rate: u32,
+    /// Length of the current silent run that has been let through, in samples.
+    silent_run: usize,
+    /// Longest silent run that is let through, in samples.
+    max_gap: usize,
+    block: Vec<f32>,
+    /// Number of samples read into `block` so far.
+    block_filled: usize,
+    /// Range of samples in `block` that are awaiting write into the output.
+    pending: Range<usize>,
+}
+
+impl<S> SilenceSkippingSource<S>
+where
+    S: AudioSource,
+{
+    pub fn new(source: S, skip: SilenceSkip, seeks: SeekCount) -> Self {
+        const BLOCK_DURATION: Duration = Duration::from_millis(10);
+        const MAX_GAP: Duration = Duration::from_millis(500);
+
+        let channels = source.channel_count();
+        let sample_rate = source.sample_rate();
+        let samples_per_sec = sample_rate as f64 * channels as f64;
+        let block_len = (samples_per_sec * BLOCK_DURATION.as_secs_f64()) as usize;
+        Self {
+            source,
+            skip,
+            last_seek: seeks.get(),
+            seeks,
+            channels,
+            sample_rate,
+            silent_run: 0,
+            max_gap: (samples_per_sec * MAX_GAP.as_secs_f64()) as usize,
+            // Keep the block aligned to whole frames.
+            block: vec![0.0; block_len - block_len % channels],
+            block_filled: 0,
+            pending: 0..0,
+        }
+    }
+
+    /// Forget the current silent run and the buffered samples, used after a
+    /// seek, so the first frames at the new position are not taken as the
+    /// continuation of a gap.
+    fn reset(&mut self) {
+        self.silent_run = 0;
+        self.block_filled = 0;
+        self.pending = 0..0;
+    }
+
+    fn write_pending(&mut self, output: &mut [f32]) -> usize {
+        let pending = &self.block[self.pending.clone()];
+        let n = pending.len().min(output.len());
+        output[..n].copy_from_slice(&pending[..n]);
+        self.pending.start += n;
+        n
+    }
+
+    /// Decide what to do with the samples gathered in `block`.  Either they are
+    /// queued for output, or dropped if they extend a silent run over the
+    /// maximum gap.
+    fn process_block(&mut self) {
+        const SILENCE_THRESHOLD: f32 = 0.01; // About -40 dBFS.
+
+        let len = self.block_filled;
+        self.block_filled = 0;
+
+        let is_silent = self.block[..len]
+            .iter()
+            .all(|s| s.abs() < SILENCE_THRESHOLD);
+        if is_silent {
+            if self.silent_run >= self.max_gap {
+                let frames = (len / self.channels) as u64;
+                self.skip
+                    .add_skipped(frames * 1_000_000 / self.sample_rate as u64);
+                return;
+            }
+            self.silent_run += len;
+        } else {
+            self.silent_run = 0;
+        }
+        self.pending = 0..len;
+    }
+}
+
+impl<S> AudioSource for SilenceSkippingSource<S>
+where
+    S: AudioSource,
+{
+    fn write(&mut self, output: &mut [f32]) -> usize {
+        let seek = self.seeks.get();
+        if seek != self.last_seek {
+            self.last_seek = seek;
+            self.reset();
+        }
+
+        let enabled = self.skip.is_enabled();
+        if !enabled && self.block_filled > 0 {
+            // Flush the partially inspected block, so nothing gets lost when switching
+            // skipping off.
+            self.pending = 0..self.block_filled;
+            self.block_filled = 0;
+            self.silent_run = 0;
+        }
+
+        let mut total = 0;
+        while total < output.len() {
+            if !self.pending.is_empty() {
+                total += self.write_pending(&mut output[total..]);
+            } else if !enabled {
+                total += self.source.write(&mut output[total..]);
+                break;
+            } else {
+                let written = self.source.write(&mut self.block[self.block_filled..]);
+                self.block_filled += written;
+                let is_full = self.block_filled == self.block.len();
+                if is_full || (written == 0 && self.block_filled > 0) {
+                    // Either the block is full, or the source has nothing more to give at
+                    // the moment and we cannot wait, process what we have.
+                    self.process_block();
+                } else if written == 0 {
+                    break;
+                }
+            }
+        }
+        total
+    }
+
+    fn channel_count(&self) -> usize {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
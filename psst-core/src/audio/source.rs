@@ -1,3 +1,8 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
 use crate::audio::resample::ResamplingSpec;
 
 use super::resample::{AudioResampler, ResamplingQuality};
@@ -13,6 +18,22 @@ pub trait AudioSource: Send + 'static {
     fn sample_rate(&self) -> u32;
 }
 
+/// Number of seeks done in a decoded stream, shared between the decoding worker
+/// and the audio thread.  Sources that buffer samples compare it against the
+/// last value they have seen, and drop whatever they hold from before a seek.
+#[derive(Clone, Default)]
+pub struct SeekCount(Arc<AtomicU64>);
+
+impl SeekCount {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 /// Empty audio source. Does not produce any samples.
 pub struct Empty;
 
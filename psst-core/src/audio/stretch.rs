@@ -0,0 +1,428 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use super::source::{AudioSource, SeekCount};
+
+/// Playback speed factor shared between the player and the audio thread.  All
+/// clones observe the same value, so the speed can be changed while a source
+/// is playing.
+#[derive(Clone)]
+pub struct PlaybackSpeed(Arc<AtomicU32>);
+
+impl PlaybackSpeed {
+    pub fn new(speed: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(speed.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, speed: f32) {
+        self.0.store(speed.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_normal(&self) -> bool {
+        (self.get() - 1.0).abs() < f32::EPSILON
+    }
+}
+
+/// Changes the tempo of the inner source without affecting its pitch, using
+/// the WSOLA (waveform-similarity overlap-add) algorithm.  Hann-windowed
+/// frames are taken from the input at a hop scaled by the speed factor and
+/// overlapped at a fixed hop in the output.  Each frame start is shifted within
+/// a small tolerance to best match the natural continuation of the previous
+/// frame, which avoids the phasing artifacts of plain overlap-add.
+///
+/// At 1.0x the source is passed through untouched.
+pub struct TimeStretchedSource<S> {
+    source: S,
+    speed: PlaybackSpeed,
+    seeks: SeekCount,
+    /// Value of `seeks` the buffered samples belong to.
+    last_seek: u64,
+    channels: usize,
+    /// Synthesis hop, in frames.  The window is twice as long.
+    hop: usize,
+    /// Maximum shift of a frame start during the similarity search, in frames.
+    tolerance: usize,
+    window: Vec<f32>,
+    /// Interleaved samples read from the source that are still needed.
+    input: Vec<f32>,
+    /// Nominal start of the next analysis frame in `input`, in frames.
+    input_pos: f64,
+    /// Start of the natural continuation of the last frame in `input`, in
+    /// frames.  `None` while passing through.
+    continuation: Option<usize>,
+    /// Windowed tail of the last frame, waiting to be overlapped.
+    overlap: Vec<f32>,
+    /// Finished samples waiting to be written out.
+    output: Vec<f32>,
+    output_pos: usize,
+    read_buf: Vec<f32>,
+}
+
+impl<S> TimeStretchedSource<S>
+where
+    S: AudioSource,
+{
+    pub fn new(source: S, speed: PlaybackSpeed, seeks: SeekCount) -> Self {
+        const HOP_DURATION_SECS: f64 = 0.015;
+        const READ_BUFFER_SIZE: usize = 4 * 1024;
+
+        let channels = source.channel_count();
+        let hop = (source.sample_rate() as f64 * HOP_DURATION_SECS) as usize;
+        let window_len = hop * 2;
+        let window = (0..window_len)
+            .map(|n| {
+                let phase = n as f32 / window_len as f32;
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+            })
+            .collect();
+        Self {
+            source,
+            speed,
+            last_seek: seeks.get(),
+            seeks,
+            channels,
+            hop,
+            tolerance: hop / 2,
+            window,
+            input: Vec::new(),
+            input_pos: 0.0,
+            continuation: None,
+            overlap: vec![0.0; hop * channels],
+            output: Vec::new(),
+            output_pos: 0,
+            read_buf: vec![0.0; READ_BUFFER_SIZE],
+        }
+    }
+
+    fn input_frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    fn is_stretching(&self) -> bool {
+        !self.input.is_empty() || self.continuation.is_some()
+    }
+
+    /// Read from the inner source until `input` holds at least `frames`
+    /// frames.  Returns `false` if the source ran dry before that.
+    fn fill_input(&mut self, frames: usize) -> bool {
+        while self.input_frames() < frames {
+            let written = self.source.write(&mut self.read_buf);
+            if written == 0 {
+                return false;
+            }
+            self.input.extend_from_slice(&self.read_buf[..written]);
+        }
+        true
+    }
+
+    fn mono(&self, frame: usize) -> f32 {
+        let start = frame * self.channels;
+        self.input[start..start + self.channels].iter().sum::<f32>() / self.channels as f32
+    }
+
+    /// Normalized cross-correlation of `hop` frames starting at `candidate`
+    /// against the same length starting at `reference`.
+    fn similarity(&self, candidate: usize, reference: usize) -> f32 {
+        const STRIDE: usize = 4;
+
+        let mut dot = 0.0;
+        let mut energy = 0.0;
+        for i in (0..self.hop).step_by(STRIDE) {
+            let c = self.mono(candidate + i);
+            let r = self.mono(reference + i);
+            dot += c * r;
+            energy += c * c;
+        }
+        dot / energy.sqrt().max(f32::EPSILON)
+    }
+
+    /// Pretend a frame has just been played right before the current input, so
+    /// the first overlap reconstructs the input exactly instead of fading in.
+    fn start_stretching(&mut self) -> bool {
+        if !self.fill_input(self.hop) {
+            return false;
+        }
+        let ch = self.channels;
+        for i in 0..self.hop {
+            for c in 0..ch {
+                self.overlap[i * ch + c] = self.input[i * ch + c] * self.window[self.hop + i];
+            }
+        }
+        self.input_pos = 0.0;
+        self.continuation = Some(0);
+        true
+    }
+
+    /// Produce the next `hop` frames of output.  Returns `false` if there is
+    /// not enough input available at the moment.
+    fn step(&mut self, speed: f32) -> bool {
+        if self.continuation.is_none() && !self.start_stretching() {
+            return false;
+        }
+        let Some(reference) = self.continuation else {
+            return false;
+        };
+        let window_len = self.hop * 2;
+        let nominal = self.input_pos.round() as usize;
+        if !self.fill_input(nominal + self.tolerance + window_len) {
+            return false;
+        }
+
+        let lo = nominal.saturating_sub(self.tolerance);
+        let hi = nominal + self.tolerance;
+        let mut start = nominal;
+        let mut best = f32::MIN;
+        for candidate in lo..=hi {
+            let score = self.similarity(candidate, reference);
+            if score > best {
+                start = candidate;
+                best = score;
+            }
+        }
+
+        // Overlap the first half of the windowed frame with the tail of the
+        // previous one and keep its second half for the next step.
+        let ch = self.channels;
+        for i in 0..self.hop {
+            for c in 0..ch {
+                let head = self.input[(start + i) * ch + c] * self.window[i];
+                let tail = self.input[(start + self.hop + i) * ch + c] * self.window[self.hop + i];
+                self.output.push(self.overlap[i * ch + c] + head);
+                self.overlap[i * ch + c] = tail;
+            }
+        }
+        self.continuation = Some(start + self.hop);
+        self.input_pos += self.hop as f64 * speed as f64;
+
+        // Discard input that neither the next search nor the continuation
+        // reference can reach anymore.
+        let next = (self.input_pos.round() as usize).saturating_sub(self.tolerance);
+        let consumed = next.min(start + self.hop);
+        self.input.drain(..consumed * ch);
+        self.input_pos -= consumed as f64;
+        self.continuation = Some(start + self.hop - consumed);
+        true
+    }
+
+    /// Cross-fade the pending overlap back into the unmodified input and hand
+    /// the remaining buffered input over to the output, so that the pass-through
+    /// mode can continue seamlessly.
+    fn finish_stretching(&mut self) {
+        if let Some(reference) = self.continuation {
+            if self.fill_input(reference + self.hop) {
+                let ch = self.channels;
+                for i in 0..self.hop {
+                    for c in 0..ch {
+                        let head = self.input[(reference + i) * ch + c] * self.window[i];
+                        self.output.push(self.overlap[i * ch + c] + head);
+                    }
+                }
+                self.output
+                    .extend_from_slice(&self.input[(reference + self.hop) * ch..]);
+            }
+        }
+        self.input.clear();
+        self.input_pos = 0.0;
+        self.continuation = None;
+        self.overlap.iter_mut().for_each(|s| *s = 0.0);
+    }
+
+    /// Drop all buffered audio, used after a seek, when nothing buffered
+    /// belongs to the new position.
+    fn reset(&mut self) {
+        self.input.clear();
+        self.input_pos = 0.0;
+        self.continuation = None;
+        self.overlap.iter_mut().for_each(|s| *s = 0.0);
+        self.output.clear();
+        self.output_pos = 0;
+    }
+
+    fn write_pending(&mut self, output: &mut [f32]) -> usize {
+        let pending = &self.output[self.output_pos..];
+        let n = pending.len().min(output.len());
+        output[..n].copy_from_slice(&pending[..n]);
+        self.output_pos += n;
+        if self.output_pos == self.output.len() {
+            self.output.clear();
+            self.output_pos = 0;
+        }
+        n
+    }
+}
+
+impl<S> AudioSource for TimeStretchedSource<S>
+where
+    S: AudioSource,
+{
+    fn write(&mut self, output: &mut [f32]) -> usize {
+        let seek = self.seeks.get();
+        if seek != self.last_seek {
+            self.last_seek = seek;
+            self.reset();
+        }
+
+        let speed = self.speed.get();
+        let bypass = self.speed.is_normal();
+        if bypass && self.is_stretching() {
+            self.finish_stretching();
+        }
+
+        let mut total = 0;
+        while total < output.len() {
+            if self.output_pos < self.output.len() {
+                total += self.write_pending(&mut output[total..]);
+            } else if bypass {
+                total += self.source.write(&mut output[total..]);
+                break;
+            } else if !self.step(speed) {
+                break;
+            }
+        }
+        total
+    }
+
+    fn channel_count(&self) -> usize {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    /// Mono sine wave of a fixed length.
+    struct Sine {
+        frequency: f32,
+        amplitude: f32,
+        pos: usize,
+        len: usize,
+    }
+
+    impl Sine {
+        fn new(frequency: f32, len: usize) -> Self {
+            Self {
+                frequency,
+                amplitude: 0.5,
+                pos: 0,
+                len,
+            }
+        }
+    }
+
+    impl AudioSource for Sine {
+        fn write(&mut self, output: &mut [f32]) -> usize {
+            let n = output.len().min(self.len - self.pos);
+            for (i, sample) in output[..n].iter_mut().enumerate() {
+                let t = (self.pos + i) as f32 / SAMPLE_RATE as f32;
+                *sample = self.amplitude * (2.0 * PI * self.frequency * t).sin();
+            }
+            self.pos += n;
+            n
+        }
+
+        fn channel_count(&self) -> usize {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            SAMPLE_RATE
+        }
+    }
+
+    fn read_all<S: AudioSource>(source: &mut S) -> Vec<f32> {
+        let mut samples = Vec::new();
+        let mut buf = vec![0.0; 1024];
+        loop {
+            let n = source.write(&mut buf);
+            if n == 0 {
+                return samples;
+            }
+            samples.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    fn stretch(speed: f32, source: Sine) -> Vec<f32> {
+        let mut stretched =
+            TimeStretchedSource::new(source, PlaybackSpeed::new(speed), SeekCount::default());
+        read_all(&mut stretched)
+    }
+
+    /// Frequency estimated from the rising zero crossings.
+    fn frequency(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        crossings as f32 * SAMPLE_RATE as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn output_length_follows_speed() {
+        let len = 2 * SAMPLE_RATE as usize;
+        for speed in [0.5, 0.75, 1.5, 2.0] {
+            let output = stretch(speed, Sine::new(440.0, len));
+            let expected = len as f32 / speed;
+            let error = (output.len() as f32 - expected).abs() / expected;
+            assert!(
+                error < 0.05,
+                "speed {speed}: got {} samples, expected about {expected}",
+                output.len()
+            );
+        }
+    }
+
+    #[test]
+    fn normal_speed_passes_through() {
+        let len = SAMPLE_RATE as usize;
+        let output = stretch(1.0, Sine::new(440.0, len));
+        assert_eq!(output.len(), len);
+    }
+
+    #[test]
+    fn pitch_is_preserved() {
+        let len = 2 * SAMPLE_RATE as usize;
+        for speed in [0.5, 1.5, 2.0] {
+            let output = stretch(speed, Sine::new(440.0, len));
+            // Skip the edges, where the stretcher starts and runs dry.
+            let middle = &output[output.len() / 4..output.len() * 3 / 4];
+            let estimated = frequency(middle);
+            assert!(
+                (estimated - 440.0).abs() < 440.0 * 0.02,
+                "speed {speed}: estimated {estimated} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn seek_drops_buffered_audio() {
+        let seeks = SeekCount::default();
+        let source = Sine::new(440.0, 2 * SAMPLE_RATE as usize);
+        let mut stretched =
+            TimeStretchedSource::new(source, PlaybackSpeed::new(1.5), seeks.clone());
+        let mut buf = vec![0.0; 256];
+        stretched.write(&mut buf);
+        assert!(!stretched.output.is_empty() || !stretched.input.is_empty());
+
+        seeks.increment();
+        stretched.source.amplitude = 0.0;
+        let mut buf = vec![0.0; 4096];
+        let n = stretched.write(&mut buf);
+        assert!(n > 0);
+        assert!(buf[..n].iter().all(|s| *s == 0.0));
+    }
+}
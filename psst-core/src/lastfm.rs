@@ -1,44 +1,169 @@
 use crate::error::Error;
 use crate::oauth::listen_for_callback_parameter;
-use rustfm_scrobble::{responses::SessionResponse, Scrobble, Scrobbler, ScrobblerError};
-use std::{net::SocketAddr, time::Duration};
+use crate::scrobble::{ScrobbleEntry, ScrobbleError, Scrobbler};
+use crate::util::default_ureq_agent_builder;
+use serde::{de::DeserializeOwned, de::IgnoredAny, Deserialize};
+use std::{collections::BTreeMap, fmt, net::SocketAddr, time::Duration};
 use url::Url;
 
-pub struct LastFmClient;
+/// Root of the official API.  Libre.fm and other servers implementing the
+/// Last.fm API can be used instead.
+pub const DEFAULT_LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
 
-impl LastFmClient {
-    /// Report a track as "now playing" to Last.fm using an existing Scrobbler instance.
-    pub fn now_playing_song(
-        scrobbler: &Scrobbler, // Requires an authenticated Scrobbler
-        artist: &str,
-        title: &str,
-        album: Option<&str>,
-    ) -> Result<(), Error> {
-        let song = Scrobble::new(artist, title, album.unwrap_or(""));
-        scrobbler
-            .now_playing(&song)
-            .map(|_| ())
-            .map_err(Error::from)
-    }
-
-    /// Scrobble a finished track to Last.fm using an existing Scrobbler instance.
-    pub fn scrobble_song(
-        scrobbler: &Scrobbler, // Requires an authenticated Scrobbler
-        artist: &str,
-        title: &str,
-        album: Option<&str>,
-    ) -> Result<(), Error> {
-        let song = Scrobble::new(artist, title, album.unwrap_or(""));
-        scrobbler.scrobble(&song).map(|_| ()).map_err(Error::from)
+const DEFAULT_LASTFM_AUTH_URL: &str = "http://www.last.fm/api/auth/";
+
+/// Last.fm accepts at most this many scrobbles in a single request.
+const MAX_BATCH_LEN: usize = 50;
+
+/// Client of the Last.fm API, signing every call with the API secret.
+struct LastFmApi {
+    agent: ureq::Agent,
+    url: String,
+    api_key: String,
+    api_secret: String,
+}
+
+impl LastFmApi {
+    fn new(url: Option<&str>, api_key: &str, api_secret: &str, proxy_url: Option<&str>) -> Self {
+        Self {
+            // Error responses carry the reason in the body.
+            agent: default_ureq_agent_builder(proxy_url)
+                .http_status_as_error(false)
+                .build()
+                .into(),
+            url: url.unwrap_or(DEFAULT_LASTFM_URL).to_string(),
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        mut params: BTreeMap<String, String>,
+    ) -> Result<T, ScrobbleError> {
+        params.insert("method".to_string(), method.to_string());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        let signature = self.sign(&params);
+        params.insert("api_sig".to_string(), signature);
+        params.insert("format".to_string(), "json".to_string());
+
+        let mut response = self.agent.post(&self.url).send_form(&params)?;
+        let status = response.status().as_u16();
+        let body = response.body_mut().read_to_string()?;
+        if let Ok(err) = serde_json::from_str::<ApiError>(&body) {
+            return Err(err.into());
+        }
+        if status >= 400 {
+            return Err(ureq::Error::StatusCode(status).into());
+        }
+        serde_json::from_str(&body).map_err(|err| ScrobbleError::Transient(err.into()))
+    }
+
+    /// MD5 of the parameters sorted by name, concatenated with the secret.
+    fn sign(&self, params: &BTreeMap<String, String>) -> String {
+        let mut signed = String::new();
+        for (name, value) in params {
+            signed.push_str(name);
+            signed.push_str(value);
+        }
+        signed.push_str(&self.api_secret);
+        format!("{:x}", md5::compute(signed))
+    }
+}
+
+/// Error reported in the body of a Last.fm response.
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: u32,
+    message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Last.fm error {}: {}", self.error, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ApiError> for ScrobbleError {
+    fn from(err: ApiError) -> Self {
+        let code = err.error;
+        let err = Error::ScrobblerError(Box::new(err));
+        match code {
+            // Authentication failed, invalid session key, API key, signature or
+            // token, suspended API key.
+            4 | 9 | 10 | 13 | 14 | 15 | 26 => Self::Unauthorized(err),
+            // Operation failed, service offline, temporary error, rate limit
+            // exceeded.
+            8 | 11 | 16 | 29 => Self::Transient(err),
+            _ => Self::Rejected(err),
+        }
+    }
+}
+
+/// Authenticated Last.fm scrobbler.
+pub struct LastFmScrobbler {
+    api: LastFmApi,
+    session_key: String,
+}
+
+impl LastFmScrobbler {
+    fn params(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([("sk".to_string(), self.session_key.clone())])
+    }
+}
+
+impl Scrobbler for LastFmScrobbler {
+    fn id(&self) -> &'static str {
+        "lastfm"
+    }
+
+    fn max_batch_len(&self) -> usize {
+        MAX_BATCH_LEN
+    }
+
+    fn now_playing(&self, entry: &ScrobbleEntry) -> Result<(), ScrobbleError> {
+        let mut params = self.params();
+        params.insert("artist".to_string(), entry.artist.clone());
+        params.insert("track".to_string(), entry.title.clone());
+        if let Some(album) = &entry.album {
+            params.insert("album".to_string(), album.clone());
+        }
+        self.api
+            .call::<IgnoredAny>("track.updateNowPlaying", params)?;
+        Ok(())
     }
 
+    fn scrobble(&self, entries: &[ScrobbleEntry]) -> Result<(), ScrobbleError> {
+        let mut params = self.params();
+        for (i, entry) in entries.iter().enumerate() {
+            params.insert(format!("artist[{i}]"), entry.artist.clone());
+            params.insert(format!("track[{i}]"), entry.title.clone());
+            params.insert(format!("timestamp[{i}]"), entry.timestamp.to_string());
+            if let Some(album) = &entry.album {
+                params.insert(format!("album[{i}]"), album.clone());
+            }
+        }
+        self.api.call::<IgnoredAny>("track.scrobble", params)?;
+        Ok(())
+    }
+}
+
+pub struct LastFmClient;
+
+impl LastFmClient {
     /// Creates an authenticated Last.fm Scrobbler instance with provided credentials.
+    /// `url` is the root of the API, `DEFAULT_LASTFM_URL` is used if `None`.
     /// Note: This assumes the session_key is valid. Validity is checked on first API call.
     pub fn create_scrobbler(
+        url: Option<&str>,
         api_key: Option<&str>,
         api_secret: Option<&str>,
         session_key: Option<&str>,
-    ) -> Result<Scrobbler, Error> {
+        proxy_url: Option<&str>,
+    ) -> Result<LastFmScrobbler, Error> {
         let (Some(api_key), Some(api_secret), Some(session_key)) =
             (api_key, api_secret, session_key)
         else {
@@ -48,41 +173,51 @@ impl LastFmClient {
             ));
         };
 
-        let mut scrobbler = Scrobbler::new(api_key, api_secret);
-        // Associate the session key with the scrobbler instance.
-        scrobbler.authenticate_with_session_key(session_key);
         log::info!("scrobbler instance created with session key (validity checked on first use).");
-        Ok(scrobbler)
-    }
-}
-
-impl From<ScrobblerError> for Error {
-    fn from(value: ScrobblerError) -> Self {
-        Self::ScrobblerError(Box::new(value))
+        Ok(LastFmScrobbler {
+            api: LastFmApi::new(url, api_key, api_secret, proxy_url),
+            session_key: session_key.to_string(),
+        })
     }
 }
 
-/// Generate a Last.fm authentication URL
+/// Generate a Last.fm authentication URL.  For a custom API `url`, the
+/// authentication page is expected at `/api/auth/` of the same host.
 pub fn generate_lastfm_auth_url(
+    url: Option<&str>,
     api_key: &str,
     callback_url: &str,
 ) -> Result<String, url::ParseError> {
-    let base = "http://www.last.fm/api/auth/";
-    let url = Url::parse_with_params(base, &[("api_key", api_key), ("cb", callback_url)])?;
+    let base = match url {
+        Some(url) => Url::parse(url)?.join("/api/auth/")?,
+        None => Url::parse(DEFAULT_LASTFM_AUTH_URL)?,
+    };
+    let url = Url::parse_with_params(base.as_str(), &[("api_key", api_key), ("cb", callback_url)])?;
     Ok(url.to_string())
 }
 
 /// Exchange a token for a Last.fm session key
 pub fn exchange_token_for_session(
+    url: Option<&str>,
     api_key: &str,
     api_secret: &str,
     token: &str,
+    proxy_url: Option<&str>,
 ) -> Result<String, Error> {
-    let mut scrobbler = Scrobbler::new(api_key, api_secret);
-    scrobbler
-        .authenticate_with_token(token) // Uses auth.getSession API call internally
-        .map(|response: SessionResponse| response.key) // Extract the session key string
-        .map_err(Error::from) // Map ScrobblerError to crate::error::Error
+    #[derive(Deserialize)]
+    struct SessionResponse {
+        session: Session,
+    }
+
+    #[derive(Deserialize)]
+    struct Session {
+        key: String,
+    }
+
+    let api = LastFmApi::new(url, api_key, api_secret, proxy_url);
+    let params = BTreeMap::from([("token".to_string(), token.to_string())]);
+    let response: SessionResponse = api.call("auth.getSession", params)?;
+    Ok(response.session.key)
 }
 
 /// Listen for a Last.fm token from the callback
@@ -93,3 +228,87 @@ pub fn get_lastfm_token_listener(
     // Use the shared listener function, specifying "token" as the parameter
     listen_for_callback_parameter(socket_address, timeout, "token")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrobble::test_server::{entry, TestBackend, TestServer};
+
+    const OK: &str = r#"{"scrobbles":{"@attr":{"accepted":1,"ignored":0}}}"#;
+
+    fn scrobbler(url: &str) -> LastFmScrobbler {
+        LastFmClient::create_scrobbler(Some(url), Some("key"), Some("secret"), Some("sk"), None)
+            .unwrap()
+    }
+
+    fn form(body: &str) -> BTreeMap<String, String> {
+        url::form_urlencoded::parse(body.as_bytes())
+            .into_owned()
+            .collect()
+    }
+
+    #[test]
+    fn submits_signed_batch_in_one_request() {
+        let server = TestServer::start(vec![(200, OK)]);
+        scrobbler(&server.url)
+            .scrobble(&[entry(0), entry(1)])
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let mut params = form(&requests[0].body);
+        assert_eq!(params["method"], "track.scrobble");
+        assert_eq!(params["sk"], "sk");
+        assert_eq!(params["artist[1]"], entry(1).artist);
+        assert_eq!(params["timestamp[1]"], entry(1).timestamp.to_string());
+        assert_eq!(params.remove("format").unwrap(), "json");
+        let signature = params.remove("api_sig").unwrap();
+        let api = LastFmApi::new(None, "key", "secret", None);
+        assert_eq!(signature, api.sign(&params));
+    }
+
+    #[test]
+    fn retries_after_temporary_error() {
+        let server = TestServer::start(vec![
+            (503, r#"{"error":16,"message":"Try again later"}"#),
+            (200, OK),
+        ]);
+        let mut backend = TestBackend::new(scrobbler(&server.url));
+        backend.scrobble(&[entry(0)]);
+        assert!(backend.is_retrying());
+        assert_eq!(backend.pending(), 1);
+
+        backend.flush();
+        assert!(!backend.is_retrying());
+        assert_eq!(backend.pending(), 0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn pauses_on_invalid_session() {
+        let server = TestServer::start(vec![(
+            403,
+            r#"{"error":9,"message":"Invalid session key"}"#,
+        )]);
+        let mut backend = TestBackend::new(scrobbler(&server.url));
+        backend.scrobble(&[entry(0)]);
+        assert!(backend.is_unauthorized());
+        assert_eq!(backend.pending(), 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn pauses_on_invalid_session_when_reporting_now_playing() {
+        let server = TestServer::start(vec![(
+            403,
+            r#"{"error":9,"message":"Invalid session key"}"#,
+        )]);
+        let mut backend = TestBackend::new(scrobbler(&server.url));
+        backend.now_playing(&entry(0));
+        assert!(backend.is_unauthorized());
+
+        // Nothing is sent once paused.
+        backend.now_playing(&entry(1));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
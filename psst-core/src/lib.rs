@@ -17,6 +17,7 @@ pub mod lastfm;
 pub mod metadata;
 pub mod oauth;
 pub mod player;
+pub mod scrobble;
 pub mod session;
 pub mod system_info;
 pub mod util;
@@ -23,12 +23,44 @@ pub struct LoadedPlaybackItem {
     pub file: MediaFile,
     pub source: AudioDecoder,
     pub norm_factor: f32,
+    /// Parts of the item that should not be played.  Always empty for items
+    /// other than episodes.
+    pub trim: PlaybackTrim,
+    /// Position to start the playback at.
+    pub start_position: Duration,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct PlaybackItem {
     pub item_id: ItemId,
     pub norm_level: NormalizationLevel,
+    pub trim: PlaybackTrim,
+    /// Position to resume the playback from.  Only honored for episodes, and
+    /// only if it lies within the untrimmed part.
+    pub start_position: Duration,
+}
+
+/// Lengths of an episode intro and outro to skip over during playback.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct PlaybackTrim {
+    pub intro: Duration,
+    pub outro: Duration,
+}
+
+impl PlaybackTrim {
+    pub fn is_empty(&self) -> bool {
+        self.intro.is_zero() && self.outro.is_zero()
+    }
+
+    /// Shrink the trim so that it never covers the whole `duration`.  In case it
+    /// would, the trim is dropped altogether.
+    fn clamp_to(self, duration: Duration) -> Self {
+        if self.intro + self.outro < duration {
+            self
+        } else {
+            Self::default()
+        }
+    }
 }
 
 impl PlaybackItem {
@@ -54,10 +86,26 @@ impl PlaybackItem {
             }
         };
         let norm_factor = norm_data.factor_for_level(self.norm_level, config.pregain);
+        let (trim, start_position) = match self.item_id.id_type {
+            ItemIdType::Podcast => {
+                let duration = file.path().duration;
+                let trim = self.trim.clamp_to(duration);
+                let end = duration - trim.outro;
+                let start_position = if self.start_position < end {
+                    self.start_position.max(trim.intro)
+                } else {
+                    trim.intro
+                };
+                (trim, start_position)
+            }
+            _ => (PlaybackTrim::default(), Duration::default()),
+        };
         Ok(LoadedPlaybackItem {
             file,
             source,
             norm_factor,
+            trim,
+            start_position,
         })
     }
 }
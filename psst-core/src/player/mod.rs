@@ -1,10 +1,15 @@
 pub mod file;
 pub mod item;
 pub mod queue;
+pub mod sleep;
 mod storage;
 mod worker;
 
-use std::{mem, thread, thread::JoinHandle, time::Duration};
+use std::{
+    mem, thread,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
 
@@ -20,12 +25,16 @@ use self::{
     file::MediaPath,
     item::{LoadedPlaybackItem, PlaybackItem},
     queue::{Queue, QueueBehavior},
+    sleep::{ActiveSleepTimer, SleepTimer},
     worker::PlaybackManager,
 };
 
 const PREVIOUS_TRACK_THRESHOLD: Duration = Duration::from_secs(3);
 const STOP_AFTER_CONSECUTIVE_LOADING_FAILURES: usize = 3;
 
+pub const MIN_PLAYBACK_SPEED: f32 = 0.5;
+pub const MAX_PLAYBACK_SPEED: f32 = 3.0;
+
 #[derive(Clone)]
 pub struct PlaybackConfig {
     pub bitrate: usize,
@@ -54,6 +63,9 @@ pub struct Player {
     audio_output_sink: DefaultAudioSink,
     playback_mgr: PlaybackManager,
     consecutive_loading_failures: usize,
+    /// Outro of the current item that is not going to be played.
+    trimmed_outro: Duration,
+    sleep_timer: Option<ActiveSleepTimer>,
 }
 
 impl Player {
@@ -78,6 +90,8 @@ impl Player {
             preload: PreloadState::None,
             queue: Queue::new(),
             consecutive_loading_failures: 0,
+            trimmed_outro: Duration::default(),
+            sleep_timer: None,
         }
     }
 
@@ -96,12 +110,15 @@ impl Player {
             PlayerEvent::Preloaded { item, result } => self.handle_preloaded(item, result),
             PlayerEvent::Position { position, path } => self.handle_position(position, path),
             PlayerEvent::EndOfTrack => self.handle_end_of_track(),
+            PlayerEvent::SleepTimerTick => self.handle_sleep_timer_tick(),
             PlayerEvent::Loading { .. }
             | PlayerEvent::Playing { .. }
             | PlayerEvent::Pausing { .. }
             | PlayerEvent::Resuming { .. }
             | PlayerEvent::Stopped
-            | PlayerEvent::Blocked { .. } => {}
+            | PlayerEvent::Blocked { .. }
+            | PlayerEvent::TimeSaved { .. }
+            | PlayerEvent::SleepTimer { .. } => {}
         };
     }
 
@@ -121,6 +138,14 @@ impl Player {
             PlayerCommand::SetQueueBehavior { behavior } => self.queue.set_behaviour(behavior),
             PlayerCommand::AddToQueue { item } => self.queue.add(item),
             PlayerCommand::SetVolume { volume } => self.set_volume(volume),
+            PlayerCommand::SetSpeed { speed } => self.set_speed(speed),
+            PlayerCommand::SetSkipSilence { enabled } => {
+                self.playback_mgr.set_skip_silence(enabled)
+            }
+            PlayerCommand::SetSleepTimer { timer, fade_out } => {
+                self.set_sleep_timer(timer, fade_out)
+            }
+            PlayerCommand::CancelSleepTimer => self.cancel_sleep_timer(),
         }
     }
 
@@ -178,6 +203,15 @@ impl Player {
     }
 
     fn handle_position(&mut self, new_position: Duration, path: MediaPath) {
+        let skipped = self.playback_mgr.take_skipped_silence();
+        if !skipped.is_zero() {
+            self.sender
+                .send(PlayerEvent::TimeSaved {
+                    path,
+                    saved: skipped,
+                })
+                .unwrap();
+        }
         match &mut self.state {
             PlayerState::Playing { position, .. } | PlayerState::Paused { position, .. } => {
                 *position = new_position;
@@ -196,6 +230,24 @@ impl Player {
     }
 
     fn handle_end_of_track(&mut self) {
+        if let PlayerState::Playing { path, .. } = self.state {
+            if !self.trimmed_outro.is_zero() {
+                self.sender
+                    .send(PlayerEvent::TimeSaved {
+                        path,
+                        saved: self.trimmed_outro,
+                    })
+                    .unwrap();
+            }
+        }
+        if matches!(
+            self.sleep_timer.as_ref().map(|sleep| sleep.timer),
+            Some(SleepTimer::EndOfItem)
+        ) {
+            log::info!("sleep timer expired, stopping");
+            self.stop();
+            return;
+        }
         self.queue.skip_to_following();
         if let Some(&item) = self.queue.get_current() {
             self.load_and_play(item);
@@ -288,15 +340,93 @@ impl Player {
         self.audio_output_sink.set_volume(volume as f32);
     }
 
+    fn set_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+        if let PlayerState::Loading { .. } = self.state {
+            // The speed is meant for the item being loaded, the rest of the
+            // previous one keeps its own.
+            self.playback_mgr.set_next_speed(speed);
+        } else {
+            self.playback_mgr.set_speed(speed);
+        }
+    }
+
+    fn set_sleep_timer(&mut self, timer: SleepTimer, fade_out: Duration) {
+        self.sleep_timer = Some(ActiveSleepTimer::new(timer, fade_out, self.sender.clone()));
+        self.playback_mgr.set_fade_gain(1.0);
+        self.handle_sleep_timer_tick();
+    }
+
+    fn cancel_sleep_timer(&mut self) {
+        if self.sleep_timer.take().is_some() {
+            self.playback_mgr.set_fade_gain(1.0);
+            self.sender
+                .send(PlayerEvent::SleepTimer { remaining: None })
+                .unwrap();
+        }
+    }
+
+    fn sleep_timer_remaining(&self) -> Option<Duration> {
+        let sleep_timer = self.sleep_timer.as_ref()?;
+        match sleep_timer.deadline {
+            Some(deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+            None => match self.state {
+                PlayerState::Playing { path, position }
+                | PlayerState::Paused { path, position } => {
+                    // Time until the end of the current item, accounting for the playback
+                    // speed.
+                    let end = path.duration.saturating_sub(self.trimmed_outro);
+                    let remaining = end.saturating_sub(position);
+                    Some(remaining.div_f32(self.playback_mgr.speed()))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    fn handle_sleep_timer_tick(&mut self) {
+        let Some(sleep_timer) = &self.sleep_timer else {
+            return;
+        };
+        let Some(remaining) = self.sleep_timer_remaining() else {
+            return;
+        };
+        let gain = sleep_timer.gain(remaining);
+        // Timers ending with the current item are handled on the end of track.
+        if remaining.is_zero() && matches!(sleep_timer.timer, SleepTimer::After(_)) {
+            log::info!("sleep timer expired, stopping");
+            self.stop();
+            return;
+        }
+        self.playback_mgr.set_fade_gain(gain);
+        self.sender
+            .send(PlayerEvent::SleepTimer {
+                remaining: Some(remaining),
+            })
+            .unwrap();
+    }
+
     fn play_loaded(&mut self, loaded_item: LoadedPlaybackItem) {
         log::info!("starting playback");
         let path = loaded_item.file.path();
-        let position = Duration::default();
+        let position = loaded_item.start_position;
+        let trim = loaded_item.trim;
+        self.trimmed_outro = trim.outro;
         self.playback_mgr.play(loaded_item);
         self.state = PlayerState::Playing { path, position };
         self.sender
             .send(PlayerEvent::Playing { path, position })
             .unwrap();
+        // The intro only counts as skipped if we are not resuming from a later
+        // position.
+        if !trim.intro.is_zero() && position == trim.intro {
+            self.sender
+                .send(PlayerEvent::TimeSaved {
+                    path,
+                    saved: trim.intro,
+                })
+                .unwrap();
+        }
     }
 
     fn pause(&mut self) {
@@ -364,6 +494,7 @@ impl Player {
     }
 
     fn stop(&mut self) {
+        self.cancel_sleep_timer();
         self.sender.send(PlayerEvent::Stopped).unwrap();
         self.audio_output_sink.stop();
         self.state = PlayerState::Stopped;
@@ -430,6 +561,24 @@ pub enum PlayerCommand {
     SetVolume {
         volume: f64,
     },
+    /// Change playback speed, clamped to the `MIN_PLAYBACK_SPEED` to
+    /// `MAX_PLAYBACK_SPEED` range.  Pitch is preserved, positions and seeking
+    /// stay in media time.  While an item is loading, the speed applies from
+    /// that item on.
+    SetSpeed {
+        speed: f32,
+    },
+    /// Toggle shortening of long silent gaps in the played audio.
+    SetSkipSilence {
+        enabled: bool,
+    },
+    /// Stop the playback later, fading the volume out over the `fade_out`
+    /// period right before.  Replaces any previously set sleep timer.
+    SetSleepTimer {
+        timer: SleepTimer,
+        fade_out: Duration,
+    },
+    CancelSleepTimer,
 }
 
 pub enum PlayerEvent {
@@ -480,6 +629,20 @@ pub enum PlayerEvent {
     EndOfTrack,
     /// The queue is empty.
     Stopped,
+    /// Player has skipped over a part of the track, either a long silence or a
+    /// trimmed intro or outro.
+    TimeSaved {
+        path: MediaPath,
+        saved: Duration,
+    },
+    /// Time left until the sleep timer stops the playback has changed.  `None`
+    /// if the timer has been cancelled or has expired.
+    SleepTimer {
+        remaining: Option<Duration>,
+    },
+    /// Sleep timer should be re-evaluated.  Sent periodically while a sleep
+    /// timer is set.
+    SleepTimerTick,
 }
 
 enum PlayerState {
@@ -0,0 +1,74 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+
+use super::PlayerEvent;
+
+pub const DEFAULT_SLEEP_FADE_OUT: Duration = Duration::from_secs(30);
+
+/// When should the sleep timer stop the playback.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SleepTimer {
+    /// Stop after the given time has elapsed.
+    After(Duration),
+    /// Stop at the end of the currently playing track or episode.
+    EndOfItem,
+}
+
+/// Sleep timer that has been set on the player.
+pub(super) struct ActiveSleepTimer {
+    pub timer: SleepTimer,
+    pub deadline: Option<Instant>,
+    pub fade_out: Duration,
+    _ticker: Ticker,
+}
+
+impl ActiveSleepTimer {
+    pub fn new(timer: SleepTimer, fade_out: Duration, sender: Sender<PlayerEvent>) -> Self {
+        Self {
+            timer,
+            deadline: match timer {
+                SleepTimer::After(duration) => Some(Instant::now() + duration),
+                SleepTimer::EndOfItem => None,
+            },
+            fade_out,
+            _ticker: Ticker::spawn(sender),
+        }
+    }
+
+    /// Volume factor to apply with `remaining` time left until the timer
+    /// fires.
+    pub fn gain(&self, remaining: Duration) -> f32 {
+        if remaining >= self.fade_out {
+            1.0
+        } else {
+            remaining.as_secs_f32() / self.fade_out.as_secs_f32()
+        }
+    }
+}
+
+/// Periodically sends `PlayerEvent::SleepTimerTick` to the player, until
+/// dropped.
+struct Ticker {
+    _stop: Sender<()>,
+}
+
+impl Ticker {
+    fn spawn(sender: Sender<PlayerEvent>) -> Self {
+        const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+        let (stop_send, stop_recv) = bounded::<()>(0);
+        thread::spawn(move || {
+            // The channel is disconnected once the `Ticker` is dropped.
+            while let Err(RecvTimeoutError::Timeout) = stop_recv.recv_timeout(TICK_INTERVAL) {
+                if sender.send(PlayerEvent::SleepTimerTick).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { _stop: stop_send }
+    }
+}
@@ -11,22 +11,26 @@ use crossbeam_channel::Sender;
 use rb::{Consumer, Producer, RbConsumer, RbProducer, SpscRb, RB};
 use symphonia::core::{
     audio::{SampleBuffer, SignalSpec},
-    units::TimeBase,
+    units::{Time, TimeBase},
 };
 
 use crate::{
     actor::{Act, Actor, ActorHandle},
     audio::{
         decode::AudioDecoder,
+        fade::{FadeGain, FadingSource},
         output::{AudioSink, DefaultAudioSink},
         resample::ResamplingQuality,
-        source::{AudioSource, ResampledSource, StereoMappedSource},
+        silence::{SilenceSkip, SilenceSkippingSource},
+        source::{AudioSource, ResampledSource, SeekCount, StereoMappedSource},
+        stretch::{PlaybackSpeed, TimeStretchedSource},
     },
     error::Error,
 };
 
 use super::{
     file::{MediaFile, MediaPath},
+    item::PlaybackTrim,
     LoadedPlaybackItem, PlayerEvent,
 };
 
@@ -34,6 +38,12 @@ pub struct PlaybackManager {
     sink: DefaultAudioSink,
     event_send: Sender<PlayerEvent>,
     current: Option<(MediaPath, Sender<Msg>)>,
+    /// Speed of the source currently playing.
+    speed: PlaybackSpeed,
+    /// Speed the next source starts playing at.
+    next_speed: f32,
+    silence: SilenceSkip,
+    fade: FadeGain,
 }
 
 impl PlaybackManager {
@@ -42,6 +52,10 @@ impl PlaybackManager {
             sink,
             event_send,
             current: None,
+            speed: PlaybackSpeed::new(1.0),
+            next_speed: 1.0,
+            silence: SilenceSkip::new(false),
+            fade: FadeGain::new(1.0),
         }
     }
 
@@ -51,9 +65,19 @@ impl PlaybackManager {
             loaded.file,
             loaded.source,
             loaded.norm_factor,
+            loaded.start_position,
+            loaded.trim,
             self.event_send.clone(),
         );
         self.current = Some((path, source.actor.sender()));
+        let seeks = source.seeks.clone();
+        let source = SilenceSkippingSource::new(source, self.silence.clone(), seeks.clone());
+        // Position is reported by the `DecoderSource` in media time, so stretching
+        // the output does not affect it.  Each source gets its own speed, so that
+        // whatever is left of the previous one keeps playing at its own speed.
+        self.speed = PlaybackSpeed::new(self.next_speed);
+        let source = TimeStretchedSource::new(source, self.speed.clone(), seeks);
+        let source = FadingSource::new(source, self.fade.clone());
         if source.sample_rate() == self.sink.sample_rate()
             && source.channel_count() == self.sink.channel_count()
         {
@@ -75,6 +99,36 @@ impl PlaybackManager {
         self.sink.resume();
     }
 
+    pub fn speed(&self) -> f32 {
+        self.speed.get()
+    }
+
+    /// Change the speed of the current source and of the following ones.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed.set(speed);
+        self.next_speed = speed;
+    }
+
+    /// Change the speed of the following sources only.
+    pub fn set_next_speed(&mut self, speed: f32) {
+        self.next_speed = speed;
+    }
+
+    /// Set the volume factor applied on top of the sink volume.  Changes are
+    /// ramped smoothly.
+    pub fn set_fade_gain(&self, gain: f32) {
+        self.fade.set(gain);
+    }
+
+    pub fn set_skip_silence(&self, enabled: bool) {
+        self.silence.set_enabled(enabled);
+    }
+
+    /// Return the amount of silence skipped since the last call.
+    pub fn take_skipped_silence(&self) -> Duration {
+        self.silence.take_skipped()
+    }
+
     pub fn seek(&self, position: Duration) {
         if let Some((path, worker)) = &self.current {
             let _ = worker.send(Msg::Seek(position));
@@ -97,6 +151,7 @@ pub struct DecoderSource {
     event_send: Sender<PlayerEvent>,
     total_samples: Arc<AtomicU64>,
     position: Arc<AtomicU64>,
+    seeks: SeekCount,
     precision: u64,
     reported: u64,
     end_of_track: bool,
@@ -108,8 +163,10 @@ pub struct DecoderSource {
 impl DecoderSource {
     pub fn new(
         file: MediaFile,
-        decoder: AudioDecoder,
+        mut decoder: AudioDecoder,
         norm_factor: f32,
+        start_position: Duration,
+        trim: PlaybackTrim,
         event_send: Sender<PlayerEvent>,
     ) -> Self {
         const REPORT_PRECISION: Duration = Duration::from_millis(900);
@@ -129,20 +186,52 @@ impl DecoderSource {
 
         // We keep track of the current play-head position by sharing an atomic sample
         // counter with the decoding worker.  Worker is setting this on seek, we are
-        // incrementing on reading from the ring-buffer.
-        let position = Arc::new(AtomicU64::new(0));
+        // incrementing on reading from the ring-buffer.  Playback might not start at
+        // the beginning, in case of a trimmed intro or a resumed episode.
+        let channel_count = signal_spec.channels.count() as u64;
+        let start_samples = if start_position.is_zero() {
+            0
+        } else {
+            match decoder.seek(start_position) {
+                Ok(timestamp) => timestamp * channel_count,
+                Err(err) => {
+                    log::error!("failed to seek to start position: {err}");
+                    0
+                }
+            }
+        };
+        let position = Arc::new(AtomicU64::new(start_samples));
 
         // Because the `n_frames` count that Symphonia gives us can be a bit unreliable,
         // we track the total number of samples in this stream in this atomic, set when
-        // the underlying decoder returns EOF.
-        let total_samples = Arc::new(AtomicU64::new(u64::MAX));
+        // the underlying decoder returns EOF.  For items with a trimmed outro, we know
+        // where to stop in advance.
+        let end_samples = if trim.outro.is_zero() {
+            u64::MAX
+        } else {
+            let end = file.path().duration.saturating_sub(trim.outro);
+            time_base.calc_timestamp(Time::from(end.as_secs_f64())) * channel_count
+        };
+        let total_samples = Arc::new(AtomicU64::new(end_samples));
+        let seeks = SeekCount::default();
 
         // Spawn the worker and kick-start the decoding.  The buffer will start filling
         // now.
         let actor = Worker::spawn_with_default_cap("audio_decoding", {
             let position = Arc::clone(&position);
             let total_samples = Arc::clone(&total_samples);
-            move |this| Worker::new(this, decoder, buffer, position, total_samples)
+            let seeks = seeks.clone();
+            move |this| {
+                Worker::new(
+                    this,
+                    decoder,
+                    buffer,
+                    start_samples,
+                    position,
+                    total_samples,
+                    seeks,
+                )
+            }
         });
         let _ = actor.send(Msg::Read);
 
@@ -157,6 +246,7 @@ impl DecoderSource {
             total_samples,
             end_of_track: false,
             position,
+            seeks,
             precision,
             reported: u64::MAX, // Something sufficiently distinct from any position.
         }
@@ -254,8 +344,11 @@ struct Worker {
     output_producer: Producer<f32>,
     /// Shared atomic position.  We update this on seek only.
     position: Arc<AtomicU64>,
-    /// Shared atomic for total number of samples.  We set this on EOF.
+    /// Shared atomic for total number of samples.  We lower this to the number of
+    /// samples written on EOF.
     total_samples: Arc<AtomicU64>,
+    /// Shared seek counter.  We increment this after each seek.
+    seeks: SeekCount,
     /// Range of samples in `resampled` that are awaiting flush into `output`.
     samples_to_write: Range<usize>,
     /// Number of samples written into the output channel.
@@ -275,8 +368,10 @@ impl Worker {
         this: Sender<Msg>,
         input: AudioDecoder,
         output: SpscRb<f32>,
+        start_samples: u64,
         position: Arc<AtomicU64>,
         total_samples: Arc<AtomicU64>,
+        seeks: SeekCount,
     ) -> Self {
         const DEFAULT_MAX_FRAMES: u64 = 8 * 1024;
 
@@ -302,7 +397,8 @@ impl Worker {
             output,
             position,
             total_samples,
-            samples_written: 0,
+            seeks,
+            samples_written: start_samples,
             samples_to_write: 0..0, // Arbitrary empty range.
             is_reading: false,
         }
@@ -335,6 +431,8 @@ impl Worker {
                 self.samples_written = position;
                 self.position.store(position, Ordering::Relaxed);
                 self.output.clear();
+                // Let the sources down the chain drop what they buffered before.
+                self.seeks.increment();
             }
             Err(err) => {
                 log::error!("failed to seek: {err}");
@@ -372,7 +470,7 @@ impl Worker {
                 None => {
                     self.is_reading = false;
                     self.total_samples
-                        .store(self.samples_written, Ordering::Relaxed);
+                        .fetch_min(self.samples_written, Ordering::Relaxed);
                 }
             }
             Ok(Act::Continue)
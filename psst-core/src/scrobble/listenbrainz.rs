@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::util::default_ureq_agent_builder;
+
+use super::{ScrobbleEntry, ScrobbleError, Scrobbler};
+
+pub const DEFAULT_LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+
+/// ListenBrainz accepts at most this many listens in a single request.
+const MAX_LISTENS_PER_REQUEST: usize = 1000;
+
+/// Submits listens through the ListenBrainz API, either to the official
+/// instance or a self-hosted one.
+pub struct ListenBrainzScrobbler {
+    agent: ureq::Agent,
+    url: String,
+    token: String,
+}
+
+impl ListenBrainzScrobbler {
+    /// Create a scrobbler authenticated with the user `token`.  `url` is the
+    /// root of the API, `DEFAULT_LISTENBRAINZ_URL` is used if `None`.
+    pub fn new(url: Option<&str>, token: &str, proxy_url: Option<&str>) -> Self {
+        Self {
+            agent: default_ureq_agent_builder(proxy_url).build().into(),
+            url: url
+                .unwrap_or(DEFAULT_LISTENBRAINZ_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    fn submit(&self, listen_type: &str, payload: Vec<Listen>) -> Result<(), ureq::Error> {
+        self.agent
+            .post(format!("{}/1/submit-listens", self.url))
+            .header("Authorization", format!("Token {}", self.token))
+            .send_json(SubmitListens {
+                listen_type,
+                payload,
+            })?;
+        Ok(())
+    }
+}
+
+impl Scrobbler for ListenBrainzScrobbler {
+    fn id(&self) -> &'static str {
+        "listenbrainz"
+    }
+
+    fn max_batch_len(&self) -> usize {
+        MAX_LISTENS_PER_REQUEST
+    }
+
+    fn now_playing(&self, entry: &ScrobbleEntry) -> Result<(), ScrobbleError> {
+        self.submit("playing_now", vec![Listen::new(entry, None)])?;
+        Ok(())
+    }
+
+    fn scrobble(&self, entries: &[ScrobbleEntry]) -> Result<(), ScrobbleError> {
+        let listen_type = if entries.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+        let payload = entries
+            .iter()
+            .map(|entry| Listen::new(entry, Some(entry.timestamp)))
+            .collect();
+        self.submit(listen_type, payload)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct SubmitListens<'a> {
+    listen_type: &'a str,
+    payload: Vec<Listen<'a>>,
+}
+
+#[derive(Serialize)]
+struct Listen<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    listened_at: Option<u64>,
+    track_metadata: TrackMetadata<'a>,
+}
+
+impl<'a> Listen<'a> {
+    fn new(entry: &'a ScrobbleEntry, listened_at: Option<u64>) -> Self {
+        Self {
+            listened_at,
+            track_metadata: TrackMetadata {
+                artist_name: &entry.artist,
+                track_name: &entry.title,
+                release_name: entry.album.as_deref(),
+                additional_info: AdditionalInfo {
+                    submission_client: "Psst",
+                },
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TrackMetadata<'a> {
+    artist_name: &'a str,
+    track_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_name: Option<&'a str>,
+    additional_info: AdditionalInfo,
+}
+
+#[derive(Serialize)]
+struct AdditionalInfo {
+    submission_client: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrobble::test_server::{entry, TestBackend, TestServer};
+
+    const OK: &str = r#"{"status":"ok"}"#;
+
+    #[test]
+    fn submits_batch_in_one_request() {
+        let server = TestServer::start(vec![(200, OK)]);
+        let scrobbler = ListenBrainzScrobbler::new(Some(&server.url), "secret", None);
+        scrobbler.scrobble(&[entry(0), entry(1), entry(2)]).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/1/submit-listens");
+        assert!(requests[0].head.contains("Token secret"));
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["listen_type"], "import");
+        assert_eq!(body["payload"].as_array().unwrap().len(), 3);
+        assert_eq!(body["payload"][1]["listened_at"], entry(1).timestamp);
+        assert_eq!(
+            body["payload"][1]["track_metadata"]["track_name"],
+            entry(1).title
+        );
+    }
+
+    #[test]
+    fn retries_after_server_error() {
+        let server = TestServer::start(vec![(503, "{}"), (200, OK)]);
+        let mut backend = TestBackend::new(ListenBrainzScrobbler::new(
+            Some(&server.url),
+            "secret",
+            None,
+        ));
+        backend.scrobble(&[entry(0)]);
+        assert!(backend.is_retrying());
+        assert_eq!(backend.pending(), 1);
+
+        backend.flush();
+        assert!(!backend.is_retrying());
+        assert_eq!(backend.pending(), 0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn sets_rejected_batch_aside() {
+        let server = TestServer::start(vec![(400, r#"{"code":400}"#)]);
+        let mut backend = TestBackend::new(ListenBrainzScrobbler::new(
+            Some(&server.url),
+            "secret",
+            None,
+        ));
+        backend.scrobble(&[entry(0), entry(1)]);
+        assert!(!backend.is_retrying());
+        assert_eq!(backend.pending(), 0);
+        assert_eq!(backend.rejected(), 2);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
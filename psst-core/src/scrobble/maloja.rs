@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+use crate::util::default_ureq_agent_builder;
+
+use super::{ScrobbleEntry, ScrobbleError, Scrobbler};
+
+/// Submits scrobbles to a Maloja server, or any other server implementing its
+/// native `newscrobble` API, running at a custom URL.
+pub struct MalojaScrobbler {
+    agent: ureq::Agent,
+    url: String,
+    api_key: String,
+}
+
+impl MalojaScrobbler {
+    pub fn new(url: &str, api_key: &str, proxy_url: Option<&str>) -> Self {
+        Self {
+            agent: default_ureq_agent_builder(proxy_url).build().into(),
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+}
+
+impl Scrobbler for MalojaScrobbler {
+    fn id(&self) -> &'static str {
+        "maloja"
+    }
+
+    fn max_batch_len(&self) -> usize {
+        // The API takes a single scrobble per request.
+        1
+    }
+
+    fn now_playing(&self, _entry: &ScrobbleEntry) -> Result<(), ScrobbleError> {
+        // Maloja does not track the currently playing song.
+        Ok(())
+    }
+
+    fn scrobble(&self, entries: &[ScrobbleEntry]) -> Result<(), ScrobbleError> {
+        for entry in entries {
+            self.agent
+                .post(format!("{}/apis/mlj_1/newscrobble", self.url))
+                .send_json(NewScrobble {
+                    key: &self.api_key,
+                    artists: [entry.artist.as_str()],
+                    title: &entry.title,
+                    album: entry.album.as_deref(),
+                    time: entry.timestamp,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct NewScrobble<'a> {
+    key: &'a str,
+    artists: [&'a str; 1],
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    album: Option<&'a str>,
+    time: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrobble::test_server::{entry, TestBackend, TestServer};
+
+    const OK: &str = r#"{"status":"success"}"#;
+
+    #[test]
+    fn submits_each_entry_of_batch() {
+        let server = TestServer::start(vec![(200, OK), (200, OK)]);
+        let scrobbler = MalojaScrobbler::new(&server.url, "secret", None);
+        scrobbler.scrobble(&[entry(0), entry(1)]).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for (index, request) in requests.iter().enumerate() {
+            assert_eq!(request.path, "/apis/mlj_1/newscrobble");
+            let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            assert_eq!(body["key"], "secret");
+            assert_eq!(body["title"], entry(index as u64).title);
+            assert_eq!(body["time"], entry(index as u64).timestamp);
+        }
+    }
+
+    #[test]
+    fn retries_after_rate_limit() {
+        let server = TestServer::start(vec![(429, "{}"), (200, OK)]);
+        let mut backend = TestBackend::new(MalojaScrobbler::new(&server.url, "secret", None));
+        backend.scrobble(&[entry(0)]);
+        assert!(backend.is_retrying());
+        assert_eq!(backend.pending(), 1);
+
+        backend.flush();
+        assert!(!backend.is_retrying());
+        assert_eq!(backend.pending(), 0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn pauses_on_refused_key() {
+        let server = TestServer::start(vec![(403, "{}")]);
+        let mut backend = TestBackend::new(MalojaScrobbler::new(&server.url, "secret", None));
+        backend.scrobble(&[entry(0)]);
+        assert!(backend.is_unauthorized());
+        assert_eq!(backend.pending(), 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
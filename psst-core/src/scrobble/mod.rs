@@ -0,0 +1,441 @@
+pub mod listenbrainz;
+pub mod maloja;
+#[cfg(test)]
+pub(crate) mod test_server;
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use librespot_protocol::metadata::Track;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::{
+    actor::{Act, Actor, ActorHandle},
+    error::Error,
+    item_id::ItemId,
+    metadata::Fetch,
+    session::SessionService,
+};
+
+/// Tracks shorter than this are never scrobbled.
+const MIN_TRACK_DURATION: Duration = Duration::from_secs(30);
+
+/// A track is scrobbled after being played for half of its duration, or for
+/// this long, whichever comes first.
+const MAX_PLAYED_DURATION: Duration = Duration::from_secs(4 * 60);
+
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Returns true if a track of `duration` has been played long enough to be
+/// scrobbled, following the Last.fm rules.
+pub fn should_scrobble(duration: Duration, played: Duration) -> bool {
+    duration > MIN_TRACK_DURATION && played >= (duration / 2).min(MAX_PLAYED_DURATION)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScrobbleEntry {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// Time the track started playing, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl ScrobbleEntry {
+    /// Create an entry for a track that has been playing for `played`.
+    pub fn new(artist: String, title: String, album: Option<String>, played: Duration) -> Self {
+        let started_at = SystemTime::now() - played;
+        Self {
+            artist,
+            title,
+            album,
+            timestamp: started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    /// Fetch the metadata of track `item_id` and create an entry for it.
+    pub fn fetch(
+        session: &SessionService,
+        item_id: ItemId,
+        played: Duration,
+    ) -> Result<Self, Error> {
+        let track = Track::fetch(session, item_id)?;
+        let artist = track
+            .artist
+            .first()
+            .map(|artist| artist.name().to_string())
+            .unwrap_or_default();
+        let album = track
+            .album
+            .has_name()
+            .then(|| track.album.name().to_string());
+        Ok(Self::new(artist, track.name().to_string(), album, played))
+    }
+}
+
+/// Failed submission of scrobbles.
+#[derive(Debug)]
+pub enum ScrobbleError {
+    /// The service could not be reached, is overloaded or failed.  The
+    /// submission is retried later.
+    Transient(Error),
+    /// The service refused the submitted scrobbles.  Submitting them again
+    /// would fail the same way.
+    Rejected(Error),
+    /// The service refused the credentials.
+    Unauthorized(Error),
+}
+
+impl ScrobbleError {
+    /// Classify a failed HTTP request by its status code.
+    pub fn from_status(status: u16, err: Error) -> Self {
+        match status {
+            401 | 403 => Self::Unauthorized(err),
+            // Timeouts and rate limiting pass with time.
+            408 | 429 => Self::Transient(err),
+            400..=499 => Self::Rejected(err),
+            _ => Self::Transient(err),
+        }
+    }
+}
+
+impl From<ureq::Error> for ScrobbleError {
+    fn from(err: ureq::Error) -> Self {
+        match err {
+            ureq::Error::StatusCode(status) => {
+                Self::from_status(status, Error::ScrobblerError(Box::new(err)))
+            }
+            err => Self::Transient(Error::ScrobblerError(Box::new(err))),
+        }
+    }
+}
+
+impl From<ScrobbleError> for Error {
+    fn from(err: ScrobbleError) -> Self {
+        match err {
+            ScrobbleError::Transient(err)
+            | ScrobbleError::Rejected(err)
+            | ScrobbleError::Unauthorized(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for ScrobbleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transient(err) | Self::Rejected(err) | Self::Unauthorized(err) => err.fmt(f),
+        }
+    }
+}
+
+/// Service that listened tracks can be reported to.
+pub trait Scrobbler: Send {
+    /// Short identifier of the service, used in logs and to name its journal.
+    fn id(&self) -> &'static str;
+
+    /// Largest number of entries accepted by a single `scrobble` call.
+    fn max_batch_len(&self) -> usize;
+
+    /// Report a track that has just started playing.
+    fn now_playing(&self, entry: &ScrobbleEntry) -> Result<(), ScrobbleError>;
+
+    /// Submit listened tracks.
+    fn scrobble(&self, entries: &[ScrobbleEntry]) -> Result<(), ScrobbleError>;
+}
+
+/// Called with the ID of a scrobbler whose credentials were refused.
+/// Submissions to it stop until it is set again with `set_scrobblers`.
+pub type UnauthorizedCallback = Box<dyn Fn(&'static str) + Send>;
+
+/// Submits scrobbles in the background.  Scrobbles are first written into a
+/// journal on disk, one for each scrobbler, and removed from it only after the
+/// service has accepted them, so nothing is lost while offline or after a
+/// restart.  Failed submissions are retried with an increasing delay, except
+/// scrobbles the service refused, which are moved into a separate journal.
+pub struct ScrobbleQueue {
+    actor: ActorHandle<ScrobbleMsg>,
+}
+
+impl ScrobbleQueue {
+    pub fn new(journal_dir: PathBuf, on_unauthorized: UnauthorizedCallback) -> Self {
+        Self {
+            actor: ScrobbleWorker::spawn_with_default_cap("scrobble_queue", |_| ScrobbleWorker {
+                journal_dir,
+                backends: Vec::new(),
+                on_unauthorized,
+            }),
+        }
+    }
+
+    /// Set the scrobblers to submit to.  Journaled scrobbles of a scrobbler
+    /// that has been removed are kept on disk, and submitted once it is set
+    /// again.
+    pub fn set_scrobblers(&self, scrobblers: Vec<Box<dyn Scrobbler>>) {
+        self.send(ScrobbleMsg::SetScrobblers(scrobblers));
+    }
+
+    pub fn now_playing(&self, entry: ScrobbleEntry) {
+        self.send(ScrobbleMsg::NowPlaying(entry));
+    }
+
+    pub fn scrobble(&self, entry: ScrobbleEntry) {
+        self.send(ScrobbleMsg::Scrobble(entry));
+    }
+
+    fn send(&self, msg: ScrobbleMsg) {
+        self.actor
+            .send(msg)
+            .map_err(|e| log::error!("error sending message: {e:?}"))
+            .ok();
+    }
+}
+
+enum ScrobbleMsg {
+    SetScrobblers(Vec<Box<dyn Scrobbler>>),
+    NowPlaying(ScrobbleEntry),
+    Scrobble(ScrobbleEntry),
+    Flush,
+}
+
+struct Retry {
+    at: Instant,
+    delay: Duration,
+}
+
+struct Backend {
+    scrobbler: Box<dyn Scrobbler>,
+    journal: Journal,
+    /// Scrobbles the service refused, kept for the user to inspect.
+    rejected: Journal,
+    retry: Option<Retry>,
+    /// The credentials were refused, nothing is submitted until the scrobbler
+    /// is replaced.
+    unauthorized: bool,
+}
+
+impl Backend {
+    fn is_due(&self, now: Instant) -> bool {
+        !self.unauthorized && self.retry.as_ref().is_none_or(|retry| retry.at <= now)
+    }
+
+    /// Submit all journaled scrobbles, in batches.  Stops on the first batch
+    /// that fails transiently and schedules a retry, or when the credentials
+    /// are refused.
+    fn flush(&mut self, on_unauthorized: &UnauthorizedCallback) {
+        let id = self.scrobbler.id();
+        while !self.journal.entries.is_empty() {
+            let len = self
+                .journal
+                .entries
+                .len()
+                .min(self.scrobbler.max_batch_len());
+            match self.scrobbler.scrobble(&self.journal.entries[..len]) {
+                Ok(()) => {
+                    log::info!("submitted {len} scrobbles to {id}");
+                    self.journal.entries.drain(..len);
+                    self.journal.save_or_log();
+                }
+                Err(ScrobbleError::Rejected(err)) => {
+                    log::error!("{id} rejected {len} scrobbles, moving them aside: {err}");
+                    let batch = self.journal.entries.drain(..len);
+                    self.rejected.entries.extend(batch);
+                    self.rejected.save_or_log();
+                    self.journal.save_or_log();
+                }
+                Err(ScrobbleError::Unauthorized(err)) => {
+                    self.pause_unauthorized(err, on_unauthorized);
+                    return;
+                }
+                Err(ScrobbleError::Transient(err)) => {
+                    let delay = self
+                        .retry
+                        .as_ref()
+                        .map_or(MIN_RETRY_DELAY, |retry| retry.delay * 2)
+                        .min(MAX_RETRY_DELAY);
+                    log::warn!("failed to submit scrobbles to {id}, retrying in {delay:?}: {err}");
+                    self.retry = Some(Retry {
+                        at: Instant::now() + delay,
+                        delay,
+                    });
+                    return;
+                }
+            }
+        }
+        self.retry = None;
+    }
+
+    /// Report a track that has just started playing.  Failures are not
+    /// retried, but refused credentials pause the scrobbler.
+    fn now_playing(&mut self, entry: &ScrobbleEntry, on_unauthorized: &UnauthorizedCallback) {
+        if self.unauthorized {
+            return;
+        }
+        match self.scrobbler.now_playing(entry) {
+            Ok(()) => {}
+            Err(ScrobbleError::Unauthorized(err)) => {
+                self.pause_unauthorized(err, on_unauthorized);
+            }
+            Err(err) => {
+                let id = self.scrobbler.id();
+                log::warn!("failed to report 'Now Playing' to {id}: {err}");
+            }
+        }
+    }
+
+    /// Stop submitting until the scrobbler is replaced.
+    fn pause_unauthorized(&mut self, err: Error, on_unauthorized: &UnauthorizedCallback) {
+        let id = self.scrobbler.id();
+        log::error!("{id} refused the credentials, pausing scrobbling: {err}");
+        self.unauthorized = true;
+        self.retry = None;
+        on_unauthorized(id);
+    }
+}
+
+struct ScrobbleWorker {
+    journal_dir: PathBuf,
+    backends: Vec<Backend>,
+    on_unauthorized: UnauthorizedCallback,
+}
+
+impl ScrobbleWorker {
+    fn set_scrobblers(&mut self, scrobblers: Vec<Box<dyn Scrobbler>>) {
+        let mut old_backends = std::mem::take(&mut self.backends);
+        for scrobbler in scrobblers {
+            let id = scrobbler.id();
+            // Keep the journals of a scrobbler that is being replaced, there is no
+            // need to read them again.
+            let old = old_backends
+                .iter()
+                .position(|b| b.scrobbler.id() == id)
+                .map(|index| old_backends.swap_remove(index));
+            let (journal, rejected) = match old {
+                Some(old) => (old.journal, old.rejected),
+                None => (
+                    Journal::load(self.journal_dir.join(format!("{id}.json"))),
+                    Journal::load(self.journal_dir.join(format!("{id}.rejected.json"))),
+                ),
+            };
+            let mut backend = Backend {
+                scrobbler,
+                journal,
+                rejected,
+                retry: None,
+                unauthorized: false,
+            };
+            backend.flush(&self.on_unauthorized);
+            self.backends.push(backend);
+        }
+    }
+}
+
+impl Actor for ScrobbleWorker {
+    type Message = ScrobbleMsg;
+    type Error = Error;
+
+    fn handle(&mut self, msg: Self::Message) -> Result<Act<Self>, Self::Error> {
+        match msg {
+            ScrobbleMsg::SetScrobblers(scrobblers) => {
+                self.set_scrobblers(scrobblers);
+            }
+            ScrobbleMsg::NowPlaying(entry) => {
+                for backend in &mut self.backends {
+                    backend.now_playing(&entry, &self.on_unauthorized);
+                }
+            }
+            ScrobbleMsg::Scrobble(entry) => {
+                log::info!("scrobbling {} - {}", entry.artist, entry.title);
+                let now = Instant::now();
+                for backend in &mut self.backends {
+                    backend.journal.entries.push(entry.clone());
+                    backend.journal.save_or_log();
+                    // While waiting for a retry, the entry is submitted with the others.
+                    if backend.is_due(now) {
+                        backend.flush(&self.on_unauthorized);
+                    }
+                }
+            }
+            ScrobbleMsg::Flush => {
+                let now = Instant::now();
+                for backend in &mut self.backends {
+                    if backend.is_due(now) {
+                        backend.flush(&self.on_unauthorized);
+                    }
+                }
+            }
+        }
+        let next_retry = self
+            .backends
+            .iter()
+            .filter_map(|backend| backend.retry.as_ref())
+            .map(|retry| retry.at)
+            .min();
+        match next_retry {
+            Some(at) => Ok(Act::WaitOr {
+                timeout: at.saturating_duration_since(Instant::now()),
+                timeout_msg: ScrobbleMsg::Flush,
+            }),
+            None => Ok(Act::Continue),
+        }
+    }
+}
+
+/// Scrobbles waiting for submission, persisted as a JSON file.
+struct Journal {
+    path: PathBuf,
+    entries: Vec<ScrobbleEntry>,
+}
+
+impl Journal {
+    fn load(path: PathBuf) -> Self {
+        let entries = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|err| {
+                log::error!("failed to read scrobble journal: {err}");
+                Vec::new()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                log::error!("failed to open scrobble journal: {err}");
+                Vec::new()
+            }
+        };
+        if !entries.is_empty() {
+            log::info!("loaded {} pending scrobbles", entries.len());
+        }
+        Self { path, entries }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+        // Write into a temporary file first, so the journal is never left
+        // half-written.
+        let file = NamedTempFile::new_in(dir)?;
+        {
+            let mut writer = BufWriter::new(file.as_file());
+            serde_json::to_writer(&mut writer, &self.entries)?;
+            writer.flush()?;
+        }
+        file.persist(&self.path).map_err(|err| err.error)?;
+        Ok(())
+    }
+
+    fn save_or_log(&self) {
+        if let Err(err) = self.save() {
+            log::error!("failed to save scrobble journal: {err}");
+        }
+    }
+}
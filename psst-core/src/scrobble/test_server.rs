@@ -0,0 +1,152 @@
+//! Local HTTP server answering with prepared responses, and a backend around
+//! a scrobbler journaling into a temporary directory, for testing scrobblers.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+};
+
+use tempfile::TempDir;
+
+use super::{Backend, Journal, ScrobbleEntry, Scrobbler, UnauthorizedCallback};
+
+/// Request received by `TestServer`.
+pub struct Request {
+    pub path: String,
+    /// Request line and headers.
+    pub head: String,
+    pub body: String,
+}
+
+pub struct TestServer {
+    pub url: String,
+    thread: JoinHandle<Vec<Request>>,
+}
+
+impl TestServer {
+    /// Answer one connection for each of `responses`, a status code and a
+    /// JSON body, in order.
+    pub fn start(responses: Vec<(u16, &'static str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let thread = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|(status, body)| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let request = read_request(&mut reader);
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 {status} Test\r\n\
+                         Content-Type: application/json\r\n\
+                         Content-Length: {}\r\n\
+                         Connection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .unwrap();
+                    request
+                })
+                .collect()
+        });
+        Self { url, thread }
+    }
+
+    /// Wait until all responses are sent, and return the received requests.
+    pub fn requests(self) -> Vec<Request> {
+        self.thread.join().unwrap()
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Request {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim_end().is_empty() {
+            break;
+        }
+        head.push_str(&line);
+    }
+    let path = head.split(' ').nth(1).unwrap_or_default().to_string();
+    let len = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse().unwrap())
+        })
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+    Request {
+        path,
+        head,
+        body: String::from_utf8(body).unwrap(),
+    }
+}
+
+pub struct TestBackend {
+    backend: Backend,
+    on_unauthorized: UnauthorizedCallback,
+    _dir: TempDir,
+}
+
+impl TestBackend {
+    pub fn new(scrobbler: impl Scrobbler + 'static) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        Self {
+            backend: Backend {
+                scrobbler: Box::new(scrobbler),
+                journal: Journal::load(dir.path().join("journal.json")),
+                rejected: Journal::load(dir.path().join("rejected.json")),
+                retry: None,
+                unauthorized: false,
+            },
+            on_unauthorized: Box::new(|_| {}),
+            _dir: dir,
+        }
+    }
+
+    /// Journal `entries` and submit them.
+    pub fn scrobble(&mut self, entries: &[ScrobbleEntry]) {
+        self.backend.journal.entries.extend_from_slice(entries);
+        self.flush();
+    }
+
+    pub fn now_playing(&mut self, entry: &ScrobbleEntry) {
+        self.backend.now_playing(entry, &self.on_unauthorized);
+    }
+
+    pub fn flush(&mut self) {
+        self.backend.flush(&self.on_unauthorized);
+    }
+
+    /// Number of journaled entries waiting for submission.
+    pub fn pending(&self) -> usize {
+        self.backend.journal.entries.len()
+    }
+
+    /// Number of entries the service rejected.
+    pub fn rejected(&self) -> usize {
+        self.backend.rejected.entries.len()
+    }
+
+    pub fn is_retrying(&self) -> bool {
+        self.backend.retry.is_some()
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.backend.unauthorized
+    }
+}
+
+pub fn entry(index: u64) -> ScrobbleEntry {
+    ScrobbleEntry {
+        artist: format!("Artist {index}"),
+        title: format!("Title {index}"),
+        album: Some("Album".to_string()),
+        timestamp: 1_700_000_000 + index,
+    }
+}
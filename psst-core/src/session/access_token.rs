@@ -27,6 +27,7 @@ pub const WEBAPI_SCOPES: &[&str] = &[
     "playlist-read-collaborative",
     "playlist-modify-public",
     "playlist-modify-private",
+    "ugc-image-upload",
     "user-follow-read",
     "user-follow-modify",
     "user-top-read",
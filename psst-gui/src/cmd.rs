@@ -1,11 +1,17 @@
 use crate::data::Track;
 use druid::{Selector, WidgetId};
-use psst_core::{item_id::ItemId, player::item::PlaybackItem};
+use psst_core::{
+    item_id::ItemId,
+    player::{item::PlaybackItem, sleep::SleepTimer},
+};
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::{
-    data::{Nav, PlaybackPayload, QueueBehavior, QueueEntry},
+    data::{
+        DuplicateSource, Episode, ListeningHistory, Nav, PlaybackPayload, QueueBehavior,
+        QueueEntry, SelectMode, TrackSelection,
+    },
     ui::find::Find,
 };
 
@@ -26,6 +32,9 @@ pub const TOGGLE_FINDER: Selector = Selector::new("app.show-finder");
 pub const FIND_IN_PLAYLIST: Selector<Find> = Selector::new("find-in-playlist");
 pub const FIND_IN_SAVED_TRACKS: Selector<Find> = Selector::new("find-in-saved-tracks");
 
+// Command palette
+pub const TOGGLE_PALETTE: Selector = Selector::new("app.toggle-palette");
+
 // Session
 pub const SESSION_CONNECT: Selector = Selector::new("app.session-connect");
 pub const LOG_OUT: Selector = Selector::new("app.log-out");
@@ -34,7 +43,11 @@ pub const LOG_OUT: Selector = Selector::new("app.log-out");
 pub const NAVIGATE: Selector<Nav> = Selector::new("app.navigates");
 pub const NAVIGATE_BACK: Selector<usize> = Selector::new("app.navigate-back");
 pub const NAVIGATE_REFRESH: Selector = Selector::new("app.navigate-refresh");
+pub const NAVIGATE_REFRESH_ALL: Selector = Selector::new("app.navigate-refresh-all");
 pub const TOGGLE_LYRICS: Selector = Selector::new("app.toggle-lyrics");
+// Bucket and key of a cached WebAPI response that got refreshed in the background.
+pub const WEBAPI_CACHE_REFRESHED: Selector<(Arc<str>, Arc<str>)> =
+    Selector::new("app.webapi-cache-refreshed");
 
 // Playback state
 pub const PLAYBACK_LOADING: Selector<ItemId> = Selector::new("app.playback-loading");
@@ -44,20 +57,47 @@ pub const PLAYBACK_PAUSING: Selector = Selector::new("app.playback-pausing");
 pub const PLAYBACK_RESUMING: Selector = Selector::new("app.playback-resuming");
 pub const PLAYBACK_BLOCKED: Selector = Selector::new("app.playback-blocked");
 pub const PLAYBACK_STOPPED: Selector = Selector::new("app.playback-stopped");
+pub const PLAYBACK_ENDED: Selector = Selector::new("app.playback-ended");
+pub const PLAYBACK_TIME_SAVED: Selector<(ItemId, Duration)> =
+    Selector::new("app.playback-time-saved");
+pub const PLAYBACK_SLEEP_TIMER: Selector<Option<Duration>> =
+    Selector::new("app.playback-sleep-timer");
+pub const SCROBBLER_UNAUTHORIZED: Selector<&'static str> =
+    Selector::new("app.scrobbler-unauthorized");
+
+// Listening history
+pub const LISTENING_HISTORY_LOADED: Selector<ListeningHistory> =
+    Selector::new("app.listening-history-loaded");
+
+// Episodes
+pub const SET_EPISODE_PLAYED: Selector<(Arc<Episode>, bool)> =
+    Selector::new("app.episode.set-played");
 
 // Playback control
 pub const PLAY: Selector<usize> = Selector::new("app.play-index");
 pub const PLAY_TRACKS: Selector<PlaybackPayload> = Selector::new("app.play-tracks");
 pub const PLAY_PREVIOUS: Selector = Selector::new("app.play-previous");
 pub const PLAY_PAUSE: Selector = Selector::new("app.play-pause");
+pub const PLAY_PAUSE_OR_RESUME: Selector = Selector::new("app.play-pause-or-resume");
 pub const PLAY_RESUME: Selector = Selector::new("app.play-resume");
 pub const PLAY_NEXT: Selector = Selector::new("app.play-next");
 pub const PLAY_STOP: Selector = Selector::new("app.play-stop");
 pub const ADD_TO_QUEUE: Selector<(QueueEntry, PlaybackItem)> = Selector::new("app.add-to-queue");
+pub const ADD_ALL_TO_QUEUE: Selector<Vec<(QueueEntry, PlaybackItem)>> =
+    Selector::new("app.add-all-to-queue");
 pub const PLAY_QUEUE_BEHAVIOR: Selector<QueueBehavior> = Selector::new("app.play-queue-behavior");
 pub const PLAY_SEEK: Selector<f64> = Selector::new("app.play-seek");
+pub const PLAY_SEEK_FORWARD: Selector = Selector::new("app.play-seek-forward");
+pub const PLAY_SEEK_BACKWARD: Selector = Selector::new("app.play-seek-backward");
+pub const PLAY_SPEED: Selector<f64> = Selector::new("app.play-speed");
+/// Set the sleep timer, or cancel it with `None`.
+pub const PLAY_SLEEP_TIMER: Selector<Option<SleepTimer>> = Selector::new("app.play-sleep-timer");
 pub const SKIP_TO_POSITION: Selector<u64> = Selector::new("app.skip-to-position");
 
+// Track selection
+pub const SELECT: Selector<(usize, SelectMode)> = Selector::new("app.select-index");
+pub const SET_SELECTION: Selector<TrackSelection> = Selector::new("app.set-selection");
+
 // Sorting control
 pub const SORT_BY_DATE_ADDED: Selector = Selector::new("app.sort-by-date-added");
 pub const SORT_BY_TITLE: Selector = Selector::new("app.sort-by-title");
@@ -72,5 +112,13 @@ pub const TOGGLE_SORT_ORDER: Selector = Selector::new("app.toggle-sort-order");
 pub const SHOW_CREDITS_WINDOW: Selector<Arc<Track>> = Selector::new("app.credits-show-window");
 pub const LOAD_TRACK_CREDITS: Selector<Arc<Track>> = Selector::new("app.credits-load");
 
+// Duplicate tracks
+pub const SHOW_DUPLICATES_WINDOW: Selector<DuplicateSource> =
+    Selector::new("app.duplicates-show-window");
+pub const LOAD_DUPLICATES: Selector<DuplicateSource> = Selector::new("app.duplicates-load");
+
 // Artwork
 pub const SHOW_ARTWORK: Selector = Selector::new("app.show-artwork");
+
+// Mini player
+pub const SHOW_MINI_PLAYER: Selector = Selector::new("app.show-mini-player");
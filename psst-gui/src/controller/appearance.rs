@@ -0,0 +1,246 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use druid::{
+    widget::{prelude::*, Controller},
+    Color, ExtEventSink, Selector, Target,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    data::{accent_color, system_is_dark, AppState, Theme, ThemeFile},
+    error::Error,
+    ui::theme,
+    webapi::WebApi,
+};
+
+/// How often the theme file is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the system preference is checked.  Asking spawns a process, so
+/// this is less frequent than checking the theme file.
+const SYSTEM_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+const THEME_FILE_LOADED: Selector<Result<Option<Arc<ThemeFile>>, Error>> =
+    Selector::new("app.appearance.theme-file-loaded");
+const SYSTEM_DARK_CHANGED: Selector<bool> = Selector::new("app.appearance.system-dark-changed");
+const ACCENT_LOADED: Selector<(Option<Arc<str>>, Option<Color>)> =
+    Selector::new("app.appearance.accent-loaded");
+
+#[derive(Clone, Default)]
+struct WatchTarget {
+    theme_name: Option<String>,
+    follow_system: bool,
+}
+
+/// Keeps `AppState::appearance` up to date: reloads the custom theme file when
+/// it changes on disk, follows the system light or dark preference, and picks
+/// the accent color from the album art.
+pub struct AppearanceController {
+    target: Arc<Mutex<WatchTarget>>,
+    sink: Option<ExtEventSink>,
+    accent_image: Option<Arc<str>>,
+}
+
+impl AppearanceController {
+    pub fn new() -> Self {
+        Self {
+            target: Arc::default(),
+            sink: None,
+            accent_image: None,
+        }
+    }
+
+    fn update_target(&self, data: &AppState) {
+        *self.target.lock() = WatchTarget {
+            theme_name: data.config.custom_theme.clone(),
+            follow_system: data.config.theme == Theme::System,
+        };
+    }
+
+    fn watch(target: Arc<Mutex<WatchTarget>>, sink: ExtEventSink, widget_id: WidgetId) {
+        let mut last_file: Option<(String, Option<PathBuf>, Option<SystemTime>)> = None;
+        let mut last_dark = None;
+        let mut last_system_check: Option<Instant> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let WatchTarget {
+                theme_name,
+                follow_system,
+            } = target.lock().clone();
+
+            let file = theme_name.map(|name| {
+                let path = ThemeFile::path(&name);
+                let modified = path
+                    .as_ref()
+                    .and_then(|path| fs::metadata(path).ok()?.modified().ok());
+                (name, path, modified)
+            });
+            if file != last_file {
+                let loaded = match &file {
+                    Some((name, _, _)) => ThemeFile::load(name).map(|file| file.map(Arc::new)),
+                    None => Ok(None),
+                };
+                last_file = file;
+                if sink
+                    .submit_command(THEME_FILE_LOADED, loaded, Target::Widget(widget_id))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+
+            let system_check_due =
+                last_system_check.is_none_or(|checked| checked.elapsed() >= SYSTEM_POLL_INTERVAL);
+            if !follow_system {
+                // Check right away once following the system again.
+                last_system_check = None;
+            } else if system_check_due {
+                last_system_check = Some(Instant::now());
+                let dark = system_is_dark();
+                if last_dark != Some(dark) {
+                    last_dark = Some(dark);
+                    if sink
+                        .submit_command(SYSTEM_DARK_CHANGED, dark, Target::Widget(widget_id))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn load_accent(&mut self, ctx: &mut UpdateCtx, data: &AppState) {
+        let image = data
+            .playback
+            .now_playing
+            .as_ref()
+            .filter(|_| data.config.album_accent)
+            .and_then(|np| np.cover_image_url(64.0, 64.0))
+            .map(Arc::from);
+        if image == self.accent_image {
+            return;
+        }
+        self.accent_image = image.clone();
+        let Some(uri) = image else {
+            ctx.submit_command(ACCENT_LOADED.with((None, None)).to(ctx.widget_id()));
+            return;
+        };
+        let Some(sink) = self.sink.clone() else {
+            return;
+        };
+        let widget_id = ctx.widget_id();
+        thread::spawn(move || {
+            let accent = WebApi::global()
+                .get_image(uri.clone(), Some(64))
+                .map_err(|err| log::warn!("failed to load album art for the accent: {err}"))
+                .ok()
+                .and_then(|image| accent_color(&image));
+            sink.submit_command(
+                ACCENT_LOADED,
+                (Some(uri), accent),
+                Target::Widget(widget_id),
+            )
+            .ok();
+        });
+    }
+}
+
+impl<W> Controller<AppState, W> for AppearanceController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(THEME_FILE_LOADED) => {
+                match cmd.get_unchecked(THEME_FILE_LOADED) {
+                    Ok(file) => {
+                        if let Some(file) = file {
+                            let unknown = theme::unknown_keys(file);
+                            if !unknown.is_empty() {
+                                log::warn!("unknown keys in theme: {}", unknown.join(", "));
+                            }
+                        }
+                        data.appearance.theme_file = file.clone();
+                    }
+                    Err(err) => {
+                        log::error!("failed to load theme: {err}");
+                        data.error_alert(format!("Failed to load theme: {err}"));
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(SYSTEM_DARK_CHANGED) => {
+                data.appearance.system_dark = *cmd.get_unchecked(SYSTEM_DARK_CHANGED);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(ACCENT_LOADED) => {
+                let (image, accent) = cmd.get_unchecked(ACCENT_LOADED);
+                // Ignore accents of album art that is not playing anymore.
+                if image == &self.accent_image {
+                    data.appearance.accent = *accent;
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_target(data);
+            let target = self.target.clone();
+            let sink = ctx.get_external_handle();
+            let widget_id = ctx.widget_id();
+            self.sink = Some(sink.clone());
+            thread::spawn(move || Self::watch(target, sink, widget_id));
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.config.custom_theme != data.config.custom_theme
+            || old_data.config.theme != data.config.theme
+        {
+            self.update_target(data);
+        }
+        if !old_data
+            .playback
+            .now_playing
+            .same(&data.playback.now_playing)
+            || old_data.config.album_accent != data.config.album_accent
+        {
+            self.load_accent(ctx, data);
+        }
+        child.update(ctx, old_data, data, env);
+    }
+}
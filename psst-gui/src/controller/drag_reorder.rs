@@ -0,0 +1,58 @@
+use druid::{widget::Controller, Cursor, Data, Env, Event, EventCtx, MouseButton, Point, Widget};
+
+/// Distance the mouse has to travel before a press turns into a drag.
+const DRAG_THRESHOLD: f64 = 4.0;
+
+/// Lets a row of a list be dragged over the other rows.  All rows are expected
+/// to be as high as the dragged one, and on drop, the handler receives the
+/// number of rows the mouse has moved by.
+pub struct DragReorder<T> {
+    start: Option<Point>,
+    dragging: bool,
+    handler: Box<dyn Fn(&mut EventCtx, &T, isize)>,
+}
+
+impl<T> DragReorder<T> {
+    pub fn new(handler: impl Fn(&mut EventCtx, &T, isize) + 'static) -> Self {
+        Self {
+            start: None,
+            dragging: false,
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Controller<T, W> for DragReorder<T> {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                self.start = Some(mouse.pos);
+                self.dragging = false;
+            }
+            Event::MouseMove(mouse) => {
+                if let Some(start) = self.start {
+                    if !mouse.buttons.has_left() {
+                        self.start = None;
+                    } else if (mouse.pos - start).hypot() > DRAG_THRESHOLD {
+                        self.dragging = true;
+                        ctx.set_cursor(&Cursor::ResizeUpDown);
+                    }
+                }
+            }
+            Event::MouseUp(mouse) if mouse.button == MouseButton::Left => {
+                let height = ctx.size().height;
+                if self.dragging && height > 0.0 {
+                    ctx.clear_cursor();
+                    let offset = (mouse.pos.y / height).floor() as isize;
+                    if offset != 0 {
+                        (self.handler)(ctx, data, offset);
+                    }
+                }
+                self.start = None;
+                self.dragging = false;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env);
+    }
+}
@@ -0,0 +1,51 @@
+use druid::widget::{prelude::*, Controller};
+
+use crate::{
+    cmd,
+    data::{AppState, KeyAction},
+};
+
+/// Runs the actions bound to key chords in `Config::keymap`.  Needs to wrap
+/// the widget holding the keyboard focus when no text input has it.
+pub struct KeymapController;
+
+impl<W> Controller<AppState, W> for KeymapController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        let action = match event {
+            Event::KeyDown(key) => data.config.keymap.action(key),
+            _ => None,
+        };
+        match action {
+            Some(KeyAction::PlayPause) => ctx.submit_command(cmd::PLAY_PAUSE_OR_RESUME),
+            Some(KeyAction::Next) => ctx.submit_command(cmd::PLAY_NEXT),
+            Some(KeyAction::Previous) => ctx.submit_command(cmd::PLAY_PREVIOUS),
+            Some(KeyAction::Stop) => ctx.submit_command(cmd::PLAY_STOP),
+            Some(KeyAction::SeekForward) => ctx.submit_command(cmd::PLAY_SEEK_FORWARD),
+            Some(KeyAction::SeekBackward) => ctx.submit_command(cmd::PLAY_SEEK_BACKWARD),
+            Some(KeyAction::VolumeUp) => {
+                data.playback.volume = (data.playback.volume + 0.1).min(1.0);
+            }
+            Some(KeyAction::VolumeDown) => {
+                data.playback.volume = (data.playback.volume - 0.1).max(0.0);
+            }
+            Some(KeyAction::ToggleLyrics) => ctx.submit_command(cmd::TOGGLE_LYRICS),
+            Some(KeyAction::NavigateBack) => ctx.submit_command(cmd::NAVIGATE_BACK.with(1)),
+            Some(KeyAction::RefreshAll) => ctx.submit_command(cmd::NAVIGATE_REFRESH_ALL),
+            None => {
+                child.event(ctx, event, data, env);
+                return;
+            }
+        }
+        ctx.set_handled();
+    }
+}
@@ -1,29 +1,37 @@
 mod after_delay;
 mod alert_cleanup;
+mod appearance;
+mod drag_reorder;
 mod ex_click;
 mod ex_cursor;
 mod ex_scroll;
 mod input;
+mod keymap;
 mod nav;
 mod on_command;
 mod on_command_async;
 mod on_debounce;
 mod on_update;
+mod on_visible;
 mod playback;
 mod session;
 mod sort;
 
 pub use after_delay::AfterDelay;
 pub use alert_cleanup::AlertCleanupController;
+pub use appearance::AppearanceController;
+pub use drag_reorder::DragReorder;
 pub use ex_click::ExClick;
 pub use ex_cursor::ExCursor;
 pub use ex_scroll::ExScroll;
 pub use input::InputController;
+pub use keymap::KeymapController;
 pub use nav::NavController;
 pub use on_command::OnCommand;
 pub use on_command_async::OnCommandAsync;
 pub use on_debounce::OnDebounce;
 pub use on_update::OnUpdate;
+pub use on_visible::OnVisible;
 pub use playback::PlaybackController;
 pub use session::SessionController;
 pub use sort::SortController;
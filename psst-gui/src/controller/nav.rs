@@ -1,10 +1,9 @@
 use crate::{
     cmd,
     data::{AppState, Nav, SpotifyUrl},
-    ui::{album, artist, library, lyrics, playlist, recommend, search, show},
+    ui::{album, artist, library, lyrics, playlist, recommend, search, show, smart_playlist},
 };
 use druid::widget::{prelude::*, Controller};
-use druid::Code;
 
 pub struct NavController;
 
@@ -28,6 +27,7 @@ impl NavController {
                     ctx.submit_command(library::LOAD_SHOWS);
                 }
             }
+            Nav::RecentlyPlayed | Nav::Stats => {}
             Nav::SearchResults(query) => {
                 if let Some(link) = SpotifyUrl::parse(query) {
                     ctx.submit_command(search::OPEN_LINK.with(link));
@@ -36,9 +36,7 @@ impl NavController {
                     .results
                     .contains(&(query.clone(), data.search.topic))
                 {
-                    ctx.submit_command(
-                        search::LOAD_RESULTS.with((query.to_owned(), data.search.topic)),
-                    );
+                    ctx.submit_command(search::load_results_command(data, query.clone()));
                 }
             }
             Nav::AlbumDetail(link, _) => {
@@ -58,6 +56,14 @@ impl NavController {
                     );
                 }
             }
+            Nav::SmartPlaylistDetail(link) => {
+                if let Some(playlist) = data.smart_playlist(&link.id).cloned() {
+                    data.smart_playlist_detail.playlist = playlist.clone();
+                    ctx.submit_command(
+                        smart_playlist::LOAD_DETAIL.with(data.smart_playlist_load(playlist)),
+                    );
+                }
+            }
             Nav::ShowDetail(link) => {
                 if !data.show_detail.show.contains(link) {
                     ctx.submit_command(show::LOAD_DETAIL.with(link.to_owned()));
@@ -70,6 +76,27 @@ impl NavController {
             }
         }
     }
+
+    /// Load the current page again if it shows a cached response that has
+    /// just been refreshed.
+    fn reload_route_data(&self, ctx: &mut EventCtx, data: &AppState, bucket: &str, key: &str) {
+        match &data.nav {
+            Nav::AlbumDetail(link, _) if bucket == "album" && key == &*link.id => {
+                ctx.submit_command(album::LOAD_DETAIL.with(link.to_owned()));
+            }
+            Nav::ArtistDetail(link)
+                if bucket == "artist-albums"
+                    || matches!(bucket, "artist" | "artist-info" | "related-artists")
+                        && key == &*link.id =>
+            {
+                ctx.submit_command(artist::LOAD_DETAIL.with(link.to_owned()));
+            }
+            Nav::ShowDetail(link) if bucket == "show" && key == &*link.id => {
+                ctx.submit_command(show::LOAD_DETAIL.with(link.to_owned()));
+            }
+            _ => {}
+        }
+    }
 }
 
 impl<W> Controller<AppState, W> for NavController
@@ -117,12 +144,17 @@ where
                 ctx.set_handled();
                 self.load_route_data(ctx, data);
             }
+            Event::Command(cmd) if cmd.is(cmd::WEBAPI_CACHE_REFRESHED) => {
+                let (bucket, key) = cmd.get_unchecked(cmd::WEBAPI_CACHE_REFRESHED);
+                self.reload_route_data(ctx, data, bucket, key);
+                ctx.set_handled();
+            }
             Event::MouseDown(cmd) if cmd.button.is_x1() => {
                 data.navigate_back();
                 ctx.set_handled();
                 self.load_route_data(ctx, data);
             }
-            Event::KeyDown(key) if key.mods.ctrl() && key.code == Code::KeyR => {
+            Event::Command(cmd) if cmd.is(cmd::NAVIGATE_REFRESH_ALL) => {
                 data.refresh_all();
                 ctx.set_handled();
                 self.load_route_data(ctx, data);
@@ -0,0 +1,33 @@
+use druid::{widget::Controller, Data, Env, LifeCycle, LifeCycleCtx, Widget};
+
+/// Calls the handler whenever the view context of the widget changes while
+/// some part of it is visible, e.g. when it gets scrolled into view.
+pub struct OnVisible<T> {
+    handler: Box<dyn Fn(&mut LifeCycleCtx, &T)>,
+}
+
+impl<T> OnVisible<T> {
+    pub fn new(handler: impl Fn(&mut LifeCycleCtx, &T) + 'static) -> Self {
+        Self {
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Controller<T, W> for OnVisible<T> {
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        if let LifeCycle::ViewContextChanged(view) = event {
+            if view.clip.intersect(ctx.size().to_rect()).area() > 0.0 {
+                (self.handler)(ctx, data);
+            }
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+}
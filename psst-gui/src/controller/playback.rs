@@ -5,19 +5,23 @@ use std::{
 
 use crossbeam_channel::Sender;
 use druid::{
-    im::Vector,
     widget::{prelude::*, Controller},
-    Code, ExtEventSink, InternalLifeCycle, KbKey, WindowHandle,
+    ExtEventSink, InternalLifeCycle, WindowHandle,
 };
 use psst_core::{
     audio::{normalize::NormalizationLevel, output::DefaultAudioOutput},
     cache::Cache,
     cdn::Cdn,
     lastfm::LastFmClient,
-    player::{item::PlaybackItem, PlaybackConfig, Player, PlayerCommand, PlayerEvent},
+    player::{
+        item::PlaybackItem, sleep::SleepTimer, PlaybackConfig, Player, PlayerCommand, PlayerEvent,
+    },
+    scrobble::{
+        listenbrainz::ListenBrainzScrobbler, maloja::MalojaScrobbler, should_scrobble,
+        ScrobbleEntry, ScrobbleQueue, Scrobbler,
+    },
     session::SessionService,
 };
-use rustfm_scrobble::Scrobbler;
 use souvlaki::{
     MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
 };
@@ -25,10 +29,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::{
     cmd,
-    data::Nav,
     data::{
-        AppState, Config, NowPlaying, Playable, Playback, PlaybackOrigin, PlaybackState,
-        QueueBehavior, QueueEntry,
+        AppState, Config, ListenTracker, Playable, Playback, PlaybackOrigin, PlaybackState,
+        QueueBehavior, QueueEntry, Track,
     },
     ui::lyrics,
 };
@@ -39,21 +42,27 @@ pub struct PlaybackController {
     output: Option<DefaultAudioOutput>,
     media_controls: Option<MediaControls>,
     has_scrobbled: bool,
-    scrobbler: Option<Scrobbler>,
+    scrobble_queue: Option<ScrobbleQueue>,
+    listen: Option<ListenTracker>,
     startup: bool,
 }
-fn init_scrobbler_instance(data: &AppState) -> Option<Scrobbler> {
+fn init_lastfm_scrobbler(data: &AppState, proxy_url: Option<&str>) -> Option<Box<dyn Scrobbler>> {
     if data.config.lastfm_enable {
         if let (Some(api_key), Some(api_secret), Some(session_key)) = (
             data.config.lastfm_api_key.as_deref(),
             data.config.lastfm_api_secret.as_deref(),
             data.config.lastfm_session_key.as_deref(),
         ) {
-            match LastFmClient::create_scrobbler(Some(api_key), Some(api_secret), Some(session_key))
-            {
+            match LastFmClient::create_scrobbler(
+                data.config.lastfm_url.as_deref(),
+                Some(api_key),
+                Some(api_secret),
+                Some(session_key),
+                proxy_url,
+            ) {
                 Ok(scr) => {
                     log::info!("Last.fm Scrobbler instance created/updated.");
-                    return Some(scr);
+                    return Some(Box::new(scr));
                 }
                 Err(e) => {
                     log::warn!("Failed to create/update Last.fm Scrobbler instance: {e}");
@@ -68,6 +77,35 @@ fn init_scrobbler_instance(data: &AppState) -> Option<Scrobbler> {
     None
 }
 
+fn init_scrobblers(data: &AppState) -> Vec<Box<dyn Scrobbler>> {
+    let config = &data.config;
+    let proxy_url = Config::proxy();
+    let mut scrobblers = Vec::new();
+    scrobblers.extend(init_lastfm_scrobbler(data, proxy_url.as_deref()));
+    if config.listenbrainz_enable {
+        if let Some(token) = config.listenbrainz_token.as_deref() {
+            scrobblers.push(Box::new(ListenBrainzScrobbler::new(
+                config.listenbrainz_url.as_deref(),
+                token,
+                proxy_url.as_deref(),
+            )));
+        }
+    }
+    if config.maloja_enable {
+        if let (Some(url), Some(api_key)) = (
+            config.maloja_url.as_deref(),
+            config.maloja_api_key.as_deref(),
+        ) {
+            scrobblers.push(Box::new(MalojaScrobbler::new(
+                url,
+                api_key,
+                proxy_url.as_deref(),
+            )));
+        }
+    }
+    scrobblers
+}
+
 impl PlaybackController {
     pub fn new() -> Self {
         Self {
@@ -76,7 +114,8 @@ impl PlaybackController {
             output: None,
             media_controls: None,
             has_scrobbled: false,
-            scrobbler: None,
+            scrobble_queue: None,
+            listen: None,
             startup: true,
         }
     }
@@ -87,7 +126,8 @@ impl PlaybackController {
         config: PlaybackConfig,
         event_sink: ExtEventSink,
         widget_id: WidgetId,
-        #[allow(unused_variables)] window: &WindowHandle,
+        window: &WindowHandle,
+        media_keys: bool,
     ) {
         let output = DefaultAudioOutput::open().unwrap();
         let cache_dir = Config::cache_dir().unwrap();
@@ -100,11 +140,10 @@ impl PlaybackController {
             &output,
         );
 
-        self.media_controls = Self::create_media_controls(player.sender(), window)
-            .map_err(|err| log::error!("failed to connect to media control interface: {err:?}"))
-            .ok();
-
         self.sender = Some(player.sender());
+        if media_keys {
+            self.connect_media_controls(window);
+        }
         self.thread = Some(thread::spawn(move || {
             Self::service_events(player, event_sink, widget_id);
         }));
@@ -147,11 +186,30 @@ impl PlaybackController {
                         .submit_command(cmd::PLAYBACK_BLOCKED, (), widget_id)
                         .unwrap();
                 }
+                PlayerEvent::EndOfTrack => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_ENDED, (), widget_id)
+                        .unwrap();
+                }
                 PlayerEvent::Stopped => {
                     event_sink
                         .submit_command(cmd::PLAYBACK_STOPPED, (), widget_id)
                         .unwrap();
                 }
+                PlayerEvent::SleepTimer { remaining } => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_SLEEP_TIMER, remaining.to_owned(), widget_id)
+                        .unwrap();
+                }
+                PlayerEvent::TimeSaved { path, saved } => {
+                    event_sink
+                        .submit_command(
+                            cmd::PLAYBACK_TIME_SAVED,
+                            (path.item_id, saved.to_owned()),
+                            widget_id,
+                        )
+                        .unwrap();
+                }
                 _ => {}
             }
 
@@ -160,6 +218,14 @@ impl PlaybackController {
         }
     }
 
+    fn connect_media_controls(&mut self, window: &WindowHandle) {
+        if let Some(sender) = self.sender.clone() {
+            self.media_controls = Self::create_media_controls(sender, window)
+                .map_err(|err| log::error!("failed to connect to media control interface: {err:?}"))
+                .ok();
+        }
+    }
+
     fn create_media_controls(
         sender: Sender<PlayerEvent>,
         #[allow(unused_variables)] window: &WindowHandle,
@@ -265,23 +331,8 @@ impl PlaybackController {
     fn report_now_playing(&mut self, playback: &Playback) {
         if let Some(now_playing) = playback.now_playing.as_ref() {
             if let Playable::Track(track) = &now_playing.item {
-                if let Some(scrobbler) = &self.scrobbler {
-                    let artist = track.artist_name();
-                    let title = track.name.clone();
-                    let album = track.album.clone();
-
-                    if let Err(e) = LastFmClient::now_playing_song(
-                        scrobbler,
-                        artist.as_ref(),
-                        title.as_ref(),
-                        album.as_ref().map(|a| a.name.as_ref()),
-                    ) {
-                        log::warn!("failed to report 'Now Playing' to Last.fm: {e}");
-                    } else {
-                        log::info!("reported 'Now Playing' to Last.fm: {artist} - {title}");
-                    }
-                } else {
-                    log::debug!("Last.fm not configured, skipping now_playing report.");
+                if let Some(queue) = &self.scrobble_queue {
+                    queue.now_playing(scrobble_entry(track, now_playing.progress));
                 }
             }
         }
@@ -290,39 +341,48 @@ impl PlaybackController {
     fn report_scrobble(&mut self, playback: &Playback) {
         if let Some(now_playing) = playback.now_playing.as_ref() {
             if let Playable::Track(track) = &now_playing.item {
-                if now_playing.progress >= track.duration / 2 && !self.has_scrobbled {
-                    if let Some(scrobbler) = &self.scrobbler {
-                        let artist = track.artist_name();
-                        let title = track.name.clone();
-                        let album = track.album.clone();
-
-                        if let Err(e) = LastFmClient::scrobble_song(
-                            scrobbler,
-                            artist.as_ref(),
-                            title.as_ref(),
-                            album.as_ref().map(|a| a.name.as_ref()),
-                        ) {
-                            log::warn!("failed to scrobble track to Last.fm: {e}");
-                        } else {
-                            log::info!("scrobbled track to Last.fm: {artist} - {title}");
-                            self.has_scrobbled = true;
-                        }
-                    } else {
-                        log::debug!("Last.fm not configured, skipping scrobble.");
+                if !self.has_scrobbled && should_scrobble(track.duration, now_playing.progress) {
+                    if let Some(queue) = &self.scrobble_queue {
+                        queue.scrobble(scrobble_entry(track, now_playing.progress));
+                        self.has_scrobbled = true;
                     }
                 }
             }
         }
     }
 
-    fn play(&mut self, items: &Vector<QueueEntry>, position: usize) {
-        let playback_items = items.iter().map(|queued| PlaybackItem {
-            item_id: queued.item.id(),
-            norm_level: match queued.origin {
-                PlaybackOrigin::Album(_) => NormalizationLevel::Album,
-                _ => NormalizationLevel::Track,
-            },
-        });
+    /// Record the listen in progress into the listening history.
+    fn finish_listen(&mut self, data: &mut AppState, skipped: bool) {
+        if let Some(listen) = self.listen.take().and_then(|listen| listen.finish(skipped)) {
+            data.record_listen(listen);
+        }
+    }
+
+    fn update_scrobblers(&mut self, data: &AppState) {
+        if let Some(queue) = &self.scrobble_queue {
+            queue.set_scrobblers(init_scrobblers(data));
+        }
+    }
+
+    fn play(&mut self, data: &AppState, position: usize, start_position: Duration) {
+        let playback_items = data
+            .playback
+            .queue
+            .iter()
+            .enumerate()
+            .map(|(index, queued)| PlaybackItem {
+                item_id: queued.item.id(),
+                norm_level: match queued.origin {
+                    PlaybackOrigin::Album(_) => NormalizationLevel::Album,
+                    _ => NormalizationLevel::Track,
+                },
+                trim: data.playback_trim_for(&queued.item),
+                start_position: if index == position && !start_position.is_zero() {
+                    start_position
+                } else {
+                    data.resume_position_for(&queued.item)
+                },
+            });
         let playback_items_vec: Vec<PlaybackItem> = playback_items.collect();
 
         // Make sure position is within bounds
@@ -386,6 +446,25 @@ impl PlaybackController {
         self.send(PlayerEvent::Command(PlayerCommand::SetVolume { volume }));
     }
 
+    fn set_speed(&mut self, speed: f64) {
+        self.send(PlayerEvent::Command(PlayerCommand::SetSpeed {
+            speed: speed as f32,
+        }));
+    }
+
+    fn set_sleep_timer(&mut self, timer: Option<SleepTimer>, fade_out: Duration) {
+        self.send(PlayerEvent::Command(match timer {
+            Some(timer) => PlayerCommand::SetSleepTimer { timer, fade_out },
+            None => PlayerCommand::CancelSleepTimer,
+        }));
+    }
+
+    fn set_skip_silence(&mut self, enabled: bool) {
+        self.send(PlayerEvent::Command(PlayerCommand::SetSkipSilence {
+            enabled,
+        }));
+    }
+
     fn add_to_queue(&mut self, item: &PlaybackItem) {
         self.send(PlayerEvent::Command(PlayerCommand::AddToQueue {
             item: *item,
@@ -403,9 +482,14 @@ impl PlaybackController {
         }));
     }
 
-    fn update_lyrics(&mut self, ctx: &mut EventCtx, data: &AppState, now_playing: &NowPlaying) {
-        if matches!(data.nav, Nav::Lyrics) {
-            ctx.submit_command(lyrics::SHOW_LYRICS.with(now_playing.clone()));
+    fn update_lyrics(&mut self, ctx: &mut EventCtx, data: &mut AppState) {
+        if let Some(now_playing) = &data.playback.now_playing {
+            if lyrics::are_lyrics_shown(data) {
+                ctx.submit_command(lyrics::SHOW_LYRICS.with(now_playing.clone()));
+            } else {
+                // Don't keep the lyrics of the previous track around.
+                data.lyrics.clear();
+            }
         }
     }
 }
@@ -430,6 +514,14 @@ where
                 let item = cmd.get_unchecked(cmd::PLAYBACK_LOADING);
 
                 if let Some(queued) = data.queued_entry(*item) {
+                    // Apply the speed before the item starts playing, so episodes do not
+                    // momentarily play at the wrong speed.
+                    let speed = data.playback_speed_for(&queued.item);
+                    data.playback.speed = speed;
+                    self.set_speed(speed);
+                    self.set_skip_silence(data.skip_silence_for(&queued.item));
+                    self.finish_listen(data, true);
+                    data.record_episode_progress();
                     data.loading_playback(queued.item, queued.origin);
                     self.update_media_control_playback(&data.playback);
                     self.update_media_control_metadata(&data.playback);
@@ -446,12 +538,16 @@ where
                 self.report_now_playing(&data.playback);
 
                 if let Some(queued) = data.queued_entry(*item) {
+                    self.finish_listen(data, true);
+                    self.listen = Some(ListenTracker::start(
+                        &queued.item,
+                        queued.origin.clone(),
+                        progress.to_owned(),
+                    ));
                     data.start_playback(queued.item, queued.origin, progress.to_owned());
                     self.update_media_control_playback(&data.playback);
                     self.update_media_control_metadata(&data.playback);
-                    if let Some(now_playing) = &data.playback.now_playing {
-                        self.update_lyrics(ctx, data, now_playing);
-                    }
+                    self.update_lyrics(ctx, data);
                 } else {
                     log::warn!("played item not found in playback queue");
                 }
@@ -460,6 +556,12 @@ where
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_PROGRESS) => {
                 let progress = cmd.get_unchecked(cmd::PLAYBACK_PROGRESS);
                 data.progress_playback(progress.to_owned());
+                if let Some(lyrics) = data.lyrics.resolved_mut() {
+                    lyrics.set_progress(progress.to_owned());
+                }
+                if let Some(listen) = &mut self.listen {
+                    listen.progress(progress.to_owned());
+                }
 
                 self.report_scrobble(&data.playback);
                 self.update_media_control_playback(&data.playback);
@@ -467,6 +569,7 @@ where
             }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_PAUSING) => {
                 data.pause_playback();
+                data.record_episode_progress();
                 self.update_media_control_playback(&data.playback);
                 ctx.set_handled();
             }
@@ -479,11 +582,42 @@ where
                 data.block_playback();
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::PLAYBACK_ENDED) => {
+                self.finish_listen(data, false);
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_STOPPED) => {
+                self.finish_listen(data, true);
+                data.record_episode_progress();
                 data.stop_playback();
                 self.update_media_control_playback(&data.playback);
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::PLAYBACK_TIME_SAVED) => {
+                let (item, saved) = cmd.get_unchecked(cmd::PLAYBACK_TIME_SAVED);
+
+                if let Some(queued) = data.queued_entry(*item) {
+                    data.add_time_saved(&queued.item, saved.to_owned());
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::SCROBBLER_UNAUTHORIZED) => {
+                let name = match *cmd.get_unchecked(cmd::SCROBBLER_UNAUTHORIZED) {
+                    "lastfm" => "Last.fm",
+                    "listenbrainz" => "ListenBrainz",
+                    "maloja" => "Maloja",
+                    id => id,
+                };
+                data.error_alert(format!(
+                    "{name} refused the credentials, scrobbling is paused. Check them in Preferences."
+                ));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::PLAYBACK_SLEEP_TIMER) => {
+                let remaining = cmd.get_unchecked(cmd::PLAYBACK_SLEEP_TIMER);
+                data.playback.sleep_timer = remaining.to_owned();
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAY_TRACKS) => {
                 let payload = cmd.get_unchecked(cmd::PLAY_TRACKS);
                 data.playback.queue = payload
@@ -495,13 +629,17 @@ where
                     })
                     .collect();
 
-                self.play(&data.playback.queue, payload.position);
+                self.play(data, payload.position, payload.start_position);
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAY_PAUSE) => {
                 self.pause();
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::PLAY_PAUSE_OR_RESUME) => {
+                self.pause_or_resume();
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAY_RESUME) => {
                 self.resume();
                 ctx.set_handled();
@@ -526,6 +664,13 @@ where
                 data.add_queued_entry(entry.clone());
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::ADD_ALL_TO_QUEUE) => {
+                for (entry, item) in cmd.get_unchecked(cmd::ADD_ALL_TO_QUEUE) {
+                    self.add_to_queue(item);
+                    data.add_queued_entry(entry.clone());
+                }
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAY_QUEUE_BEHAVIOR) => {
                 let behavior = cmd.get_unchecked(cmd::PLAY_QUEUE_BEHAVIOR);
                 data.set_queue_behavior(behavior.to_owned());
@@ -542,38 +687,29 @@ where
                 }
                 ctx.set_handled();
             }
-            Event::Command(cmd) if cmd.is(cmd::SKIP_TO_POSITION) => {
-                let location = cmd.get_unchecked(cmd::SKIP_TO_POSITION);
-                self.seek(Duration::from_millis(*location));
-                ctx.set_handled();
-            }
-            // Keyboard shortcuts.
-            Event::KeyDown(key) if key.code == Code::Space => {
-                self.pause_or_resume();
+            Event::Command(cmd) if cmd.is(cmd::PLAY_SLEEP_TIMER) => {
+                let timer = cmd.get_unchecked(cmd::PLAY_SLEEP_TIMER);
+                let fade_out = Duration::from_secs(data.config.sleep_fade_out as u64);
+                self.set_sleep_timer(*timer, fade_out);
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.code == Code::ArrowRight => {
-                if key.mods.shift() {
-                    self.next();
-                } else {
-                    self.seek_relative(data, true);
-                }
+            Event::Command(cmd) if cmd.is(cmd::PLAY_SPEED) => {
+                let speed = cmd.get_unchecked(cmd::PLAY_SPEED);
+                data.set_playback_speed(*speed);
+                self.set_speed(*speed);
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.code == Code::ArrowLeft => {
-                if key.mods.shift() {
-                    self.previous();
-                } else {
-                    self.seek_relative(data, false);
-                }
+            Event::Command(cmd) if cmd.is(cmd::SKIP_TO_POSITION) => {
+                let location = cmd.get_unchecked(cmd::SKIP_TO_POSITION);
+                self.seek(Duration::from_millis(*location));
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.key == KbKey::Character("+".to_string()) => {
-                data.playback.volume = (data.playback.volume + 0.1).min(1.0);
+            Event::Command(cmd) if cmd.is(cmd::PLAY_SEEK_FORWARD) => {
+                self.seek_relative(data, true);
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.key == KbKey::Character("-".to_string()) => {
-                data.playback.volume = (data.playback.volume - 0.1).max(0.0);
+            Event::Command(cmd) if cmd.is(cmd::PLAY_SEEK_BACKWARD) => {
+                self.seek_relative(data, false);
                 ctx.set_handled();
             }
             _ => child.event(ctx, event, data, env),
@@ -596,6 +732,7 @@ where
                     ctx.get_external_handle(),
                     ctx.widget_id(),
                     ctx.window(),
+                    data.config.media_keys,
                 );
 
                 // Initialize values loaded from the config.
@@ -614,7 +751,19 @@ where
         }
         if self.startup {
             self.startup = false;
-            self.scrobbler = init_scrobbler_instance(data);
+            let event_sink = ctx.get_external_handle();
+            let widget_id = ctx.widget_id();
+            self.scrobble_queue = Config::scrobble_journal_dir().map(|dir| {
+                ScrobbleQueue::new(
+                    dir,
+                    Box::new(move |id| {
+                        event_sink
+                            .submit_command(cmd::SCROBBLER_UNAUTHORIZED, id, widget_id)
+                            .ok();
+                    }),
+                )
+            });
+            self.update_scrobblers(data);
         }
         child.lifecycle(ctx, event, data, env);
     }
@@ -631,19 +780,51 @@ where
             self.set_volume(data.playback.volume);
         }
 
+        if old_data.config.media_keys != data.config.media_keys {
+            if data.config.media_keys {
+                self.connect_media_controls(ctx.window());
+                self.update_media_control_playback(&data.playback);
+                self.update_media_control_metadata(&data.playback);
+            } else {
+                self.media_controls = None;
+            }
+        }
+
+        if old_data.config.skip_silence != data.config.skip_silence {
+            if let Some(now_playing) = &data.playback.now_playing {
+                self.set_skip_silence(data.skip_silence_for(&now_playing.item));
+            }
+        }
+
         let lastfm_changed = old_data.config.lastfm_api_key != data.config.lastfm_api_key
             || old_data.config.lastfm_api_secret != data.config.lastfm_api_secret
             || old_data.config.lastfm_session_key != data.config.lastfm_session_key
             || old_data.config.lastfm_enable != data.config.lastfm_enable;
-
-        if lastfm_changed {
-            self.scrobbler = init_scrobbler_instance(data);
+        let listenbrainz_changed = old_data.config.listenbrainz_enable
+            != data.config.listenbrainz_enable
+            || old_data.config.listenbrainz_token != data.config.listenbrainz_token
+            || old_data.config.listenbrainz_url != data.config.listenbrainz_url;
+        let maloja_changed = old_data.config.maloja_enable != data.config.maloja_enable
+            || old_data.config.maloja_url != data.config.maloja_url
+            || old_data.config.maloja_api_key != data.config.maloja_api_key;
+
+        if lastfm_changed || listenbrainz_changed || maloja_changed {
+            self.update_scrobblers(data);
         }
 
         child.update(ctx, old_data, data, env);
     }
 }
 
+fn scrobble_entry(track: &Track, played: Duration) -> ScrobbleEntry {
+    ScrobbleEntry::new(
+        track.artist_name().to_string(),
+        track.name.to_string(),
+        track.album.as_ref().map(|album| album.name.to_string()),
+        played,
+    )
+}
+
 // This uses the current system time to generate a random lowercase string of a given length.
 fn random_lowercase_string(len: usize) -> String {
     let now = SystemTime::now()
@@ -75,6 +75,7 @@ impl Album {
             id: self.id.clone(),
             name: self.name.clone(),
             images: self.images.clone(),
+            release_year: self.release_date.map(|date| date.year()),
         }
     }
 
@@ -101,6 +102,9 @@ pub struct AlbumLink {
     pub name: Arc<str>,
     #[serde(default)]
     pub images: Vector<Image>,
+    #[serde(default, alias = "release_date")]
+    #[serde(deserialize_with = "super::utils::deserialize_release_year")]
+    pub release_year: Option<i32>,
 }
 
 impl AlbumLink {
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, fmt, fs, path::PathBuf, process::Command, sync::Arc};
+
+use druid::{Color, Data, ImageBuf, Lens};
+use serde::Deserialize;
+
+use super::{Config, Theme};
+use crate::error::Error;
+
+const THEMES_DIRNAME: &str = "themes";
+
+/// Inputs of the theme coming from outside of the config.
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct Appearance {
+    /// The system prefers dark colors, used for `Theme::System`.
+    pub system_dark: bool,
+    /// Custom theme loaded from the themes directory.
+    #[data(same_fn = "PartialEq::eq")]
+    pub theme_file: Option<Arc<ThemeFile>>,
+    /// Accent color picked from the album art of the playing item.
+    pub accent: Option<Color>,
+}
+
+impl Appearance {
+    pub fn load(config: &Config) -> Self {
+        let theme_file = config.custom_theme.as_deref().and_then(|name| {
+            ThemeFile::load(name)
+                .map_err(|err| log::error!("failed to load theme {name}: {err}"))
+                .ok()
+                .flatten()
+        });
+        Self {
+            system_dark: system_is_dark(),
+            theme_file: theme_file.map(Arc::new),
+            accent: None,
+        }
+    }
+
+    pub fn is_dark(&self, theme: Theme) -> bool {
+        match theme {
+            Theme::Light => false,
+            Theme::Dark => true,
+            Theme::System => self.system_dark,
+        }
+    }
+}
+
+/// Theme file in the `themes` directory of the config, in TOML or JSON.  Keys
+/// are named after the constants in `ui::theme`, in lowercase:
+///
+/// ```toml
+/// base = "Dark"
+///
+/// [colors]
+/// grey_700 = "#1e1e2e"
+/// blue_200 = "#f5c2e7"
+///
+/// [sizes]
+/// text_size_normal = 14.0
+///
+/// [fonts]
+/// ui_font = { family = "Inter", size = 14.0 }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThemeFile {
+    /// Built-in palette the theme starts from, the configured one if missing.
+    pub base: Option<Theme>,
+    /// Colors as `#rrggbb` or `#rrggbbaa`.
+    pub colors: HashMap<String, String>,
+    pub sizes: HashMap<String, f64>,
+    pub fonts: HashMap<String, FontSpec>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FontSpec {
+    pub family: Option<String>,
+    pub size: Option<f64>,
+    pub weight: Option<u16>,
+}
+
+impl ThemeFile {
+    pub fn themes_dir() -> Option<PathBuf> {
+        Config::config_dir().map(|dir| dir.join(THEMES_DIRNAME))
+    }
+
+    /// Path of the theme called `name`, preferring TOML over JSON.
+    pub fn path(name: &str) -> Option<PathBuf> {
+        let dir = Self::themes_dir()?;
+        ["toml", "json"]
+            .into_iter()
+            .map(|ext| dir.join(format!("{name}.{ext}")))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the theme called `name`, `None` if there is no such file.
+    pub fn load(name: &str) -> Result<Option<Self>, Error> {
+        let Some(path) = Self::path(name) else {
+            return Ok(None);
+        };
+        let error =
+            |err: &dyn fmt::Display| Error::ThemeError(format!("{}: {err}", path.display()));
+        let contents = fs::read_to_string(&path).map_err(|err| error(&err))?;
+        let theme = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|err| error(&err))?
+        } else {
+            toml::from_str(&contents).map_err(|err| error(&err))?
+        };
+        Ok(Some(theme))
+    }
+}
+
+/// Whether the desktop is set to prefer dark colors.  Druid doesn't tell, so
+/// ask the platform settings.
+pub fn system_is_dark() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        command_output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+            .is_some_and(|out| out.trim() == "Dark")
+    }
+    #[cfg(target_os = "windows")]
+    {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )
+        .is_some_and(|out| out.contains("0x0"))
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "color-scheme"],
+        )
+        .is_some_and(|out| out.contains("dark"))
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // Don't flash a console window.
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Pick an accent color from an image, weighing the pixels by how vivid they
+/// are.  Returns `None` for mostly grey images.
+pub fn accent_color(image: &ImageBuf) -> Option<Color> {
+    let mut sum = (0.0, 0.0, 0.0);
+    let mut total_weight = 0.0;
+    let mut count = 0;
+    for color in image.pixel_colors().flatten() {
+        let (r, g, b, _) = color.as_rgba();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+        // Prefer saturated, not too dark colors.
+        let weight = chroma * chroma * max;
+        sum.0 += r * weight;
+        sum.1 += g * weight;
+        sum.2 += b * weight;
+        total_weight += weight;
+        count += 1;
+    }
+    if count == 0 || total_weight / (count as f64) < 0.01 {
+        return None;
+    }
+    Some(Color::rgb(
+        sum.0 / total_weight,
+        sum.1 / total_weight,
+        sum.2 / total_weight,
+    ))
+}
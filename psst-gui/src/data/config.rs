@@ -1,25 +1,28 @@
 use std::{
+    collections::HashMap,
     env::{self, VarError},
     fs::{self, File, OpenOptions},
     io::{BufReader, BufWriter},
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::OpenOptionsExt;
 
-use druid::{Data, Lens, Size};
+use druid::{im::Vector, Data, Lens, Point, Size};
 use platform_dirs::AppDirs;
 use psst_core::{
     cache::{mkdir_if_not_exists, CacheHandle},
     connection::Credentials,
     oauth::{self, WebApiToken},
-    player::PlaybackConfig,
+    player::{item::PlaybackTrim, sleep::DEFAULT_SLEEP_FADE_OUT, PlaybackConfig},
     session::{SessionConfig, SessionConnection},
 };
 use serde::{Deserialize, Serialize};
 
-use super::{Nav, Promise, QueueBehavior, SliderScrollScale};
+use super::{KeyAction, Keymap, Nav, Promise, QueueBehavior, SliderScrollScale, SmartPlaylist};
 use crate::ui::theme;
 
 #[derive(Clone, Debug, Data, Lens)]
@@ -30,6 +33,8 @@ pub struct Preferences {
     pub cache_size: Promise<u64, (), ()>,
     pub auth: Authentication,
     pub lastfm_auth_result: Option<String>,
+    /// Action waiting for a key chord to be pressed in the Shortcuts tab.
+    pub recording_shortcut: Option<KeyAction>,
 }
 
 impl Preferences {
@@ -38,6 +43,7 @@ impl Preferences {
         self.auth.result.clear();
         self.auth.lastfm_api_key_input.clear();
         self.auth.lastfm_api_secret_input.clear();
+        self.recording_shortcut = None;
     }
 
     pub fn measure_cache_usage() -> Option<u64> {
@@ -50,6 +56,7 @@ pub enum PreferencesTab {
     General,
     Account,
     Cache,
+    Shortcuts,
     About,
 }
 
@@ -104,8 +111,26 @@ impl Authentication {
 
 const APP_NAME: &str = "Psst";
 const CONFIG_FILENAME: &str = "config.json";
+const SCROBBLE_JOURNAL_DIRNAME: &str = "scrobbles";
 const PROXY_ENV_VAR: &str = "SOCKS_PROXY";
 
+/// Lengths of a podcast show's intro and outro to skip, in seconds.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShowTrim {
+    pub intro_secs: u64,
+    pub outro_secs: u64,
+}
+
+impl ShowTrim {
+    pub fn to_playback_trim(self) -> PlaybackTrim {
+        PlaybackTrim {
+            intro: Duration::from_secs(self.intro_secs),
+            outro: Duration::from_secs(self.outro_secs),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
@@ -113,20 +138,73 @@ pub struct Config {
     credentials: Option<Credentials>,
     pub audio_quality: AudioQuality,
     pub theme: Theme,
+    /// Name of a theme file in the themes directory, applied over `theme`.
+    pub custom_theme: Option<String>,
+    /// Tint the accent color after the album art of the playing item.
+    pub album_accent: bool,
     pub volume: f64,
     pub last_route: Option<Nav>,
     pub queue_behavior: QueueBehavior,
     pub show_track_cover: bool,
+    /// Show the current line of synced lyrics in the playback bar.
+    pub show_lyrics_overlay: bool,
     pub window_size: Size,
+    pub mini_player_size: Size,
+    /// Last position of the mini player window, placed by the system if not
+    /// set.
+    pub mini_player_position: Option<Point>,
+    /// Keep the mini player above other windows.
+    pub mini_player_on_top: bool,
+    /// Hide the main window while the mini player is open.
+    pub mini_player_hides_main: bool,
     pub slider_scroll_scale: SliderScrollScale,
     pub sort_order: SortOrder,
     pub sort_criteria: SortCriteria,
     pub paginated_limit: usize,
     pub seek_duration: usize,
+    /// Keyboard shortcuts of the main window.
+    pub keymap: Keymap,
+    /// Control the playback from the system media keys and media controls.
+    pub media_keys: bool,
+    /// Length of the volume fade-out before the sleep timer stops the
+    /// playback, in seconds.
+    pub sleep_fade_out: usize,
+    /// Playback speed remembered for each podcast show, by show ID.
+    #[data(ignore)]
+    pub show_playback_speeds: HashMap<Arc<str>, f64>,
+    /// Shorten long silences when playing podcast episodes.
+    pub skip_silence: bool,
+    /// Intro and outro lengths to skip for each podcast show, by show ID.
+    #[data(same_fn = "PartialEq::eq")]
+    pub show_trims: HashMap<Arc<str>, ShowTrim>,
+    /// Playback time saved by skipping silence and trimming, by show ID.
+    #[data(same_fn = "PartialEq::eq")]
+    pub show_time_saved: HashMap<Arc<str>, Duration>,
+    /// Locally defined rule-based playlists.
+    pub smart_playlists: Vector<SmartPlaylist>,
+    /// Previous search queries, most recent first.
+    pub search_history: Vector<Arc<str>>,
     pub lastfm_session_key: Option<String>,
     pub lastfm_api_key: Option<String>,
     pub lastfm_api_secret: Option<String>,
     pub lastfm_enable: bool,
+    /// Root of a Last.fm-compatible API, like Libre.fm, the official one is
+    /// used if not set.
+    pub lastfm_url: Option<String>,
+    /// Submit listens to ListenBrainz.
+    pub listenbrainz_enable: bool,
+    pub listenbrainz_token: Option<String>,
+    /// Root of a self-hosted ListenBrainz API, the official one is used if
+    /// not set.
+    pub listenbrainz_url: Option<String>,
+    /// Submit scrobbles to a Maloja server.
+    pub maloja_enable: bool,
+    pub maloja_url: Option<String>,
+    pub maloja_api_key: Option<String>,
+    /// Look up lyrics missing elsewhere on LRCLIB.
+    pub lrclib_enable: bool,
+    /// Root of an LRCLIB-compatible API, the official one is used if not set.
+    pub lrclib_url: Option<String>,
     /// User-provided Spotify Developer Client ID for Web API calls.
     /// Register one at https://developer.spotify.com/dashboard
     pub webapi_client_id: Option<String>,
@@ -141,20 +219,45 @@ impl Default for Config {
             credentials: Default::default(),
             audio_quality: Default::default(),
             theme: Default::default(),
+            custom_theme: None,
+            album_accent: false,
             volume: 1.0,
             last_route: Default::default(),
             queue_behavior: Default::default(),
             show_track_cover: Default::default(),
+            show_lyrics_overlay: false,
             window_size: Size::new(theme::grid(80.0), theme::grid(100.0)),
+            mini_player_size: Size::new(theme::grid(45.0), theme::grid(17.0)),
+            mini_player_position: None,
+            mini_player_on_top: true,
+            mini_player_hides_main: false,
             slider_scroll_scale: Default::default(),
             sort_order: Default::default(),
             sort_criteria: Default::default(),
             paginated_limit: 500,
             seek_duration: 10,
+            keymap: Keymap::default(),
+            media_keys: true,
+            sleep_fade_out: DEFAULT_SLEEP_FADE_OUT.as_secs() as usize,
+            show_playback_speeds: HashMap::new(),
+            skip_silence: false,
+            show_trims: HashMap::new(),
+            show_time_saved: HashMap::new(),
+            smart_playlists: Vector::new(),
+            search_history: Vector::new(),
             lastfm_session_key: None,
             lastfm_api_key: None,
             lastfm_api_secret: None,
             lastfm_enable: false,
+            lastfm_url: None,
+            listenbrainz_enable: false,
+            listenbrainz_token: None,
+            listenbrainz_url: None,
+            maloja_enable: false,
+            maloja_url: None,
+            maloja_api_key: None,
+            lrclib_enable: false,
+            lrclib_url: None,
             webapi_client_id: None,
             webapi_token: None,
         }
@@ -187,6 +290,10 @@ impl Config {
         Self::config_dir().map(|dir| dir.join(CONFIG_FILENAME))
     }
 
+    pub fn scrobble_journal_dir() -> Option<PathBuf> {
+        Self::config_dir().map(|dir| dir.join(SCROBBLE_JOURNAL_DIRNAME))
+    }
+
     pub fn load() -> Option<Config> {
         let path = Self::config_path().expect("Failed to get config path");
         if let Ok(file) = File::open(&path) {
@@ -291,6 +398,44 @@ impl Config {
         }
     }
 
+    pub fn show_playback_speed(&self, show_id: &str) -> f64 {
+        self.show_playback_speeds
+            .get(show_id)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn set_show_playback_speed(&mut self, show_id: Arc<str>, speed: f64) {
+        if speed == 1.0 {
+            self.show_playback_speeds.remove(&show_id);
+        } else {
+            self.show_playback_speeds.insert(show_id, speed);
+        }
+    }
+
+    pub fn show_trim(&self, show_id: &str) -> ShowTrim {
+        self.show_trims.get(show_id).copied().unwrap_or_default()
+    }
+
+    pub fn set_show_trim(&mut self, show_id: Arc<str>, trim: ShowTrim) {
+        if trim == ShowTrim::default() {
+            self.show_trims.remove(&show_id);
+        } else {
+            self.show_trims.insert(show_id, trim);
+        }
+    }
+
+    pub fn show_time_saved(&self, show_id: &str) -> Duration {
+        self.show_time_saved
+            .get(show_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn add_show_time_saved(&mut self, show_id: Arc<str>, saved: Duration) {
+        *self.show_time_saved.entry(show_id).or_default() += saved;
+    }
+
     pub fn proxy() -> Option<String> {
         env::var(PROXY_ENV_VAR).map_or_else(
             |err| match err {
@@ -328,6 +473,8 @@ pub enum Theme {
     #[default]
     Light,
     Dark,
+    /// Follow the light or dark preference of the system.
+    System,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize, Default)]
@@ -0,0 +1,135 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use druid::{im::Vector, Data, Lens};
+
+use crate::data::{utils::normalize_name, PlaylistLink, Track};
+
+/// Largest difference in duration of two recordings considered to be the same.
+const DURATION_TOLERANCE: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, Data)]
+pub enum DuplicateSource {
+    Playlist(PlaylistLink),
+    SavedTracks,
+}
+
+impl DuplicateSource {
+    pub fn name(&self) -> Arc<str> {
+        match self {
+            Self::Playlist(link) => link.name.clone(),
+            Self::SavedTracks => "Saved Tracks".into(),
+        }
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct DuplicateEntry {
+    pub track: Arc<Track>,
+    pub remove: bool,
+}
+
+/// Tracks considered to be the same recording, in the order they were added.
+#[derive(Clone, Data, Lens)]
+pub struct DuplicateGroup {
+    pub entries: Vector<DuplicateEntry>,
+    /// All the entries have the same track ID.
+    pub exact: bool,
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct Duplicates {
+    pub source: DuplicateSource,
+    /// Version of the playlist the track positions refer to.
+    pub snapshot_id: Option<Arc<str>>,
+    pub groups: Vector<DuplicateGroup>,
+}
+
+impl Duplicates {
+    /// Group the duplicates among `tracks`, marking all but the first entry of
+    /// each group for removal.
+    pub fn find(
+        source: DuplicateSource,
+        snapshot_id: Option<Arc<str>>,
+        tracks: Vector<Arc<Track>>,
+    ) -> Self {
+        // Tracks with the same normalized name and first artist, split further
+        // by their duration below.
+        let mut candidates: Vec<Vec<Arc<Track>>> = Vec::new();
+        let mut by_name: HashMap<(String, String), usize> = HashMap::new();
+        for track in tracks.iter().filter(|track| !track.is_local) {
+            let key = (
+                normalize_name(&track.name),
+                normalize_name(&track.artist_name()),
+            );
+            let index = *by_name.entry(key).or_insert_with(|| {
+                candidates.push(Vec::new());
+                candidates.len() - 1
+            });
+            candidates[index].push(track.clone());
+        }
+
+        let mut groups = Vector::new();
+        for candidate in candidates {
+            let mut clusters: Vec<Vec<Arc<Track>>> = Vec::new();
+            for track in candidate {
+                let cluster = clusters.iter_mut().find(|cluster| {
+                    let first = &cluster[0];
+                    let difference = if first.duration > track.duration {
+                        first.duration - track.duration
+                    } else {
+                        track.duration - first.duration
+                    };
+                    first.id == track.id || difference <= DURATION_TOLERANCE
+                });
+                match cluster {
+                    Some(cluster) => cluster.push(track),
+                    None => clusters.push(vec![track]),
+                }
+            }
+            for cluster in clusters.into_iter().filter(|cluster| cluster.len() > 1) {
+                let exact = cluster.iter().all(|track| track.id == cluster[0].id);
+                let entries = cluster
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, track)| DuplicateEntry {
+                        track,
+                        remove: index > 0,
+                    })
+                    .collect();
+                groups.push_back(DuplicateGroup { entries, exact });
+            }
+        }
+
+        Self {
+            source,
+            snapshot_id,
+            groups,
+        }
+    }
+
+    /// Entries marked for removal.
+    pub fn removed(&self) -> Vector<Arc<Track>> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.entries.iter())
+            .filter(|entry| entry.remove)
+            .map(|entry| entry.track.clone())
+            .collect()
+    }
+
+    /// Playlist positions of the entries marked for removal, by track URI.
+    /// Other occurrences of the same tracks are not included.
+    pub fn removed_positions(&self) -> Vec<(String, Vec<usize>)> {
+        let mut positions: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for track in self.removed() {
+            if let Some(uri) = track.id.0.to_uri() {
+                positions.entry(uri).or_default().push(track.track_pos);
+            }
+        }
+        positions.into_iter().collect()
+    }
+}
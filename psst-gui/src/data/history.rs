@@ -0,0 +1,506 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use druid::{
+    im::{self, Vector},
+    Data, Lens,
+};
+use psst_core::{cache::mkdir_if_not_exists, item_id::ItemId};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{deserialize_millis, serialize_millis, unix_now},
+    AlbumLink, ArtistLink, Config, EpisodeId, Nav, Playable, PlaybackOrigin, ShowLink, TrackId,
+};
+
+const HISTORY_FILENAME: &str = "history.jsonl";
+
+/// Longest progress step that is counted as listening time.  Larger jumps are
+/// seeks.
+const MAX_PROGRESS_STEP: Duration = Duration::from_secs(5);
+
+/// Number of entries in the top track and artist charts.
+const TOP_LEN: usize = 10;
+
+/// Number of contexts kept in the recently played list.
+const RECENT_LEN: usize = 50;
+
+/// Statistics are computed again from the whole history once the start of
+/// their period moves by this much.  Until then, listens that fell out of the
+/// period are still counted.
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Item that has been played, with enough metadata to show it without
+/// querying the Web API.
+#[derive(Clone, Debug, Data, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayedItem {
+    Track {
+        id: TrackId,
+        name: Arc<str>,
+        artists: Vector<ArtistLink>,
+        album: Option<AlbumLink>,
+    },
+    Episode {
+        id: EpisodeId,
+        name: Arc<str>,
+        show: ShowLink,
+    },
+}
+
+impl PlayedItem {
+    pub fn from_playable(item: &Playable) -> Self {
+        match item {
+            Playable::Track(track) => Self::Track {
+                id: track.id,
+                name: track.name.clone(),
+                artists: track.artists.clone(),
+                album: track.album.clone(),
+            },
+            Playable::Episode(episode) => Self::Episode {
+                id: episode.id,
+                name: episode.name.clone(),
+                show: episode.show.clone(),
+            },
+        }
+    }
+
+    pub fn item_id(&self) -> ItemId {
+        match self {
+            Self::Track { id, .. } => id.0,
+            Self::Episode { id, .. } => id.0,
+        }
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        match self {
+            Self::Track { name, .. } | Self::Episode { name, .. } => name,
+        }
+    }
+
+    /// Artist of a track, or the show of an episode.
+    pub fn author_name(&self) -> Arc<str> {
+        match self {
+            Self::Track { artists, .. } => artists
+                .front()
+                .map(|artist| artist.name.clone())
+                .unwrap_or_else(|| "Unknown".into()),
+            Self::Episode { show, .. } => show.name.clone(),
+        }
+    }
+}
+
+/// A single listen of a track or an episode.
+#[derive(Clone, Debug, Data, Serialize, Deserialize)]
+pub struct Listen {
+    pub item: PlayedItem,
+    pub origin: PlaybackOrigin,
+    /// Time the playback has started, in seconds since the Unix epoch.
+    pub played_at: u64,
+    /// For how long the item has actually been listened to.
+    #[serde(
+        serialize_with = "serialize_millis",
+        deserialize_with = "deserialize_millis"
+    )]
+    pub duration: Duration,
+    /// The playback has moved on before the item ended.
+    pub skipped: bool,
+}
+
+/// Listen that is in progress, turned into a `Listen` once the playback moves
+/// on.
+pub struct ListenTracker {
+    item: PlayedItem,
+    origin: PlaybackOrigin,
+    played_at: u64,
+    position: Duration,
+    listened: Duration,
+}
+
+impl ListenTracker {
+    pub fn start(item: &Playable, origin: PlaybackOrigin, position: Duration) -> Self {
+        Self {
+            item: PlayedItem::from_playable(item),
+            origin,
+            played_at: unix_now(),
+            position,
+            listened: Duration::ZERO,
+        }
+    }
+
+    pub fn progress(&mut self, position: Duration) {
+        let step = position.saturating_sub(self.position);
+        if step <= MAX_PROGRESS_STEP {
+            self.listened += step;
+        }
+        self.position = position;
+    }
+
+    /// Finish the listen, `None` is returned if nothing has been heard.
+    pub fn finish(self, skipped: bool) -> Option<Listen> {
+        (!self.listened.is_zero()).then_some(Listen {
+            item: self.item,
+            origin: self.origin,
+            played_at: self.played_at,
+            duration: self.listened,
+            skipped,
+        })
+    }
+}
+
+/// Local record of everything that has been played, stored as JSON lines in
+/// the config directory.
+#[derive(Clone, Default, Data, Lens)]
+pub struct ListeningHistory {
+    pub listens: Vector<Listen>,
+    /// The stored history has been read.  Until then, recorded listens are
+    /// only kept in memory, and written out by `merge_loaded`.
+    pub loaded: bool,
+}
+
+impl ListeningHistory {
+    /// Read the stored history.  Meant to run off the UI thread, the history
+    /// grows with every listen.
+    pub fn load() -> Self {
+        let path = Config::config_dir().map(|dir| dir.join(HISTORY_FILENAME));
+        let Some(file) = path.and_then(|path| File::open(path).ok()) else {
+            return Self {
+                loaded: true,
+                ..Self::default()
+            };
+        };
+        let listens = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| match serde_json::from_str(&line) {
+                Ok(listen) => Some(listen),
+                Err(err) => {
+                    log::warn!("skipping invalid listening history entry: {err}");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            listens,
+            loaded: true,
+        }
+    }
+
+    pub fn record(&mut self, listen: Listen) {
+        if self.loaded {
+            Self::append_or_log(&listen);
+        }
+        self.listens.push_back(listen);
+    }
+
+    /// Take over the `loaded` history, storing the listens recorded before it
+    /// has been read.
+    pub fn merge_loaded(&mut self, mut loaded: ListeningHistory) {
+        for listen in &self.listens {
+            Self::append_or_log(listen);
+            loaded.listens.push_back(listen.clone());
+        }
+        *self = loaded;
+    }
+
+    fn append_or_log(listen: &Listen) {
+        if let Err(err) = Self::append(listen) {
+            log::error!("failed to save listening history: {err}");
+        }
+    }
+
+    fn append(listen: &Listen) -> io::Result<()> {
+        let dir = Config::config_dir().ok_or(io::ErrorKind::NotFound)?;
+        mkdir_if_not_exists(&dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(HISTORY_FILENAME))?;
+        let mut line = serde_json::to_string(listen)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    /// Contexts played from, most recent first.  Albums, playlists and shows
+    /// are listed once, items played from anywhere else are listed on their
+    /// own.
+    pub fn recently_played(&self) -> Vector<RecentContext> {
+        let mut seen = HashSet::new();
+        self.listens
+            .iter()
+            .rev()
+            .map(RecentContext::from_listen)
+            .filter(|recent| seen.insert(recent.key()))
+            .take(RECENT_LEN)
+            .collect()
+    }
+
+    /// Play count and time of the last listen of every track in the history.
+    pub fn track_plays(&self) -> HashMap<TrackId, TrackPlays> {
+        let mut plays: HashMap<TrackId, TrackPlays> = HashMap::new();
+        for listen in &self.listens {
+            if let PlayedItem::Track { id, .. } = &listen.item {
+                let track = plays.entry(*id).or_default();
+                if !listen.skipped {
+                    track.count += 1;
+                }
+                track.last_played = track.last_played.max(listen.played_at);
+            }
+        }
+        plays
+    }
+
+    pub fn stats(&self, period: StatsPeriod) -> ListeningStats {
+        let mut stats = ListeningStats::new(period);
+        for listen in self
+            .listens
+            .iter()
+            .filter(|listen| listen.played_at >= stats.since)
+        {
+            stats.add(listen);
+        }
+        stats
+    }
+}
+
+/// Put `item`, whose key has just grown, into the `top` chart sorted by `key`,
+/// if it belongs there.
+fn update_top<T: Clone, K: Ord>(
+    top: &mut Vector<T>,
+    item: T,
+    is_same: impl Fn(&T) -> bool,
+    key: impl Fn(&T) -> K,
+) {
+    if let Some(index) = top.iter().position(is_same) {
+        top.remove(index);
+    }
+    let item_key = key(&item);
+    let index = top
+        .iter()
+        .position(|other| key(other) < item_key)
+        .unwrap_or(top.len());
+    if index < TOP_LEN {
+        top.insert(index, item);
+        top.truncate(TOP_LEN);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct TrackPlays {
+    /// Number of listens that have not been skipped.
+    pub count: usize,
+    /// Time of the last listen, in seconds since the Unix epoch.
+    pub last_played: u64,
+}
+
+/// Context that has been played recently, together with the last item played
+/// in it.
+#[derive(Clone, Debug, Data)]
+pub struct RecentContext {
+    pub origin: PlaybackOrigin,
+    pub item: PlayedItem,
+    pub played_at: u64,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum RecentKey {
+    Collection(Arc<str>),
+    Item(ItemId),
+}
+
+impl RecentContext {
+    fn from_listen(listen: &Listen) -> Self {
+        Self {
+            origin: listen.origin.clone(),
+            item: listen.item.clone(),
+            played_at: listen.played_at,
+        }
+    }
+
+    /// Put the context of a new `listen` at the front of `recent`.
+    pub fn push(recent: &mut Vector<RecentContext>, listen: &Listen) {
+        let context = Self::from_listen(listen);
+        let key = context.key();
+        recent.retain(|other| other.key() != key);
+        recent.push_front(context);
+        recent.truncate(RECENT_LEN);
+    }
+
+    fn key(&self) -> RecentKey {
+        match &self.origin {
+            PlaybackOrigin::Album(link) => RecentKey::Collection(link.id.clone()),
+            PlaybackOrigin::Playlist(link) => RecentKey::Collection(link.id.clone()),
+            PlaybackOrigin::Show(link) => RecentKey::Collection(link.id.clone()),
+            _ => RecentKey::Item(self.item.item_id()),
+        }
+    }
+
+    pub fn title(&self) -> Arc<str> {
+        match &self.origin {
+            PlaybackOrigin::Album(link) => link.name.clone(),
+            PlaybackOrigin::Playlist(link) => link.name.clone(),
+            PlaybackOrigin::Show(link) => link.name.clone(),
+            _ => self.item.name().clone(),
+        }
+    }
+
+    pub fn subtitle(&self) -> String {
+        match &self.origin {
+            PlaybackOrigin::Album(_) => format!("Album · {}", self.item.author_name()),
+            PlaybackOrigin::Playlist(_) => format!("Playlist · {}", self.item.name()),
+            PlaybackOrigin::Show(_) => format!("Podcast · {}", self.item.name()),
+            _ => self.item.author_name().to_string(),
+        }
+    }
+
+    /// Time since the context has last been played.
+    pub fn played_ago(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.played_at))
+    }
+
+    pub fn to_nav(&self) -> Nav {
+        match (&self.origin, &self.item) {
+            (
+                PlaybackOrigin::Album(_) | PlaybackOrigin::Playlist(_) | PlaybackOrigin::Show(_),
+                _,
+            ) => self.origin.to_nav(),
+            (
+                _,
+                PlayedItem::Track {
+                    id,
+                    album: Some(album),
+                    ..
+                },
+            ) => Nav::AlbumDetail(album.clone(), Some(*id)),
+            (_, PlayedItem::Episode { show, .. }) => Nav::ShowDetail(show.clone()),
+            _ => self.origin.to_nav(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Data, PartialEq, Eq)]
+pub enum StatsPeriod {
+    Week,
+    #[default]
+    Month,
+    Year,
+    AllTime,
+}
+
+impl StatsPeriod {
+    /// Start of the period ending now, in seconds since the Unix epoch.
+    fn start(self) -> u64 {
+        self.duration()
+            .map_or(0, |duration| unix_now().saturating_sub(duration.as_secs()))
+    }
+
+    fn duration(self) -> Option<Duration> {
+        const DAY: u64 = 24 * 60 * 60;
+        match self {
+            Self::Week => Some(Duration::from_secs(7 * DAY)),
+            Self::Month => Some(Duration::from_secs(30 * DAY)),
+            Self::Year => Some(Duration::from_secs(365 * DAY)),
+            Self::AllTime => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct TopTrack {
+    pub item: PlayedItem,
+    pub plays: usize,
+    pub listening_time: Duration,
+}
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct TopArtist {
+    pub artist: ArtistLink,
+    pub plays: usize,
+    pub listening_time: Duration,
+}
+
+/// Statistics of the listening history over a period.  Skipped listens count
+/// into the listening time, but not into the plays and charts.
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct ListeningStats {
+    pub period: StatsPeriod,
+    pub listening_time: Duration,
+    pub plays: usize,
+    pub skips: usize,
+    pub top_tracks: Vector<TopTrack>,
+    pub top_artists: Vector<TopArtist>,
+    /// Start of the period, in seconds since the Unix epoch.
+    #[data(ignore)]
+    since: u64,
+    /// Plays of every track and artist in the period, the charts are the top
+    /// of these.
+    #[data(ignore)]
+    tracks: im::HashMap<TrackId, TopTrack>,
+    #[data(ignore)]
+    artists: im::HashMap<Arc<str>, TopArtist>,
+}
+
+impl ListeningStats {
+    fn new(period: StatsPeriod) -> Self {
+        Self {
+            period,
+            since: period.start(),
+            ..Self::default()
+        }
+    }
+
+    /// The start of the period has moved on enough for the stats to be
+    /// computed again.
+    pub fn is_outdated(&self) -> bool {
+        self.period.start().saturating_sub(self.since) >= STATS_REFRESH_INTERVAL.as_secs()
+    }
+
+    /// Count in a new listen.
+    pub fn add(&mut self, listen: &Listen) {
+        self.listening_time += listen.duration;
+        if listen.skipped {
+            self.skips += 1;
+            return;
+        }
+        self.plays += 1;
+        let PlayedItem::Track { id, artists, .. } = &listen.item else {
+            return;
+        };
+        let track = self.tracks.entry(*id).or_insert_with(|| TopTrack {
+            item: listen.item.clone(),
+            plays: 0,
+            listening_time: Duration::ZERO,
+        });
+        track.plays += 1;
+        track.listening_time += listen.duration;
+        update_top(
+            &mut self.top_tracks,
+            track.clone(),
+            |other| other.item.item_id() == id.0,
+            |t| (t.plays, t.listening_time),
+        );
+        for artist_link in artists {
+            let artist = self
+                .artists
+                .entry(artist_link.id.clone())
+                .or_insert_with(|| TopArtist {
+                    artist: artist_link.clone(),
+                    plays: 0,
+                    listening_time: Duration::ZERO,
+                });
+            artist.plays += 1;
+            artist.listening_time += listen.duration;
+            update_top(
+                &mut self.top_artists,
+                artist.clone(),
+                |other| other.artist.id == artist_link.id,
+                |a| (a.plays, a.listening_time),
+            );
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::{fmt, sync::Arc};
+
+use druid::{im::Vector, Data, KbKey, KeyEvent, SysMods};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::ui::menu::{self, Hotkey};
+
+/// Something that can be triggered from the keyboard.
+#[derive(Clone, Copy, Debug, Data, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    SeekForward,
+    SeekBackward,
+    VolumeUp,
+    VolumeDown,
+    ToggleLyrics,
+    NavigateBack,
+    RefreshAll,
+}
+
+impl KeyAction {
+    pub fn all() -> [Self; 11] {
+        [
+            Self::PlayPause,
+            Self::Next,
+            Self::Previous,
+            Self::Stop,
+            Self::SeekForward,
+            Self::SeekBackward,
+            Self::VolumeUp,
+            Self::VolumeDown,
+            Self::ToggleLyrics,
+            Self::NavigateBack,
+            Self::RefreshAll,
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PlayPause => "Play / Pause",
+            Self::Next => "Next Track",
+            Self::Previous => "Previous Track",
+            Self::Stop => "Stop Playback",
+            Self::SeekForward => "Seek Forward",
+            Self::SeekBackward => "Seek Backward",
+            Self::VolumeUp => "Volume Up",
+            Self::VolumeDown => "Volume Down",
+            Self::ToggleLyrics => "Toggle Lyrics",
+            Self::NavigateBack => "Go Back",
+            Self::RefreshAll => "Refresh Everything",
+        }
+    }
+}
+
+/// A key together with the modifiers held while pressing it, written as
+/// `Ctrl+Shift+ArrowRight`.  For printable keys, Shift is already part of the
+/// character, so `+` matches no matter which keys produce it.
+#[derive(Clone, Debug, Data, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    key: Arc<str>,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+}
+
+impl KeyChord {
+    fn new(key: &str) -> Self {
+        Self {
+            key: key.into(),
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+        }
+    }
+
+    fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// Chord pressed to trigger a menu shortcut.
+    fn from_hotkey(hotkey: Hotkey) -> Self {
+        let (cmd, alt, shift) = match hotkey.mods {
+            SysMods::None => (false, false, false),
+            SysMods::Shift => (false, false, true),
+            SysMods::Cmd => (true, false, false),
+            SysMods::AltCmd => (true, true, false),
+            SysMods::CmdShift => (true, false, true),
+            SysMods::AltCmdShift => (true, true, true),
+        };
+        let is_printable = hotkey.key.chars().count() == 1;
+        let mut chord = Self::new(hotkey.key);
+        chord.alt = alt;
+        chord.shift = shift && !is_printable;
+        if cfg!(target_os = "macos") {
+            chord.meta = cmd;
+        } else {
+            chord.ctrl = cmd;
+        }
+        chord
+    }
+
+    /// Chord of a key press, `None` for presses of the modifiers alone.
+    pub fn from_event(event: &KeyEvent) -> Option<Self> {
+        let (key, is_printable) = match &event.key {
+            KbKey::Character(c) if c == " " => ("Space".to_string(), false),
+            KbKey::Character(c) => (c.to_lowercase(), true),
+            KbKey::Unidentified
+            | KbKey::Alt
+            | KbKey::AltGraph
+            | KbKey::CapsLock
+            | KbKey::Control
+            | KbKey::Fn
+            | KbKey::FnLock
+            | KbKey::Meta
+            | KbKey::NumLock
+            | KbKey::Shift
+            | KbKey::Super
+            | KbKey::Hyper => return None,
+            key => (key.to_string(), false),
+        };
+        Some(Self {
+            key: key.into(),
+            ctrl: event.mods.ctrl(),
+            alt: event.mods.alt(),
+            shift: event.mods.shift() && !is_printable,
+            meta: event.mods.meta(),
+        })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.meta {
+            f.write_str(if cfg!(target_os = "macos") {
+                "Cmd+"
+            } else {
+                "Meta+"
+            })?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let mut chord = Self::new("");
+        let mut rest = s.as_str();
+        // The key itself can be `+`, so only strip known modifier prefixes.
+        loop {
+            if let Some(r) = rest.strip_prefix("Ctrl+") {
+                chord.ctrl = true;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("Alt+") {
+                chord.alt = true;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("Shift+") {
+                chord.shift = true;
+                rest = r;
+            } else if let Some(r) = rest
+                .strip_prefix("Meta+")
+                .or_else(|| rest.strip_prefix("Cmd+"))
+            {
+                chord.meta = true;
+                rest = r;
+            } else {
+                break;
+            }
+        }
+        if rest.is_empty() {
+            return Err(format!("missing key in shortcut \"{s}\""));
+        }
+        chord.key = if rest.chars().count() == 1 {
+            rest.to_lowercase().into()
+        } else {
+            rest.into()
+        };
+        Ok(chord)
+    }
+}
+
+#[derive(Clone, Debug, Data, PartialEq, Eq, Serialize, Deserialize)]
+struct KeyBinding {
+    action: KeyAction,
+    chord: KeyChord,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyConflict {
+    /// The chord triggers more than one action, only the first one runs.
+    Shared(KeyChord, Vec<KeyAction>),
+    /// The chord is taken by a menu shortcut, the action never runs.
+    Reserved(KeyChord, KeyAction),
+}
+
+impl fmt::Display for KeyConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Shared(chord, actions) => {
+                let labels: Vec<_> = actions.iter().map(KeyAction::label).collect();
+                write!(f, "{chord} is bound to {}", labels.join(", "))
+            }
+            Self::Reserved(chord, action) => {
+                write!(f, "{chord} of {} is used by the menu", action.label())
+            }
+        }
+    }
+}
+
+/// Key chords bound to actions.  An action can have any number of chords.
+#[derive(Clone, Debug, Data, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    bindings: Vector<KeyBinding>,
+}
+
+/// Invalid bindings, like an unknown action or a malformed chord, are skipped
+/// instead of failing the whole config.  A keymap that is not a list at all is
+/// replaced by the default one.
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let Some(entries) = value.as_array() else {
+            log::warn!("invalid keymap {value}, using the default one");
+            return Ok(Self::default());
+        };
+        let bindings = entries
+            .iter()
+            .filter_map(|entry| match KeyBinding::deserialize(entry) {
+                Ok(binding) => Some(binding),
+                Err(err) => {
+                    log::warn!("ignoring key binding {entry}: {err}");
+                    None
+                }
+            })
+            .collect();
+        Ok(Self { bindings })
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = [
+            (KeyAction::PlayPause, KeyChord::new("Space")),
+            (KeyAction::Next, KeyChord::new("ArrowRight").shift()),
+            (KeyAction::Previous, KeyChord::new("ArrowLeft").shift()),
+            (KeyAction::SeekForward, KeyChord::new("ArrowRight")),
+            (KeyAction::SeekBackward, KeyChord::new("ArrowLeft")),
+            (KeyAction::VolumeUp, KeyChord::new("+")),
+            (KeyAction::VolumeDown, KeyChord::new("-")),
+            (KeyAction::NavigateBack, KeyChord::new("ArrowLeft").alt()),
+            (KeyAction::RefreshAll, KeyChord::new("r").ctrl()),
+        ];
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(action, chord)| KeyBinding { action, chord })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Chords taken by the shortcuts of the main menu.
+    fn reserved() -> impl Iterator<Item = KeyChord> {
+        menu::hotkeys().into_iter().map(KeyChord::from_hotkey)
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<KeyAction> {
+        let chord = KeyChord::from_event(event)?;
+        self.bindings
+            .iter()
+            .find(|binding| binding.chord == chord)
+            .map(|binding| binding.action)
+    }
+
+    pub fn chords(&self, action: KeyAction) -> impl Iterator<Item = &KeyChord> {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.action == action)
+            .map(|binding| &binding.chord)
+    }
+
+    /// Replace the chords of `action` with `chord`.
+    pub fn bind(&mut self, action: KeyAction, chord: KeyChord) {
+        self.unbind(action);
+        self.bindings.push_back(KeyBinding { action, chord });
+    }
+
+    pub fn unbind(&mut self, action: KeyAction) {
+        self.bindings.retain(|binding| binding.action != action);
+    }
+
+    pub fn conflicts(&self) -> Vec<KeyConflict> {
+        let mut conflicts = Vec::new();
+        for (index, binding) in self.bindings.iter().enumerate() {
+            let is_first = self
+                .bindings
+                .iter()
+                .position(|other| other.chord == binding.chord)
+                == Some(index);
+            if !is_first {
+                continue;
+            }
+            let mut actions: Vec<_> = self
+                .bindings
+                .iter()
+                .filter(|other| other.chord == binding.chord)
+                .map(|other| other.action)
+                .collect();
+            actions.dedup();
+            if actions.len() > 1 {
+                conflicts.push(KeyConflict::Shared(binding.chord.clone(), actions));
+            }
+        }
+        for chord in Self::reserved() {
+            for binding in self.bindings.iter().filter(|b| b.chord == chord) {
+                conflicts.push(KeyConflict::Reserved(chord.clone(), binding.action));
+            }
+        }
+        conflicts
+    }
+}
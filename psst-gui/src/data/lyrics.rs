@@ -0,0 +1,220 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use druid::{im::Vector, Data, Lens};
+
+use crate::data::{Track, TrackLines};
+
+/// Most matching lines shown for a single track.
+const MATCHES_PER_TRACK: usize = 3;
+
+/// Most matches returned by a search of the lyrics index.
+const MATCHES_LIMIT: usize = 200;
+
+/// Lyrics of the playing track.  Lines of synced lyrics have their start
+/// times, so the line being sung can be followed with the playback progress.
+#[derive(Clone, Data, Lens)]
+pub struct Lyrics {
+    pub lines: Vector<LyricsLine>,
+    pub synced: bool,
+    pub current: Option<usize>,
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct LyricsLine {
+    pub words: Arc<str>,
+    pub start: Duration,
+    pub is_current: bool,
+}
+
+impl Lyrics {
+    pub fn new(lines: Vector<TrackLines>) -> Self {
+        let lines: Vector<LyricsLine> = lines
+            .into_iter()
+            .map(|line| LyricsLine {
+                words: line.words.into(),
+                start: Duration::from_millis(line.start_time_ms.parse().unwrap_or(0)),
+                is_current: false,
+            })
+            .collect();
+        // Unsynced lyrics come with all the start times set to zero.
+        let synced = lines.iter().any(|line| !line.start.is_zero());
+        Self {
+            lines,
+            synced,
+            current: None,
+        }
+    }
+
+    /// Move the current line to the last one started before `progress`.
+    pub fn set_progress(&mut self, progress: Duration) {
+        if !self.synced {
+            return;
+        }
+        let current = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.start <= progress && !line.words.is_empty())
+            .map(|(index, _)| index)
+            .last();
+        if current == self.current {
+            return;
+        }
+        if let Some(line) = self.current.and_then(|index| self.lines.get_mut(index)) {
+            line.is_current = false;
+        }
+        if let Some(line) = current.and_then(|index| self.lines.get_mut(index)) {
+            line.is_current = true;
+        }
+        self.current = current;
+    }
+
+    pub fn current_line(&self) -> Option<&LyricsLine> {
+        self.current.and_then(|index| self.lines.get(index))
+    }
+
+    pub fn next_line(&self) -> Option<&LyricsLine> {
+        let next = self.current.map_or(0, |index| index + 1);
+        self.lines
+            .iter()
+            .skip(next)
+            .find(|line| !line.words.is_empty())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Data, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LyricsMatchField {
+    Name,
+    Artist,
+    Album,
+    Lyrics,
+}
+
+/// Track found in the lyrics index, with the text that matched.
+#[derive(Clone, Data, Lens)]
+pub struct LyricsMatch {
+    pub track: Arc<Track>,
+    pub field: LyricsMatchField,
+    pub snippet: Arc<str>,
+    /// Start of the matching line, if the lyrics are synced.
+    pub start: Option<Duration>,
+}
+
+struct Document {
+    track: usize,
+    field: LyricsMatchField,
+    text: Arc<str>,
+    start: Option<Duration>,
+}
+
+/// Full-text index over the names of tracks and their cached lyrics.  Every
+/// name and lyrics line is a document, found by the words it contains.
+pub struct LyricsIndex {
+    tracks: Vec<Arc<Track>>,
+    documents: Vec<Document>,
+    postings: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl LyricsIndex {
+    pub fn new(tracks: impl IntoIterator<Item = (Arc<Track>, Option<Vector<TrackLines>>)>) -> Self {
+        let mut index = Self {
+            tracks: Vec::new(),
+            documents: Vec::new(),
+            postings: BTreeMap::new(),
+        };
+        for (track, lines) in tracks {
+            let track_index = index.tracks.len();
+            let names = [
+                (LyricsMatchField::Name, track.name.clone()),
+                (LyricsMatchField::Artist, track.artist_names().into()),
+                (LyricsMatchField::Album, track.album_name()),
+            ];
+            for (field, text) in names {
+                index.add(track_index, field, text, None);
+            }
+            let lyrics = Lyrics::new(lines.unwrap_or_default());
+            for line in lyrics.lines {
+                let start = lyrics.synced.then_some(line.start);
+                index.add(track_index, LyricsMatchField::Lyrics, line.words, start);
+            }
+            index.tracks.push(track);
+        }
+        index
+    }
+
+    fn add(
+        &mut self,
+        track: usize,
+        field: LyricsMatchField,
+        text: Arc<str>,
+        start: Option<Duration>,
+    ) {
+        let document = self.documents.len();
+        for word in words(&text) {
+            self.postings.entry(word).or_default().insert(document);
+        }
+        self.documents.push(Document {
+            track,
+            field,
+            text,
+            start,
+        });
+    }
+
+    /// Documents containing all the words of `query`, the last one possibly
+    /// unfinished.  Matches in the track names come first.
+    pub fn search(&self, query: &str) -> Vector<LyricsMatch> {
+        let words = words(query);
+        let Some((last, complete)) = words.split_last() else {
+            return Vector::new();
+        };
+        let mut found: Option<BTreeSet<usize>> = None;
+        for word in complete {
+            let documents = self.postings.get(word).cloned().unwrap_or_default();
+            found = Some(intersect(found, documents));
+        }
+        let prefixed = self
+            .postings
+            .range(last.clone()..)
+            .take_while(|(word, _)| word.starts_with(last.as_str()))
+            .flat_map(|(_, documents)| documents.iter().copied())
+            .collect();
+        let found = intersect(found, prefixed);
+
+        let mut documents: Vec<&Document> = found.iter().map(|&d| &self.documents[d]).collect();
+        documents.sort_by_key(|document| (document.field, document.track));
+        let mut per_track = vec![0; self.tracks.len()];
+        documents
+            .into_iter()
+            .filter(|document| {
+                per_track[document.track] += 1;
+                per_track[document.track] <= MATCHES_PER_TRACK
+            })
+            .take(MATCHES_LIMIT)
+            .map(|document| LyricsMatch {
+                track: self.tracks[document.track].clone(),
+                field: document.field,
+                snippet: document.text.clone(),
+                start: document.start,
+            })
+            .collect()
+    }
+}
+
+fn intersect(found: Option<BTreeSet<usize>>, documents: BTreeSet<usize>) -> BTreeSet<usize> {
+    match found {
+        Some(found) => found.intersection(&documents).copied().collect(),
+        None => documents,
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
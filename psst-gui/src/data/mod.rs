@@ -1,17 +1,27 @@
 mod album;
+mod appearance;
 mod artist;
 pub mod config;
 mod ctx;
+mod duplicates;
 mod find;
+mod history;
 mod id;
+mod keymap;
+mod lyrics;
 mod nav;
+mod palette;
 mod playback;
 mod playlist;
+mod playlist_file;
 mod promise;
 mod recommend;
+mod resume_points;
 mod search;
+mod selection;
 mod show;
 mod slider_scroll_scale;
+mod smart_playlist;
 mod track;
 mod user;
 pub mod utils;
@@ -30,33 +40,57 @@ use druid::{
     im::{HashSet, Vector},
     Data, Lens,
 };
-use psst_core::{item_id::ItemId, session::SessionService};
+use psst_core::{item_id::ItemId, player::item::PlaybackTrim, session::SessionService};
 
 pub use crate::data::{
     album::{Album, AlbumDetail, AlbumLink, AlbumType},
+    appearance::{accent_color, system_is_dark, Appearance, ThemeFile},
     artist::{
         Artist, ArtistAlbums, ArtistDetail, ArtistInfo, ArtistLink, ArtistStats,
     },
-    config::{AudioQuality, Authentication, Config, Preferences, PreferencesTab, Theme},
+    config::{AudioQuality, Authentication, Config, Preferences, PreferencesTab, ShowTrim, Theme},
     ctx::Ctx,
+    duplicates::{DuplicateEntry, DuplicateGroup, DuplicateSource, Duplicates},
     find::{FindQuery, Finder, MatchFindQuery},
+    history::{
+        Listen, ListenTracker, ListeningHistory, ListeningStats, PlayedItem, RecentContext,
+        StatsPeriod, TopArtist, TopTrack,
+    },
+    keymap::{KeyAction, KeyChord, Keymap},
+    lyrics::{Lyrics, LyricsIndex, LyricsLine, LyricsMatch, LyricsMatchField},
     nav::{Nav, Route, SpotifyUrl},
+    palette::{fuzzy_score, Palette},
     playback::{
         NowPlaying, Playable, PlayableMatcher, Playback, PlaybackOrigin, PlaybackPayload,
-        PlaybackState, QueueBehavior, QueueEntry,
+        PlaybackState, QueueBehavior, QueueEntry, PLAYBACK_SPEEDS,
     },
     playlist::{
-        Playlist, PlaylistAddTrack, PlaylistDetail, PlaylistLink, PlaylistRemoveTrack,
-        PlaylistTracks,
+        Playlist, PlaylistAddTracks, PlaylistDetail, PlaylistDetails, PlaylistLink,
+        PlaylistMoveTrack, PlaylistRemoveTracks, PlaylistTracks,
+    },
+    playlist_file::{
+        ExportSource, FileEntry, PlaylistExport, PlaylistFile, PlaylistFormat, PlaylistImport,
     },
     promise::{Promise, PromiseState},
     recommend::{
         Range, Recommend, Recommendations, RecommendationsKnobs, RecommendationsParams,
         RecommendationsRequest, Toggled,
     },
-    search::{Search, SearchResults, SearchTopic},
-    show::{Episode, EpisodeId, EpisodeLink, Show, ShowDetail, ShowEpisodes, ShowLink},
+    resume_points::EpisodeResumePoints,
+    search::{
+        record_search, search_suggestions, Search, SearchField, SearchFilter, SearchQuery,
+        SearchResults, SearchTopic,
+    },
+    selection::{SelectMode, TrackSelection},
+    show::{
+        Episode, EpisodeId, EpisodeLink, LocalResumePoint, ResumePoint, Show, ShowDetail,
+        ShowEpisodes, ShowLink,
+    },
     slider_scroll_scale::SliderScrollScale,
+    smart_playlist::{
+        RuleField, RuleMatching, RuleOp, SmartPlaylist, SmartPlaylistDetail, SmartPlaylistLink,
+        SmartPlaylistLoad, SmartPlaylistPool, SmartPlaylistTracks, SmartRule,
+    },
     track::{AudioAnalysis, Track, TrackId, TrackLines},
     user::{PublicUser, UserProfile},
     utils::{Cached, Float64, Image, Page},
@@ -65,6 +99,12 @@ use crate::ui::credits::TrackCredits;
 
 pub const ALERT_DURATION: Duration = Duration::from_secs(5);
 
+/// Episodes stopped closer than this to their end are considered played.
+const EPISODE_PLAYED_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Shortest time between saving the resume points because of playback progress.
+const RESUME_POINT_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Data, Lens)]
 pub struct AppState {
     #[data(ignore)]
@@ -72,6 +112,7 @@ pub struct AppState {
     pub nav: Nav,
     pub history: Vector<Nav>,
     pub config: Config,
+    pub appearance: Appearance,
     pub preferences: Preferences,
     pub playback: Playback,
     pub search: Search,
@@ -79,15 +120,24 @@ pub struct AppState {
     pub album_detail: AlbumDetail,
     pub artist_detail: ArtistDetail,
     pub playlist_detail: PlaylistDetail,
+    pub smart_playlist_detail: SmartPlaylistDetail,
     pub show_detail: ShowDetail,
     pub library: Arc<Library>,
     pub common_ctx: Arc<CommonCtx>,
     pub home_detail: HomeDetail,
     pub alerts: Vector<Alert>,
     pub finder: Finder,
+    pub palette: Palette,
     pub added_queue: Vector<QueueEntry>,
-    pub lyrics: Promise<Vector<TrackLines>>,
+    pub lyrics: Promise<Lyrics>,
     pub credits: Option<TrackCredits>,
+    pub duplicates: Promise<Duplicates, DuplicateSource>,
+    pub listening_history: ListeningHistory,
+    pub recently_played: Vector<RecentContext>,
+    pub stats: ListeningStats,
+    /// Last time the resume points were saved because of recorded progress.
+    #[data(ignore)]
+    pub resume_points_saved: Instant,
 }
 
 impl AppState {
@@ -104,31 +154,39 @@ impl AppState {
             library: Arc::clone(&library),
             show_track_cover: config.show_track_cover,
             nav: Nav::Home,
+            episode_resume_points: Arc::new(EpisodeResumePoints::load()),
+            selection: TrackSelection::default(),
         });
+        let appearance = Appearance::load(&config);
         let playback = Playback {
             state: PlaybackState::Stopped,
             now_playing: None,
             queue_behavior: config.queue_behavior,
             queue: Vector::new(),
             volume: config.volume,
+            speed: 1.0,
+            sleep_timer: None,
         };
         Self {
             session: SessionService::empty(),
             nav: Nav::Home,
             history: Vector::new(),
             config,
+            appearance,
             preferences: Preferences {
                 active: PreferencesTab::General,
                 cache: None,
                 cache_size: Promise::Empty,
                 auth: Authentication::new(),
                 lastfm_auth_result: None,
+                recording_shortcut: None,
             },
             playback,
             added_queue: Vector::new(),
             search: Search {
                 input: "".into(),
                 topic: None,
+                query: SearchQuery::default(),
                 results: Promise::Empty,
             },
             recommend: Recommend {
@@ -143,7 +201,6 @@ impl AppState {
                 your_shows: Promise::Empty,
                 shows_that_you_might_like: Promise::Empty,
                 uniquely_yours: Promise::Empty,
-                jump_back_in: Promise::Empty,
                 user_top_tracks: Promise::Empty,
                 user_top_artists: Promise::Empty,
             },
@@ -160,6 +217,10 @@ impl AppState {
                 playlist: Promise::Empty,
                 tracks: Promise::Empty,
             },
+            smart_playlist_detail: SmartPlaylistDetail {
+                playlist: SmartPlaylist::new(""),
+                tracks: Promise::Empty,
+            },
             show_detail: ShowDetail {
                 show: Promise::Empty,
                 episodes: Promise::Empty,
@@ -168,8 +229,15 @@ impl AppState {
             common_ctx,
             alerts: Vector::new(),
             finder: Finder::new(),
+            palette: Palette::default(),
             lyrics: Promise::Empty,
             credits: None,
+            duplicates: Promise::Empty,
+            // Loaded in the background, see `set_listening_history`.
+            listening_history: ListeningHistory::default(),
+            recently_played: Vector::new(),
+            stats: ListeningStats::default(),
+            resume_points_saved: Instant::now(),
         }
     }
 }
@@ -180,7 +248,9 @@ impl AppState {
             let previous = mem::replace(&mut self.nav, nav.to_owned());
             self.history.push_back(previous);
             self.config.last_route.replace(nav.to_owned());
-            Arc::make_mut(&mut self.common_ctx).nav = nav.to_owned();
+            let common_ctx = Arc::make_mut(&mut self.common_ctx);
+            common_ctx.nav = nav.to_owned();
+            common_ctx.selection = TrackSelection::default();
         }
     }
 
@@ -198,7 +268,9 @@ impl AppState {
 
             self.nav = nav;
             self.config.last_route.replace(self.nav.to_owned());
-            Arc::make_mut(&mut self.common_ctx).nav = self.nav.clone();
+            let common_ctx = Arc::make_mut(&mut self.common_ctx);
+            common_ctx.nav = self.nav.clone();
+            common_ctx.selection = TrackSelection::default();
         }
     }
 
@@ -293,6 +365,176 @@ impl AppState {
         self.config.queue_behavior = queue_behavior;
         self.config.save();
     }
+
+    /// Speed to play `item` at.  Episodes use the speed remembered for their
+    /// show, music always plays at 1.0x.
+    pub fn playback_speed_for(&self, item: &Playable) -> f64 {
+        match item {
+            Playable::Episode(episode) => self.config.show_playback_speed(&episode.show.id),
+            Playable::Track(_) => 1.0,
+        }
+    }
+
+    pub fn set_playback_speed(&mut self, speed: f64) {
+        self.playback.speed = speed;
+        if let Some(Playable::Episode(episode)) =
+            self.playback.now_playing.as_ref().map(|np| &np.item)
+        {
+            self.config
+                .set_show_playback_speed(episode.show.id.clone(), speed);
+            self.config.save();
+        }
+    }
+
+    /// Parts of `item` to skip over, as configured for the show of an episode.
+    pub fn playback_trim_for(&self, item: &Playable) -> PlaybackTrim {
+        match item {
+            Playable::Episode(episode) => {
+                self.config.show_trim(&episode.show.id).to_playback_trim()
+            }
+            Playable::Track(_) => PlaybackTrim::default(),
+        }
+    }
+
+    /// Silence is only ever skipped in episodes, never in music.
+    pub fn skip_silence_for(&self, item: &Playable) -> bool {
+        matches!(item, Playable::Episode(_)) && self.config.skip_silence
+    }
+
+    /// Position to start playing `item` from.  Unfinished episodes resume where
+    /// they were left off.
+    pub fn resume_position_for(&self, item: &Playable) -> Duration {
+        match item {
+            Playable::Episode(episode) => match self.common_ctx.resume_point(episode) {
+                Some(point) if !point.fully_played => point.resume_position,
+                _ => Duration::default(),
+            },
+            Playable::Track(_) => Duration::default(),
+        }
+    }
+
+    /// Remember how far into the currently playing episode we got.
+    pub fn record_episode_progress(&mut self) {
+        if matches!(self.playback.state, PlaybackState::Loading) {
+            // The playback has not started yet, there is no progress to record.
+            return;
+        }
+        let Some(now_playing) = &self.playback.now_playing else {
+            return;
+        };
+        let Playable::Episode(episode) = &now_playing.item else {
+            return;
+        };
+        let episode = episode.clone();
+        let end = episode
+            .duration
+            .saturating_sub(self.playback_trim_for(&now_playing.item).outro);
+        let point = ResumePoint {
+            fully_played: now_playing.progress + EPISODE_PLAYED_THRESHOLD >= end,
+            resume_position: now_playing.progress,
+        };
+        self.set_episode_resume_point(&episode, point);
+        // Progress is recorded on every pause, don't write the file that often.
+        // It is saved on exit anyway.
+        if self.resume_points_saved.elapsed() >= RESUME_POINT_SAVE_INTERVAL {
+            self.save_episode_resume_points();
+        }
+    }
+
+    pub fn record_listen(&mut self, listen: Listen) {
+        RecentContext::push(&mut self.recently_played, &listen);
+        self.stats.add(&listen);
+        self.listening_history.record(listen);
+        if self.stats.is_outdated() {
+            self.stats = self.listening_history.stats(self.stats.period);
+        }
+    }
+
+    pub fn set_listening_history(&mut self, loaded: ListeningHistory) {
+        self.listening_history.merge_loaded(loaded);
+        self.recently_played = self.listening_history.recently_played();
+        self.stats = self.listening_history.stats(self.stats.period);
+    }
+
+    pub fn set_stats_period(&mut self, period: StatsPeriod) {
+        self.stats = self.listening_history.stats(period);
+    }
+
+    pub fn smart_playlist(&self, id: &str) -> Option<&SmartPlaylist> {
+        self.config
+            .smart_playlists
+            .iter()
+            .find(|playlist| &*playlist.id == id)
+    }
+
+    pub fn save_smart_playlist(&mut self, playlist: SmartPlaylist) {
+        let playlists = &mut self.config.smart_playlists;
+        match playlists.iter().position(|p| p.id == playlist.id) {
+            Some(index) => playlists[index] = playlist,
+            None => playlists.push_back(playlist),
+        }
+        self.config.save();
+    }
+
+    pub fn delete_smart_playlist(&mut self, id: &str) {
+        self.config
+            .smart_playlists
+            .retain(|playlist| &*playlist.id != id);
+        self.config.save();
+    }
+
+    /// Collect the library data needed to evaluate `playlist`.
+    pub fn smart_playlist_load(&self, playlist: SmartPlaylist) -> SmartPlaylistLoad {
+        let names = playlist.playlist_names();
+        let playlists = self
+            .library
+            .playlists
+            .resolved()
+            .map(|playlists| {
+                playlists
+                    .iter()
+                    .filter(|p| names.contains(&p.name.to_lowercase()))
+                    .map(Playlist::link)
+                    .collect()
+            })
+            .unwrap_or_default();
+        SmartPlaylistLoad {
+            playlist,
+            saved_tracks: self
+                .library
+                .saved_tracks
+                .resolved()
+                .map(|saved| saved.tracks.clone()),
+            playlists,
+        }
+    }
+
+    pub fn set_episode_played(&mut self, episode: &Episode, played: bool) {
+        let point = ResumePoint {
+            fully_played: played,
+            resume_position: Duration::default(),
+        };
+        self.set_episode_resume_point(episode, point);
+        self.save_episode_resume_points();
+    }
+
+    fn set_episode_resume_point(&mut self, episode: &Episode, point: ResumePoint) {
+        let mut points = (*self.common_ctx.episode_resume_points).clone();
+        points.set(episode.id, point, episode.resume_point.clone());
+        self.common_ctx_mut().episode_resume_points = Arc::new(points);
+    }
+
+    pub fn save_episode_resume_points(&mut self) {
+        self.common_ctx.episode_resume_points.save();
+        self.resume_points_saved = Instant::now();
+    }
+
+    pub fn add_time_saved(&mut self, item: &Playable, saved: Duration) {
+        if let Playable::Episode(episode) = item {
+            self.config
+                .add_show_time_saved(episode.show.id.clone(), saved);
+        }
+    }
 }
 
 impl AppState {
@@ -448,13 +690,10 @@ impl Library {
         }
     }
 
-    pub fn rename_playlist(&mut self, link: PlaylistLink) {
+    pub fn set_playlist_details(&mut self, details: &PlaylistDetails) {
         if let Some(saved) = self.playlists.resolved_mut() {
-            for playlist in saved.iter_mut() {
-                if playlist.id == link.id {
-                    playlist.name = link.name;
-                    break;
-                }
+            if let Some(playlist) = saved.iter_mut().find(|p| p.id == details.id) {
+                playlist.set_details(details);
             }
         }
     }
@@ -475,18 +714,20 @@ impl Library {
         }
     }
 
-    pub fn increment_playlist_track_count(&mut self, link: &PlaylistLink) {
+    pub fn increment_playlist_track_count(&mut self, link: &PlaylistLink, added: usize) {
         if let Some(saved) = self.playlists.resolved_mut() {
             if let Some(playlist) = saved.iter_mut().find(|p| p.id == link.id) {
-                playlist.track_count = playlist.track_count.map(|count| count + 1);
+                playlist.track_count = playlist.track_count.map(|count| count + added);
             }
         }
     }
 
-    pub fn decrement_playlist_track_count(&mut self, link: &PlaylistLink) {
+    pub fn decrement_playlist_track_count(&mut self, link: &PlaylistLink, removed: usize) {
         if let Some(saved) = self.playlists.resolved_mut() {
             if let Some(playlist) = saved.iter_mut().find(|p| p.id == link.id) {
-                playlist.track_count = playlist.track_count.map(|count| count.saturating_sub(1));
+                playlist.track_count = playlist
+                    .track_count
+                    .map(|count| count.saturating_sub(removed));
             }
         }
     }
@@ -549,12 +790,23 @@ pub struct CommonCtx {
     pub library: Arc<Library>,
     pub show_track_cover: bool,
     pub nav: Nav,
+    pub episode_resume_points: Arc<EpisodeResumePoints>,
+    pub selection: TrackSelection,
 }
 
 impl CommonCtx {
     pub fn is_playing(&self, item: &Playable) -> bool {
         matches!(&self.now_playing, Some(i) if i.same(item))
     }
+
+    /// Resume point of `episode`, the more recent of the one stored locally
+    /// and the one reported by Spotify.
+    pub fn resume_point(&self, episode: &Episode) -> Option<ResumePoint> {
+        match self.episode_resume_points.get(&episode.id) {
+            Some(local) => Some(local.resolve(episode.resume_point.as_ref())),
+            None => episode.resume_point.clone(),
+        }
+    }
 }
 
 pub type WithCtx<T> = Ctx<Arc<CommonCtx>, T>;
@@ -568,7 +820,6 @@ pub struct HomeDetail {
     pub uniquely_yours: Promise<MixedView>,
     pub your_shows: Promise<MixedView>,
     pub shows_that_you_might_like: Promise<MixedView>,
-    pub jump_back_in: Promise<MixedView>,
     pub user_top_tracks: Promise<Vector<Arc<Track>>>,
     pub user_top_artists: Promise<Vector<Artist>>,
 }
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::data::track::TrackId;
-use crate::data::{AlbumLink, ArtistLink, PlaylistLink, ShowLink};
+use crate::data::{AlbumLink, ArtistLink, PlaylistLink, ShowLink, SmartPlaylistLink};
 
 use super::RecommendationsRequest;
 
@@ -16,11 +16,14 @@ pub enum Route {
     SavedTracks,
     SavedAlbums,
     Shows,
+    RecentlyPlayed,
+    Stats,
     SearchResults,
     ArtistDetail,
     AlbumDetail,
     ShowDetail,
     PlaylistDetail,
+    SmartPlaylistDetail,
     Recommendations,
 }
 
@@ -32,10 +35,13 @@ pub enum Nav {
     SavedTracks,
     SavedAlbums,
     Shows,
+    RecentlyPlayed,
+    Stats,
     SearchResults(Arc<str>),
     AlbumDetail(AlbumLink, Option<TrackId>),
     ArtistDetail(ArtistLink),
     PlaylistDetail(PlaylistLink),
+    SmartPlaylistDetail(SmartPlaylistLink),
     ShowDetail(ShowLink),
     Recommendations(Arc<RecommendationsRequest>),
 }
@@ -48,10 +54,13 @@ impl Nav {
             Nav::SavedTracks => Route::SavedTracks,
             Nav::SavedAlbums => Route::SavedAlbums,
             Nav::Shows => Route::Shows,
+            Nav::RecentlyPlayed => Route::RecentlyPlayed,
+            Nav::Stats => Route::Stats,
             Nav::SearchResults(_) => Route::SearchResults,
             Nav::AlbumDetail(_, _) => Route::AlbumDetail,
             Nav::ArtistDetail(_) => Route::ArtistDetail,
             Nav::PlaylistDetail(_) => Route::PlaylistDetail,
+            Nav::SmartPlaylistDetail(_) => Route::SmartPlaylistDetail,
             Nav::ShowDetail(_) => Route::ShowDetail,
             Nav::Recommendations(_) => Route::Recommendations,
         }
@@ -64,10 +73,13 @@ impl Nav {
             Nav::SavedTracks => "Saved Tracks".to_string(),
             Nav::SavedAlbums => "Saved Albums".to_string(),
             Nav::Shows => "Podcasts".to_string(),
+            Nav::RecentlyPlayed => "Recently Played".to_string(),
+            Nav::Stats => "Stats".to_string(),
             Nav::SearchResults(query) => query.to_string(),
             Nav::AlbumDetail(link, _) => link.name.to_string(),
             Nav::ArtistDetail(link) => link.name.to_string(),
             Nav::PlaylistDetail(link) => link.name.to_string(),
+            Nav::SmartPlaylistDetail(link) => link.name.to_string(),
             Nav::ShowDetail(link) => link.name.to_string(),
             Nav::Recommendations(_) => "Recommended".to_string(),
         }
@@ -80,10 +92,13 @@ impl Nav {
             Nav::SavedTracks => "Saved Tracks".to_string(),
             Nav::SavedAlbums => "Saved Albums".to_string(),
             Nav::Shows => "Saved Shows".to_string(),
+            Nav::RecentlyPlayed => "Recently Played".to_string(),
+            Nav::Stats => "Listening Stats".to_string(),
             Nav::SearchResults(query) => format!("Search \"{query}\""),
             Nav::AlbumDetail(link, _) => format!("Album \"{}\"", link.name),
             Nav::ArtistDetail(link) => format!("Artist \"{}\"", link.name),
             Nav::PlaylistDetail(link) => format!("Playlist \"{}\"", link.name),
+            Nav::SmartPlaylistDetail(link) => format!("Smart Playlist \"{}\"", link.name),
             Nav::ShowDetail(link) => format!("Show \"{}\"", link.name),
             Nav::Recommendations(_) => "Recommended".to_string(),
         }
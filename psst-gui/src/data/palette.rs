@@ -0,0 +1,70 @@
+use druid::{Data, Lens};
+
+#[derive(Clone, Default, Debug, Data, Lens)]
+pub struct Palette {
+    pub show: bool,
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Palette {
+    pub fn reset(&mut self) {
+        self.query = String::new();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.show = false;
+        self.reset();
+    }
+
+    pub fn select_previous(&mut self, count: usize) {
+        self.selected = if self.selected > 0 {
+            self.selected - 1
+        } else {
+            count.saturating_sub(1)
+        };
+    }
+
+    pub fn select_next(&mut self, count: usize) {
+        self.selected = if self.selected + 1 < count {
+            self.selected + 1
+        } else {
+            0
+        };
+    }
+}
+
+/// Match `query` as a case-insensitive subsequence of `text`.  Returns `None`
+/// if some character of the query is missing, otherwise a score that is higher
+/// for consecutive characters and characters at the start of words.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in text.chars().flat_map(char::to_lowercase).enumerate() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if c == wanted {
+            query.next();
+            score += 1;
+            if previous.map_or(true, |p| !p.is_alphanumeric()) {
+                score += 8;
+            }
+            match last_match {
+                Some(last) if last + 1 == index => score += 5,
+                Some(last) => score -= (index - last - 1).min(3) as i64,
+                None => score -= index.min(5) as i64,
+            }
+            last_match = Some(index);
+        }
+        previous = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
@@ -6,9 +6,13 @@ use psst_core::item_id::ItemId;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    AlbumLink, Episode, Library, Nav, PlaylistLink, RecommendationsRequest, ShowLink, Track,
+    AlbumLink, Episode, Library, Nav, PlaylistLink, RecommendationsRequest, ShowLink,
+    SmartPlaylistLink, Track,
 };
 
+/// Speeds offered in the playback bar, all within the range the player accepts.
+pub const PLAYBACK_SPEEDS: [f64; 9] = [0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0];
+
 #[derive(Clone, Data, Lens)]
 pub struct Playback {
     pub state: PlaybackState,
@@ -16,6 +20,9 @@ pub struct Playback {
     pub queue_behavior: QueueBehavior,
     pub queue: Vector<QueueEntry>,
     pub volume: f64,
+    pub speed: f64,
+    /// Time left until the sleep timer stops the playback, if one is set.
+    pub sleep_timer: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Data, Lens)]
@@ -139,12 +146,13 @@ impl NowPlaying {
     }
 }
 
-#[derive(Clone, Debug, Data)]
+#[derive(Clone, Debug, Data, Serialize, Deserialize)]
 pub enum PlaybackOrigin {
     Home,
     Library,
     Album(AlbumLink),
     Playlist(PlaylistLink),
+    SmartPlaylist(SmartPlaylistLink),
     Show(ShowLink),
     Search(Arc<str>),
     Recommendations(Arc<RecommendationsRequest>),
@@ -157,6 +165,7 @@ impl PlaybackOrigin {
             PlaybackOrigin::Library => Nav::SavedTracks,
             PlaybackOrigin::Album(link) => Nav::AlbumDetail(link.clone(), None),
             PlaybackOrigin::Playlist(link) => Nav::PlaylistDetail(link.clone()),
+            PlaybackOrigin::SmartPlaylist(link) => Nav::SmartPlaylistDetail(link.clone()),
             PlaybackOrigin::Show(link) => Nav::ShowDetail(link.clone()),
             PlaybackOrigin::Search(query) => Nav::SearchResults(query.clone()),
             PlaybackOrigin::Recommendations(request) => Nav::Recommendations(request.clone()),
@@ -171,6 +180,7 @@ impl fmt::Display for PlaybackOrigin {
             PlaybackOrigin::Library => f.write_str("Saved Tracks"),
             PlaybackOrigin::Album(link) => link.name.fmt(f),
             PlaybackOrigin::Playlist(link) => link.name.fmt(f),
+            PlaybackOrigin::SmartPlaylist(link) => link.name.fmt(f),
             PlaybackOrigin::Show(link) => link.name.fmt(f),
             PlaybackOrigin::Search(query) => query.fmt(f),
             PlaybackOrigin::Recommendations(_) => f.write_str("Recommended"),
@@ -183,4 +193,6 @@ pub struct PlaybackPayload {
     pub origin: PlaybackOrigin,
     pub items: Vector<Playable>,
     pub position: usize,
+    /// Where to start playing the item at `position`.
+    pub start_position: Duration,
 }
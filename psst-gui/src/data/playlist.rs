@@ -4,7 +4,7 @@ use druid::{im::Vector, Data, Lens};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::data::utils::sanitize_html_string;
-use crate::data::{user::PublicUser, Image, Promise, Track, TrackId};
+use crate::data::{user::PublicUser, Image, Page, Promise, Track, TrackId};
 
 #[derive(Clone, Debug, Data, Lens)]
 pub struct PlaylistDetail {
@@ -12,16 +12,34 @@ pub struct PlaylistDetail {
     pub tracks: Promise<PlaylistTracks, PlaylistLink>,
 }
 
-#[derive(Clone, Debug, Data, Lens, Deserialize)]
-pub struct PlaylistAddTrack {
+#[derive(Clone, Debug, Data, Lens)]
+pub struct PlaylistAddTracks {
     pub link: PlaylistLink,
-    pub track_id: TrackId,
+    pub track_ids: Vector<TrackId>,
 }
 
-#[derive(Clone, Debug, Data, Lens, Deserialize)]
-pub struct PlaylistRemoveTrack {
+#[derive(Clone, Debug, Data, Lens)]
+pub struct PlaylistRemoveTracks {
     pub link: PlaylistLink,
-    pub track_uri: Arc<str>,
+    pub track_uris: Vector<Arc<str>>,
+}
+
+/// Move of a single track to another position of the playlist.
+#[derive(Clone, Debug, Data)]
+pub struct PlaylistMoveTrack {
+    pub link: PlaylistLink,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Details of a playlist editable by its owner.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct PlaylistDetails {
+    pub id: Arc<str>,
+    pub name: String,
+    pub description: String,
+    pub public: bool,
+    pub collaborative: bool,
 }
 
 #[derive(Clone, Debug, Data, Lens, Deserialize)]
@@ -62,6 +80,23 @@ impl Playlist {
     pub fn url(&self) -> String {
         format!("https://open.spotify.com/playlist/{id}", id = self.id)
     }
+
+    pub fn details(&self) -> PlaylistDetails {
+        PlaylistDetails {
+            id: self.id.clone(),
+            name: self.name.to_string(),
+            description: self.description.to_string(),
+            public: self.public.unwrap_or(false),
+            collaborative: self.collaborative,
+        }
+    }
+
+    pub fn set_details(&mut self, details: &PlaylistDetails) {
+        self.name = details.name.as_str().into();
+        self.description = details.description.as_str().into();
+        self.public = Some(details.public && !details.collaborative);
+        self.collaborative = details.collaborative;
+    }
 }
 
 #[derive(Clone, Debug, Data, Lens)]
@@ -69,15 +104,67 @@ pub struct PlaylistTracks {
     pub id: Arc<str>,
     pub name: Arc<str>,
     pub tracks: Vector<Arc<Track>>,
+    /// Number of playlist items fetched so far, including the ones that could
+    /// not be turned into tracks.
+    pub loaded: usize,
+    pub total: usize,
+    pub loading_more: bool,
 }
 
 impl PlaylistTracks {
+    /// Playlist with all of its tracks already loaded.
+    pub fn new(link: &PlaylistLink, tracks: Vector<Arc<Track>>) -> Self {
+        Self {
+            id: link.id.clone(),
+            name: link.name.clone(),
+            loaded: tracks.len(),
+            total: tracks.len(),
+            tracks,
+            loading_more: false,
+        }
+    }
+
+    /// Playlist with only its first page of tracks loaded.
+    pub fn first_page(link: &PlaylistLink, page: Page<Arc<Track>>) -> Self {
+        let mut tracks = Self::new(link, Vector::new());
+        tracks.append_page(page);
+        tracks
+    }
+
+    pub fn append_page(&mut self, page: Page<Arc<Track>>) {
+        // An empty page means there is nothing more to load.
+        self.loaded = if page.limit == 0 {
+            page.total
+        } else {
+            (page.offset + page.limit).min(page.total)
+        };
+        self.total = page.total;
+        self.tracks.append(page.items);
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.loaded < self.total
+    }
+
     pub fn link(&self) -> PlaylistLink {
         PlaylistLink {
             id: self.id.clone(),
             name: self.name.clone(),
         }
     }
+
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        for (index, track) in self.tracks.iter_mut().enumerate() {
+            if track.track_pos != index {
+                Arc::make_mut(track).track_pos = index;
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, Data, Lens, Eq, PartialEq, Hash, Deserialize, Serialize)]
@@ -0,0 +1,545 @@
+use std::{
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use druid::{im::Vector, Data};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::Error;
+
+use super::{utils::normalize_name, AlbumLink, PlaylistLink, Track};
+
+/// Entries scoring below this are not considered to be the searched track.
+const MATCH_THRESHOLD: f64 = 0.7;
+
+#[derive(Clone, Copy, Debug, Data, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    M3u8,
+    Xspf,
+    Csv,
+    Json,
+}
+
+impl PlaylistFormat {
+    pub const ALL: [Self; 4] = [Self::M3u8, Self::Xspf, Self::Csv, Self::Json];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::M3u8 => "M3U8",
+            Self::Xspf => "XSPF",
+            Self::Csv => "CSV",
+            Self::Json => "JSON",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u8),
+            "xspf" => Some(Self::Xspf),
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data)]
+pub enum ExportSource {
+    Playlist(PlaylistLink),
+    Album(AlbumLink),
+    SavedTracks,
+    /// Tracks picked by the user, exported as they are.
+    Tracks(Vector<Arc<Track>>),
+}
+
+impl ExportSource {
+    pub fn name(&self) -> Arc<str> {
+        match self {
+            Self::Playlist(link) => link.name.clone(),
+            Self::Album(link) => link.name.clone(),
+            Self::SavedTracks => "Saved Tracks".into(),
+            Self::Tracks(_) => "Selected Tracks".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data)]
+pub struct PlaylistExport {
+    pub source: ExportSource,
+    pub format: PlaylistFormat,
+}
+
+/// Outcome of importing a playlist file.
+#[derive(Clone, Debug, Data)]
+pub struct PlaylistImport {
+    /// Name of the playlist created for the import.
+    pub name: Arc<str>,
+    pub added: usize,
+    /// Descriptions of the entries that could not be matched to a track.
+    pub unmatched: Vector<Arc<str>>,
+    /// Error that stopped creating the playlist or adding the tracks.
+    pub error: Option<Error>,
+}
+
+/// Track entry of a playlist file.  Every field is optional, as imported files
+/// often carry only some of them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileEntry {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl FileEntry {
+    pub fn from_track(track: &Track) -> Self {
+        Self {
+            title: track.name.to_string(),
+            artist: track.artist_names(),
+            album: track
+                .album
+                .as_ref()
+                .map(|album| album.name.to_string())
+                .unwrap_or_default(),
+            duration_ms: track.duration.as_millis() as u64,
+            uri: if track.is_local {
+                None
+            } else {
+                track.id.0.to_uri()
+            },
+            path: track.local_path.as_ref().map(|path| path.to_string()),
+        }
+    }
+
+    /// Spotify track URI of the entry, if it has one.
+    pub fn track_uri(&self) -> Option<&str> {
+        self.uri
+            .as_deref()
+            .filter(|uri| uri.starts_with("spotify:track:"))
+    }
+
+    pub fn description(&self) -> String {
+        match (self.artist.is_empty(), self.title.is_empty()) {
+            (false, false) => format!("{} - {}", self.artist, self.title),
+            (true, false) => self.title.clone(),
+            _ => self
+                .path
+                .clone()
+                .or_else(|| self.uri.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+        }
+    }
+
+    /// Track from `candidates` that matches the entry best, if any is close
+    /// enough.
+    pub fn best_match<'a>(&self, candidates: &'a Vector<Arc<Track>>) -> Option<&'a Arc<Track>> {
+        candidates
+            .iter()
+            .map(|track| (self.match_score(track), track))
+            .filter(|(score, _)| *score >= MATCH_THRESHOLD)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, track)| track)
+    }
+
+    /// How well does `track` match this entry, from 0 to 1.
+    fn match_score(&self, track: &Track) -> f64 {
+        let title = similarity(&normalize_name(&self.title), &normalize_name(&track.name));
+        let mut score = match self.artist.split([',', ';', '&']).next() {
+            Some(artist) if !artist.trim().is_empty() => {
+                let artist = normalize_name(artist);
+                let artist = track
+                    .artists
+                    .iter()
+                    .map(|a| similarity(&artist, &normalize_name(&a.name)))
+                    .fold(0.0, f64::max);
+                0.65 * title + 0.35 * artist
+            }
+            _ => title,
+        };
+        if self.duration_ms > 0 {
+            let diff = self.duration_ms.abs_diff(track.duration.as_millis() as u64);
+            if diff > 5_000 {
+                score *= 0.85;
+            }
+        }
+        score
+    }
+
+    fn with_location(mut self, location: &str) -> Self {
+        if let Some(uri) = spotify_uri(location) {
+            self.uri = Some(uri);
+        } else {
+            let path = Url::parse(location)
+                .ok()
+                .filter(|url| url.scheme() == "file")
+                .and_then(|url| url.to_file_path().ok())
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|| location.to_string());
+            if self.title.is_empty() {
+                if let Some(stem) = Path::new(&path).file_stem() {
+                    self.title = stem.to_string_lossy().into_owned();
+                }
+            }
+            self.path = Some(path);
+        }
+        self
+    }
+
+    fn location(&self) -> Option<&str> {
+        self.path.as_deref().or(self.uri.as_deref())
+    }
+}
+
+/// Contents of a playlist file.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistFile {
+    pub name: String,
+    #[serde(rename = "tracks")]
+    pub entries: Vec<FileEntry>,
+}
+
+impl PlaylistFile {
+    pub fn new(name: &str, tracks: &Vector<Arc<Track>>) -> Self {
+        Self {
+            name: name.to_string(),
+            entries: tracks
+                .iter()
+                .map(|track| FileEntry::from_track(track))
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, format: PlaylistFormat) -> String {
+        match format {
+            PlaylistFormat::M3u8 => self.write_m3u(),
+            PlaylistFormat::Xspf => self.write_xspf(),
+            PlaylistFormat::Csv => self.write_csv(),
+            PlaylistFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+        }
+    }
+
+    pub fn parse(format: PlaylistFormat, contents: &str) -> Result<Self, Error> {
+        let contents = contents.trim_start_matches('\u{feff}');
+        match format {
+            PlaylistFormat::M3u8 => Ok(Self::parse_m3u(contents)),
+            PlaylistFormat::Xspf => Ok(Self::parse_xspf(contents)),
+            PlaylistFormat::Csv => Self::parse_csv(contents),
+            PlaylistFormat::Json => Self::parse_json(contents),
+        }
+    }
+
+    fn write_m3u(&self) -> String {
+        let mut out = format!("#EXTM3U\n#PLAYLIST:{}\n", self.name);
+        for entry in &self.entries {
+            // Entries without any location cannot be represented in M3U.
+            let Some(location) = entry.location() else {
+                continue;
+            };
+            out += &format!(
+                "#EXTINF:{},{} - {}\n{}\n",
+                entry.duration_ms / 1000,
+                entry.artist,
+                entry.title,
+                location
+            );
+        }
+        out
+    }
+
+    fn parse_m3u(contents: &str) -> Self {
+        let mut file = Self::default();
+        let mut pending = None;
+        for line in contents.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+                file.name = name.trim().to_string();
+            } else if let Some(info) = line.strip_prefix("#EXTINF:") {
+                let (secs, display) = info.split_once(',').unwrap_or((info, ""));
+                // Attributes may follow the duration, separated by a space.
+                let secs = secs.split_whitespace().next().unwrap_or_default();
+                let (artist, title) = display.split_once(" - ").unwrap_or(("", display));
+                pending = Some(FileEntry {
+                    title: title.trim().to_string(),
+                    artist: artist.trim().to_string(),
+                    duration_ms: secs.parse::<u64>().unwrap_or_default() * 1000,
+                    ..FileEntry::default()
+                });
+            } else if !line.is_empty() && !line.starts_with('#') {
+                let entry = pending.take().unwrap_or_default();
+                file.entries.push(entry.with_location(line));
+            }
+        }
+        file
+    }
+
+    fn write_xspf(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+        ));
+        out += &format!(
+            "  <title>{}</title>\n  <trackList>\n",
+            escape_xml(&self.name)
+        );
+        for entry in &self.entries {
+            out += "    <track>\n";
+            if let Some(path) = &entry.path {
+                let location = Url::from_file_path(path)
+                    .map(String::from)
+                    .unwrap_or_else(|_| path.clone());
+                out += &xml_element("location", &location);
+            }
+            if let Some(uri) = &entry.uri {
+                out += &xml_element("identifier", uri);
+            }
+            out += &xml_element("title", &entry.title);
+            out += &xml_element("creator", &entry.artist);
+            out += &xml_element("album", &entry.album);
+            out += &xml_element("duration", &entry.duration_ms.to_string());
+            out += "    </track>\n";
+        }
+        out += "  </trackList>\n</playlist>\n";
+        out
+    }
+
+    fn parse_xspf(contents: &str) -> Self {
+        static TRACK: OnceLock<Regex> = OnceLock::new();
+        static ELEMENT: OnceLock<Regex> = OnceLock::new();
+        let track_re = TRACK.get_or_init(|| Regex::new(r"(?s)<track>(.*?)</track>").unwrap());
+        let element_re = ELEMENT.get_or_init(|| {
+            Regex::new(r"(?s)<(location|identifier|title|creator|album|duration)>(.*?)</").unwrap()
+        });
+
+        let head = contents.split("<trackList").next().unwrap_or_default();
+        let name = element_re
+            .captures_iter(head)
+            .find(|caps| &caps[1] == "title")
+            .map(|caps| unescape_xml(caps[2].trim()))
+            .unwrap_or_default();
+
+        let entries = track_re
+            .captures_iter(contents)
+            .map(|track| {
+                let mut entry = FileEntry::default();
+                let mut location = None;
+                for caps in element_re.captures_iter(&track[1]) {
+                    let value = unescape_xml(caps[2].trim());
+                    match &caps[1] {
+                        "location" => location = Some(value),
+                        "identifier" => entry.uri = spotify_uri(&value),
+                        "title" => entry.title = value,
+                        "creator" => entry.artist = value,
+                        "album" => entry.album = value,
+                        "duration" => entry.duration_ms = value.parse().unwrap_or_default(),
+                        _ => {}
+                    }
+                }
+                match location {
+                    Some(location) => entry.with_location(&location),
+                    None => entry,
+                }
+            })
+            .collect();
+
+        Self { name, entries }
+    }
+
+    fn write_csv(&self) -> String {
+        let mut out = String::from("Title,Artist,Album,Duration (ms),URI,Path\n");
+        for entry in &self.entries {
+            let row = [
+                entry.title.as_str(),
+                entry.artist.as_str(),
+                entry.album.as_str(),
+                entry.duration_ms.to_string().as_str(),
+                entry.uri.as_deref().unwrap_or_default(),
+                entry.path.as_deref().unwrap_or_default(),
+            ]
+            .map(escape_csv)
+            .join(",");
+            out += &row;
+            out += "\n";
+        }
+        out
+    }
+
+    fn parse_csv(contents: &str) -> Result<Self, Error> {
+        let mut rows = parse_csv_rows(contents).into_iter();
+        let header: Vec<String> = rows
+            .next()
+            .unwrap_or_default()
+            .iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+        // Accept our own columns as well as the ones used by common exporters.
+        let column = |names: &[&str]| header.iter().position(|c| names.contains(&c.as_str()));
+        let title = column(&["title", "track name", "track", "name"]);
+        let artist = column(&["artist", "artist name(s)", "artist name", "artists"]);
+        let album = column(&["album", "album name"]);
+        let duration = column(&["duration (ms)", "duration_ms", "duration"]);
+        let uri = column(&["uri", "track uri", "spotify uri"]);
+        let path = column(&["path", "location"]);
+        if title.is_none() && uri.is_none() {
+            return Err(Error::PlaylistFileError(
+                "CSV file has neither a title nor a URI column".to_string(),
+            ));
+        }
+
+        let entries = rows
+            .map(|row| {
+                let get = |index: Option<usize>| {
+                    index
+                        .and_then(|i| row.get(i))
+                        .map(|value| value.trim().to_string())
+                        .unwrap_or_default()
+                };
+                let entry = FileEntry {
+                    title: get(title),
+                    artist: get(artist),
+                    album: get(album),
+                    duration_ms: get(duration).parse().unwrap_or_default(),
+                    uri: spotify_uri(&get(uri)),
+                    path: None,
+                };
+                match get(path) {
+                    location if location.is_empty() => entry,
+                    location => entry.with_location(&location),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            name: String::new(),
+            entries,
+        })
+    }
+
+    fn parse_json(contents: &str) -> Result<Self, Error> {
+        // Besides our own format, accept a bare list of entries.
+        serde_json::from_str(contents)
+            .or_else(|_| {
+                serde_json::from_str(contents).map(|entries| Self {
+                    name: String::new(),
+                    entries,
+                })
+            })
+            .map_err(|err| Error::PlaylistFileError(err.to_string()))
+    }
+}
+
+/// Normalize a Spotify URI or `open.spotify.com` URL into a track URI.
+fn spotify_uri(location: &str) -> Option<String> {
+    let location = location.trim();
+    if location.starts_with("spotify:") {
+        Some(location.to_string())
+    } else {
+        let url = Url::parse(location).ok()?;
+        if url.host_str() != Some("open.spotify.com") {
+            return None;
+        }
+        let mut segments = url.path_segments()?;
+        match (segments.next(), segments.next()) {
+            (Some("track"), Some(id)) => Some(format!("spotify:track:{id}")),
+            _ => None,
+        }
+    }
+}
+
+fn xml_element(name: &str, value: &str) -> String {
+    format!("      <{name}>{}</{name}>\n", escape_xml(value))
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split CSV into rows of fields, honoring quoted fields.
+fn parse_csv_rows(contents: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.is_empty()));
+    rows
+}
+
+/// Similarity of two strings based on their edit distance, from 0 to 1.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let len = a.len().max(b.len());
+    if len == 0 {
+        return 0.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    1.0 - prev[b.len()] as f64 / len as f64
+}
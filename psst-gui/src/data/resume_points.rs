@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter},
+    time::Duration,
+};
+
+use psst_core::cache::mkdir_if_not_exists;
+use serde::{Deserialize, Serialize};
+
+use super::{utils::unix_now, Config, EpisodeId, LocalResumePoint, ResumePoint};
+
+const RESUME_POINTS_FILENAME: &str = "resume_points.json";
+
+/// How long resume points of finished episodes are kept.
+const FINISHED_EPISODE_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+const MAX_EPISODE_RESUME_POINTS: usize = 1000;
+
+/// Resume points of podcast episodes played in this client.  These take
+/// precedence over the ones reported by Spotify, unless the episode was played
+/// elsewhere since.  Saved apart from the config, they change with every pause.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EpisodeResumePoints {
+    points: HashMap<EpisodeId, LocalResumePoint>,
+}
+
+impl EpisodeResumePoints {
+    pub fn load() -> Self {
+        let path = Config::config_dir().map(|dir| dir.join(RESUME_POINTS_FILENAME));
+        let Some(file) = path.and_then(|path| File::open(path).ok()) else {
+            return Self::default();
+        };
+        serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|err| {
+            log::warn!("failed to read episode resume points: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = self.write() {
+            log::error!("failed to save episode resume points: {err}");
+        }
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let dir = Config::config_dir().ok_or(io::ErrorKind::NotFound)?;
+        mkdir_if_not_exists(&dir)?;
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join(RESUME_POINTS_FILENAME))?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &EpisodeId) -> Option<&LocalResumePoint> {
+        self.points.get(id)
+    }
+
+    /// Store a resume point of an episode, together with the one Spotify
+    /// currently reports for it, and prune the stored points.
+    pub fn set(&mut self, id: EpisodeId, point: ResumePoint, remote: Option<ResumePoint>) {
+        if remote.as_ref() == Some(&point) {
+            // Nothing to remember beyond what Spotify reports.
+            self.points.remove(&id);
+        } else {
+            self.points.insert(
+                id,
+                LocalResumePoint {
+                    point,
+                    remote,
+                    saved_at: unix_now(),
+                },
+            );
+        }
+        self.prune();
+    }
+
+    /// Forget episodes finished long ago, and the oldest points over the limit.
+    fn prune(&mut self) {
+        let finished_before = unix_now().saturating_sub(FINISHED_EPISODE_RETENTION.as_secs());
+        self.points
+            .retain(|_, local| !local.point.fully_played || local.saved_at >= finished_before);
+        if self.points.len() > MAX_EPISODE_RESUME_POINTS {
+            let mut saved: Vec<_> = self
+                .points
+                .iter()
+                .map(|(id, local)| (local.saved_at, *id))
+                .collect();
+            saved.sort_unstable_by_key(|(saved_at, _)| *saved_at);
+            let excess = saved.len() - MAX_EPISODE_RESUME_POINTS;
+            for (_, id) in &saved[..excess] {
+                self.points.remove(id);
+            }
+        }
+    }
+}
@@ -1,13 +1,21 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use druid::{im::Vector, Data, Lens};
 
-use crate::data::{Album, Artist, Playlist, Promise, Show, Track};
+use crate::data::{Album, Artist, LyricsMatch, Playlist, Promise, Show, Track};
+
+/// Number of queries kept in the search history.
+const SEARCH_HISTORY_LIMIT: usize = 50;
+
+/// Number of history entries suggested while typing.
+const SUGGESTIONS_LIMIT: usize = 5;
 
 #[derive(Clone, Data, Lens)]
 pub struct Search {
     pub input: String,
     pub topic: Option<SearchTopic>,
+    /// Parsed form of the query of the current results.
+    pub query: SearchQuery,
     pub results: Promise<SearchResults, (Arc<str>, Option<SearchTopic>)>,
 }
 
@@ -18,6 +26,8 @@ pub enum SearchTopic {
     Track,
     Playlist,
     Show,
+    /// Names and cached lyrics of the saved tracks, searched locally.
+    Lyrics,
 }
 
 impl SearchTopic {
@@ -28,6 +38,7 @@ impl SearchTopic {
             SearchTopic::Track => "track",
             SearchTopic::Playlist => "playlist",
             SearchTopic::Show => "show",
+            SearchTopic::Lyrics => "lyrics",
         }
     }
 
@@ -38,9 +49,11 @@ impl SearchTopic {
             SearchTopic::Track => "Tracks",
             SearchTopic::Playlist => "Playlists",
             SearchTopic::Show => "Podcasts",
+            SearchTopic::Lyrics => "Lyrics & Library",
         }
     }
 
+    /// Topics searched on Spotify.
     pub fn all() -> &'static [Self] {
         &[
             Self::Artist,
@@ -52,6 +65,208 @@ impl SearchTopic {
     }
 }
 
+/// Field filters understood by the Spotify search.
+#[derive(Copy, Clone, Debug, Data, Eq, PartialEq)]
+pub enum SearchField {
+    Artist,
+    Album,
+    Track,
+    Year,
+    Genre,
+    Tag,
+    Isrc,
+}
+
+impl SearchField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchField::Artist => "artist",
+            SearchField::Album => "album",
+            SearchField::Track => "track",
+            SearchField::Year => "year",
+            SearchField::Genre => "genre",
+            SearchField::Tag => "tag",
+            SearchField::Isrc => "isrc",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            SearchField::Artist => "Artist",
+            SearchField::Album => "Album",
+            SearchField::Track => "Track",
+            SearchField::Year => "Year",
+            SearchField::Genre => "Genre",
+            SearchField::Tag => "New Releases",
+            SearchField::Isrc => "ISRC",
+        }
+    }
+
+    /// Text appended to the search input when adding the filter.
+    pub fn template(&self) -> &'static str {
+        match self {
+            SearchField::Tag => "tag:new",
+            SearchField::Artist => "artist:",
+            SearchField::Album => "album:",
+            SearchField::Track => "track:",
+            SearchField::Year => "year:",
+            SearchField::Genre => "genre:",
+            SearchField::Isrc => "isrc:",
+        }
+    }
+
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Artist,
+            Self::Album,
+            Self::Track,
+            Self::Year,
+            Self::Genre,
+            Self::Tag,
+            Self::Isrc,
+        ]
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|field| field.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Values Spotify would reject are kept as plain text instead.
+    fn accepts(&self, value: &str) -> bool {
+        let is_year = |year: &str| year.len() == 4 && year.chars().all(|c| c.is_ascii_digit());
+        match self {
+            SearchField::Year => match value.split_once('-') {
+                Some((from, to)) => is_year(from) && is_year(to),
+                None => is_year(value),
+            },
+            SearchField::Tag => value == "new" || value == "hipster",
+            _ => !value.is_empty(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data, Eq, PartialEq)]
+pub struct SearchFilter {
+    pub field: SearchField,
+    pub value: Arc<str>,
+}
+
+impl fmt::Display for SearchFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value.contains(char::is_whitespace) {
+            write!(f, "{}:\"{}\"", self.field.as_str(), self.value)
+        } else {
+            write!(f, "{}:{}", self.field.as_str(), self.value)
+        }
+    }
+}
+
+/// Search query split into free text and field filters, i.e.
+/// `around the world artist:"Daft Punk" year:1990-1999`.
+#[derive(Clone, Debug, Default, Data, Eq, PartialEq, Lens)]
+pub struct SearchQuery {
+    pub text: Arc<str>,
+    pub filters: Vector<SearchFilter>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut text = Vec::new();
+        let mut filters = Vector::new();
+        for token in tokenize(input) {
+            let filter = token.split_once(':').and_then(|(name, value)| {
+                let field = SearchField::parse(name)?;
+                let value = value.trim_matches('"');
+                field.accepts(value).then(|| SearchFilter {
+                    field,
+                    value: value.into(),
+                })
+            });
+            match filter {
+                Some(filter) => filters.push_back(filter),
+                None => text.push(token),
+            }
+        }
+        Self {
+            text: text.join(" ").into(),
+            filters,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters.is_empty()
+    }
+
+    pub fn without_filter(&self, filter: &SearchFilter) -> Self {
+        let mut query = self.clone();
+        query.filters.retain(|f| f != filter);
+        query
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)?;
+        for (index, filter) in self.filters.iter().enumerate() {
+            if index > 0 || !self.text.is_empty() {
+                f.write_str(" ")?;
+            }
+            write!(f, "{filter}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Split `input` on whitespace, keeping quoted parts together.
+fn tokenize(input: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (index, c) in input.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c.is_whitespace() && !quoted {
+            if let Some(start) = start.take() {
+                tokens.push(&input[start..index]);
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&input[start..]);
+    }
+    tokens
+}
+
+/// Add `query` to the front of the search `history`.
+pub fn record_search(history: &mut Vector<Arc<str>>, query: Arc<str>) {
+    history.retain(|q| !q.eq_ignore_ascii_case(&query));
+    history.push_front(query);
+    history.truncate(SEARCH_HISTORY_LIMIT);
+}
+
+/// Previous queries containing `input`, most recent first.
+pub fn search_suggestions(history: &Vector<Arc<str>>, input: &str) -> Vector<Arc<str>> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Vector::new();
+    }
+    history
+        .iter()
+        .filter(|query| {
+            let query = query.to_lowercase();
+            query != input && query.contains(&input)
+        })
+        .take(SUGGESTIONS_LIMIT)
+        .cloned()
+        .collect()
+}
+
 #[derive(Clone, Data, Lens)]
 pub struct SearchResults {
     pub query: Arc<str>,
@@ -61,6 +276,10 @@ pub struct SearchResults {
     pub tracks: Vector<Arc<Track>>,
     pub playlists: Vector<Playlist>,
     pub shows: Vector<Arc<Show>>,
+    pub lyrics: Vector<LyricsMatch>,
+    /// Offset of the next page of results, if there are more results of the
+    /// topic.
+    pub next_offset: Option<usize>,
 }
 
 impl SearchResults {
@@ -70,5 +289,31 @@ impl SearchResults {
             && self.tracks.is_empty()
             && self.playlists.is_empty()
             && self.shows.is_empty()
+            && self.lyrics.is_empty()
+    }
+
+    pub fn from_lyrics(query: Arc<str>, lyrics: Vector<LyricsMatch>) -> Self {
+        Self {
+            query,
+            topic: Some(SearchTopic::Lyrics),
+            artists: Vector::new(),
+            albums: Vector::new(),
+            tracks: Vector::new(),
+            playlists: Vector::new(),
+            shows: Vector::new(),
+            lyrics,
+            next_offset: None,
+        }
+    }
+
+    /// Add the next page of results.
+    pub fn append(&mut self, page: SearchResults) {
+        self.artists.append(page.artists);
+        self.albums.append(page.albums);
+        self.tracks.append(page.tracks);
+        self.playlists.append(page.playlists);
+        self.shows.append(page.shows);
+        self.lyrics.append(page.lyrics);
+        self.next_offset = page.next_offset;
     }
 }
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, Data};
+
+use crate::data::{Track, TrackId};
+
+/// How a click with a modifier key held changes the track selection.
+#[derive(Copy, Clone, Debug, Data, PartialEq, Eq)]
+pub enum SelectMode {
+    /// Add the clicked track, or remove it if already selected.
+    Toggle,
+    /// Add every track between the last clicked one and the clicked one.
+    Extend,
+}
+
+/// Tracks selected for a bulk action.  Tracks are matched by their ids, so the
+/// selection survives re-sorting the list they were selected in.
+#[derive(Clone, Default, Data)]
+pub struct TrackSelection {
+    pub tracks: Vector<Arc<Track>>,
+    pub anchor: Option<TrackId>,
+}
+
+impl TrackSelection {
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn contains(&self, id: &TrackId) -> bool {
+        self.tracks.iter().any(|track| &track.id == id)
+    }
+
+    /// Apply a click on `track`, with `list` holding the tracks of the clicked
+    /// list in their displayed order.
+    pub fn select(&mut self, track: &Arc<Track>, mode: SelectMode, list: &[Arc<Track>]) {
+        match mode {
+            SelectMode::Toggle => {
+                if let Some(index) = self.tracks.iter().position(|t| t.id == track.id) {
+                    self.tracks.remove(index);
+                } else {
+                    self.tracks.push_back(track.clone());
+                }
+                self.anchor = Some(track.id);
+            }
+            SelectMode::Extend => {
+                let position_of = |id: TrackId| list.iter().position(|t| t.id == id);
+                let to = position_of(track.id);
+                let from = self.anchor.and_then(position_of).or(to);
+                if let (Some(from), Some(to)) = (from, to) {
+                    for track in &list[from.min(to)..=from.max(to)] {
+                        if !self.contains(&track.id) {
+                            self.tracks.push_back(track.clone());
+                        }
+                    }
+                }
+                if self.anchor.is_none() {
+                    self.anchor = Some(track.id);
+                }
+            }
+        }
+    }
+
+    pub fn track_ids(&self) -> Vector<TrackId> {
+        self.tracks.iter().map(|track| track.id).collect()
+    }
+
+    /// Spotify URIs of the selected tracks.  Local files have none and are left
+    /// out.
+    pub fn track_uris(&self) -> Vector<Arc<str>> {
+        self.tracks
+            .iter()
+            .filter(|track| !track.is_local)
+            .filter_map(|track| track.id.0.to_uri())
+            .map(Arc::from)
+            .collect()
+    }
+
+    /// Links to the selected tracks, one per line.
+    pub fn urls(&self) -> String {
+        self.tracks
+            .iter()
+            .map(|track| track.url())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
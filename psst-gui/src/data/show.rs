@@ -103,14 +103,54 @@ pub struct EpisodeLink {
     pub name: Arc<str>,
 }
 
-#[derive(Clone, Debug, Data, Lens, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Data, Lens, Deserialize, Serialize)]
 pub struct ResumePoint {
     pub fully_played: bool,
     #[serde(rename = "resume_position_ms")]
     #[serde(deserialize_with = "super::utils::deserialize_millis")]
+    #[serde(serialize_with = "super::utils::serialize_millis")]
     pub resume_position: Duration,
 }
 
+impl ResumePoint {
+    /// Fraction of `duration` that has been played, in 0.0..=1.0 range.
+    pub fn progress(&self, duration: Duration) -> f64 {
+        if self.fully_played {
+            1.0
+        } else if duration.is_zero() {
+            0.0
+        } else {
+            (self.resume_position.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+        }
+    }
+}
+
+/// Resume point saved by this client, together with the one Spotify reported
+/// at the time.  Spotify has no timestamps on its resume points, but it only
+/// reports a different one after the episode was played on another device, so
+/// then the reported one is the more recent.
+#[derive(Clone, Debug, PartialEq, Eq, Data, Deserialize, Serialize)]
+pub struct LocalResumePoint {
+    #[serde(flatten)]
+    pub point: ResumePoint,
+    #[serde(default)]
+    pub remote: Option<ResumePoint>,
+    /// When the point was saved, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+impl LocalResumePoint {
+    /// The more recent of this point and `remote`, the one Spotify reports
+    /// now.
+    pub fn resolve(&self, remote: Option<&ResumePoint>) -> ResumePoint {
+        match remote {
+            Some(remote) if self.remote.as_ref() != Some(remote) => remote.clone(),
+            _ => self.point.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash, Deserialize, Serialize)]
 #[serde(try_from = "String")]
 #[serde(into = "String")]
@@ -0,0 +1,327 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use druid::{im::Vector, Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    history::TrackPlays, utils::unix_now, PlaylistLink, PlaylistTracks, Promise, Track, TrackId,
+};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Data, Lens)]
+pub struct SmartPlaylistDetail {
+    /// Copy of the playlist that is being edited, saved into the config on
+    /// request.
+    pub playlist: SmartPlaylist,
+    pub tracks: Promise<SmartPlaylistTracks, SmartPlaylistLink>,
+}
+
+/// Locally defined playlist, made of the saved and playlist tracks matching a
+/// set of rules.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct SmartPlaylist {
+    pub id: Arc<str>,
+    pub name: String,
+    pub matching: RuleMatching,
+    pub rules: Vector<SmartRule>,
+}
+
+impl SmartPlaylist {
+    pub fn new(name: &str) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self {
+            id: format!("{nanos:x}").into(),
+            name: name.to_string(),
+            matching: RuleMatching::All,
+            rules: Vector::unit(SmartRule::new(RuleField::Saved)),
+        }
+    }
+
+    pub fn link(&self) -> SmartPlaylistLink {
+        SmartPlaylistLink {
+            id: self.id.clone(),
+            name: self.name.as_str().into(),
+        }
+    }
+
+    /// Names of the playlists the rules depend on, lowercased.
+    pub fn playlist_names(&self) -> HashSet<String> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.field == RuleField::Playlist)
+            .map(|rule| rule.value.trim().to_lowercase())
+            .collect()
+    }
+
+    fn matches(&self, track: &Track, ctx: &RuleContext) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+        match self.matching {
+            RuleMatching::All => self.rules.iter().all(|rule| rule.matches(track, ctx)),
+            RuleMatching::Any => self.rules.iter().any(|rule| rule.matches(track, ctx)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data, Lens, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SmartPlaylistLink {
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Data, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleMatching {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct SmartRule {
+    pub field: RuleField,
+    pub op: RuleOp,
+    pub value: String,
+}
+
+impl SmartRule {
+    pub fn new(field: RuleField) -> Self {
+        Self {
+            field,
+            op: field.ops()[0],
+            value: String::new(),
+        }
+    }
+
+    /// Change the field, keeping the operator if the new field supports it.
+    pub fn set_field(&mut self, field: RuleField) {
+        self.field = field;
+        if !field.ops().contains(&self.op) {
+            self.op = field.ops()[0];
+        }
+    }
+
+    fn matches(&self, track: &Track, ctx: &RuleContext) -> bool {
+        match self.field {
+            RuleField::Saved => ctx.saved.contains(&track.id) == (self.op == RuleOp::Is),
+            RuleField::Playlist => {
+                let is_member = ctx
+                    .playlists
+                    .get(&self.value.trim().to_lowercase())
+                    .is_some_and(|tracks| tracks.contains(&track.id));
+                is_member == (self.op == RuleOp::Is)
+            }
+            RuleField::Artist => self.matches_text(track.artists.iter().map(|a| &*a.name)),
+            RuleField::Album => self.matches_text(track.album.iter().map(|a| &*a.name)),
+            RuleField::Year => self.matches_number(
+                track
+                    .album
+                    .as_ref()
+                    .and_then(|album| album.release_year)
+                    .map(f64::from),
+            ),
+            RuleField::Duration => self.matches_number(Some(track.duration.as_secs_f64() / 60.0)),
+            RuleField::Popularity => self.matches_number(track.popularity.map(f64::from)),
+            RuleField::PlayCount => {
+                let count = ctx.plays.get(&track.id).map_or(0, |plays| plays.count);
+                self.matches_number(Some(count as f64))
+            }
+            RuleField::LastPlayed => {
+                let Ok(days) = self.value.trim().parse::<u64>() else {
+                    return false;
+                };
+                let since = ctx.now.saturating_sub(days * DAY_SECS);
+                let played_since = ctx
+                    .plays
+                    .get(&track.id)
+                    .is_some_and(|plays| plays.last_played >= since);
+                played_since == (self.op == RuleOp::InLast)
+            }
+        }
+    }
+
+    fn matches_text<'a>(&self, mut values: impl Iterator<Item = &'a str>) -> bool {
+        let pattern = self.value.trim().to_lowercase();
+        match self.op {
+            RuleOp::Is => values.any(|value| value.to_lowercase() == pattern),
+            RuleOp::IsNot => values.all(|value| value.to_lowercase() != pattern),
+            RuleOp::Contains => values.any(|value| value.to_lowercase().contains(&pattern)),
+            _ => false,
+        }
+    }
+
+    fn matches_number(&self, value: Option<f64>) -> bool {
+        let (Some(value), Ok(limit)) = (value, self.value.trim().parse::<f64>()) else {
+            return false;
+        };
+        match self.op {
+            RuleOp::Is => value == limit,
+            RuleOp::AtLeast => value >= limit,
+            RuleOp::AtMost => value <= limit,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Data, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleField {
+    Saved,
+    Playlist,
+    Artist,
+    Album,
+    Year,
+    Duration,
+    Popularity,
+    PlayCount,
+    LastPlayed,
+}
+
+impl RuleField {
+    pub const ALL: [Self; 9] = [
+        Self::Saved,
+        Self::Playlist,
+        Self::Artist,
+        Self::Album,
+        Self::Year,
+        Self::Duration,
+        Self::Popularity,
+        Self::PlayCount,
+        Self::LastPlayed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Saved => "Saved Track",
+            Self::Playlist => "Playlist",
+            Self::Artist => "Artist",
+            Self::Album => "Album",
+            Self::Year => "Album Year",
+            Self::Duration => "Duration",
+            Self::Popularity => "Popularity",
+            Self::PlayCount => "Play Count",
+            Self::LastPlayed => "Last Played",
+        }
+    }
+
+    pub fn ops(self) -> &'static [RuleOp] {
+        match self {
+            Self::Saved | Self::Playlist => &[RuleOp::Is, RuleOp::IsNot],
+            Self::Artist | Self::Album => &[RuleOp::Is, RuleOp::IsNot, RuleOp::Contains],
+            Self::Duration => &[RuleOp::AtLeast, RuleOp::AtMost],
+            Self::Year | Self::Popularity | Self::PlayCount => {
+                &[RuleOp::Is, RuleOp::AtLeast, RuleOp::AtMost]
+            }
+            Self::LastPlayed => &[RuleOp::InLast, RuleOp::NotInLast],
+        }
+    }
+
+    pub fn has_value(self) -> bool {
+        self != Self::Saved
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Duration => "min",
+            Self::LastPlayed => "days",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Data, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleOp {
+    Is,
+    IsNot,
+    Contains,
+    AtLeast,
+    AtMost,
+    InLast,
+    NotInLast,
+}
+
+impl RuleOp {
+    pub fn name(self, field: RuleField) -> &'static str {
+        match (field, self) {
+            (RuleField::Saved, Self::Is) => "yes",
+            (RuleField::Saved, Self::IsNot) => "no",
+            (_, Self::Is) => "is",
+            (_, Self::IsNot) => "is not",
+            (_, Self::Contains) => "contains",
+            (_, Self::AtLeast) => "is at least",
+            (_, Self::AtMost) => "is at most",
+            (_, Self::InLast) => "in the last",
+            (_, Self::NotInLast) => "not in the last",
+        }
+    }
+}
+
+/// Data the rules are evaluated against, besides the tracks themselves.
+struct RuleContext<'a> {
+    saved: HashSet<TrackId>,
+    playlists: HashMap<String, HashSet<TrackId>>,
+    plays: &'a HashMap<TrackId, TrackPlays>,
+    now: u64,
+}
+
+/// Tracks a smart playlist can be made of.
+#[derive(Clone, Data)]
+pub struct SmartPlaylistPool {
+    pub saved_tracks: Vector<Arc<Track>>,
+    pub playlists: Vector<PlaylistTracks>,
+}
+
+impl SmartPlaylistPool {
+    pub fn evaluate(
+        &self,
+        playlist: &SmartPlaylist,
+        plays: &HashMap<TrackId, TrackPlays>,
+    ) -> Vector<Arc<Track>> {
+        let ctx = RuleContext {
+            saved: self.saved_tracks.iter().map(|track| track.id).collect(),
+            playlists: self
+                .playlists
+                .iter()
+                .map(|playlist| {
+                    (
+                        playlist.name.to_lowercase(),
+                        playlist.tracks.iter().map(|track| track.id).collect(),
+                    )
+                })
+                .collect(),
+            plays,
+            now: unix_now(),
+        };
+        let mut seen = HashSet::new();
+        self.saved_tracks
+            .iter()
+            .chain(self.playlists.iter().flat_map(|playlist| &playlist.tracks))
+            .filter(|track| seen.insert(track.id))
+            .filter(|track| playlist.matches(track, &ctx))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct SmartPlaylistTracks {
+    pub link: SmartPlaylistLink,
+    pub tracks: Vector<Arc<Track>>,
+}
+
+/// Request to evaluate a smart playlist, carrying the library data it depends
+/// on.
+#[derive(Clone, Data)]
+pub struct SmartPlaylistLoad {
+    pub playlist: SmartPlaylist,
+    /// Saved tracks, if they are already loaded in the library.
+    pub saved_tracks: Option<Vector<Arc<Track>>>,
+    pub playlists: Vector<PlaylistLink>,
+}
@@ -1,14 +1,15 @@
 use std::{
     convert::TryFrom,
     fmt, hash,
-    sync::Arc,
-    time::{Duration, SystemTime},
+    sync::{Arc, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use druid::{im::Vector, Data, Lens};
+use regex::Regex;
 use sanitize_html::rules::predefined::DEFAULT;
 use sanitize_html::sanitize_str;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use time::{Date, Month};
 
 #[derive(Clone, Data, Lens)]
@@ -41,7 +42,7 @@ impl<T: Data> Cached<T> {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Data, Deserialize)]
 pub struct Page<T: Clone> {
     pub items: Vector<T>,
     pub limit: usize,
@@ -74,6 +75,14 @@ impl Image {
     }
 }
 
+/// Current time, in seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 pub fn default_str() -> Arc<str> {
     "".into()
 }
@@ -133,6 +142,13 @@ where
     Ok(duration)
 }
 
+pub fn serialize_millis<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
 pub fn deserialize_date<'de, D>(deserializer: D) -> Result<Date, D::Error>
 where
     D: Deserializer<'de>,
@@ -158,6 +174,27 @@ where
     Ok(Option::deserialize(deserializer)?.map(|Wrapper(val)| val))
 }
 
+/// Read the year of a release, either from a date string returned by the Web
+/// API, or from a plain year.
+pub fn deserialize_release_year<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum YearOrDate {
+        Year(i32),
+        Date(String),
+    }
+
+    Ok(
+        Option::deserialize(deserializer)?.and_then(|value| match value {
+            YearOrDate::Year(year) => Some(year),
+            YearOrDate::Date(date) => date.split('-').next()?.parse().ok(),
+        }),
+    )
+}
+
 pub fn deserialize_first_page<'de, D, T>(deserializer: D) -> Result<Vector<T>, D::Error>
 where
     T: Clone,
@@ -180,3 +217,20 @@ pub fn sanitize_html_string(text: &str) -> Arc<str> {
     let sanitized = sanitize_str(&DEFAULT, text).unwrap_or_default();
     Arc::from(sanitized.replace("&amp;", "&"))
 }
+
+/// Lowercase `text` and strip the decorations commonly added to track names,
+/// like "(Remastered)" or " - Live", so that variants of a name compare equal.
+pub fn normalize_name(text: &str) -> String {
+    static DECORATIONS: OnceLock<Regex> = OnceLock::new();
+    let decorations =
+        DECORATIONS.get_or_init(|| Regex::new(r"\([^)]*\)|\[[^\]]*\]| - .*$").unwrap());
+
+    decorations
+        .replace_all(&text.to_lowercase(), "")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -7,7 +7,9 @@ use std::fs;
 use threadpool::ThreadPool;
 
 use crate::ui::playlist::{
-    RENAME_PLAYLIST, RENAME_PLAYLIST_CONFIRM, UNFOLLOW_PLAYLIST, UNFOLLOW_PLAYLIST_CONFIRM,
+    CREATE_PLAYLIST, CREATE_PLAYLIST_CONFIRM, EDIT_PLAYLIST, EDIT_PLAYLIST_CONFIRM,
+    UNFOLLOW_PLAYLIST, UNFOLLOW_PLAYLIST_CONFIRM, UPLOAD_PLAYLIST_COVER,
+    UPLOAD_PLAYLIST_COVER_CONFIRM,
 };
 use crate::ui::theme;
 use crate::ui::DOWNLOAD_ARTWORK;
@@ -24,6 +26,8 @@ pub struct Delegate {
     preferences_window: Option<WindowId>,
     credits_window: Option<WindowId>,
     artwork_window: Option<WindowId>,
+    duplicates_window: Option<WindowId>,
+    mini_player_window: Option<WindowId>,
     image_pool: ThreadPool,
     size_updated: bool,
 }
@@ -37,6 +41,8 @@ impl Delegate {
             preferences_window: None,
             credits_window: None,
             artwork_window: None,
+            duplicates_window: None,
+            mini_player_window: None,
             image_pool: ThreadPool::with_name("image_loading".into(), MAX_IMAGE_THREADS),
             size_updated: false,
         }
@@ -128,6 +134,43 @@ impl Delegate {
     fn show_artwork(&mut self, ctx: &mut DelegateCtx) {
         Self::show_or_create_window(&mut self.artwork_window, ui::artwork_window, ctx);
     }
+
+    fn show_duplicates(&mut self, ctx: &mut DelegateCtx) {
+        Self::show_or_create_window(
+            &mut self.duplicates_window,
+            ui::duplicates::duplicates_window,
+            ctx,
+        );
+    }
+
+    fn show_mini_player(&mut self, config: &Config, ctx: &mut DelegateCtx) {
+        let config_clone = config.clone();
+        Self::show_or_create_window(
+            &mut self.mini_player_window,
+            || ui::mini_player_window(&config_clone),
+            ctx,
+        );
+        if config.mini_player_hides_main {
+            if let Some(id) = self.main_window {
+                ctx.submit_command(commands::HIDE_WINDOW.to(id));
+            }
+        }
+    }
+
+    /// Playback commands submitted from the mini player window.  The playback
+    /// controller lives in the main window, so these get forwarded there.
+    fn is_mini_player_command(&self, target: Target, cmd: &Command) -> bool {
+        self.mini_player_window.map(Target::Window) == Some(target)
+            && (cmd.is(cmd::PLAY_PREVIOUS)
+                || cmd.is(cmd::PLAY_PAUSE)
+                || cmd.is(cmd::PLAY_PAUSE_OR_RESUME)
+                || cmd.is(cmd::PLAY_RESUME)
+                || cmd.is(cmd::PLAY_NEXT)
+                || cmd.is(cmd::PLAY_STOP)
+                || cmd.is(cmd::PLAY_SEEK)
+                || cmd.is(cmd::PLAY_SEEK_FORWARD)
+                || cmd.is(cmd::PLAY_SEEK_BACKWARD))
+    }
 }
 
 impl AppDelegate<AppState> for Delegate {
@@ -139,7 +182,12 @@ impl AppDelegate<AppState> for Delegate {
         data: &mut AppState,
         _env: &Env,
     ) -> Handled {
-        if cmd.is(cmd::SHOW_CREDITS_WINDOW) {
+        if self.is_mini_player_command(target, cmd) {
+            if let Some(id) = self.main_window {
+                ctx.submit_command(cmd.clone().to(id));
+            }
+            Handled::Yes
+        } else if cmd.is(cmd::SHOW_CREDITS_WINDOW) {
             let _window_id = self.show_credits(ctx);
             if let Some(track) = cmd.get(cmd::SHOW_CREDITS_WINDOW) {
                 ctx.submit_command(
@@ -149,6 +197,10 @@ impl AppDelegate<AppState> for Delegate {
                 );
             }
             Handled::Yes
+        } else if let Some(source) = cmd.get(cmd::SHOW_DUPLICATES_WINDOW) {
+            self.show_duplicates(ctx);
+            ctx.submit_command(cmd::LOAD_DUPLICATES.with(source.clone()).to(Target::Global));
+            Handled::Yes
         } else if cmd.is(cmd::SHOW_MAIN) {
             self.show_main(&data.config, ctx);
             Handled::Yes
@@ -174,6 +226,15 @@ impl AppDelegate<AppState> for Delegate {
                 }
             }
             Handled::No
+        } else if let Some(history) = cmd.get(cmd::LISTENING_HISTORY_LOADED) {
+            data.set_listening_history(history.clone());
+            Handled::Yes
+        } else if let Some((episode, played)) = cmd.get(cmd::SET_EPISODE_PLAYED) {
+            data.set_episode_played(episode, *played);
+            Handled::Yes
+        } else if let Some(selection) = cmd.get(cmd::SET_SELECTION) {
+            data.common_ctx_mut().selection = selection.clone();
+            Handled::Yes
         } else if let Some(text) = cmd.get(cmd::COPY) {
             Application::global().clipboard().put_string(text);
             Handled::Yes
@@ -185,8 +246,14 @@ impl AppDelegate<AppState> for Delegate {
         } else if let Some(link) = cmd.get(UNFOLLOW_PLAYLIST_CONFIRM) {
             ctx.submit_command(UNFOLLOW_PLAYLIST.with(link.clone()));
             Handled::Yes
-        } else if let Some(link) = cmd.get(RENAME_PLAYLIST_CONFIRM) {
-            ctx.submit_command(RENAME_PLAYLIST.with(link.clone()));
+        } else if let Some(details) = cmd.get(EDIT_PLAYLIST_CONFIRM) {
+            ctx.submit_command(EDIT_PLAYLIST.with(details.clone()));
+            Handled::Yes
+        } else if let Some(cover) = cmd.get(UPLOAD_PLAYLIST_COVER_CONFIRM) {
+            ctx.submit_command(UPLOAD_PLAYLIST_COVER.with(cover.clone()));
+            Handled::Yes
+        } else if let Some(name) = cmd.get(CREATE_PLAYLIST_CONFIRM) {
+            ctx.submit_command(CREATE_PLAYLIST.with(name.clone()));
             Handled::Yes
         } else if cmd.is(cmd::QUIT_APP_WITH_SAVE) {
             ctx.submit_command(commands::QUIT_APP);
@@ -196,6 +263,9 @@ impl AppDelegate<AppState> for Delegate {
         } else if cmd.is(crate::cmd::SHOW_ARTWORK) {
             self.show_artwork(ctx);
             Handled::Yes
+        } else if cmd.is(cmd::SHOW_MINI_PLAYER) {
+            self.show_mini_player(&data.config, ctx);
+            Handled::Yes
         } else if let Some((url, title)) = cmd.get(DOWNLOAD_ARTWORK) {
             let safe_title = title.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
             let file_name = format!("{safe_title} cover.jpg");
@@ -241,6 +311,8 @@ impl AppDelegate<AppState> for Delegate {
         }
         if self.main_window == Some(id) {
             data.config.volume = data.playback.volume;
+            data.record_episode_progress();
+            data.save_episode_resume_points();
             data.config.save();
             ctx.submit_command(commands::CLOSE_ALL_WINDOWS);
             ctx.submit_command(commands::QUIT_APP);
@@ -248,6 +320,19 @@ impl AppDelegate<AppState> for Delegate {
         if self.artwork_window == Some(id) {
             self.artwork_window = None;
         }
+        if self.duplicates_window == Some(id) {
+            self.duplicates_window = None;
+            data.duplicates.clear();
+        }
+        if self.mini_player_window == Some(id) {
+            self.mini_player_window = None;
+            data.config.save();
+            if data.config.mini_player_hides_main {
+                if let Some(id) = self.main_window {
+                    ctx.submit_command(commands::SHOW_WINDOW.to(id));
+                }
+            }
+        }
     }
 
     fn event(
@@ -266,10 +351,22 @@ impl AppDelegate<AppState> for Delegate {
                     data.config.window_size = size;
                 }
             }
+        } else if self.mini_player_window == Some(window_id) {
+            match &event {
+                Event::WindowSize(size) => {
+                    data.config.mini_player_size = *size;
+                }
+                Event::KeyDown(key_event) if key_event.key == druid::KbKey::Escape => {
+                    ctx.submit_command(commands::CLOSE_WINDOW.to(window_id));
+                    return None;
+                }
+                _ => {}
+            }
         } else if [
             self.preferences_window,
             self.artwork_window,
             self.credits_window,
+            self.duplicates_window,
         ]
         .contains(&Some(window_id))
         {
@@ -292,9 +389,10 @@ impl Delegate {
         cmd: &Command,
         _data: &mut AppState,
     ) -> Handled {
-        if let Some(location) = cmd.get(remote_image::REQUEST_DATA).cloned() {
+        if let Some(request) = cmd.get(remote_image::REQUEST_DATA).cloned() {
             let sink = ctx.get_external_handle();
-            if let Some(image_buf) = WebApi::global().get_cached_image(&location) {
+            let location = request.location;
+            if let Some(image_buf) = WebApi::global().get_cached_image(&location, request.size) {
                 let payload = remote_image::ImagePayload {
                     location,
                     image_buf,
@@ -303,7 +401,7 @@ impl Delegate {
                     .unwrap();
             } else {
                 self.image_pool.execute(move || {
-                    let result = WebApi::global().get_image(location.clone());
+                    let result = WebApi::global().get_image(location.clone(), request.size);
                     match result {
                         Ok(image_buf) => {
                             let payload = remote_image::ImagePayload {
@@ -5,6 +5,8 @@ use druid::Data;
 #[derive(Clone, Debug, Data)]
 pub enum Error {
     WebApiError(String),
+    PlaylistFileError(String),
+    ThemeError(String),
 }
 
 impl error::Error for Error {}
@@ -13,6 +15,8 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::WebApiError(err) => f.write_str(err),
+            Self::PlaylistFileError(err) => f.write_str(err),
+            Self::ThemeError(err) => f.write_str(err),
         }
     }
 }
@@ -10,14 +10,16 @@ mod ui;
 mod webapi;
 mod widget;
 
-use druid::AppLauncher;
+use std::thread;
+
+use druid::{AppLauncher, Target};
 use env_logger::{Builder, Env};
 use webapi::WebApi;
 
 use psst_core::cache::Cache;
 
 use crate::{
-    data::{AppState, Config},
+    data::{AppState, Config, ListeningHistory},
     delegate::Delegate,
 };
 
@@ -56,6 +58,7 @@ fn main() {
         paginated_limit,
     )
     .install_as_global();
+    WebApi::global().set_lyrics_providers(webapi::lyrics_providers(&state.config));
 
     // Share the core session so the WebApi can authenticate `api-partner`
     // (pathfinder GraphQL) calls with first-party tokens.
@@ -103,6 +106,20 @@ fn main() {
         launcher = AppLauncher::with_window(window).configure_env(ui::theme::setup);
     };
 
+    // Let the WebApi reload pages whose cached data got refreshed.
+    WebApi::global().set_event_sink(launcher.get_external_handle());
+
+    // The listening history grows with every listen, read it in the background.
+    let event_sink = launcher.get_external_handle();
+    thread::spawn(move || {
+        let history = ListeningHistory::load();
+        if let Err(err) =
+            event_sink.submit_command(cmd::LISTENING_HISTORY_LOADED, history, Target::Auto)
+        {
+            log::error!("failed to pass the listening history: {err}");
+        }
+    });
+
     launcher
         .delegate(delegate)
         .launch(state)
@@ -8,15 +8,15 @@ use druid::{
 use crate::{
     cmd,
     data::{
-        Album, AlbumDetail, AlbumLink, AppState, ArtistLink, Cached, Ctx, Library, Nav, Playable,
-        PlaybackOrigin, WithCtx,
+        Album, AlbumDetail, AlbumLink, AppState, ArtistLink, Cached, Ctx, ExportSource, Library,
+        Nav, Playable, PlaybackOrigin, PlaylistAddTracks, WithCtx,
     },
     ui::playable::PlayableIter,
     webapi::WebApi,
     widget::{icons, Async, MyWidgetExt, RemoteImage},
 };
 
-use super::{artist, library, playable, theme, track, utils};
+use super::{artist, library, playable, playlist, playlist_file, theme, track, utils};
 
 pub const LOAD_DETAIL: Selector<AlbumLink> = Selector::new("app.album.load-detail");
 
@@ -94,6 +94,7 @@ fn cover_widget(size: f64) -> impl Widget<Arc<Album>> {
     RemoteImage::new(utils::placeholder_widget(), move |album: &Arc<Album>, _| {
         album.image(size, size).map(|image| image.url.clone())
     })
+    .thumbnail(size)
     .fix_size(size, size)
 }
 
@@ -212,6 +213,27 @@ fn album_menu(album: &Arc<Album>, library: &Arc<Library>) -> Menu<AppState> {
         )
         .command(cmd::COPY.with(album.url())),
     );
+    menu = menu.entry(playlist_file::export_menu(ExportSource::Album(
+        album.link(),
+    )));
+
+    let mut playlist_menu = Menu::new(
+        LocalizedString::new("menu-item-add-album-to-playlist")
+            .with_placeholder("Add Album to Playlist"),
+    );
+    for playlist in library.writable_playlists() {
+        playlist_menu = playlist_menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-save-to-playlist")
+                    .with_placeholder(format!("{}", playlist.name)),
+            )
+            .command(playlist::ADD_TRACKS.with(PlaylistAddTracks {
+                link: playlist.link(),
+                track_ids: album.tracks.iter().map(|track| track.id).collect(),
+            })),
+        );
+    }
+    menu = menu.entry(playlist_menu);
 
     menu = menu.separator();
 
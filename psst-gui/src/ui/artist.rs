@@ -134,6 +134,7 @@ pub fn cover_widget(size: f64) -> impl Widget<Artist> {
     RemoteImage::new(utils::placeholder_widget(), move |artist: &Artist, _| {
         artist.image(size, size).map(|image| image.url.clone())
     })
+    .thumbnail(size)
     .fix_size(size, size)
     .clip(Circle::new((radius, radius), radius))
 }
@@ -145,6 +146,7 @@ fn artist_info_widget() -> impl Widget<WithCtx<ArtistInfo>> {
         utils::placeholder_widget(),
         move |artist: &ArtistInfo, _| Some(artist.main_image.clone()),
     )
+    .thumbnail(size)
     .fix_size(size, size)
     .clip(Size::new(size, size).to_rounded_rect(4.0))
     .lens(Ctx::data());
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use druid::{
+    im::Vector,
+    widget::{Button, CrossAxisAlignment, Flex, Label, LineBreaking, List, Scroll},
+    Insets, LensExt, Selector, Target, Widget, WidgetExt, WindowDesc,
+};
+
+use crate::{
+    data::{AppState, DuplicateEntry, DuplicateGroup, DuplicateSource, Duplicates, Nav, Track},
+    error::Error,
+    ui::menu,
+    webapi::WebApi,
+    widget::{Async, Checkbox, ThemeScope},
+};
+
+use super::{playlist, theme, utils};
+
+const REMOVE_DUPLICATES: Selector<Duplicates> = Selector::new("app.duplicates.remove");
+
+pub fn duplicates_window() -> WindowDesc<AppState> {
+    let win = WindowDesc::new(duplicates_widget())
+        .window_size((theme::grid(80.0), theme::grid(60.0)))
+        .title(
+            |data: &AppState, _env: &_| match data.duplicates.deferred() {
+                Some(source) => format!("Duplicates in {}", source.name()),
+                None => "Duplicates".to_string(),
+            },
+        )
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+/// Load the tracks of `source` and group the duplicates among them.
+pub fn find_duplicates(source: DuplicateSource) -> Result<Duplicates, Error> {
+    let (snapshot_id, tracks) = match &source {
+        DuplicateSource::Playlist(link) => (
+            // Taken first, so the positions of the tracks are in this version.
+            Some(WebApi::global().get_playlist_snapshot_id(&link.id)?),
+            WebApi::global().get_playlist_tracks(&link.id)?,
+        ),
+        DuplicateSource::SavedTracks => (None, WebApi::global().get_saved_tracks()?),
+    };
+    Ok(Duplicates::find(source, snapshot_id, tracks))
+}
+
+fn duplicates_widget() -> impl Widget<AppState> {
+    ThemeScope::new(
+        Async::new(utils::spinner_widget, loaded_widget, utils::error_widget)
+            .lens(AppState::duplicates)
+            .on_command_async(
+                REMOVE_DUPLICATES,
+                |duplicates| remove_duplicates(&duplicates),
+                |_, data, _| data.info_alert("Removing duplicates…"),
+                |ctx, data, (duplicates, result)| match result {
+                    Ok(removed) => {
+                        match &duplicates.source {
+                            DuplicateSource::Playlist(link) => {
+                                data.with_library_mut(|library| {
+                                    library.decrement_playlist_track_count(link, removed.len())
+                                });
+                                if data.nav == Nav::PlaylistDetail(link.clone()) {
+                                    ctx.submit_command(
+                                        playlist::LOAD_DETAIL
+                                            .with((link.clone(), data.clone()))
+                                            .to(Target::Global),
+                                    );
+                                }
+                            }
+                            DuplicateSource::SavedTracks => {
+                                data.with_library_mut(|library| {
+                                    for track in &removed {
+                                        library.remove_track(&track.id);
+                                    }
+                                });
+                            }
+                        }
+                        data.info_alert(format!("Removed {} duplicate tracks.", removed.len()));
+                        ctx.window().close();
+                    }
+                    Err(err) => {
+                        data.error_alert(err);
+                    }
+                },
+            )
+            .background(theme::BACKGROUND_DARK)
+            .expand(),
+    )
+}
+
+fn loaded_widget() -> impl Widget<Duplicates> {
+    let title = Label::dynamic(|duplicates: &Duplicates, _| {
+        let name = duplicates.source.name();
+        match duplicates.groups.len() {
+            0 => format!("No duplicates found in {name}"),
+            1 => format!("1 group of duplicates in {name}"),
+            n => format!("{n} groups of duplicates in {name}"),
+        }
+    })
+    .with_text_size(theme::TEXT_SIZE_LARGE)
+    .padding(theme::grid(2.0));
+
+    let groups = Scroll::new(
+        List::new(group_widget)
+            .with_spacing(theme::grid(2.0))
+            .lens(Duplicates::groups)
+            .padding_horizontal(theme::grid(2.0)),
+    )
+    .vertical()
+    .expand();
+
+    let remove_button = Button::new("Remove Selected")
+        .fix_height(theme::grid(5.0))
+        .on_click(|ctx, duplicates: &mut Duplicates, _| {
+            if duplicates.removed().is_empty() {
+                ctx.window().close();
+            } else {
+                ctx.submit_command(REMOVE_DUPLICATES.with(duplicates.clone()));
+            }
+        });
+    let cancel_button = Button::new("Cancel")
+        .fix_height(theme::grid(5.0))
+        .fix_width(theme::grid(8.0))
+        .padding_left(theme::grid(2.0))
+        .on_click(|ctx, _, _| ctx.window().close());
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(title)
+        .with_flex_child(groups, 1.0)
+        .with_child(
+            Flex::row()
+                .with_child(remove_button)
+                .with_child(cancel_button)
+                .align_right()
+                .padding(theme::grid(2.0)),
+        )
+}
+
+fn group_widget() -> impl Widget<DuplicateGroup> {
+    let kind = Label::dynamic(|group: &DuplicateGroup, _| {
+        if group.exact {
+            "Same track added more than once".to_string()
+        } else {
+            "Same recording from different releases".to_string()
+        }
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    let entries = Scroll::new(
+        List::new(entry_widget)
+            .horizontal()
+            .with_spacing(theme::grid(1.0))
+            .lens(DuplicateGroup::entries),
+    )
+    .horizontal();
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(kind)
+        .with_spacer(theme::grid(0.5))
+        .with_child(entries)
+}
+
+/// One of the tracks of a group, shown next to the others for comparison.
+fn entry_widget() -> impl Widget<DuplicateEntry> {
+    let name = Label::raw()
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_line_break_mode(LineBreaking::Clip)
+        .lens(DuplicateEntry::track.then(Track::name.in_arc()));
+
+    let artist = Label::dynamic(|entry: &DuplicateEntry, _| entry.track.artist_names())
+        .with_line_break_mode(LineBreaking::Clip)
+        .with_text_size(theme::TEXT_SIZE_SMALL);
+
+    let album = Label::dynamic(|entry: &DuplicateEntry, _| entry.track.album_name().to_string())
+        .with_line_break_mode(LineBreaking::Clip)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    let duration = Label::dynamic(|entry: &DuplicateEntry, _| {
+        utils::as_minutes_and_seconds(entry.track.duration)
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(name)
+        .with_spacer(2.0)
+        .with_child(artist)
+        .with_spacer(2.0)
+        .with_child(album)
+        .with_spacer(2.0)
+        .with_child(duration)
+        .with_default_spacer()
+        .with_child(Checkbox::new("Remove").lens(DuplicateEntry::remove))
+        .fix_width(theme::grid(24.0))
+        .padding(Insets::uniform(theme::grid(1.0)))
+        .background(theme::BACKGROUND_LIGHT)
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+}
+
+/// Remove the marked duplicates, returning the removed tracks.
+fn remove_duplicates(duplicates: &Duplicates) -> Result<Vector<Arc<Track>>, Error> {
+    let removed = duplicates.removed();
+    match &duplicates.source {
+        DuplicateSource::Playlist(link) => {
+            let snapshot_id = duplicates.snapshot_id.as_deref().unwrap_or_default();
+            WebApi::global().remove_playlist_positions(
+                &link.id,
+                snapshot_id,
+                &duplicates.removed_positions(),
+            )?;
+        }
+        DuplicateSource::SavedTracks => {
+            let ids: Vec<_> = removed.iter().map(|track| track.id.0.to_base62()).collect();
+            WebApi::global().unsave_tracks(&ids)?;
+        }
+    }
+    Ok(removed)
+}
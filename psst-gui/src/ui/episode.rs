@@ -1,13 +1,14 @@
 use std::sync::Arc;
 
 use druid::{
-    widget::{CrossAxisAlignment, Flex, Label, LineBreaking},
-    LensExt, LocalizedString, Menu, MenuItem, Size, Widget, WidgetExt,
+    lens::Map,
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, Painter, SizedBox},
+    LensExt, LocalizedString, Menu, MenuItem, RenderContext, Size, Widget, WidgetExt,
 };
 
 use crate::{
     cmd,
-    data::{AppState, Episode, Library, Nav},
+    data::{AppState, Episode, Library, Nav, ResumePoint},
     widget::{fill_between::FillBetween, FadeOut, MyWidgetExt, RemoteImage},
 };
 
@@ -40,10 +41,34 @@ pub fn playable_widget() -> impl Widget<PlayRow<Arc<Episode>>> {
 
     let is_playing = playable::is_playing_marker_widget().lens(PlayRow::is_playing);
 
-    let duration = Label::<Arc<Episode>>::dynamic(|episode, _| utils::as_human(episode.duration))
+    let duration =
+        Label::<PlayRow<Arc<Episode>>>::dynamic(|row, _| match row.ctx.resume_point(&row.item) {
+            Some(point) if point.fully_played => "Played".to_string(),
+            Some(point) if !point.resume_position.is_zero() => {
+                let remaining = row.item.duration.saturating_sub(point.resume_position);
+                format!("{} left", utils::as_human(remaining))
+            }
+            _ => utils::as_human(row.item.duration),
+        })
         .with_text_size(theme::TEXT_SIZE_SMALL)
-        .with_text_color(theme::PLACEHOLDER_COLOR)
-        .lens(PlayRow::item);
+        .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    let progress = Either::new(
+        |row: &PlayRow<Arc<Episode>>, _| is_partially_played(row),
+        progress_bar_widget()
+            .padding_top(theme::grid(1.0))
+            .lens(Map::new(
+                |row: &PlayRow<Arc<Episode>>| {
+                    row.ctx
+                        .resume_point(&row.item)
+                        .map_or(0.0, |point| point.progress(row.item.duration))
+                },
+                |_, _| {
+                    // Ignore mutation.
+                },
+            )),
+        SizedBox::empty(),
+    );
 
     let top_row = Flex::row()
         .cross_axis_alignment(CrossAxisAlignment::Center)
@@ -69,6 +94,7 @@ pub fn playable_widget() -> impl Widget<PlayRow<Arc<Episode>>> {
         .with_child(top_row)
         .with_default_spacer()
         .with_child(content)
+        .with_child(progress)
         .padding(theme::grid(1.0))
         .link()
         .rounded(theme::BUTTON_BORDER_RADIUS)
@@ -76,11 +102,31 @@ pub fn playable_widget() -> impl Widget<PlayRow<Arc<Episode>>> {
         .context_menu(episode_row_menu)
 }
 
+fn is_partially_played(row: &PlayRow<Arc<Episode>>) -> bool {
+    matches!(
+        row.ctx.resume_point(&row.item),
+        Some(ResumePoint { fully_played: false, resume_position }) if !resume_position.is_zero()
+    )
+}
+
+fn progress_bar_widget() -> impl Widget<f64> {
+    Painter::new(|ctx, progress: &f64, env| {
+        let size = ctx.size();
+        let radius = size.height / 2.0;
+        ctx.fill(size.to_rounded_rect(radius), &env.get(theme::GREY_600));
+        let played = Size::new(size.width * progress, size.height);
+        ctx.fill(played.to_rounded_rect(radius), &env.get(theme::GREY_300));
+    })
+    .expand_width()
+    .fix_height(theme::grid(0.5))
+}
+
 fn cover_widget(size: f64) -> impl Widget<Arc<Episode>> {
     RemoteImage::new(
         utils::placeholder_widget(),
         move |episode: &Arc<Episode>, _| episode.image(size, size).map(|image| image.url.clone()),
     )
+    .thumbnail(size)
     .fix_size(size, size)
 }
 
@@ -90,7 +136,32 @@ fn rounded_cover_widget(size: f64) -> impl Widget<Arc<Episode>> {
 }
 
 fn episode_row_menu(row: &PlayRow<Arc<Episode>>) -> Menu<AppState> {
-    episode_menu(&row.item, &row.ctx.library)
+    let mut menu = episode_menu(&row.item, &row.ctx.library);
+
+    menu = menu.separator();
+
+    let is_played = row
+        .ctx
+        .resume_point(&row.item)
+        .is_some_and(|point| point.fully_played);
+    menu = if is_played {
+        menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-mark-as-unplayed")
+                    .with_placeholder("Mark as Unplayed"),
+            )
+            .command(cmd::SET_EPISODE_PLAYED.with((row.item.clone(), false))),
+        )
+    } else {
+        menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-mark-as-played").with_placeholder("Mark as Played"),
+            )
+            .command(cmd::SET_EPISODE_PLAYED.with((row.item.clone(), true))),
+        )
+    };
+
+    menu
 }
 
 pub fn episode_menu(episode: &Episode, _library: &Arc<Library>) -> Menu<AppState> {
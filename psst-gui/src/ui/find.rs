@@ -17,6 +17,13 @@ pub struct Find {
     query: FindQuery,
 }
 
+impl Find {
+    /// There is something to find, the finder was not cleared.
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+    }
+}
+
 #[derive(Clone)]
 struct Report {
     sender: WidgetId,
@@ -202,6 +209,8 @@ where
                 ctx.set_handled();
             }
             Event::KeyDown(k_e) if k_e.key == KbKey::Escape => {
+                // Clearing the query removes the highlights, like toggling.
+                data.reset();
                 data.show = false;
             }
             _ => {}
@@ -13,7 +13,7 @@ use crate::{
     widget::{Async, MyWidgetExt},
 };
 
-use super::{album, artist, playable, show, theme, track};
+use super::{album, artist, playable, recent, show, theme, track};
 use super::{
     playlist,
     utils::{error_widget, spinner_widget},
@@ -24,7 +24,7 @@ pub const LOAD_MADE_FOR_YOU: Selector = Selector::new("app.home.load-made-for-yo
 pub fn home_widget() -> impl Widget<AppState> {
     Flex::column()
         .with_child(made_for_you())
-        .with_child(jump_back_in())
+        .with_child(recent::jump_back_in_widget())
         .with_child(user_top_mixes())
         .with_child(recommended_stations())
         .with_child(best_of_artists())
@@ -223,23 +223,6 @@ pub fn your_shows() -> impl Widget<AppState> {
         )
 }
 
-fn jump_back_in() -> impl Widget<AppState> {
-    Async::new(spinner_widget, loaded_results_widget, || Empty)
-        .lens(
-            Ctx::make(
-                AppState::common_ctx,
-                AppState::home_detail.then(HomeDetail::jump_back_in),
-            )
-            .then(Ctx::in_promise()),
-        )
-        .on_command_async(
-            LOAD_MADE_FOR_YOU,
-            |_| WebApi::global().jump_back_in(),
-            |_, data, q| data.home_detail.jump_back_in.defer(q),
-            |_, data, r| data.home_detail.jump_back_in.update(r),
-        )
-}
-
 pub fn shows_that_you_might_like() -> impl Widget<AppState> {
     Async::new(spinner_widget, loaded_results_widget, || Empty)
         .lens(
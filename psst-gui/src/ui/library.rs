@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use druid::{
+    im::Vector,
     widget::{Flex, List},
     LensExt, Selector, Widget, WidgetExt,
 };
@@ -24,6 +25,8 @@ pub const LOAD_SHOWS: Selector = Selector::new("app.library.load-shows");
 
 pub const SAVE_TRACK: Selector<Arc<Track>> = Selector::new("app.library.save-track");
 pub const UNSAVE_TRACK: Selector<TrackId> = Selector::new("app.library.unsave-track");
+pub const SAVE_TRACKS: Selector<Vector<Arc<Track>>> = Selector::new("app.library.save-tracks");
+pub const UNSAVE_TRACKS: Selector<Vector<TrackId>> = Selector::new("app.library.unsave-tracks");
 
 pub const SAVE_ALBUM: Selector<Arc<Album>> = Selector::new("app.library.save-album");
 pub const UNSAVE_ALBUM: Selector<AlbumLink> = Selector::new("app.library.unsave-album");
@@ -103,6 +106,48 @@ pub fn saved_tracks_widget() -> impl Widget<AppState> {
             }
         },
     )
+    .on_command_async(
+        SAVE_TRACKS,
+        |tracks| {
+            let ids: Vec<_> = tracks.iter().map(|t| t.id.0.to_base62()).collect();
+            WebApi::global().save_tracks(&ids)
+        },
+        |_, data, tracks| {
+            data.with_library_mut(|library| {
+                for track in tracks {
+                    library.add_track(track);
+                }
+            });
+        },
+        |_, data, (tracks, r)| {
+            if let Err(err) = r {
+                data.error_alert(err);
+            } else {
+                data.info_alert(format!("{} tracks added to library.", tracks.len()))
+            }
+        },
+    )
+    .on_command_async(
+        UNSAVE_TRACKS,
+        |ids| {
+            let ids: Vec<_> = ids.iter().map(|i| i.0.to_base62()).collect();
+            WebApi::global().unsave_tracks(&ids)
+        },
+        |_, data, ids| {
+            data.with_library_mut(|library| {
+                for id in &ids {
+                    library.remove_track(id);
+                }
+            });
+        },
+        |_, data, (ids, r)| {
+            if let Err(err) = r {
+                data.error_alert(err);
+            } else {
+                data.info_alert(format!("{} tracks removed from library.", ids.len()))
+            }
+        },
+    )
 }
 
 pub fn saved_albums_widget() -> impl Widget<AppState> {
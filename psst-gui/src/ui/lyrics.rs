@@ -1,10 +1,13 @@
-use druid::widget::{Container, CrossAxisAlignment, Flex, Label, LineBreaking, List, Scroll};
-use druid::{Insets, LensExt, Selector, Widget, WidgetExt};
+use druid::widget::{
+    Container, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll,
+};
+use druid::{Insets, Selector, Widget, WidgetExt};
 
 use crate::cmd;
-use crate::data::{AppState, Ctx, NowPlaying, Playable, TrackLines};
-use crate::widget::MyWidgetExt;
-use crate::{webapi::WebApi, widget::Async};
+use crate::data::{AppState, Lyrics, LyricsLine, Nav, NowPlaying, Playable};
+use crate::error::Error;
+use crate::webapi::{self, WebApi};
+use crate::widget::{Async, Empty, MyWidgetExt};
 
 use super::theme;
 use super::utils;
@@ -65,32 +68,115 @@ fn track_lyrics_widget() -> impl Widget<AppState> {
     Async::new(
         utils::spinner_widget,
         || {
-            List::new(|| {
-                Label::raw()
-                    .with_line_break_mode(LineBreaking::WordWrap)
-                    .lens(Ctx::data().then(TrackLines::words))
-                    .expand_width()
-                    .center()
-                    .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
-                    .link()
-                    .rounded(theme::BUTTON_BORDER_RADIUS)
-                    .on_left_click(|ctx, _, c, _| {
-                        if c.data.start_time_ms.parse::<u64>().unwrap() != 0 {
-                            ctx.submit_command(
-                                cmd::SKIP_TO_POSITION
-                                    .with(c.data.start_time_ms.parse::<u64>().unwrap()),
-                            )
-                        }
-                    })
-            })
+            Flex::column()
+                .with_child(Either::new(
+                    |lyrics: &Lyrics, _| lyrics.synced,
+                    Empty,
+                    Label::new("These lyrics aren't synced to the track yet")
+                        .with_text_size(theme::TEXT_SIZE_SMALL)
+                        .with_text_color(theme::PLACEHOLDER_COLOR)
+                        .padding_bottom(theme::grid(2.0)),
+                ))
+                .with_child(List::new(line_widget).lens(Lyrics::lines))
         },
         || Label::new("No lyrics found for this track").center(),
     )
-    .lens(Ctx::make(AppState::common_ctx, AppState::lyrics).then(Ctx::in_promise()))
+    .lens(AppState::lyrics)
+}
+
+fn line_widget() -> impl Widget<LyricsLine> {
+    Label::raw()
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .lens(LyricsLine::words)
+        .expand_width()
+        .center()
+        .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .active(|line, _| line.is_current)
+        .on_left_click(|ctx, _, line, _| {
+            // Unsynced lines all start at zero.
+            if !line.start.is_zero() {
+                ctx.submit_command(cmd::SKIP_TO_POSITION.with(line.start.as_millis() as u64))
+            }
+        })
+        .on_update(|ctx, old_line, line, _| {
+            if line.is_current && !old_line.is_current {
+                // Keep a few lines around the current one in view.
+                let area = ctx.size().to_rect().inflate(0.0, theme::grid(12.0));
+                ctx.scroll_area_to_view(area);
+            }
+        })
+}
+
+/// Current and next line of the synced lyrics, shown above the playback
+/// controls.
+pub fn overlay_widget() -> impl Widget<AppState> {
+    let current = Label::dynamic(|data: &AppState, _| {
+        data.lyrics
+            .resolved()
+            .and_then(Lyrics::current_line)
+            .map_or_else(String::new, |line| line.words.to_string())
+    })
+    .with_font(theme::UI_FONT_MEDIUM)
+    .with_line_break_mode(LineBreaking::Clip);
+
+    let next = Label::dynamic(|data: &AppState, _| {
+        data.lyrics
+            .resolved()
+            .and_then(Lyrics::next_line)
+            .map_or_else(String::new, |line| line.words.to_string())
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .with_line_break_mode(LineBreaking::Clip);
+
+    Either::new(
+        |data: &AppState, _| {
+            data.config.show_lyrics_overlay
+                && data.playback.now_playing.is_some()
+                && data.lyrics.resolved().map_or(false, |lyrics| lyrics.synced)
+        },
+        Flex::column()
+            .with_child(current)
+            .with_spacer(2.0)
+            .with_child(next)
+            .padding(Insets::uniform_xy(theme::grid(2.0), theme::grid(1.0)))
+            .on_left_click(|ctx, _, _, _| ctx.submit_command(cmd::TOGGLE_LYRICS)),
+        Empty,
+    )
+    // The overlay is always present, unlike the lyrics page, so it loads the
+    // lyrics for both.
     .on_command_async(
         SHOW_LYRICS,
-        |t| WebApi::global().get_lyrics(t.item.id().to_base62()),
+        |np| match &np.item {
+            Playable::Track(track) => WebApi::global().get_lyrics(track),
+            Playable::Episode(_) => Err(Error::WebApiError(
+                "Podcast episodes don't have lyrics".to_string(),
+            )),
+        },
         |_, data, _| data.lyrics.defer(()),
-        |_, data, r| data.lyrics.update(((), r.1)),
+        |_, data, r| {
+            data.lyrics.update(((), r.1.map(Lyrics::new)));
+            let progress = data.playback.now_playing.as_ref().map(|np| np.progress);
+            if let (Some(lyrics), Some(progress)) = (data.lyrics.resolved_mut(), progress) {
+                lyrics.set_progress(progress);
+            }
+        },
     )
+    .on_update(|_, old_data, data, _| {
+        let old_config = &old_data.config;
+        let config = &data.config;
+        if old_config.lrclib_enable != config.lrclib_enable
+            || old_config.lrclib_url != config.lrclib_url
+        {
+            WebApi::global().set_lyrics_providers(webapi::lyrics_providers(config));
+        }
+    })
+}
+
+/// Lyrics are loaded when they are shown, either on the lyrics page or in the
+/// playback bar.
+pub fn are_lyrics_shown(data: &AppState) -> bool {
+    matches!(data.nav, Nav::Lyrics) || data.config.show_lyrics_overlay
 }
@@ -5,6 +5,78 @@ use crate::{
     data::{AppState, Nav},
 };
 
+/// Key shortcut of a menu item.
+#[derive(Clone, Copy, Debug)]
+pub struct Hotkey {
+    pub mods: SysMods,
+    pub key: &'static str,
+}
+
+impl Hotkey {
+    const fn cmd(key: &'static str) -> Self {
+        Self {
+            mods: SysMods::Cmd,
+            key,
+        }
+    }
+}
+
+const QUIT: Hotkey = Hotkey::cmd("q");
+const HIDE: Hotkey = Hotkey::cmd("h");
+const HIDE_OTHERS: Hotkey = Hotkey {
+    mods: SysMods::AltCmd,
+    key: "h",
+};
+const CUT: Hotkey = Hotkey::cmd("x");
+const COPY: Hotkey = Hotkey::cmd("c");
+const PASTE: Hotkey = Hotkey::cmd("v");
+const HOME: Hotkey = Hotkey::cmd("1");
+const SAVED_TRACKS: Hotkey = Hotkey::cmd("2");
+const SAVED_ALBUMS: Hotkey = Hotkey::cmd("3");
+const SAVED_SHOWS: Hotkey = Hotkey::cmd("4");
+const RECENTLY_PLAYED: Hotkey = Hotkey::cmd("5");
+const STATS: Hotkey = Hotkey::cmd("6");
+const SEARCH: Hotkey = Hotkey::cmd("l");
+const FIND: Hotkey = Hotkey::cmd("f");
+const COMMAND_PALETTE: Hotkey = Hotkey::cmd("k");
+const MINI_PLAYER: Hotkey = Hotkey {
+    mods: SysMods::CmdShift,
+    key: "m",
+};
+
+/// Shortcuts of all the items in `main_menu`.
+pub fn hotkeys() -> Vec<Hotkey> {
+    let mut hotkeys = vec![
+        CUT,
+        COPY,
+        PASTE,
+        HOME,
+        SAVED_TRACKS,
+        SAVED_ALBUMS,
+        SAVED_SHOWS,
+        RECENTLY_PLAYED,
+        STATS,
+        SEARCH,
+        FIND,
+        COMMAND_PALETTE,
+        MINI_PLAYER,
+    ];
+    if cfg!(target_os = "macos") {
+        hotkeys.extend([QUIT, HIDE, HIDE_OTHERS]);
+    }
+    hotkeys
+}
+
+trait MenuItemExt {
+    fn with_hotkey(self, hotkey: Hotkey) -> Self;
+}
+
+impl MenuItemExt for MenuItem<AppState> {
+    fn with_hotkey(self, hotkey: Hotkey) -> Self {
+        self.hotkey(hotkey.mods, hotkey.key)
+    }
+}
+
 pub fn main_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<AppState> {
     if cfg!(target_os = "macos") {
         Menu::empty().entry(mac_app_menu())
@@ -27,27 +99,40 @@ fn mac_app_menu() -> Menu<AppState> {
             //  because l10n is a bit stupid now.
             MenuItem::new(LocalizedString::new("macos-menu-quit").with_placeholder("Quit Psst"))
                 .command(cmd::QUIT_APP_WITH_SAVE)
-                .hotkey(SysMods::Cmd, "q"),
+                .with_hotkey(QUIT),
         )
         .entry(
             MenuItem::new(LocalizedString::new("macos-menu-hide").with_placeholder("Hide Psst"))
                 .command(commands::HIDE_APPLICATION)
-                .hotkey(SysMods::Cmd, "h"),
+                .with_hotkey(HIDE),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("macos-menu-hide-others").with_placeholder("Hide Others"),
             )
             .command(commands::HIDE_OTHERS)
-            .hotkey(SysMods::AltCmd, "h"),
+            .with_hotkey(HIDE_OTHERS),
         )
 }
 
 fn edit_menu() -> Menu<AppState> {
     Menu::new(LocalizedString::new("common-menu-edit-menu").with_placeholder("Edit"))
-        .entry(platform_menus::common::cut())
-        .entry(platform_menus::common::copy())
-        .entry(platform_menus::common::paste())
+        // Same as `platform_menus::common`, with the shortcuts listed above.
+        .entry(
+            MenuItem::new(LocalizedString::new("common-menu-cut"))
+                .command(commands::CUT)
+                .with_hotkey(CUT),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("common-menu-copy"))
+                .command(commands::COPY)
+                .with_hotkey(COPY),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("common-menu-paste"))
+                .command(commands::PASTE)
+                .with_hotkey(PASTE),
+        )
 }
 
 fn view_menu() -> Menu<AppState> {
@@ -55,37 +140,67 @@ fn view_menu() -> Menu<AppState> {
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-home").with_placeholder("Home"))
                 .command(cmd::NAVIGATE.with(Nav::Home))
-                .hotkey(SysMods::Cmd, "1"),
+                .with_hotkey(HOME),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-tracks").with_placeholder("Saved Tracks"),
             )
             .command(cmd::NAVIGATE.with(Nav::SavedTracks))
-            .hotkey(SysMods::Cmd, "2"),
+            .with_hotkey(SAVED_TRACKS),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-albums").with_placeholder("Saved Albums"),
             )
             .command(cmd::NAVIGATE.with(Nav::SavedAlbums))
-            .hotkey(SysMods::Cmd, "3"),
+            .with_hotkey(SAVED_ALBUMS),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-shows").with_placeholder("Saved Shows"),
             )
             .command(cmd::NAVIGATE.with(Nav::Shows))
-            .hotkey(SysMods::Cmd, "4"),
+            .with_hotkey(SAVED_SHOWS),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-recently-played")
+                    .with_placeholder("Recently Played"),
+            )
+            .command(cmd::NAVIGATE.with(Nav::RecentlyPlayed))
+            .with_hotkey(RECENTLY_PLAYED),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-stats").with_placeholder("Listening Stats"),
+            )
+            .command(cmd::NAVIGATE.with(Nav::Stats))
+            .with_hotkey(STATS),
         )
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-search").with_placeholder("Search..."))
                 .command(cmd::SET_FOCUS.to(cmd::WIDGET_SEARCH_INPUT))
-                .hotkey(SysMods::Cmd, "l"),
+                .with_hotkey(SEARCH),
         )
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-find").with_placeholder("Find..."))
                 .command(cmd::TOGGLE_FINDER)
-                .hotkey(SysMods::Cmd, "f"),
+                .with_hotkey(FIND),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-command-palette")
+                    .with_placeholder("Command Palette..."),
+            )
+            .command(cmd::TOGGLE_PALETTE)
+            .with_hotkey(COMMAND_PALETTE),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-mini-player").with_placeholder("Mini Player"),
+            )
+            .command(cmd::SHOW_MINI_PLAYER)
+            .with_hotkey(MINI_PLAYER),
         )
 }
@@ -4,11 +4,12 @@ use crate::error::Error;
 use crate::{
     cmd,
     controller::{
-        AfterDelay, AlertCleanupController, NavController, SessionController, SortController,
+        AfterDelay, AlertCleanupController, AppearanceController, NavController, SessionController,
+        SortController,
     },
     data::{
-        config::SortOrder, Alert, AlertStyle, AppState, Config, Nav, Playable, Playback, Route,
-        ALERT_DURATION,
+        config::SortOrder, Alert, AlertStyle, AppState, Config, DuplicateSource, ExportSource, Nav,
+        Playable, Playback, Route, ALERT_DURATION,
     },
     webapi::WebApi,
     widget::{
@@ -25,7 +26,8 @@ use druid::{
         CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll, Slider, Split,
         ViewSwitcher,
     },
-    Color, Env, Insets, Key, LensExt, Menu, MenuItem, Selector, Widget, WidgetExt, WindowDesc,
+    Color, Env, Insets, Key, LensExt, LocalizedString, Menu, MenuItem, Selector, Widget, WidgetExt,
+    WindowDesc,
 };
 use druid_shell::Cursor;
 use std::sync::Arc;
@@ -34,19 +36,25 @@ use std::time::Duration;
 pub mod album;
 pub mod artist;
 pub mod credits;
+pub mod duplicates;
 pub mod episode;
 pub mod find;
 pub mod home;
 pub mod library;
 pub mod lyrics;
 pub mod menu;
+pub mod palette;
 pub mod playable;
 pub mod playback;
 pub mod playlist;
+pub mod playlist_file;
 pub mod preferences;
+pub mod recent;
 pub mod recommend;
 pub mod search;
 pub mod show;
+pub mod smart_playlist;
+pub mod stats;
 pub mod theme;
 pub mod track;
 pub mod user;
@@ -142,6 +150,32 @@ pub fn artwork_window() -> WindowDesc<AppState> {
     }
 }
 
+pub fn mini_player_window(config: &Config) -> WindowDesc<AppState> {
+    let mut win = WindowDesc::new(mini_player_widget())
+        .title(compute_main_window_title)
+        .with_min_size((theme::grid(30.0), theme::grid(15.0)))
+        .window_size(config.mini_player_size)
+        .set_always_on_top(config.mini_player_on_top)
+        .show_title(false)
+        .transparent_titlebar(true);
+    if let Some(position) = config.mini_player_position {
+        win = win.set_position(position);
+    }
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+fn mini_player_widget() -> impl Widget<AppState> {
+    ThemeScope::new(
+        playback::mini_player_widget()
+            .background(theme::BACKGROUND_DARK)
+            .expand(),
+    )
+}
+
 fn preferences_widget() -> impl Widget<AppState> {
     ThemeScope::new(
         preferences::preferences_widget()
@@ -213,9 +247,15 @@ pub fn artwork_widget() -> impl Widget<AppState> {
 }
 
 fn root_widget() -> impl Widget<AppState> {
-    let playlists = Scroll::new(playlist::list_widget())
-        .vertical()
-        .expand_height();
+    let playlists = Scroll::new(
+        Flex::column()
+            .with_child(playlist::list_widget())
+            .with_child(playlist::new_playlist_widget())
+            .with_child(smart_playlist::list_widget())
+            .with_child(playlist_file::import_widget()),
+    )
+    .vertical()
+    .expand_height();
 
     let playlists = Flex::column()
         .must_fill_main_axis(true)
@@ -253,6 +293,7 @@ fn root_widget() -> impl Widget<AppState> {
     let main = Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(topbar)
+        .with_child(palette::palette_widget())
         .with_flex_child(Overlay::bottom(route_widget(), alert_widget()), 1.0)
         .with_child(playback::panel_widget())
         .background(theme::BACKGROUND_LIGHT);
@@ -266,6 +307,7 @@ fn root_widget() -> impl Widget<AppState> {
 
     ThemeScope::new(split)
         .controller(SessionController)
+        .controller(AppearanceController::new())
         .controller(NavController)
         .controller(SortController)
         .on_command_async(
@@ -287,6 +329,12 @@ fn root_widget() -> impl Widget<AppState> {
                 }
             },
         )
+        .on_command_async(
+            cmd::LOAD_DUPLICATES,
+            duplicates::find_duplicates,
+            |_, data, source| data.duplicates.defer(source),
+            |_, data, (source, result)| data.duplicates.update((source, result)),
+        )
     // .debug_invalidation()
     // .debug_widget_id()
     // .debug_paint_layout()
@@ -361,6 +409,14 @@ fn route_widget() -> impl Widget<AppState> {
             Route::Shows => Scroll::new(library::saved_shows_widget().padding(theme::grid(1.0)))
                 .vertical()
                 .boxed(),
+            Route::RecentlyPlayed => {
+                Scroll::new(recent::recently_played_widget().padding(theme::grid(1.0)))
+                    .vertical()
+                    .boxed()
+            }
+            Route::Stats => Scroll::new(stats::stats_widget().padding(theme::grid(1.0)))
+                .vertical()
+                .boxed(),
             Route::SearchResults => search::results_widget().padding(theme::grid(1.0)).boxed(),
             Route::AlbumDetail => Scroll::new(album::detail_widget().padding(theme::grid(1.0)))
                 .vertical()
@@ -378,6 +434,11 @@ fn route_widget() -> impl Widget<AppState> {
                     1.0,
                 )
                 .boxed(),
+            Route::SmartPlaylistDetail => {
+                Scroll::new(smart_playlist::detail_widget().padding(theme::grid(1.0)))
+                    .vertical()
+                    .boxed()
+            }
             Route::ShowDetail => Scroll::new(show::detail_widget().padding(theme::grid(1.0)))
                 .vertical()
                 .boxed(),
@@ -395,11 +456,23 @@ fn sidebar_menu_widget() -> impl Widget<AppState> {
     Flex::column()
         .with_default_spacer()
         .with_child(sidebar_link_widget("Home", Some(&icons::HOME), Nav::Home))
-        .with_child(sidebar_link_widget(
-            "Tracks",
-            Some(&icons::MUSIC_NOTE),
-            Nav::SavedTracks,
-        ))
+        .with_child(
+            sidebar_link_widget("Tracks", Some(&icons::MUSIC_NOTE), Nav::SavedTracks).context_menu(
+                |_| {
+                    Menu::empty()
+                        .entry(playlist_file::export_menu(ExportSource::SavedTracks))
+                        .entry(
+                            MenuItem::new(
+                                LocalizedString::new("menu-item-find-duplicates")
+                                    .with_placeholder("Find Duplicates"),
+                            )
+                            .command(
+                                cmd::SHOW_DUPLICATES_WINDOW.with(DuplicateSource::SavedTracks),
+                            ),
+                        )
+                },
+            ),
+        )
         .with_child(sidebar_link_widget(
             "Albums",
             Some(&icons::ALBUM),
@@ -410,7 +483,18 @@ fn sidebar_menu_widget() -> impl Widget<AppState> {
             Some(&icons::PODCAST),
             Nav::Shows,
         ))
+        .with_child(sidebar_link_widget(
+            "Recent",
+            Some(&icons::HISTORY),
+            Nav::RecentlyPlayed,
+        ))
+        .with_child(sidebar_link_widget(
+            "Stats",
+            Some(&icons::STATS),
+            Nav::Stats,
+        ))
         .with_child(search::input_widget().padding((theme::grid(1.0), theme::grid(1.0))))
+        .with_child(search::suggestions_widget())
 }
 
 fn sidebar_link_widget(
@@ -618,13 +702,19 @@ fn route_icon_widget() -> impl Widget<Nav> {
         |nav: &Nav, _, _| {
             let icon = |icon: &SvgIcon| icon.scale(theme::ICON_SIZE_MEDIUM);
             match &nav {
-                Nav::Home | Nav::Lyrics | Nav::SavedTracks | Nav::SavedAlbums | Nav::Shows => {
-                    Empty.boxed()
-                }
+                Nav::Home
+                | Nav::Lyrics
+                | Nav::SavedTracks
+                | Nav::SavedAlbums
+                | Nav::Shows
+                | Nav::RecentlyPlayed
+                | Nav::Stats => Empty.boxed(),
                 Nav::SearchResults(_) | Nav::Recommendations(_) => icon(&icons::SEARCH).boxed(),
                 Nav::AlbumDetail(_, _) => icon(&icons::ALBUM).boxed(),
                 Nav::ArtistDetail(_) => icon(&icons::ARTIST).boxed(),
-                Nav::PlaylistDetail(_) => icon(&icons::PLAYLIST).boxed(),
+                Nav::PlaylistDetail(_) | Nav::SmartPlaylistDetail(_) => {
+                    icon(&icons::PLAYLIST).boxed()
+                }
                 Nav::ShowDetail(_) => icon(&icons::PODCAST).boxed(),
             }
         },
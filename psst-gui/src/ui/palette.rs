@@ -0,0 +1,298 @@
+use std::{cmp::Reverse, sync::Arc, time::Duration};
+
+use druid::{
+    commands,
+    im::Vector,
+    lens::Map,
+    widget::{prelude::*, Controller, CrossAxisAlignment, Either, Flex, Label, List, TextBox},
+    Command, Data, Insets, KbKey, LensExt, Selector, WidgetExt,
+};
+use psst_core::player::sleep::SleepTimer;
+
+use crate::{
+    cmd,
+    controller::InputController,
+    data::{fuzzy_score, AppState, DuplicateSource, Nav, Palette, QueueBehavior},
+    ui::theme,
+    widget::{Empty, MyWidgetExt},
+};
+
+/// Entries shown at once, the rest is reachable by typing.
+const MAX_ENTRIES: usize = 12;
+
+const RUN_ENTRY: Selector<Command> = Selector::new("app.palette.run-entry");
+
+#[derive(Clone, Data)]
+struct PaletteEntry {
+    title: Arc<str>,
+    kind: &'static str,
+    #[data(ignore)]
+    command: Command,
+    is_selected: bool,
+}
+
+pub fn palette_widget() -> impl Widget<AppState> {
+    let input_id = WidgetId::next();
+
+    let input = TextBox::new()
+        .with_placeholder("Type a command, page, playlist or search...")
+        .controller(InputController::new())
+        .with_id(input_id)
+        .expand_width()
+        .lens(AppState::palette.then(Palette::query));
+
+    let entries = List::new(entry_widget).lens(Map::new(palette_entries, |_, _| {}));
+
+    let palette = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Fill)
+        .with_child(input)
+        .with_spacer(theme::grid(0.5))
+        .with_child(entries)
+        .padding(theme::grid(1.0))
+        .background(theme::GREY_600);
+
+    Either::new(|data: &AppState, _| data.palette.show, palette, Empty)
+        .controller(PaletteController { input_id })
+}
+
+fn entry_widget() -> impl Widget<PaletteEntry> {
+    let title = Label::dynamic(|entry: &PaletteEntry, _| entry.title.to_string())
+        .with_text_size(theme::TEXT_SIZE_NORMAL);
+
+    let kind = Label::dynamic(|entry: &PaletteEntry, _| entry.kind.to_string())
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    Flex::row()
+        .with_flex_child(title.expand_width(), 1.0)
+        .with_default_spacer()
+        .with_child(kind)
+        .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .active(|entry, _| entry.is_selected)
+        .on_left_click(|ctx, _, entry, _| {
+            ctx.submit_command(RUN_ENTRY.with(entry.command.clone()));
+        })
+}
+
+fn palette_entries(data: &AppState) -> Vector<PaletteEntry> {
+    let query = data.palette.query.trim();
+
+    let mut matches: Vec<_> = candidates(data)
+        .into_iter()
+        .filter_map(|(kind, title, command)| {
+            let score = fuzzy_score(query, &title)?;
+            Some((score, kind, title, command))
+        })
+        .collect();
+    matches.sort_by_key(|(score, ..)| Reverse(*score));
+
+    let mut entries: Vec<_> = matches
+        .into_iter()
+        .map(|(_, kind, title, command)| (kind, title, command))
+        .collect();
+    if !query.is_empty() {
+        // Searching the catalog is always possible, but never the best match.
+        entries.truncate(MAX_ENTRIES - 1);
+        entries.push((
+            "Search",
+            format!("Search for \"{query}\"").into(),
+            cmd::NAVIGATE.with(Nav::SearchResults(query.into())),
+        ));
+    }
+
+    let selected = data.palette.selected.min(entries.len().saturating_sub(1));
+    entries
+        .into_iter()
+        .take(MAX_ENTRIES)
+        .enumerate()
+        .map(|(index, (kind, title, command))| PaletteEntry {
+            title,
+            kind,
+            command,
+            is_selected: index == selected,
+        })
+        .collect()
+}
+
+fn candidates(data: &AppState) -> Vec<(&'static str, Arc<str>, Command)> {
+    let mut candidates = Vec::new();
+
+    for nav in [
+        Nav::Home,
+        Nav::SavedTracks,
+        Nav::SavedAlbums,
+        Nav::Shows,
+        Nav::RecentlyPlayed,
+        Nav::Stats,
+        Nav::Lyrics,
+    ] {
+        candidates.push(("Go to", nav.full_title().into(), cmd::NAVIGATE.with(nav)));
+    }
+
+    for (title, command) in actions() {
+        candidates.push(("Action", title.into(), command));
+    }
+
+    if let Some(playlists) = data.library.playlists.resolved() {
+        for playlist in playlists {
+            candidates.push((
+                "Playlist",
+                playlist.name.clone(),
+                cmd::NAVIGATE.with(Nav::PlaylistDetail(playlist.link())),
+            ));
+        }
+    }
+    for playlist in &data.config.smart_playlists {
+        candidates.push((
+            "Smart Playlist",
+            playlist.name.as_str().into(),
+            cmd::NAVIGATE.with(Nav::SmartPlaylistDetail(playlist.link())),
+        ));
+    }
+
+    for query in &data.config.search_history {
+        candidates.push((
+            "Recent Search",
+            query.clone(),
+            cmd::NAVIGATE.with(Nav::SearchResults(query.clone())),
+        ));
+    }
+
+    candidates
+}
+
+fn actions() -> Vec<(&'static str, Command)> {
+    vec![
+        ("Play / Pause", cmd::PLAY_PAUSE_OR_RESUME.into()),
+        ("Next Track", cmd::PLAY_NEXT.into()),
+        ("Previous Track", cmd::PLAY_PREVIOUS.into()),
+        ("Stop Playback", cmd::PLAY_STOP.into()),
+        ("Seek Forward", cmd::PLAY_SEEK_FORWARD.into()),
+        ("Seek Backward", cmd::PLAY_SEEK_BACKWARD.into()),
+        (
+            "Play in Order",
+            cmd::PLAY_QUEUE_BEHAVIOR.with(QueueBehavior::Sequential),
+        ),
+        (
+            "Shuffle",
+            cmd::PLAY_QUEUE_BEHAVIOR.with(QueueBehavior::Random),
+        ),
+        (
+            "Repeat Track",
+            cmd::PLAY_QUEUE_BEHAVIOR.with(QueueBehavior::LoopTrack),
+        ),
+        (
+            "Repeat All",
+            cmd::PLAY_QUEUE_BEHAVIOR.with(QueueBehavior::LoopAll),
+        ),
+        ("Normal Speed", cmd::PLAY_SPEED.with(1.0)),
+        ("Sleep in 15 Minutes", sleep_after(15)),
+        ("Sleep in 30 Minutes", sleep_after(30)),
+        ("Sleep in 60 Minutes", sleep_after(60)),
+        (
+            "Sleep at End of Track",
+            cmd::PLAY_SLEEP_TIMER.with(Some(SleepTimer::EndOfItem)),
+        ),
+        ("Cancel Sleep Timer", cmd::PLAY_SLEEP_TIMER.with(None)),
+        ("Toggle Lyrics", cmd::TOGGLE_LYRICS.into()),
+        ("Show Artwork", cmd::SHOW_ARTWORK.into()),
+        ("Mini Player", cmd::SHOW_MINI_PLAYER.into()),
+        ("Find...", cmd::TOGGLE_FINDER.into()),
+        ("Search...", cmd::SET_FOCUS.to(cmd::WIDGET_SEARCH_INPUT)),
+        ("Go Back", cmd::NAVIGATE_BACK.with(1)),
+        ("Refresh", cmd::NAVIGATE_REFRESH.into()),
+        ("Refresh All", cmd::NAVIGATE_REFRESH_ALL.into()),
+        ("Sort by Date Added", cmd::SORT_BY_DATE_ADDED.into()),
+        ("Sort by Title", cmd::SORT_BY_TITLE.into()),
+        ("Sort by Artist", cmd::SORT_BY_ARTIST.into()),
+        ("Sort by Album", cmd::SORT_BY_ALBUM.into()),
+        ("Sort by Duration", cmd::SORT_BY_DURATION.into()),
+        ("Toggle Sort Order", cmd::TOGGLE_SORT_ORDER.into()),
+        (
+            "Find Duplicates in Saved Tracks",
+            cmd::SHOW_DUPLICATES_WINDOW.with(DuplicateSource::SavedTracks),
+        ),
+        ("Preferences", commands::SHOW_PREFERENCES.into()),
+        ("Quit", cmd::QUIT_APP_WITH_SAVE.into()),
+    ]
+}
+
+fn sleep_after(minutes: u64) -> Command {
+    cmd::PLAY_SLEEP_TIMER.with(Some(SleepTimer::After(Duration::from_secs(minutes * 60))))
+}
+
+struct PaletteController {
+    input_id: WidgetId,
+}
+
+impl<W> Controller<AppState, W> for PaletteController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(cmd::TOGGLE_PALETTE) => {
+                data.palette.reset();
+                data.palette.show = !data.palette.show;
+                if data.palette.show {
+                    ctx.submit_command(cmd::SET_FOCUS.to(self.input_id));
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(RUN_ENTRY) => {
+                data.palette.close();
+                ctx.submit_command(cmd.get_unchecked(RUN_ENTRY).clone());
+                ctx.set_handled();
+            }
+            Event::KeyDown(k_e) if data.palette.show => match &k_e.key {
+                KbKey::ArrowUp => {
+                    let count = palette_entries(data).len();
+                    data.palette.select_previous(count);
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::ArrowDown => {
+                    let count = palette_entries(data).len();
+                    data.palette.select_next(count);
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::Enter => {
+                    if let Some(entry) = palette_entries(data)
+                        .into_iter()
+                        .find(|entry| entry.is_selected)
+                    {
+                        data.palette.close();
+                        ctx.submit_command(entry.command);
+                    }
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::Escape => {
+                    data.palette.close();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        if data.palette.show {
+            let query = data.palette.query.clone();
+            child.event(ctx, event, data, env);
+            if data.palette.query != query {
+                // The best match of the new query goes first.
+                data.palette.selected = 0;
+            }
+        } else {
+            child.event(ctx, event, data, env);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{mem, sync::Arc};
+use std::{mem, sync::Arc, time::Duration};
 
 use druid::{
     im::Vector,
@@ -11,12 +11,14 @@ use druid::{
 
 use crate::{
     cmd,
+    controller::DragReorder,
     data::{
-        CommonCtx, FindQuery, MatchFindQuery, Playable, PlaybackOrigin,
-        PlaybackPayload, PlaylistTracks, Recommendations, SavedTracks, SearchResults, ShowEpisodes,
-        Track, WithCtx,
+        CommonCtx, FindQuery, MatchFindQuery, Playable, PlaybackOrigin, PlaybackPayload,
+        PlaylistTracks, Recommendations, SavedTracks, SearchResults, ShowEpisodes,
+        SmartPlaylistTracks, Track, TrackSelection, WithCtx,
     },
     ui::theme,
+    widget::VirtualList,
 };
 
 use super::{
@@ -45,7 +47,33 @@ where
     T: PlayableIter + Data,
 {
     ControllerHost::new(
-        List::new(move || Findable::new(playable_widget(display), selector)),
+        VirtualList::new(move || Findable::new(playable_widget(display), selector))
+            .expand_on(selector, Find::is_active),
+        PlayController,
+    )
+}
+
+/// Like `list_widget_with_find`, but the rows can be dragged onto other rows.
+/// `on_move` is called with the dragged row and the position it was dropped
+/// on.
+pub fn reorderable_list_widget<T>(
+    display: Display,
+    selector: Selector<Find>,
+    on_move: fn(&mut EventCtx, &PlayRow<Playable>, usize),
+) -> impl Widget<WithCtx<T>>
+where
+    T: PlayableIter + Data,
+{
+    ControllerHost::new(
+        VirtualList::new(move || {
+            Findable::new(playable_widget(display), selector).controller(DragReorder::new(
+                move |ctx, row: &PlayRow<Playable>, offset| {
+                    let position = (row.position as isize + offset).max(0) as usize;
+                    on_move(ctx, row, position);
+                },
+            ))
+        })
+        .expand_on(selector, Find::is_active),
         PlayController,
     )
 }
@@ -134,6 +162,24 @@ pub trait PlayableIter {
     fn origin(&self) -> PlaybackOrigin;
     fn count(&self) -> usize;
     fn for_each(&self, cb: impl FnMut(Playable, usize));
+
+    /// False while only a part of the items is loaded.  Playing such a list
+    /// is left to an enclosing widget, which loads the rest first.
+    fn is_complete(&self) -> bool {
+        true
+    }
+}
+
+/// Payload playing all items of `list`, starting at `position`.
+pub fn playback_payload(list: &impl PlayableIter, position: usize) -> PlaybackPayload {
+    let mut items = Vector::new();
+    list.for_each(|item, _| items.push_back(item));
+    PlaybackPayload {
+        items,
+        origin: list.origin(),
+        position,
+        start_position: Duration::default(),
+    }
 }
 
 // This should change to a more specific name as it could be confusing for others
@@ -168,6 +214,26 @@ impl PlayableIter for PlaylistTracks {
     fn count(&self) -> usize {
         self.tracks.len()
     }
+
+    fn is_complete(&self) -> bool {
+        !self.has_more()
+    }
+}
+
+impl PlayableIter for SmartPlaylistTracks {
+    fn origin(&self) -> PlaybackOrigin {
+        PlaybackOrigin::SmartPlaylist(self.link.clone())
+    }
+
+    fn for_each(&self, mut cb: impl FnMut(Playable, usize)) {
+        for (position, track) in self.tracks.iter().enumerate() {
+            cb(Playable::Track(track.to_owned()), position);
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.tracks.len()
+    }
 }
 
 impl PlayableIter for SavedTracks {
@@ -293,14 +359,34 @@ where
         match event {
             Event::Notification(note) => {
                 if let Some(position) = note.get(cmd::PLAY) {
-                    let mut items = Vector::new();
-                    data.data.for_each(|item, _| items.push_back(item));
-                    let payload = PlaybackPayload {
-                        items,
-                        origin: data.data.origin(),
-                        position: position.to_owned(),
-                    };
+                    if !data.data.is_complete() {
+                        // Let the notification bubble up to the widget loading
+                        // the rest of the list.
+                        return;
+                    }
+                    let payload = playback_payload(&data.data, *position);
                     ctx.submit_command(cmd::PLAY_TRACKS.with(payload));
+                    if !data.ctx.selection.is_empty() {
+                        ctx.submit_command(cmd::SET_SELECTION.with(TrackSelection::default()));
+                    }
+                    ctx.set_handled();
+                } else if let Some((position, mode)) = note.get(cmd::SELECT) {
+                    // Ranges are resolved in the displayed order of this list.
+                    let mut tracks = Vec::new();
+                    let mut clicked = None;
+                    data.data.for_each(|item, index| {
+                        if let Playable::Track(track) = item {
+                            if index == *position {
+                                clicked = Some(track.clone());
+                            }
+                            tracks.push(track);
+                        }
+                    });
+                    if let Some(track) = clicked {
+                        let mut selection = data.ctx.selection.clone();
+                        selection.select(&track, *mode, &tracks);
+                        ctx.submit_command(cmd::SET_SELECTION.with(selection));
+                    }
                     ctx.set_handled();
                 }
             }
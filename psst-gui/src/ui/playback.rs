@@ -1,20 +1,24 @@
 use std::time::Duration;
 
 use druid::{
+    commands,
     kurbo::{Affine, BezPath},
-    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, Spinner, ViewSwitcher},
+    widget::{
+        Controller, CrossAxisAlignment, Either, Flex, Label, LineBreaking, Spinner, ViewSwitcher,
+    },
     BoxConstraints, Cursor, Data, Env, Event, EventCtx, LayoutCtx, LensExt, LifeCycle,
-    LifeCycleCtx, MouseButton, PaintCtx, Point, Rect, RenderContext, Size, UpdateCtx, Widget,
-    WidgetExt, WidgetPod,
+    LifeCycleCtx, Menu, MenuItem, MouseButton, PaintCtx, Point, Rect, RenderContext, Size,
+    UpdateCtx, Widget, WidgetExt, WidgetPod,
 };
 use itertools::Itertools;
+use psst_core::player::sleep::SleepTimer;
 
 use crate::{
     cmd::{self, ADD_TO_QUEUE, SHOW_ARTWORK, TOGGLE_LYRICS},
-    controller::PlaybackController,
+    controller::{KeymapController, PlaybackController},
     data::{
         AppState, AudioAnalysis, Episode, NowPlaying, Playable, PlayableMatcher, Playback,
-        PlaybackOrigin, PlaybackState, QueueBehavior, ShowLink, Track,
+        PlaybackOrigin, PlaybackState, QueueBehavior, ShowLink, Track, PLAYBACK_SPEEDS,
     },
     widget::{
         icons::{self, SvgIcon},
@@ -22,7 +26,7 @@ use crate::{
     },
 };
 
-use super::{episode, library, theme, track, utils};
+use super::{episode, library, lyrics, theme, track, utils};
 
 pub fn panel_widget() -> impl Widget<AppState> {
     let seek_bar = Maybe::or_empty(SeekBar::new).lens(Playback::now_playing);
@@ -32,48 +36,147 @@ pub fn panel_widget() -> impl Widget<AppState> {
         player_widget(),
         Empty,
     );
-    Flex::column()
+    let bar = Flex::column()
         .with_child(seek_bar)
         .with_child(BarLayout::new(item_info, controls))
-        .lens(AppState::playback)
+        .lens(AppState::playback);
+    Flex::column()
+        .with_child(lyrics::overlay_widget())
+        .with_child(bar)
         .controller(PlaybackController::new())
+        .controller(KeymapController)
         .on_command(ADD_TO_QUEUE, |_, _, data| {
             data.info_alert("Track added to queue.")
         })
+        .on_command(cmd::ADD_ALL_TO_QUEUE, |_, items, data| {
+            data.info_alert(format!("{} tracks added to queue.", items.len()))
+        })
 }
 
-fn playing_item_widget() -> impl Widget<NowPlaying> {
-    let cover_art = cover_widget(theme::grid(8.0));
-
-    let name = PlayableMatcher::new()
-        .track(
-            Label::raw()
-                .with_line_break_mode(LineBreaking::Clip)
-                .with_font(theme::UI_FONT_MEDIUM)
-                .lens(Track::name.in_arc()),
+/// Compact player for the mini player window.  Its playback commands are
+/// forwarded to the main window by the delegate.
+pub fn mini_player_widget() -> impl Widget<AppState> {
+    let item_info = Maybe::new(mini_item_widget, || {
+        Label::new("Nothing is playing")
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .center()
+    })
+    .lens(Playback::now_playing);
+    let seek_bar = Maybe::or_empty(SeekBar::new).lens(Playback::now_playing);
+    let transport = Flex::row()
+        .with_child(
+            small_button_widget(&icons::SKIP_BACK).on_left_click(|ctx, _, _, _| {
+                ctx.submit_command(cmd::PLAY_PREVIOUS);
+            }),
         )
-        .episode(
-            Label::raw()
-                .with_line_break_mode(LineBreaking::Clip)
-                .with_font(theme::UI_FONT_MEDIUM)
-                .lens(Episode::name.in_arc()),
+        .with_default_spacer()
+        .with_child(player_play_pause_widget())
+        .with_default_spacer()
+        .with_child(
+            small_button_widget(&icons::SKIP_FORWARD).on_left_click(|ctx, _, _, _| {
+                ctx.submit_command(cmd::PLAY_NEXT);
+            }),
         )
-        .lens(NowPlaying::item);
+        .with_flex_spacer(1.0)
+        .with_child(Maybe::or_empty(durations_widget).lens(Playback::now_playing));
+
+    let pin = small_button_widget(&icons::PIN)
+        .active(|data: &AppState, _| data.config.mini_player_on_top)
+        .on_left_click(|_, _, data: &mut AppState, _| {
+            data.config.mini_player_on_top = !data.config.mini_player_on_top;
+        });
+    let expand = small_button_widget(&icons::EXPAND).on_left_click(|ctx, _, _, _| {
+        ctx.submit_command(cmd::SHOW_MAIN);
+        ctx.submit_command(commands::CLOSE_WINDOW);
+    });
 
-    let detail = PlayableMatcher::new()
-        .track(
-            Label::raw()
-                .with_line_break_mode(LineBreaking::Clip)
-                .with_text_size(theme::TEXT_SIZE_SMALL)
-                .lens(Track::lens_artist_name().in_arc()),
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Fill)
+        .with_flex_child(
+            item_info.padding(theme::grid(1.5)).lens(AppState::playback),
+            1.0,
         )
-        .episode(
-            Label::raw()
-                .with_line_break_mode(LineBreaking::Clip)
-                .with_text_size(theme::TEXT_SIZE_SMALL)
-                .lens(Episode::show.in_arc().then(ShowLink::name)),
+        .with_child(seek_bar.lens(AppState::playback))
+        .with_child(
+            Flex::row()
+                .with_flex_child(transport.lens(AppState::playback), 1.0)
+                .with_default_spacer()
+                .with_child(pin)
+                .with_child(expand)
+                .padding(theme::grid(1.0)),
         )
-        .lens(NowPlaying::item);
+        .controller(KeymapController)
+        .controller(MiniPlayerController)
+}
+
+fn mini_item_widget() -> impl Widget<NowPlaying> {
+    Flex::row()
+        .with_child(cover_widget(theme::grid(7.0)))
+        .with_spacer(theme::grid(1.5))
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(item_name_widget())
+                .with_spacer(2.0)
+                .with_child(item_detail_widget()),
+            1.0,
+        )
+}
+
+struct MiniPlayerController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for MiniPlayerController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::WindowConnected => {
+                // Take the focus so the keyboard shortcuts work right away.
+                ctx.request_focus();
+            }
+            Event::WindowSize(_) | Event::MouseMove(_) | Event::WindowCloseRequested => {
+                // There is no event for moving the window, and closing it from
+                // the code or by quitting skips `WindowCloseRequested`.  Keep
+                // the position up to date whenever the window is resized or
+                // the pointer is over it, so it is there when the config is
+                // saved.
+                let position = Some(ctx.window().get_position());
+                if data.config.mini_player_position != position {
+                    data.config.mini_player_position = position;
+                }
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env);
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.config.mini_player_on_top != data.config.mini_player_on_top {
+            ctx.window()
+                .set_always_on_top(data.config.mini_player_on_top);
+        }
+        child.update(ctx, old_data, data, env);
+    }
+}
+
+fn playing_item_widget() -> impl Widget<NowPlaying> {
+    let cover_art = cover_widget(theme::grid(8.0));
+
+    let name = item_name_widget();
+
+    let detail = item_detail_widget();
 
     let origin = ViewSwitcher::new(
         |origin: &PlaybackOrigin, _| origin.clone(),
@@ -159,10 +262,45 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
         .link()
 }
 
+fn item_name_widget() -> impl Widget<NowPlaying> {
+    PlayableMatcher::new()
+        .track(
+            Label::raw()
+                .with_line_break_mode(LineBreaking::Clip)
+                .with_font(theme::UI_FONT_MEDIUM)
+                .lens(Track::name.in_arc()),
+        )
+        .episode(
+            Label::raw()
+                .with_line_break_mode(LineBreaking::Clip)
+                .with_font(theme::UI_FONT_MEDIUM)
+                .lens(Episode::name.in_arc()),
+        )
+        .lens(NowPlaying::item)
+}
+
+fn item_detail_widget() -> impl Widget<NowPlaying> {
+    PlayableMatcher::new()
+        .track(
+            Label::raw()
+                .with_line_break_mode(LineBreaking::Clip)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .lens(Track::lens_artist_name().in_arc()),
+        )
+        .episode(
+            Label::raw()
+                .with_line_break_mode(LineBreaking::Clip)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .lens(Episode::show.in_arc().then(ShowLink::name)),
+        )
+        .lens(NowPlaying::item)
+}
+
 fn cover_widget(size: f64) -> impl Widget<NowPlaying> {
     RemoteImage::new(utils::placeholder_widget(), move |np: &NowPlaying, _| {
         np.cover_image_url(size, size).map(|url| url.into())
     })
+    .thumbnail(size)
     .fix_size(size, size)
     .clip(Size::new(size, size).to_rounded_rect(4.0))
     .on_left_click(|ctx, _, _, _| {
@@ -175,7 +313,7 @@ fn playback_origin_icon(origin: &PlaybackOrigin) -> &'static SvgIcon {
         PlaybackOrigin::Home => &icons::HOME,
         PlaybackOrigin::Library => &icons::HEART,
         PlaybackOrigin::Album { .. } => &icons::ALBUM,
-        PlaybackOrigin::Playlist { .. } => &icons::PLAYLIST,
+        PlaybackOrigin::Playlist { .. } | PlaybackOrigin::SmartPlaylist { .. } => &icons::PLAYLIST,
         PlaybackOrigin::Show { .. } => &icons::PODCAST,
         PlaybackOrigin::Search { .. } => &icons::SEARCH,
         PlaybackOrigin::Recommendations { .. } => &icons::SEARCH,
@@ -200,6 +338,8 @@ fn player_widget() -> impl Widget<Playback> {
         .with_default_spacer()
         .with_child(queue_behavior_widget())
         .with_default_spacer()
+        .with_child(playback_speed_widget())
+        .with_child(sleep_timer_widget())
         .with_child(Maybe::or_empty(durations_widget).lens(Playback::now_playing))
         .with_child(
             small_button_widget(&icons::MUSIC_NOTE)
@@ -208,6 +348,11 @@ fn player_widget() -> impl Widget<Playback> {
                     ctx.submit_command(TOGGLE_LYRICS);
                 }),
         )
+        .with_child(
+            small_button_widget(&icons::MINI_PLAYER).on_left_click(|ctx, _, _, _| {
+                ctx.submit_command(cmd::SHOW_MINI_PLAYER);
+            }),
+        )
         .padding(theme::grid(2.0))
 }
 
@@ -279,6 +424,89 @@ fn queue_behavior_icon(qb: &QueueBehavior) -> &'static SvgIcon {
     }
 }
 
+fn playback_speed_widget() -> impl Widget<Playback> {
+    Either::new(
+        |playback: &Playback, _| {
+            matches!(
+                playback.now_playing.as_ref().map(|np| &np.item),
+                Some(Playable::Episode(_))
+            )
+        },
+        Label::dynamic(|playback: &Playback, _| format!("{}×", playback.speed))
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .padding(theme::grid(1.0))
+            .link()
+            .rounded(theme::BUTTON_BORDER_RADIUS)
+            .on_left_click(|ctx, _, playback: &mut Playback, _| {
+                ctx.submit_command(cmd::PLAY_SPEED.with(cycle_playback_speed(playback.speed)));
+            })
+            .context_menu(playback_speed_menu),
+        Empty,
+    )
+}
+
+fn cycle_playback_speed(speed: f64) -> f64 {
+    PLAYBACK_SPEEDS
+        .iter()
+        .copied()
+        .find(|&s| s > speed)
+        .unwrap_or(PLAYBACK_SPEEDS[0])
+}
+
+fn playback_speed_menu(playback: &Playback) -> Menu<AppState> {
+    let mut menu = Menu::new("Playback Speed");
+    for speed in PLAYBACK_SPEEDS {
+        menu = menu.entry(
+            MenuItem::new(format!("{speed}×"))
+                .command(cmd::PLAY_SPEED.with(speed))
+                .selected(playback.speed == speed),
+        );
+    }
+    menu
+}
+
+fn sleep_timer_widget() -> impl Widget<Playback> {
+    Label::dynamic(|playback: &Playback, _| match playback.sleep_timer {
+        Some(remaining) => format!("Sleep in {}", utils::as_minutes_and_seconds(remaining)),
+        None => "Sleep".to_string(),
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .padding(theme::grid(1.0))
+    .link()
+    .rounded(theme::BUTTON_BORDER_RADIUS)
+    .on_left_click(|ctx, event, playback: &mut Playback, _| {
+        ctx.show_context_menu(sleep_timer_menu(playback), event.window_pos);
+    })
+}
+
+fn sleep_timer_menu(playback: &Playback) -> Menu<AppState> {
+    const SLEEP_TIMER_MINUTES: [u64; 5] = [15, 30, 45, 60, 90];
+
+    let mut menu = Menu::new("Sleep Timer");
+    for minutes in SLEEP_TIMER_MINUTES {
+        let timer = SleepTimer::After(Duration::from_secs(minutes * 60));
+        menu = menu.entry(
+            MenuItem::new(format!("{minutes} Minutes"))
+                .command(cmd::PLAY_SLEEP_TIMER.with(Some(timer))),
+        );
+    }
+    let end_of_item = match playback.now_playing.as_ref().map(|np| &np.item) {
+        Some(Playable::Episode(_)) => "End of Episode",
+        _ => "End of Track",
+    };
+    menu = menu.entry(
+        MenuItem::new(end_of_item).command(cmd::PLAY_SLEEP_TIMER.with(Some(SleepTimer::EndOfItem))),
+    );
+    if playback.sleep_timer.is_some() {
+        menu = menu
+            .separator()
+            .entry(MenuItem::new("Turn Off").command(cmd::PLAY_SLEEP_TIMER.with(None)));
+    }
+    menu
+}
+
 fn small_button_widget<T: Data>(svg: &SvgIcon) -> impl Widget<T> {
     svg.scale((theme::grid(2.0), theme::grid(2.0)))
         .padding(theme::grid(1.0))
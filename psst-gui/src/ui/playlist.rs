@@ -1,48 +1,71 @@
-use std::{cell::RefCell, cmp::Ordering, rc::Rc, sync::Arc};
+use std::{any::Any, cell::RefCell, cmp::Ordering, fs, path::Path, rc::Rc, sync::Arc};
 
 use druid::{
+    commands,
     im::Vector,
-    widget::{Button, Either, Flex, Label, LensWrap, LineBreaking, List, TextBox},
-    Insets, Lens, LensExt, LocalizedString, Menu, MenuItem, Selector, Size, UnitPoint, Widget,
-    WidgetExt, WindowDesc,
+    widget::{
+        prelude::*, Button, Controller, Either, Flex, Label, LensWrap, LineBreaking, List, TextBox,
+    },
+    FileDialogOptions, FileInfo, FileSpec, Insets, Lens, LensExt, LocalizedString, Menu, MenuItem,
+    Selector, UnitPoint, WidgetExt, WindowDesc,
 };
 use itertools::Itertools;
 
 use crate::{
     cmd,
+    controller::OnVisible,
     data::{
         config::{SortCriteria, SortOrder},
-        AppState, Ctx, Library, Nav, Playlist, PlaylistAddTrack, PlaylistDetail, PlaylistLink,
-        PlaylistRemoveTrack, PlaylistTracks, Track, WithCtx,
+        AppState, Ctx, DuplicateSource, ExportSource, Library, Nav, Page, PlaybackOrigin, Playlist,
+        PlaylistAddTracks, PlaylistDetail, PlaylistDetails, PlaylistLink, PlaylistMoveTrack,
+        PlaylistRemoveTracks, PlaylistTracks, Track, TrackSelection, WithCtx,
     },
     error::Error,
-    ui::menu,
+    ui::{menu, playlist_file},
     webapi::WebApi,
-    widget::{Async, Empty, MyWidgetExt, RemoteImage, ThemeScope},
+    widget::{Async, Checkbox, Empty, MyWidgetExt, RemoteImage, ThemeScope},
 };
 
-use super::{playable, theme, track, utils};
+use super::{find::Find, playable, theme, track, utils};
 
 pub const LOAD_LIST: Selector = Selector::new("app.playlist.load-list");
 pub const LOAD_DETAIL: Selector<(PlaylistLink, AppState)> =
     Selector::new("app.playlist.load-detail");
-pub const ADD_TRACK: Selector<PlaylistAddTrack> = Selector::new("app.playlist.add-track");
-pub const REMOVE_TRACK: Selector<PlaylistRemoveTrack> = Selector::new("app.playlist.remove-track");
+pub const ADD_TRACKS: Selector<PlaylistAddTracks> = Selector::new("app.playlist.add-tracks");
+pub const REMOVE_TRACKS: Selector<PlaylistRemoveTracks> =
+    Selector::new("app.playlist.remove-tracks");
 
 pub const FOLLOW_PLAYLIST: Selector<Playlist> = Selector::new("app.playlist.follow");
 pub const UNFOLLOW_PLAYLIST: Selector<PlaylistLink> = Selector::new("app.playlist.unfollow");
 pub const UNFOLLOW_PLAYLIST_CONFIRM: Selector<PlaylistLink> =
     Selector::new("app.playlist.unfollow-confirm");
 
-pub const RENAME_PLAYLIST: Selector<PlaylistLink> = Selector::new("app.playlist.rename");
-pub const RENAME_PLAYLIST_CONFIRM: Selector<PlaylistLink> =
-    Selector::new("app.playlist.rename-confirm");
+pub const CREATE_PLAYLIST: Selector<Arc<str>> = Selector::new("app.playlist.create");
+pub const CREATE_PLAYLIST_CONFIRM: Selector<Arc<str>> =
+    Selector::new("app.playlist.create-confirm");
+
+pub const EDIT_PLAYLIST: Selector<PlaylistDetails> = Selector::new("app.playlist.edit");
+pub const EDIT_PLAYLIST_CONFIRM: Selector<PlaylistDetails> =
+    Selector::new("app.playlist.edit-confirm");
+
+pub const UPLOAD_PLAYLIST_COVER: Selector<(Arc<str>, Arc<Path>)> =
+    Selector::new("app.playlist.upload-cover");
+pub const UPLOAD_PLAYLIST_COVER_CONFIRM: Selector<(Arc<str>, Arc<Path>)> =
+    Selector::new("app.playlist.upload-cover-confirm");
 
-const SHOW_RENAME_PLAYLIST_CONFIRM: Selector<PlaylistLink> =
-    Selector::new("app.playlist.show-rename");
+const SHOW_EDIT_PLAYLIST: Selector<PlaylistDetails> = Selector::new("app.playlist.show-edit");
+const COVER_FILE_CHOSEN: Selector<FileInfo> = Selector::new("app.playlist.cover-chosen");
+
+const COVER_FILES: FileSpec = FileSpec::new("Image", &["jpg", "jpeg", "png", "webp"]);
 const SHOW_UNFOLLOW_PLAYLIST_CONFIRM: Selector<UnfollowPlaylist> =
     Selector::new("app.playlist.show-unfollow-confirm");
 
+const DROP_TRACK: Selector<PlaylistMoveTrack> = Selector::new("app.playlist.drop-track");
+const MOVE_TRACK: Selector<PlaylistMoveTrack> = Selector::new("app.playlist.move-track");
+const LOAD_MORE: Selector<(PlaylistLink, usize)> = Selector::new("app.playlist.load-more");
+const LOAD_REMAINING: Selector<(PlaylistLink, usize)> =
+    Selector::new("app.playlist.load-remaining");
+
 pub fn list_widget() -> impl Widget<AppState> {
     Async::new(
         utils::spinner_widget,
@@ -79,24 +102,23 @@ pub fn list_widget() -> impl Widget<AppState> {
         |_, data, r| data.with_library_mut(|l| l.playlists.update(r)),
     )
     .on_command_async(
-        ADD_TRACK,
+        ADD_TRACKS,
         |d| {
-            WebApi::global().add_track_to_playlist(
-                &d.link.id,
-                &d.track_id
-                    .0
-                    .to_uri()
-                    .ok_or_else(|| Error::WebApiError("Item doesn't have URI".to_string()))?,
-            )
+            let uris: Vec<String> = d.track_ids.iter().filter_map(|id| id.0.to_uri()).collect();
+            WebApi::global().add_tracks_to_playlist(&d.link.id, &uris)
         },
         |_, data, d| {
-            data.with_library_mut(|library| library.increment_playlist_track_count(&d.link))
+            data.with_library_mut(|library| {
+                library.increment_playlist_track_count(&d.link, d.track_ids.len())
+            })
         },
-        |_, data, (_, r)| {
+        |_, data, (d, r)| {
             if let Err(err) = r {
                 data.error_alert(err);
-            } else {
+            } else if d.track_ids.len() == 1 {
                 data.info_alert("Added to playlist.");
+            } else {
+                data.info_alert(format!("Added {} tracks to playlist.", d.track_ids.len()));
             }
         },
     )
@@ -125,14 +147,53 @@ pub fn list_widget() -> impl Widget<AppState> {
         },
     )
     .on_command_async(
-        RENAME_PLAYLIST,
-        |link| WebApi::global().change_playlist_details(link.id.as_ref(), link.name.as_ref()),
-        |_, data: &mut AppState, link| data.with_library_mut(|l| l.rename_playlist(link)),
+        CREATE_PLAYLIST,
+        |name| WebApi::global().create_playlist(&name),
+        |_, _, _| {},
+        |ctx, data: &mut AppState, (_, r)| match r {
+            Ok(link) => {
+                data.info_alert("Playlist created.");
+                ctx.submit_command(LOAD_LIST);
+                ctx.submit_command(cmd::NAVIGATE.with(Nav::PlaylistDetail(link)));
+            }
+            Err(err) => {
+                data.error_alert(err);
+            }
+        },
+    )
+    .on_command_async(
+        EDIT_PLAYLIST,
+        |details| WebApi::global().change_playlist_details(&details),
+        |_, data: &mut AppState, details| {
+            data.with_library_mut(|l| l.set_playlist_details(&details));
+            if let Some(playlist) = data.playlist_detail.playlist.resolved_mut() {
+                if playlist.id == details.id {
+                    playlist.set_details(&details);
+                }
+            }
+        },
         |_, data: &mut AppState, (_, r)| {
             if let Err(err) = r {
                 data.error_alert(err);
             } else {
-                data.info_alert("Playlist renamed.")
+                data.info_alert("Playlist details saved.")
+            }
+        },
+    )
+    .on_command_async(
+        UPLOAD_PLAYLIST_COVER,
+        |(id, path)| {
+            let image = fs::read(&path).map_err(|err| Error::WebApiError(err.to_string()))?;
+            WebApi::global().upload_playlist_cover(&id, &image)
+        },
+        |_, data: &mut AppState, _| data.info_alert("Uploading playlist cover…"),
+        |ctx, data: &mut AppState, (_, r)| {
+            if let Err(err) = r {
+                data.error_alert(err);
+            } else {
+                data.info_alert("Playlist cover changed.");
+                // Reload the list to pick up the new image.
+                ctx.submit_command(LOAD_LIST);
             }
         },
     )
@@ -140,21 +201,31 @@ pub fn list_widget() -> impl Widget<AppState> {
         let window = unfollow_confirm_window(msg.clone());
         ctx.new_window(window);
     })
-    .on_command(SHOW_RENAME_PLAYLIST_CONFIRM, |ctx, link, _| {
-        let window = rename_playlist_window(link.clone());
+    .on_command(SHOW_EDIT_PLAYLIST, |ctx, details, _| {
+        let window = edit_playlist_window(details.clone());
         ctx.new_window(window);
     })
     .on_command_async(
-        REMOVE_TRACK,
-        |d| WebApi::global().remove_track_from_playlist(&d.link.id, &d.track_uri),
+        REMOVE_TRACKS,
+        |d| {
+            let uris: Vec<Arc<str>> = d.track_uris.iter().cloned().collect();
+            WebApi::global().remove_tracks_from_playlist(&d.link.id, &uris)
+        },
         |_, data, d| {
-            data.with_library_mut(|library| library.decrement_playlist_track_count(&d.link))
+            data.with_library_mut(|library| {
+                library.decrement_playlist_track_count(&d.link, d.track_uris.len())
+            })
         },
         |e, data, (p, r)| {
             if let Err(err) = r {
                 data.error_alert(err);
-            } else {
+            } else if p.track_uris.len() == 1 {
                 data.info_alert("Removed from playlist.");
+            } else {
+                data.info_alert(format!(
+                    "Removed {} tracks from playlist.",
+                    p.track_uris.len()
+                ));
             }
             // Re-submit the `LOAD_DETAIL` command to reload the playlist data.
             e.submit_command(LOAD_DETAIL.with((p.link, data.clone())))
@@ -207,10 +278,24 @@ fn unfollow_playlist_confirm_widget(msg: UnfollowPlaylist) -> impl Widget<AppSta
     )
 }
 
-fn rename_playlist_window(link: PlaylistLink) -> WindowDesc<AppState> {
-    let win = WindowDesc::new(rename_playlist_widget(link))
+fn edit_playlist_window(details: PlaylistDetails) -> WindowDesc<AppState> {
+    let win = WindowDesc::new(edit_playlist_widget(details))
+        .window_size((theme::grid(45.0), theme::grid(55.0)))
+        .title("Edit playlist")
+        .resizable(false)
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+fn create_playlist_window() -> WindowDesc<AppState> {
+    let win = WindowDesc::new(create_playlist_widget())
         .window_size((theme::grid(45.0), theme::grid(30.0)))
-        .title("Rename playlist")
+        .title("New playlist")
         .resizable(false)
         .show_title(false)
         .transparent_titlebar(true);
@@ -221,29 +306,110 @@ fn rename_playlist_window(link: PlaylistLink) -> WindowDesc<AppState> {
     }
 }
 
-#[derive(Clone, Lens)]
-struct TextInput {
-    input: Rc<RefCell<String>>,
+/// Value edited in a dialog, kept outside of the app state until confirmed.
+#[derive(Clone)]
+struct Input<T> {
+    input: Rc<RefCell<T>>,
+}
+
+impl<T> Input<T> {
+    fn new(value: T) -> Self {
+        Self {
+            input: Rc::new(RefCell::new(value)),
+        }
+    }
 }
 
-impl Lens<AppState, String> for TextInput {
-    fn with<V, F: FnOnce(&String) -> V>(&self, _data: &AppState, f: F) -> V {
+impl<T> Lens<AppState, T> for Input<T> {
+    fn with<V, F: FnOnce(&T) -> V>(&self, _data: &AppState, f: F) -> V {
         f(&self.input.borrow())
     }
 
-    fn with_mut<V, F: FnOnce(&mut String) -> V>(&self, _data: &mut AppState, f: F) -> V {
+    fn with_mut<V, F: FnOnce(&mut T) -> V>(&self, _data: &mut AppState, f: F) -> V {
         f(&mut self.input.borrow_mut())
     }
 }
 
-fn rename_playlist_widget(link: PlaylistLink) -> impl Widget<AppState> {
-    let text_input = TextInput {
-        input: Rc::new(RefCell::new(link.name.to_string())),
-    };
+fn edit_playlist_widget(details: PlaylistDetails) -> impl Widget<AppState> {
+    let input = Input::new(details);
 
     let information_section = information_section(
-        "Rename playlist?",
-        "Please enter a new name for your playlist",
+        "Edit details?",
+        "Change the name, description, visibility and cover of your playlist",
+    );
+    let input_section = LensWrap::new(
+        Flex::column()
+            .with_child(
+                TextBox::new()
+                    .with_placeholder("Name")
+                    .expand_width()
+                    .lens(PlaylistDetails::name),
+            )
+            .with_default_spacer()
+            .with_child(
+                TextBox::multiline()
+                    .with_placeholder("Description")
+                    .with_line_wrapping(true)
+                    .expand_width()
+                    .fix_height(theme::grid(8.0))
+                    .lens(PlaylistDetails::description),
+            )
+            .with_default_spacer()
+            .with_child(
+                Checkbox::new("Public")
+                    .lens(PlaylistDetails::public)
+                    .align_left(),
+            )
+            .with_default_spacer()
+            .with_child(
+                Checkbox::new("Collaborative")
+                    .lens(PlaylistDetails::collaborative)
+                    .align_left(),
+            )
+            .with_default_spacer()
+            .with_child(
+                Button::new("Change Cover…")
+                    .on_click(|ctx, _, _| {
+                        let options = FileDialogOptions::new()
+                            .allowed_types(vec![COVER_FILES])
+                            .title("Choose Playlist Cover")
+                            .accept_command(COVER_FILE_CHOSEN);
+                        ctx.submit_command(commands::SHOW_OPEN_PANEL.with(options));
+                    })
+                    .on_command(COVER_FILE_CHOSEN, |ctx, file, details| {
+                        ctx.submit_command(
+                            UPLOAD_PLAYLIST_COVER_CONFIRM
+                                .with((details.id.clone(), file.path().into())),
+                        );
+                    })
+                    .align_left(),
+            )
+            .padding_horizontal(theme::grid(2.0)),
+        input.clone(),
+    );
+    let button_section = button_section(
+        "Save",
+        EDIT_PLAYLIST_CONFIRM,
+        Box::new(move || input.input.borrow().clone()),
+    );
+
+    ThemeScope::new(
+        Flex::column()
+            .with_child(information_section)
+            .with_child(input_section)
+            .with_flex_spacer(2.0)
+            .with_child(button_section)
+            .with_flex_spacer(2.0)
+            .background(theme::BACKGROUND_DARK),
+    )
+}
+
+fn create_playlist_widget() -> impl Widget<AppState> {
+    let text_input = Input::new(String::new());
+
+    let information_section = information_section(
+        "Create playlist?",
+        "Please enter a name for your new playlist",
     );
     let input_section = LensWrap::new(
         TextBox::new()
@@ -252,12 +418,9 @@ fn rename_playlist_widget(link: PlaylistLink) -> impl Widget<AppState> {
         text_input.clone(),
     );
     let button_section = button_section(
-        "Rename",
-        RENAME_PLAYLIST_CONFIRM,
-        Box::new(move || PlaylistLink {
-            id: link.id.clone(),
-            name: Arc::from(text_input.input.borrow().clone().into_boxed_str()),
-        }),
+        "Create",
+        CREATE_PLAYLIST_CONFIRM,
+        Box::new(move || Arc::from(text_input.input.borrow().as_str())),
     );
 
     ThemeScope::new(
@@ -271,16 +434,16 @@ fn rename_playlist_widget(link: PlaylistLink) -> impl Widget<AppState> {
     )
 }
 
-fn button_section(
+fn button_section<T: Any>(
     action_button_name: &str,
-    selector: Selector<PlaylistLink>,
-    link_extractor: Box<dyn Fn() -> PlaylistLink>,
+    selector: Selector<T>,
+    value_extractor: Box<dyn Fn() -> T>,
 ) -> impl Widget<AppState> {
     let action_button = Button::new(action_button_name)
         .fix_height(theme::grid(5.0))
         .fix_width(theme::grid(9.0))
         .on_click(move |ctx, _, _| {
-            ctx.submit_command(selector.with(link_extractor()));
+            ctx.submit_command(selector.with(value_extractor()));
             ctx.window().close();
         });
     let cancel_button = Button::new("Cancel")
@@ -388,6 +551,7 @@ fn cover_widget(size: f64) -> impl Widget<Playlist> {
         utils::placeholder_widget(),
         move |playlist: &Playlist, _| playlist.image(size, size).map(|image| image.url.clone()),
     )
+    .thumbnail(size)
     .fix_size(size, size)
 }
 
@@ -507,25 +671,91 @@ fn async_tracks_widget() -> impl Widget<AppState> {
         )
         .on_command_async(
             LOAD_DETAIL,
-            |arg: (PlaylistLink, AppState)| {
-                let d = arg.0;
-                let data = arg.1;
-                sort_playlist(&data, WebApi::global().get_playlist_tracks(&d.id))
-            },
+            |(link, data): (PlaylistLink, AppState)| load_tracks(&link, &data),
             |_, data, d| data.playlist_detail.tracks.defer(d.0),
-            |_, data, (d, r)| {
-                let tracks = PlaylistTracks {
-                    id: d.0.id.clone(),
-                    name: d.0.name.clone(),
-                    tracks: r,
+            |_, data, (d, r)| data.playlist_detail.tracks.update((d.0, r)),
+        )
+        .on_command_async(
+            LOAD_MORE,
+            |(link, offset)| WebApi::global().get_playlist_tracks_page(&link.id, offset),
+            |_, data, _| {
+                if let Some(tracks) = data.playlist_detail.tracks.resolved_mut() {
+                    tracks.loading_more = true;
+                }
+            },
+            |_, data, ((link, offset), r)| {
+                let Some(tracks) = data.playlist_detail.tracks.resolved_mut() else {
+                    return;
                 };
-                data.playlist_detail.tracks.update((d.0, Ok(tracks)))
+                if tracks.id != link.id {
+                    return;
+                }
+                tracks.loading_more = false;
+                match r {
+                    // Drop the page if the playlist got reloaded meanwhile.
+                    Ok(page) if tracks.loaded == offset => tracks.append_page(page),
+                    Ok(_) => {}
+                    Err(err) => data.error_alert(err),
+                }
+            },
+        )
+        .on_command_async(
+            LOAD_REMAINING,
+            |(link, offset)| load_remaining_pages(&link, offset),
+            |_, data, _| {
+                if let Some(tracks) = data.playlist_detail.tracks.resolved_mut() {
+                    tracks.loading_more = true;
+                }
+            },
+            |_, data, ((link, offset), r)| {
+                let Some(tracks) = data.playlist_detail.tracks.resolved_mut() else {
+                    return;
+                };
+                if tracks.id != link.id {
+                    return;
+                }
+                tracks.loading_more = false;
+                match r {
+                    Ok(pages) if tracks.loaded == offset => {
+                        for page in pages {
+                            tracks.append_page(page);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => data.error_alert(err),
+                }
             },
         )
+        .on_command(DROP_TRACK, drop_track)
+        .on_command_async(
+            MOVE_TRACK,
+            |d| WebApi::global().move_playlist_track(&d.link.id, d.from, d.to),
+            |_, _, _| {},
+            |ctx, data, (d, r)| {
+                if let Err(err) = r {
+                    data.error_alert(err);
+                    // Reload to undo the local change.
+                    ctx.submit_command(LOAD_DETAIL.with((d.link, data.clone())));
+                }
+            },
+        )
+}
+
+/// Sidebar link opening the dialog for creating a playlist.
+pub fn new_playlist_widget() -> impl Widget<AppState> {
+    Label::new("New Playlist")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .expand_width()
+        .padding(Insets::uniform_xy(theme::grid(2.0), theme::grid(0.6)))
+        .link()
+        .on_left_click(|ctx, _, _, _| {
+            ctx.new_window(create_playlist_window());
+        })
 }
 
 fn tracks_widget() -> impl Widget<WithCtx<PlaylistTracks>> {
-    playable::list_widget_with_find(
+    let tracks = playable::reorderable_list_widget(
         playable::Display {
             track: track::Display {
                 title: true,
@@ -536,15 +766,204 @@ fn tracks_widget() -> impl Widget<WithCtx<PlaylistTracks>> {
             },
         },
         cmd::FIND_IN_PLAYLIST,
-    )
+        |ctx, row, position| {
+            if let PlaybackOrigin::Playlist(link) = &*row.origin {
+                ctx.submit_command(DROP_TRACK.with(PlaylistMoveTrack {
+                    link: link.clone(),
+                    from: row.position,
+                    to: position,
+                }));
+            }
+        },
+    );
+
+    Flex::column()
+        .with_child(tracks)
+        .with_child(Either::new(
+            |tracks: &WithCtx<PlaylistTracks>, _| tracks.data.has_more(),
+            more_tracks_widget().lens(Ctx::data()),
+            Empty,
+        ))
+        .controller(LoadRemaining::default())
+}
+
+/// Action waiting for the rest of the playlist to load.
+enum PendingAction {
+    Play(usize),
+    Find(Find),
+}
+
+/// Loads all the remaining pages of a partially loaded playlist before playing
+/// it or finding in it, so that both cover every track and not only the ones
+/// scrolled into view so far.
+#[derive(Default)]
+struct LoadRemaining {
+    pending: Option<PendingAction>,
+}
+
+impl LoadRemaining {
+    fn defer(&mut self, ctx: &mut EventCtx, tracks: &PlaylistTracks, action: PendingAction) {
+        self.pending = Some(action);
+        if !tracks.loading_more {
+            ctx.submit_command(LOAD_REMAINING.with((tracks.link(), tracks.loaded)));
+        }
+    }
 }
 
-fn sort_playlist(data: &AppState, result: Result<Vector<Arc<Track>>, Error>) -> Vector<Arc<Track>> {
+impl<W> Controller<WithCtx<PlaylistTracks>, W> for LoadRemaining
+where
+    W: Widget<WithCtx<PlaylistTracks>>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut WithCtx<PlaylistTracks>,
+        env: &Env,
+    ) {
+        match event {
+            Event::Notification(note) => {
+                if let Some(position) = note.get(cmd::PLAY) {
+                    if data.data.has_more() {
+                        self.defer(ctx, &data.data, PendingAction::Play(*position));
+                        ctx.set_handled();
+                    }
+                }
+            }
+            Event::Command(cmd) if data.data.has_more() && cmd.is(cmd::FIND_IN_PLAYLIST) => {
+                let find = cmd.get_unchecked(cmd::FIND_IN_PLAYLIST);
+                if find.is_active() {
+                    self.defer(ctx, &data.data, PendingAction::Find(find.clone()));
+                    ctx.set_handled();
+                } else {
+                    // The finder was cleared, there is nothing to load the rest for.
+                    if matches!(self.pending, Some(PendingAction::Find(_))) {
+                        self.pending = None;
+                    }
+                    child.event(ctx, event, data, env);
+                }
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &WithCtx<PlaylistTracks>,
+        data: &WithCtx<PlaylistTracks>,
+        env: &Env,
+    ) {
+        let (old, new) = (&old_data.data, &data.data);
+        if self.pending.is_some() {
+            if old.id != new.id {
+                self.pending = None;
+            } else if !new.has_more() {
+                match self.pending.take() {
+                    Some(PendingAction::Play(position)) => {
+                        let payload = playable::playback_payload(new, position);
+                        ctx.submit_command(cmd::PLAY_TRACKS.with(payload));
+                        if !data.ctx.selection.is_empty() {
+                            ctx.submit_command(cmd::SET_SELECTION.with(TrackSelection::default()));
+                        }
+                    }
+                    Some(PendingAction::Find(find)) => {
+                        ctx.submit_command(cmd::FIND_IN_PLAYLIST.with(find));
+                    }
+                    None => {}
+                }
+            } else if old.loading_more && !new.loading_more {
+                if old.loaded == new.loaded {
+                    // Loading failed, give up on the action.
+                    self.pending = None;
+                } else {
+                    // A page loaded by scrolling finished first.
+                    ctx.submit_command(LOAD_REMAINING.with((new.link(), new.loaded)));
+                }
+            }
+        }
+        child.update(ctx, old_data, data, env);
+    }
+}
+
+/// Load the pages of the playlist items following `offset`.
+fn load_remaining_pages(
+    link: &PlaylistLink,
+    mut offset: usize,
+) -> Result<Vector<Page<Arc<Track>>>, Error> {
+    let mut pages = Vector::new();
+    loop {
+        let page = WebApi::global().get_playlist_tracks_page(&link.id, offset)?;
+        offset = page.offset + page.limit;
+        let done = page.limit == 0 || offset >= page.total;
+        pages.push_back(page);
+        if done {
+            break Ok(pages);
+        }
+    }
+}
+
+/// Count of the tracks loaded so far, loading the next page once scrolled into
+/// view.
+fn more_tracks_widget() -> impl Widget<PlaylistTracks> {
+    Label::dynamic(|tracks: &PlaylistTracks, _| {
+        format!("Showing {} of {} songs", tracks.loaded, tracks.total)
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .padding(theme::grid(2.0))
+    .center()
+    .controller(OnVisible::new(|ctx, tracks: &PlaylistTracks| {
+        if tracks.has_more() && !tracks.loading_more {
+            ctx.submit_command(LOAD_MORE.with((tracks.link(), tracks.loaded)));
+        }
+    }))
+}
+
+/// Move a dropped track locally and then on the server, if the playlist can be
+/// edited and is shown in its original order.
+fn drop_track(ctx: &mut EventCtx, d: &PlaylistMoveTrack, data: &mut AppState) {
+    let writable = data
+        .library
+        .writable_playlists()
+        .iter()
+        .any(|playlist| playlist.id == d.link.id);
+    if !writable {
+        data.error_alert("This playlist can't be edited.");
+    } else if !in_original_order(data) {
+        data.error_alert("Tracks can only be rearranged when sorted by date added.");
+    } else if let Some(tracks) = data.playlist_detail.tracks.resolved_mut() {
+        let to = d.to.min(tracks.tracks.len().saturating_sub(1));
+        if tracks.id == d.link.id && to != d.from {
+            tracks.move_track(d.from, to);
+            ctx.submit_command(MOVE_TRACK.with(PlaylistMoveTrack { to, ..d.clone() }));
+        }
+    }
+}
+
+/// Load the first page of the playlist tracks, or all of them if they have to
+/// be sorted.
+fn load_tracks(link: &PlaylistLink, data: &AppState) -> Result<PlaylistTracks, Error> {
+    if in_original_order(data) {
+        let page = WebApi::global().get_playlist_tracks_page(&link.id, 0)?;
+        Ok(PlaylistTracks::first_page(link, page))
+    } else {
+        let tracks = WebApi::global().get_playlist_tracks(&link.id)?;
+        Ok(PlaylistTracks::new(link, sort_playlist(data, tracks)))
+    }
+}
+
+fn in_original_order(data: &AppState) -> bool {
+    data.config.sort_criteria == SortCriteria::DateAdded
+        && data.config.sort_order == SortOrder::Ascending
+}
+
+fn sort_playlist(data: &AppState, playlist: Vector<Arc<Track>>) -> Vector<Arc<Track>> {
     let sort_criteria = data.config.sort_criteria;
     let sort_order = data.config.sort_order;
 
-    let playlist = result.unwrap_or_else(|_| Vector::new());
-
     let sorted_playlist: Vector<Arc<Track>> = playlist
         .into_iter()
         .sorted_by(|a, b| {
@@ -583,6 +1002,9 @@ fn playlist_menu_ctx(playlist: &WithCtx<Playlist>) -> Menu<AppState> {
         )
         .command(cmd::COPY.with(playlist.url())),
     );
+    menu = menu.entry(playlist_file::export_menu(ExportSource::Playlist(
+        playlist.link(),
+    )));
 
     if library.contains_playlist(playlist) {
         let created_by_user = library.is_created_by_user(playlist);
@@ -601,10 +1023,18 @@ fn playlist_menu_ctx(playlist: &WithCtx<Playlist>) -> Menu<AppState> {
             );
             menu = menu.entry(
                 MenuItem::new(
-                    LocalizedString::new("menu-rename-playlist")
-                        .with_placeholder("Rename playlist"),
+                    LocalizedString::new("menu-edit-playlist").with_placeholder("Edit details"),
+                )
+                .command(SHOW_EDIT_PLAYLIST.with(playlist.details())),
+            );
+            menu = menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-find-duplicates")
+                        .with_placeholder("Find Duplicates"),
                 )
-                .command(SHOW_RENAME_PLAYLIST_CONFIRM.with(playlist.link())),
+                .command(
+                    cmd::SHOW_DUPLICATES_WINDOW.with(DuplicateSource::Playlist(playlist.link())),
+                ),
             );
         } else {
             let unfollow_msg = UnfollowPlaylist {
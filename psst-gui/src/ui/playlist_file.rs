@@ -0,0 +1,291 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use directories::UserDirs;
+use druid::{
+    commands,
+    im::Vector,
+    widget::{Button, CrossAxisAlignment, Flex, Label, LineBreaking, Scroll},
+    FileDialogOptions, FileInfo, FileSpec, Insets, LocalizedString, Menu, MenuItem, Selector,
+    Widget, WidgetExt, WindowDesc,
+};
+
+use crate::{
+    data::{
+        AppState, ExportSource, FileEntry, PlaylistExport, PlaylistFile, PlaylistFormat,
+        PlaylistImport, SearchQuery, SearchTopic,
+    },
+    error::Error,
+    ui::menu,
+    webapi::{WebApi, PLAYLIST_ITEMS_LIMIT},
+    widget::{MyWidgetExt, ThemeScope},
+};
+
+use super::{playlist, theme};
+
+pub const EXPORT: Selector<PlaylistExport> = Selector::new("app.playlist-file.export");
+
+const IMPORT: Selector<Arc<Path>> = Selector::new("app.playlist-file.import");
+const IMPORT_FILE_CHOSEN: Selector<FileInfo> = Selector::new("app.playlist-file.import-chosen");
+
+const PLAYLIST_FILES: FileSpec = FileSpec::new("Playlist", &["m3u8", "m3u", "xspf", "csv", "json"]);
+
+/// Number of search results considered when matching an imported entry.
+const SEARCH_LIMIT: usize = 10;
+
+/// Sidebar link to import a playlist file, also handling the exports.
+pub fn import_widget() -> impl Widget<AppState> {
+    Label::new("Import Playlist…")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .expand_width()
+        .padding(Insets::uniform_xy(theme::grid(2.0), theme::grid(0.6)))
+        .link()
+        .on_left_click(|ctx, _, _, _| {
+            let options = FileDialogOptions::new()
+                .allowed_types(vec![PLAYLIST_FILES])
+                .title("Import Playlist")
+                .accept_command(IMPORT_FILE_CHOSEN);
+            ctx.submit_command(commands::SHOW_OPEN_PANEL.with(options));
+        })
+        .on_command(IMPORT_FILE_CHOSEN, |ctx, file, _| {
+            ctx.submit_command(IMPORT.with(file.path().into()));
+        })
+        .on_command_async(
+            IMPORT,
+            |path| import_playlist(&path),
+            |_, data, _| data.info_alert("Importing playlist…"),
+            |ctx, data, (_, result)| match result {
+                Ok(import) => {
+                    match &import.error {
+                        None => data.info_alert(format!(
+                            "Imported {} tracks into \"{}\".",
+                            import.added, import.name
+                        )),
+                        Some(err) => data.error_alert(format!(
+                            "Importing into \"{}\" stopped after {} tracks: {err}",
+                            import.name, import.added
+                        )),
+                    }
+                    if !import.unmatched.is_empty() {
+                        ctx.new_window(unmatched_window(&import));
+                    }
+                    ctx.submit_command(playlist::LOAD_LIST);
+                }
+                Err(err) => {
+                    data.error_alert(err);
+                }
+            },
+        )
+        .on_command_async(
+            EXPORT,
+            |export| export_playlist(&export),
+            |_, _, _| {},
+            |_, data, (_, result)| match result {
+                Ok(path) => {
+                    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    data.info_alert(format!(
+                        "Playlist exported to Downloads as \"{file_name}\"."
+                    ));
+                }
+                Err(err) => {
+                    data.error_alert(err);
+                }
+            },
+        )
+}
+
+/// Submenu exporting `source` into each of the supported formats.
+pub fn export_menu(source: ExportSource) -> Menu<AppState> {
+    let mut menu = Menu::new(LocalizedString::new("menu-item-export").with_placeholder("Export"));
+    for format in PlaylistFormat::ALL {
+        menu = menu.entry(
+            MenuItem::new(format.name()).command(EXPORT.with(PlaylistExport {
+                source: source.clone(),
+                format,
+            })),
+        );
+    }
+    menu
+}
+
+fn export_playlist(export: &PlaylistExport) -> Result<PathBuf, Error> {
+    let tracks = match &export.source {
+        ExportSource::Playlist(link) => WebApi::global().get_playlist_tracks(&link.id)?,
+        ExportSource::Album(link) => WebApi::global()
+            .get_album(&link.id)?
+            .data
+            .into_tracks_with_context(),
+        ExportSource::SavedTracks => WebApi::global().get_saved_tracks()?,
+        ExportSource::Tracks(tracks) => tracks.clone(),
+    };
+    let name = export.source.name();
+    let contents = PlaylistFile::new(&name, &tracks).write(export.format);
+
+    let download_dir = UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(Path::to_path_buf))
+        .ok_or_else(|| Error::PlaylistFileError("No Downloads folder found".to_string()))?;
+    let safe_name = name.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+    write_new_file(
+        &download_dir,
+        &safe_name,
+        export.format.extension(),
+        &contents,
+    )
+    .map_err(|err| Error::PlaylistFileError(err.to_string()))
+}
+
+/// Write `contents` into `dir`, as `name.extension`, or with a numeric suffix
+/// like `name (2).extension` if that file already exists.
+fn write_new_file(dir: &Path, name: &str, extension: &str, contents: &str) -> io::Result<PathBuf> {
+    for n in 1.. {
+        let file_name = if n == 1 {
+            format!("{name}.{extension}")
+        } else {
+            format!("{name} ({n}).{extension}")
+        };
+        let path = dir.join(file_name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!()
+}
+
+/// Read a playlist file, match its entries with Spotify tracks and add them to
+/// a newly created playlist.  All entries are matched before anything is
+/// written, so the unmatched ones are reported even if the writes fail.
+fn import_playlist(path: &Path) -> Result<PlaylistImport, Error> {
+    let format = PlaylistFormat::from_path(path)
+        .ok_or_else(|| Error::PlaylistFileError("Unsupported playlist file".to_string()))?;
+    let contents =
+        fs::read_to_string(path).map_err(|err| Error::PlaylistFileError(err.to_string()))?;
+    let file = PlaylistFile::parse(format, &contents)?;
+
+    let mut uris = Vec::new();
+    let mut unmatched = Vector::new();
+    for entry in &file.entries {
+        match resolve_entry(entry) {
+            Some(uri) => uris.push(uri),
+            None => unmatched.push_back(entry.description().into()),
+        }
+    }
+
+    let name = if file.name.is_empty() {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Imported Playlist".to_string())
+    } else {
+        file.name.clone()
+    };
+    let mut import = PlaylistImport {
+        name: name.into(),
+        added: 0,
+        unmatched,
+        error: None,
+    };
+    let result = WebApi::global()
+        .create_playlist(&import.name)
+        .and_then(|link| {
+            for chunk in uris.chunks(PLAYLIST_ITEMS_LIMIT) {
+                WebApi::global().add_tracks_to_playlist(&link.id, chunk)?;
+                import.added += chunk.len();
+            }
+            Ok(())
+        });
+    import.error = result.err();
+    Ok(import)
+}
+
+/// Find the Spotify URI of an entry, using the one stored in the file or
+/// searching by title and artist.
+fn resolve_entry(entry: &FileEntry) -> Option<String> {
+    if let Some(uri) = entry.track_uri() {
+        return Some(uri.to_string());
+    }
+    if entry.title.is_empty() {
+        return None;
+    }
+    let mut queries = vec![format!("{} {}", entry.artist, entry.title)];
+    if !entry.artist.is_empty() {
+        // Quoted so multi-word values stay whole filters.
+        queries.insert(
+            0,
+            format!("track:\"{}\" artist:\"{}\"", entry.title, entry.artist),
+        );
+    }
+    for query in queries {
+        let query = SearchQuery::parse(query.trim());
+        let results = match WebApi::global().search(&query, &[SearchTopic::Track], SEARCH_LIMIT, 0)
+        {
+            Ok(results) => results,
+            Err(err) => {
+                log::warn!("search for \"{}\" failed: {}", query, err);
+                continue;
+            }
+        };
+        if let Some(track) = entry.best_match(&results.tracks) {
+            return track.id.0.to_uri();
+        }
+    }
+    None
+}
+
+fn unmatched_window(import: &PlaylistImport) -> WindowDesc<AppState> {
+    let mut rows = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for row in &import.unmatched {
+        rows.add_child(
+            Label::new(row.to_string())
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .padding((0.0, theme::grid(0.5))),
+        );
+    }
+
+    let title = Label::new(format!(
+        "{} tracks were not found on Spotify",
+        import.unmatched.len()
+    ))
+    .with_text_size(theme::TEXT_SIZE_LARGE)
+    .padding(theme::grid(2.0));
+
+    let close_button = Button::new("Close")
+        .fix_height(theme::grid(5.0))
+        .fix_width(theme::grid(9.0))
+        .on_click(|ctx, _, _| ctx.window().close())
+        .padding(theme::grid(2.0));
+
+    let widget = ThemeScope::new(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(title)
+            .with_flex_child(
+                Scroll::new(rows.padding_horizontal(theme::grid(2.0)))
+                    .vertical()
+                    .expand(),
+                1.0,
+            )
+            .with_child(close_button.align_right())
+            .background(theme::BACKGROUND_DARK),
+    );
+
+    let win = WindowDesc::new(widget)
+        .window_size((theme::grid(60.0), theme::grid(50.0)))
+        .title(format!("Importing \"{}\"", import.name))
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
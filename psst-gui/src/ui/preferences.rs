@@ -5,9 +5,10 @@ use std::time::Duration;
 use crate::{
     cmd,
     data::{
-        AppState, AudioQuality, Authentication, Config, Preferences, PreferencesTab, Promise,
-        SliderScrollScale, Theme,
+        AppState, AudioQuality, Authentication, Config, KeyAction, KeyChord, Keymap, Preferences,
+        PreferencesTab, Promise, SliderScrollScale, Theme, ThemeFile,
     },
+    webapi::{WebApi, DEFAULT_LRCLIB_URL},
     widget::{icons, Async, Border, Checkbox, MyWidgetExt},
 };
 use druid::{
@@ -16,10 +17,16 @@ use druid::{
         Button, Controller, CrossAxisAlignment, Flex, Label, LineBreaking, MainAxisAlignment,
         RadioGroup, SizedBox, Slider, TextBox, ViewSwitcher,
     },
-    Color, Data, Env, Event, EventCtx, Insets, Lens, LensExt, LifeCycle, LifeCycleCtx, Selector,
-    Widget, WidgetExt,
+    Color, Data, Env, Event, EventCtx, Insets, KbKey, Lens, LensExt, LifeCycle, LifeCycleCtx,
+    Selector, Widget, WidgetExt,
+};
+use psst_core::{
+    connection::Credentials,
+    lastfm::{self, DEFAULT_LASTFM_URL},
+    oauth,
+    scrobble::listenbrainz::DEFAULT_LISTENBRAINZ_URL,
+    session::SessionConfig,
 };
-use psst_core::{connection::Credentials, lastfm, oauth, session::SessionConfig};
 
 use super::{icons::SvgIcon, theme};
 
@@ -50,6 +57,19 @@ where
         )
 }
 
+// Lens editing an optional config string, empty text stands for `None`.
+fn optional_text<L>(lens: L) -> impl Lens<AppState, String> + 'static
+where
+    L: Lens<Config, Option<String>> + 'static,
+{
+    AppState::config.then(lens).map(
+        |opt: &Option<String>| opt.clone().unwrap_or_default(),
+        |opt: &mut Option<String>, val: String| {
+            *opt = if val.is_empty() { None } else { Some(val) };
+        },
+    )
+}
+
 pub fn account_setup_widget() -> impl Widget<AppState> {
     Flex::column()
         .must_fill_main_axis(true)
@@ -93,6 +113,7 @@ pub fn preferences_widget() -> impl Widget<AppState> {
                         account_tab_widget(AccountTab::InPreferences).boxed()
                     }
                     PreferencesTab::Cache => cache_tab_widget().boxed(),
+                    PreferencesTab::Shortcuts => shortcuts_tab_widget().boxed(),
                     PreferencesTab::About => about_tab_widget().boxed(),
                 },
             )
@@ -150,6 +171,12 @@ fn tabs_widget() -> impl Widget<AppState> {
             PreferencesTab::Cache,
         ))
         .with_default_spacer()
+        .with_child(tab_link_widget(
+            "Shortcuts",
+            &icons::KEYBOARD,
+            PreferencesTab::Shortcuts,
+        ))
+        .with_default_spacer()
         .with_child(tab_link_widget(
             "About",
             &icons::HEART,
@@ -188,18 +215,97 @@ fn general_tab_widget() -> impl Widget<AppState> {
         .with_child(Label::new("Theme").with_font(theme::UI_FONT_MEDIUM))
         .with_spacer(theme::grid(2.0))
         .with_child(
-            RadioGroup::column(vec![("Light", Theme::Light), ("Dark", Theme::Dark)])
-                .lens(AppState::config.then(Config::theme)),
+            RadioGroup::column(vec![
+                ("Light", Theme::Light),
+                ("Dark", Theme::Dark),
+                ("Follow System", Theme::System),
+            ])
+            .lens(AppState::config.then(Config::theme)),
+        );
+
+    col = col.with_spacer(theme::grid(1.5));
+
+    // Custom theme
+    col = col
+        .with_child(make_input_row(
+            "Theme file:",
+            "None",
+            optional_text(Config::custom_theme),
+        ))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::dynamic(|_, _| {
+                let dir = ThemeFile::themes_dir()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "None".to_string());
+                format!("Name of a .toml or .json file in {dir}, reloaded on save.")
+            })
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
         );
 
     col = col.with_spacer(theme::grid(1.5));
 
+    // Album art accent
+    col = col.with_child(
+        Checkbox::new("Tint the accent color after the album art")
+            .lens(AppState::config.then(Config::album_accent)),
+    );
+
+    col = col.with_spacer(theme::grid(1.5));
+
     // Show track covers
     col = col.with_child(
         Checkbox::new("Show album covers for tracks")
             .lens(AppState::config.then(Config::show_track_cover)),
     );
 
+    col = col.with_spacer(theme::grid(1.5));
+
+    // Lyrics overlay
+    col = col.with_child(
+        Checkbox::new("Show synced lyrics in the playback bar")
+            .lens(AppState::config.then(Config::show_lyrics_overlay)),
+    );
+
+    col = col.with_spacer(theme::grid(1.5));
+
+    // Mini player
+    col = col
+        .with_child(
+            Checkbox::new("Keep the mini player above other windows")
+                .lens(AppState::config.then(Config::mini_player_on_top)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Hide the main window while the mini player is open")
+                .lens(AppState::config.then(Config::mini_player_hides_main)),
+        );
+
+    col = col.with_spacer(theme::grid(1.5));
+
+    // LRCLIB lyrics
+    col = col
+        .with_child(
+            Checkbox::new("Look up missing lyrics on LRCLIB")
+                .lens(AppState::config.then(Config::lrclib_enable)),
+        )
+        .with_default_spacer()
+        .with_child(make_input_row(
+            "Server:",
+            DEFAULT_LRCLIB_URL,
+            optional_text(Config::lrclib_url),
+        ));
+
+    col = col.with_spacer(theme::grid(1.5));
+
+    // Skip silence
+    col = col.with_child(
+        Checkbox::new("Skip silence in podcast episodes")
+            .lens(AppState::config.then(Config::skip_silence)),
+    );
+
     col = col.with_spacer(theme::grid(3.0));
 
     // Audio quality
@@ -258,6 +364,23 @@ fn general_tab_widget() -> impl Widget<AppState> {
 
     col = col.with_spacer(theme::grid(3.0));
 
+    col = col
+        .with_child(Label::new("Sleep Timer Fade-Out").with_font(theme::UI_FONT_MEDIUM))
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Flex::row()
+                .with_child(
+                    TextBox::new().with_formatter(ParseFormatter::with_format_fn(
+                        |usize: &usize| usize.to_string(),
+                    )),
+                )
+                .with_spacer(theme::grid(0.5))
+                .with_child(Label::new("seconds"))
+                .lens(AppState::config.then(Config::sleep_fade_out)),
+        );
+
+    col = col.with_spacer(theme::grid(3.0));
+
     col = col
         .with_child(
             Label::new("Max Loaded Tracks (requires restart)").with_font(theme::UI_FONT_MEDIUM),
@@ -450,9 +573,13 @@ fn account_tab_widget(tab: AccountTab) -> impl Widget<AppState> {
             .with_child(Label::new("Last.fm Account").with_font(theme::UI_FONT_MEDIUM))
             .with_spacer(theme::grid(1.0))
             .with_child(
-                Label::new("Connect your Last.fm account to scrobble tracks you listen to.")
-                    .with_text_color(theme::PLACEHOLDER_COLOR)
-                    .with_line_break_mode(LineBreaking::WordWrap),
+                Label::new(
+                    "Connect your Last.fm account to scrobble tracks you listen to. Leave the \
+                     server empty to use Last.fm, or enter the API root of a compatible service \
+                     like Libre.fm.",
+                )
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_line_break_mode(LineBreaking::WordWrap),
             )
             .with_spacer(theme::grid(2.0))
             .with_child(ViewSwitcher::new(
@@ -466,7 +593,15 @@ fn account_tab_widget(tab: AccountTab) -> impl Widget<AppState> {
                         lastfm_disconnected_view().boxed()
                     }
                 },
-            ));
+            ))
+            .with_spacer(theme::grid(2.0))
+            .with_child(Label::new("ListenBrainz").with_font(theme::UI_FONT_MEDIUM))
+            .with_spacer(theme::grid(1.0))
+            .with_child(listenbrainz_view())
+            .with_spacer(theme::grid(2.0))
+            .with_child(Label::new("Maloja").with_font(theme::UI_FONT_MEDIUM))
+            .with_spacer(theme::grid(1.0))
+            .with_child(maloja_view());
     }
     col.controller(Authenticate::new(tab))
 }
@@ -498,6 +633,12 @@ fn lastfm_connected_view() -> impl Widget<AppState> {
 fn lastfm_disconnected_view() -> impl Widget<AppState> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(make_input_row(
+            "Server:",
+            DEFAULT_LASTFM_URL,
+            optional_text(Config::lastfm_url),
+        ))
+        .with_default_spacer()
         .with_child(make_input_row(
             "API Key:",
             "Enter your Last.fm API Key",
@@ -561,6 +702,62 @@ fn lastfm_disconnected_view() -> impl Widget<AppState> {
         ))
 }
 
+fn listenbrainz_view() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Submit listens to ListenBrainz. Leave the server empty to use the official \
+                 instance.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Submit listens")
+                .lens(AppState::config.then(Config::listenbrainz_enable)),
+        )
+        .with_default_spacer()
+        .with_child(make_input_row(
+            "Server:",
+            DEFAULT_LISTENBRAINZ_URL,
+            optional_text(Config::listenbrainz_url),
+        ))
+        .with_default_spacer()
+        .with_child(make_input_row(
+            "User Token:",
+            "Enter your ListenBrainz user token",
+            optional_text(Config::listenbrainz_token),
+        ))
+}
+
+fn maloja_view() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new("Submit scrobbles to a self-hosted Maloja server.")
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Submit scrobbles").lens(AppState::config.then(Config::maloja_enable)),
+        )
+        .with_default_spacer()
+        .with_child(make_input_row(
+            "Server:",
+            "https://maloja.example.com",
+            optional_text(Config::maloja_url),
+        ))
+        .with_default_spacer()
+        .with_child(make_input_row(
+            "API Key:",
+            "Enter your Maloja API key",
+            optional_text(Config::maloja_api_key),
+        ))
+}
+
 pub struct Authenticate {
     tab: AccountTab,
     spotify_thread: Option<JoinHandle<()>>,
@@ -783,7 +980,10 @@ impl<W: Widget<AppState>> Controller<AppState, W> for Authenticate {
                 let callback_url = format!("http://127.0.0.1:{port}/lastfm_callback");
                 let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port);
 
-                match lastfm::generate_lastfm_auth_url(&api_key, &callback_url) {
+                let url = data.config.lastfm_url.clone();
+                let auth_url =
+                    lastfm::generate_lastfm_auth_url(url.as_deref(), &api_key, &callback_url);
+                match auth_url {
                     Ok(auth_url) => {
                         self.lastfm_thread = Authenticate::spawn_auth_thread(
                             ctx,
@@ -794,8 +994,14 @@ impl<W: Widget<AppState>> Controller<AppState, W> for Authenticate {
                                 )
                                 .map_err(|e| e.to_string())?;
                                 log::info!("received Last.fm token, exchanging...");
-                                lastfm::exchange_token_for_session(&api_key, &api_secret, &token)
-                                    .map_err(|e| format!("Token exchange failed: {e}"))
+                                lastfm::exchange_token_for_session(
+                                    url.as_deref(),
+                                    &api_key,
+                                    &api_secret,
+                                    &token,
+                                    Config::proxy().as_deref(),
+                                )
+                                .map_err(|e| format!("Token exchange failed: {e}"))
                             },
                             Self::LASTFM_RESPONSE,
                             self.lastfm_thread.take(),
@@ -935,6 +1141,158 @@ fn cache_tab_widget() -> impl Widget<AppState> {
         .lens(AppState::preferences)
 }
 
+const RECORD_SHORTCUT: Selector<KeyAction> = Selector::new("app.preferences.record-shortcut");
+
+fn shortcuts_tab_widget() -> impl Widget<AppState> {
+    let mut col = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .must_fill_main_axis(true);
+
+    col = col
+        .with_child(Label::new("Keyboard Shortcuts").with_font(theme::UI_FONT_MEDIUM))
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Label::new(
+                "Click a shortcut and press the new key combination. Shortcuts work in the main \
+                 window while no text field is focused.",
+            )
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+        .with_spacer(theme::grid(2.0));
+
+    for action in KeyAction::all() {
+        col = col.with_child(shortcut_row_widget(action));
+    }
+
+    col = col
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::dynamic(|config: &Config, _| {
+                config
+                    .keymap
+                    .conflicts()
+                    .iter()
+                    .map(|conflict| conflict.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::RED)
+            .lens(AppState::config),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Reset to Defaults").on_left_click(
+            |_, _, data: &mut AppState, _| {
+                data.preferences.recording_shortcut = None;
+                data.config.keymap = Keymap::default();
+            },
+        ));
+
+    col = col.with_spacer(theme::grid(3.0));
+
+    col = col
+        .with_child(Label::new("Media Keys").with_font(theme::UI_FONT_MEDIUM))
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Checkbox::new("Control playback with the system media keys")
+                .lens(AppState::config.then(Config::media_keys)),
+        );
+
+    col.controller(ShortcutsController)
+}
+
+fn shortcut_row_widget(action: KeyAction) -> impl Widget<AppState> {
+    let chords = Label::dynamic(move |data: &AppState, _| {
+        if data.preferences.recording_shortcut == Some(action) {
+            return "Press keys, Esc to cancel...".to_string();
+        }
+        let chords: Vec<_> = data
+            .config
+            .keymap
+            .chords(action)
+            .map(|chord| chord.to_string())
+            .collect();
+        if chords.is_empty() {
+            "None".to_string()
+        } else {
+            chords.join(", ")
+        }
+    })
+    .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+    .link()
+    .rounded(theme::BUTTON_BORDER_RADIUS)
+    .active(move |data: &AppState, _| data.preferences.recording_shortcut == Some(action))
+    .on_left_click(move |ctx, _, _, _| {
+        ctx.submit_command(RECORD_SHORTCUT.with(action));
+    });
+
+    let clear = Label::new("Clear")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(move |_, _, data: &mut AppState, _| {
+            data.preferences.recording_shortcut = None;
+            data.config.keymap.unbind(action);
+        });
+
+    Flex::row()
+        .cross_axis_alignment(CrossAxisAlignment::Center)
+        .with_child(
+            SizedBox::new(Label::new(action.label()))
+                .width(theme::grid(20.0))
+                .align_left(),
+        )
+        .with_child(chords)
+        .with_default_spacer()
+        .with_child(clear)
+}
+
+/// Takes the keyboard focus while a shortcut is being recorded, and binds the
+/// next pressed chord.  Escape cancels the recording.
+struct ShortcutsController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ShortcutsController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(RECORD_SHORTCUT) => {
+                let action = *cmd.get_unchecked(RECORD_SHORTCUT);
+                if data.preferences.recording_shortcut == Some(action) {
+                    data.preferences.recording_shortcut = None;
+                } else {
+                    data.preferences.recording_shortcut = Some(action);
+                    ctx.request_focus();
+                }
+                ctx.set_handled();
+            }
+            Event::KeyDown(key) if data.preferences.recording_shortcut.is_some() => {
+                if key.key == KbKey::Escape && key.mods.is_empty() {
+                    data.preferences.recording_shortcut = None;
+                } else if let (Some(action), Some(chord)) = (
+                    data.preferences.recording_shortcut,
+                    KeyChord::from_event(key),
+                ) {
+                    data.config.keymap.bind(action, chord);
+                    data.preferences.recording_shortcut = None;
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
+
 fn about_tab_widget() -> impl Widget<AppState> {
     // Build Info
     let commit_hash = Flex::row()
@@ -953,6 +1311,13 @@ fn about_tab_widget() -> impl Widget<AppState> {
             }),
     );
 
+    // Refreshed with every change of the app state, close enough for
+    // diagnostics.
+    let request_metrics =
+        Label::dynamic(|_: &AppState, _| WebApi::global().request_metrics().to_string())
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::DISABLED_TEXT_COLOR);
+
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .must_fill_main_axis(true)
@@ -961,4 +1326,8 @@ fn about_tab_widget() -> impl Widget<AppState> {
         .with_child(commit_hash)
         .with_child(build_time)
         .with_child(remote_url)
+        .with_spacer(theme::grid(3.0))
+        .with_child(Label::new("Web API Requests").with_font(theme::UI_FONT_MEDIUM))
+        .with_spacer(theme::grid(2.0))
+        .with_child(request_metrics)
 }
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use druid::{
+    im::Vector,
+    lens::Map,
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, List},
+    LocalizedString, Menu, MenuItem, Selector, Widget, WidgetExt,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, Nav, Playable, PlaybackOrigin, PlaybackPayload, PlayedItem, RecentContext},
+    error::Error,
+    webapi::WebApi,
+    widget::{Empty, MyWidgetExt},
+};
+
+use super::theme;
+
+pub const RESUME_CONTEXT: Selector<RecentContext> = Selector::new("app.recent.resume-context");
+
+/// Number of contexts shown on the home page.
+const JUMP_BACK_LEN: usize = 5;
+
+pub fn recently_played_widget() -> impl Widget<AppState> {
+    resume_on_command(
+        Either::new(
+            |recent: &Vector<RecentContext>, _| recent.is_empty(),
+            Label::new("Nothing has been played yet.").with_text_color(theme::PLACEHOLDER_COLOR),
+            List::new(recent_context_widget),
+        )
+        .lens(AppState::recently_played),
+    )
+}
+
+pub fn jump_back_in_widget() -> impl Widget<AppState> {
+    resume_on_command(
+        Either::new(
+            |recent: &Vector<RecentContext>, _| recent.is_empty(),
+            Empty,
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_default_spacer()
+                .with_child(
+                    Label::new("Jump back in")
+                        .with_text_size(theme::grid(2.5))
+                        .padding((theme::grid(1.5), theme::grid(0.5)))
+                        .link()
+                        .rounded(theme::BUTTON_BORDER_RADIUS)
+                        .on_left_click(|ctx, _, _, _| {
+                            ctx.submit_command(cmd::NAVIGATE.with(Nav::RecentlyPlayed));
+                        }),
+                )
+                .with_child(List::new(recent_context_widget).padding((theme::grid(0.5), 0.0))),
+        )
+        .lens(Map::new(
+            |data: &AppState| data.recently_played.take(JUMP_BACK_LEN),
+            |_, _| {},
+        )),
+    )
+}
+
+/// Resume playback of a context, starting from the item played last in it.
+fn resume_on_command(widget: impl Widget<AppState>) -> impl Widget<AppState> {
+    widget.on_command_async(
+        RESUME_CONTEXT,
+        |recent| load_context(&recent),
+        |_, _, _| {},
+        |ctx, data, (recent, result)| match result {
+            Ok(items) => {
+                let position = items
+                    .iter()
+                    .position(|item| item.id() == recent.item.item_id())
+                    .unwrap_or(0);
+                ctx.submit_command(cmd::PLAY_TRACKS.with(PlaybackPayload {
+                    origin: recent.origin,
+                    items,
+                    position,
+                    start_position: Duration::default(),
+                }));
+            }
+            Err(err) => {
+                data.error_alert(err);
+            }
+        },
+    )
+}
+
+fn load_context(recent: &RecentContext) -> Result<Vector<Playable>, Error> {
+    let items = match &recent.origin {
+        PlaybackOrigin::Album(link) => WebApi::global()
+            .get_album(&link.id)?
+            .data
+            .into_tracks_with_context()
+            .into_iter()
+            .map(Playable::Track)
+            .collect(),
+        PlaybackOrigin::Playlist(link) => WebApi::global()
+            .get_playlist_tracks(&link.id)?
+            .into_iter()
+            .map(Playable::Track)
+            .collect(),
+        PlaybackOrigin::Show(link) => WebApi::global()
+            .get_show_episodes(&link.id)?
+            .into_iter()
+            .map(Playable::Episode)
+            .collect(),
+        _ => match &recent.item {
+            PlayedItem::Track { id, .. } => Vector::unit(Playable::Track(
+                WebApi::global().get_track(&id.0.to_base62())?,
+            )),
+            PlayedItem::Episode { id, .. } => WebApi::global()
+                .get_episodes([*id])?
+                .into_iter()
+                .map(Playable::Episode)
+                .collect(),
+        },
+    };
+    Ok(items)
+}
+
+fn recent_context_widget() -> impl Widget<RecentContext> {
+    Flex::row()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Label::dynamic(|recent: &RecentContext, _| recent.title().to_string())
+                        .with_line_break_mode(LineBreaking::Clip),
+                )
+                .with_child(
+                    Label::dynamic(|recent: &RecentContext, _| recent.subtitle())
+                        .with_text_size(theme::TEXT_SIZE_SMALL)
+                        .with_text_color(theme::PLACEHOLDER_COLOR)
+                        .with_line_break_mode(LineBreaking::Clip),
+                ),
+            1.0,
+        )
+        .with_child(
+            Label::dynamic(|recent: &RecentContext, _| format_played_ago(recent.played_ago()))
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+        .padding(theme::grid(1.0))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(|ctx, _, recent, _| {
+            ctx.submit_command(RESUME_CONTEXT.with(recent.clone()));
+        })
+        .context_menu(recent_context_menu)
+}
+
+fn recent_context_menu(recent: &RecentContext) -> Menu<AppState> {
+    Menu::empty()
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-resume").with_placeholder("Resume"))
+                .command(RESUME_CONTEXT.with(recent.clone())),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-show-context")
+                    .with_placeholder(format!("Go to \"{}\"", recent.title())),
+            )
+            .command(cmd::NAVIGATE.with(recent.to_nav())),
+        )
+}
+
+fn format_played_ago(ago: Duration) -> String {
+    let minutes = ago.as_secs() / 60;
+    let hours = minutes / 60;
+    let days = hours / 24;
+    match (days, hours, minutes) {
+        (0, 0, 0) => "Just now".to_string(),
+        (0, 0, minutes) => format!("{minutes} min ago"),
+        (0, hours, _) => format!("{hours} h ago"),
+        (1, _, _) => "Yesterday".to_string(),
+        (days, _, _) => format!("{days} days ago"),
+    }
+}
@@ -1,17 +1,24 @@
 use std::sync::Arc;
 
 use druid::{
+    im::Vector,
+    lens::Map,
     widget::{
-        CrossAxisAlignment, Either, Flex, Label, LabelText, List, MainAxisAlignment, Scroll,
-        TextBox,
+        Button, CrossAxisAlignment, Either, Flex, Label, LabelText, LineBreaking, List,
+        MainAxisAlignment, Scroll, TextBox,
     },
-    Data, Env, Insets, Lens, LensExt, RenderContext, Selector, Widget, WidgetExt,
+    Command, Data, Env, Insets, Lens, LensExt, LocalizedString, Menu, MenuItem, RenderContext,
+    Selector, Widget, WidgetExt,
 };
 
 use crate::{
     cmd,
     controller::InputController,
-    data::{AppState, Ctx, Nav, Search, SearchResults, SearchTopic, SpotifyUrl, WithCtx},
+    data::{
+        record_search, search_suggestions, AppState, Ctx, LyricsMatch, LyricsMatchField, Nav,
+        Playable, PlaybackOrigin, PlaybackPayload, Search, SearchField, SearchFilter, SearchQuery,
+        SearchResults, SearchTopic, SpotifyUrl, Track, WithCtx,
+    },
     ui::show,
     webapi::WebApi,
     widget::{Async, Empty, MyWidgetExt},
@@ -27,25 +34,106 @@ pub const LOAD_RESULTS: Selector<(Arc<str>, Option<SearchTopic>)> =
 pub const OPEN_LINK: Selector<SpotifyUrl> = Selector::new("app.search.open-link");
 pub const SET_TOPIC: Selector<Option<SearchTopic>> = Selector::new("app.search.set-topic");
 
+const LOAD_LYRICS_RESULTS: Selector<(Arc<str>, Vector<Arc<Track>>)> =
+    Selector::new("app.search.load-lyrics-results");
+const LOAD_MORE: Selector<(Arc<str>, SearchTopic, usize)> = Selector::new("app.search.load-more");
+const ADD_FILTER: Selector<SearchField> = Selector::new("app.search.add-filter");
+const REMOVE_FILTER: Selector<SearchFilter> = Selector::new("app.search.remove-filter");
+const SEARCH_FROM_HISTORY: Selector<Arc<str>> = Selector::new("app.search.search-from-history");
+const REMOVE_FROM_HISTORY: Selector<Arc<str>> = Selector::new("app.search.remove-from-history");
+const CLEAR_HISTORY: Selector = Selector::new("app.search.clear-history");
+
 pub fn input_widget() -> impl Widget<AppState> {
     TextBox::new()
         .with_placeholder("Search")
         .controller(InputController::new().on_submit(|ctx, query, _| {
-            if query.trim().is_empty() {
+            let query = SearchQuery::parse(query);
+            if query.is_empty() {
                 return;
             }
-            ctx.submit_command(cmd::NAVIGATE.with(Nav::SearchResults(query.clone().into())));
+            ctx.submit_command(cmd::NAVIGATE.with(Nav::SearchResults(query.to_string().into())));
         }))
         .with_id(cmd::WIDGET_SEARCH_INPUT)
         .expand_width()
         .lens(AppState::search.then(Search::input))
 }
 
+/// Previous searches matching the search input.
+pub fn suggestions_widget() -> impl Widget<AppState> {
+    List::new(|| {
+        Label::raw()
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::Clip)
+            .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+            .expand_width()
+            .link()
+            .rounded(theme::BUTTON_BORDER_RADIUS)
+            .on_left_click(|ctx, _, query, _| {
+                ctx.submit_command(SEARCH_FROM_HISTORY.with(query.clone()));
+            })
+            .context_menu(history_menu)
+    })
+    .lens(Map::new(
+        |data: &AppState| search_suggestions(&data.config.search_history, &data.search.input),
+        |_, _| {},
+    ))
+    .padding_horizontal(theme::grid(1.0))
+    .on_command(SEARCH_FROM_HISTORY, |ctx, query, data: &mut AppState| {
+        data.search.input = query.to_string();
+        ctx.submit_command(cmd::NAVIGATE.with(Nav::SearchResults(query.clone())));
+    })
+    .on_command(REMOVE_FROM_HISTORY, |_, query, data| {
+        data.config.search_history.retain(|q| q != query);
+        data.config.save();
+    })
+    .on_command(CLEAR_HISTORY, |_, _, data| {
+        data.config.search_history.clear();
+        data.config.save();
+    })
+}
+
+/// Command loading the results of `query` in the current topic.  Lyrics are
+/// searched locally, in the saved tracks if they are already loaded.
+pub fn load_results_command(data: &AppState, query: Arc<str>) -> Command {
+    match data.search.topic {
+        Some(SearchTopic::Lyrics) => {
+            let tracks = data
+                .library
+                .saved_tracks
+                .resolved()
+                .map(|saved| saved.tracks.clone())
+                .unwrap_or_default();
+            LOAD_LYRICS_RESULTS.with((query, tracks)).into()
+        }
+        topic => LOAD_RESULTS.with((query, topic)).into(),
+    }
+}
+
+fn history_menu(query: &Arc<str>) -> Menu<AppState> {
+    Menu::empty()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-remove-from-search-history")
+                    .with_placeholder("Remove from Search History"),
+            )
+            .command(REMOVE_FROM_HISTORY.with(query.clone())),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-clear-search-history")
+                    .with_placeholder("Clear Search History"),
+            )
+            .command(CLEAR_HISTORY),
+        )
+}
+
 pub fn results_widget() -> impl Widget<AppState> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Center)
         .with_spacer(theme::grid(1.0))
         .with_child(topic_widget())
+        .with_child(filter_widget())
         .with_flex_child(Scroll::new(async_results_widget()).vertical(), 1.0)
 }
 
@@ -58,6 +146,11 @@ fn topic_widget() -> impl Widget<AppState> {
         topics.add_default_spacer();
         topics.add_child(topic_button(topic.display_name(), Some(topic)));
     }
+    topics.add_default_spacer();
+    topics.add_child(topic_button(
+        SearchTopic::Lyrics.display_name(),
+        Some(SearchTopic::Lyrics),
+    ));
 
     Scroll::new(
         topics
@@ -67,6 +160,70 @@ fn topic_widget() -> impl Widget<AppState> {
     .horizontal()
 }
 
+/// Chips of the field filters of the current query, followed by the ones that
+/// can be added to it.
+fn filter_widget() -> impl Widget<AppState> {
+    let active = List::new(|| {
+        Label::dynamic(|filter: &SearchFilter, _| {
+            format!("{}: {}  ×", filter.field.display_name(), filter.value)
+        })
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding(Insets::uniform_xy(theme::grid(1.5), theme::grid(0.5)))
+        .background(theme::GREY_500)
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .link()
+        .on_left_click(|ctx, _, filter, _| {
+            ctx.submit_command(REMOVE_FILTER.with(filter.clone()));
+        })
+    })
+    .horizontal()
+    .with_spacing(theme::grid(1.0))
+    .lens(
+        AppState::search
+            .then(Search::query)
+            .then(SearchQuery::filters),
+    );
+
+    let mut available = Flex::row();
+    for &field in SearchField::all() {
+        available.add_default_spacer();
+        available.add_child(
+            Label::new(format!("+ {}", field.display_name()))
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .padding(Insets::uniform_xy(theme::grid(1.0), theme::grid(0.5)))
+                .link()
+                .rounded(theme::BUTTON_BORDER_RADIUS)
+                .on_left_click(move |ctx, _, _, _| {
+                    ctx.submit_command(ADD_FILTER.with(field));
+                }),
+        );
+    }
+
+    Scroll::new(
+        Flex::row()
+            .with_child(active)
+            .with_child(available)
+            .padding(Insets::new(0.0, 0.0, 0.0, theme::grid(2.0))),
+    )
+    .horizontal()
+    .on_command(ADD_FILTER, |ctx, field, data: &mut AppState| {
+        let input = &mut data.search.input;
+        if !input.is_empty() && !input.ends_with(' ') {
+            input.push(' ');
+        }
+        input.push_str(field.template());
+        ctx.submit_command(cmd::SET_FOCUS.to(cmd::WIDGET_SEARCH_INPUT));
+    })
+    .on_command(REMOVE_FILTER, |ctx, filter, data: &mut AppState| {
+        let query = data.search.query.without_filter(filter);
+        data.search.input = query.to_string();
+        if !query.is_empty() {
+            ctx.submit_command(cmd::NAVIGATE.with(Nav::SearchResults(query.to_string().into())));
+        }
+    })
+}
+
 fn topic_button(label: &str, topic: Option<SearchTopic>) -> impl Widget<AppState> {
     Label::new(label)
         .with_text_size(theme::TEXT_SIZE_SMALL)
@@ -114,17 +271,74 @@ fn async_results_widget() -> impl Widget<AppState> {
             } else {
                 NUMBER_OF_RESULTS_PER_TOPIC
             };
-            WebApi::global().search(&q, &topics, limit)
+            WebApi::global().search(&SearchQuery::parse(&q), &topics, limit, 0)
+        },
+        |_, data, (q, t)| {
+            data.search.query = SearchQuery::parse(&q);
+            data.search.results.defer((q, t));
+        },
+        |_, data, ((q, t), r)| {
+            if r.is_ok() {
+                record_search(&mut data.config.search_history, q.clone());
+            }
+            data.search.results.update(((q, t), r));
+        },
+    )
+    .on_command_async(
+        LOAD_LYRICS_RESULTS,
+        |(q, tracks)| {
+            let tracks = if tracks.is_empty() {
+                WebApi::global().get_saved_tracks()?
+            } else {
+                tracks
+            };
+            let matches = WebApi::global().search_lyrics(&tracks, &q);
+            Ok(SearchResults::from_lyrics(q, matches))
+        },
+        |_, data, (q, _)| {
+            data.search.query = SearchQuery::parse(&q);
+            data.search.results.defer((q, Some(SearchTopic::Lyrics)));
+        },
+        |_, data, ((q, _), r)| {
+            if r.is_ok() {
+                record_search(&mut data.config.search_history, q.clone());
+            }
+            data.search
+                .results
+                .update(((q, Some(SearchTopic::Lyrics)), r));
+        },
+    )
+    .on_command_async(
+        LOAD_MORE,
+        |(q, t, offset)| {
+            WebApi::global().search(
+                &SearchQuery::parse(&q),
+                &[t],
+                INDIVIDUAL_TOPIC_RESULTS_LIMIT,
+                offset,
+            )
+        },
+        |_, data, _| {
+            // Hide the button until the page arrives.
+            if let Some(results) = data.search.results.resolved_mut() {
+                results.next_offset = None;
+            }
+        },
+        |_, data, ((q, t, _), r)| match r {
+            Ok(page) => {
+                if let Some(results) = data.search.results.resolved_mut() {
+                    if results.query == q && results.topic == Some(t) {
+                        results.append(page);
+                    }
+                }
+            }
+            Err(err) => data.error_alert(err),
         },
-        |_, data, (q, t)| data.search.results.defer((q, t)),
-        |_, data, r| data.search.results.update(r),
     )
     .on_command(SET_TOPIC, |ctx, topic, data: &mut AppState| {
         data.search.topic = *topic;
         if !data.search.input.is_empty() {
-            ctx.submit_command(
-                LOAD_RESULTS.with((data.search.input.clone().into(), data.search.topic)),
-            );
+            ctx.submit_command(load_results_command(data, data.search.input.clone().into()));
         }
     })
     .on_command_async(
@@ -153,7 +367,7 @@ fn loaded_results_widget() -> impl Widget<WithCtx<SearchResults>> {
             .center(),
         Either::new(
             |results: &WithCtx<SearchResults>, _| results.data.topic.is_some(),
-            results_list(false),
+            results_list(false).with_child(load_more_widget()),
             results_list(true),
         ),
     )
@@ -165,7 +379,24 @@ fn results_list(include_headers: bool) -> Flex<WithCtx<SearchResults>> {
     column = column.with_child(album_results_widget(include_headers));
     column = column.with_child(track_results_widget(include_headers));
     column = column.with_child(playlist_results_widget(include_headers));
-    column.with_child(show_results_widget(include_headers))
+    column = column.with_child(show_results_widget(include_headers));
+    column.with_child(lyrics_results_widget(include_headers))
+}
+
+fn load_more_widget() -> impl Widget<WithCtx<SearchResults>> {
+    Either::new(
+        |results: &SearchResults, _| results.next_offset.is_none(),
+        Empty,
+        Button::new("Load More")
+            .on_click(|ctx, results: &mut SearchResults, _| {
+                if let (Some(topic), Some(offset)) = (results.topic, results.next_offset) {
+                    ctx.submit_command(LOAD_MORE.with((results.query.clone(), topic, offset)));
+                }
+            })
+            .center()
+            .padding(theme::grid(2.0)),
+    )
+    .lens(Ctx::data())
 }
 
 fn section_widget<T: Data, W: Widget<T> + 'static>(
@@ -246,6 +477,60 @@ fn show_results_widget(include_header: bool) -> impl Widget<WithCtx<SearchResult
     )
 }
 
+fn lyrics_results_widget(include_header: bool) -> impl Widget<WithCtx<SearchResults>> {
+    section_widget(
+        SearchTopic::Lyrics.display_name(),
+        include_header,
+        Ctx::data().then(SearchResults::lyrics),
+        |lyrics| lyrics.is_empty(),
+        || List::new(lyrics_match_widget),
+    )
+}
+
+fn lyrics_match_widget() -> impl Widget<LyricsMatch> {
+    let name = Label::raw()
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_line_break_mode(LineBreaking::Clip)
+        .lens(LyricsMatch::track.then(Track::name.in_arc()));
+
+    let artist = Label::dynamic(|m: &LyricsMatch, _| m.track.artist_names())
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_line_break_mode(LineBreaking::Clip);
+
+    let snippet = Label::dynamic(|m: &LyricsMatch, _| match (m.field, m.start) {
+        (LyricsMatchField::Lyrics, Some(start)) => {
+            format!("{}  {}", utils::as_minutes_and_seconds(start), m.snippet)
+        }
+        (LyricsMatchField::Lyrics, None) => m.snippet.to_string(),
+        (LyricsMatchField::Name, _) => format!("Title: {}", m.snippet),
+        (LyricsMatchField::Artist, _) => format!("Artist: {}", m.snippet),
+        (LyricsMatchField::Album, _) => format!("Album: {}", m.snippet),
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .with_line_break_mode(LineBreaking::WordWrap);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(name)
+        .with_spacer(2.0)
+        .with_child(artist)
+        .with_spacer(2.0)
+        .with_child(snippet)
+        .padding(theme::grid(1.0))
+        .expand_width()
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(|ctx, _, m: &mut LyricsMatch, _| {
+            ctx.submit_command(cmd::PLAY_TRACKS.with(PlaybackPayload {
+                origin: PlaybackOrigin::Library,
+                items: Vector::unit(Playable::Track(m.track.clone())),
+                position: 0,
+                start_position: m.start.unwrap_or_default(),
+            }));
+        })
+}
+
 fn header_widget<T: Data>(text: impl Into<LabelText<T>>) -> impl Widget<T> {
     Label::new(text)
         .with_font(theme::UI_FONT_MEDIUM)
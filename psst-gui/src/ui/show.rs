@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use druid::{
     widget::{CrossAxisAlignment, Flex, Label, LineBreaking, Scroll},
@@ -7,7 +7,9 @@ use druid::{
 
 use crate::{
     cmd,
-    data::{AppState, Ctx, Library, Nav, Show, ShowDetail, ShowEpisodes, ShowLink, WithCtx},
+    data::{
+        AppState, Ctx, Library, Nav, Show, ShowDetail, ShowEpisodes, ShowLink, ShowTrim, WithCtx,
+    },
     ui::utils::{stat_row, InfoLayout},
     webapi::WebApi,
     widget::{Async, MyWidgetExt, RemoteImage},
@@ -17,14 +19,93 @@ use super::{library, playable, theme, track, utils};
 
 pub const LOAD_DETAIL: Selector<ShowLink> = Selector::new("app.show.load-detail");
 
+const TRIM_STEP_SECS: u64 = 5;
+
 pub fn detail_widget() -> impl Widget<AppState> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(async_info_widget())
+        .with_child(trim_widget())
         .with_default_spacer()
         .with_child(async_episodes_widget())
 }
 
+fn trim_widget() -> impl Widget<AppState> {
+    let time_saved = Label::dynamic(|data: &AppState, _| {
+        let saved = detail_show_id(data)
+            .map(|show_id| data.config.show_time_saved(&show_id))
+            .unwrap_or_default();
+        if saved.as_secs() > 0 {
+            format!("Time saved: {}", utils::as_human(saved))
+        } else {
+            String::new()
+        }
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    Flex::row()
+        .with_child(trim_stepper("Skip intro:", |trim| &mut trim.intro_secs))
+        .with_spacer(theme::grid(2.0))
+        .with_child(trim_stepper("Skip outro:", |trim| &mut trim.outro_secs))
+        .with_spacer(theme::grid(2.0))
+        .with_child(time_saved)
+        .padding((theme::grid(1.0), 0.0))
+}
+
+fn trim_stepper(
+    label: &'static str,
+    field: fn(&mut ShowTrim) -> &mut u64,
+) -> impl Widget<AppState> {
+    let value = Label::dynamic(move |data: &AppState, _| {
+        let mut trim = detail_show_id(data)
+            .map(|show_id| data.config.show_trim(&show_id))
+            .unwrap_or_default();
+        utils::as_minutes_and_seconds(Duration::from_secs(*field(&mut trim)))
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL);
+
+    Flex::row()
+        .with_child(
+            Label::new(label)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+        .with_spacer(theme::grid(0.5))
+        .with_child(trim_step_button("−", field, false))
+        .with_child(value)
+        .with_child(trim_step_button("+", field, true))
+}
+
+fn trim_step_button(
+    text: &'static str,
+    field: fn(&mut ShowTrim) -> &mut u64,
+    increase: bool,
+) -> impl Widget<AppState> {
+    Label::new(text)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding((theme::grid(0.5), 0.0))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(move |_, _, data: &mut AppState, _| {
+            if let Some(show_id) = detail_show_id(data) {
+                let mut trim = data.config.show_trim(&show_id);
+                let secs = field(&mut trim);
+                *secs = if increase {
+                    *secs + TRIM_STEP_SECS
+                } else {
+                    secs.saturating_sub(TRIM_STEP_SECS)
+                };
+                data.config.set_show_trim(show_id, trim);
+                data.config.save();
+            }
+        })
+}
+
+fn detail_show_id(data: &AppState) -> Option<Arc<str>> {
+    data.show_detail.show.deferred().map(|link| link.id.clone())
+}
+
 fn async_info_widget() -> impl Widget<AppState> {
     Async::new(utils::spinner_widget, info_widget, utils::error_widget)
         .lens(
@@ -193,6 +274,7 @@ fn cover_widget(size: f64) -> impl Widget<Arc<Show>> {
     RemoteImage::new(utils::placeholder_widget(), move |show: &Arc<Show>, _| {
         show.image(size, size).map(|image| image.url.clone())
     })
+    .thumbnail(size)
     .fix_size(size, size)
 }
 
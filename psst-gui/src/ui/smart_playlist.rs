@@ -0,0 +1,328 @@
+use druid::{
+    im::Vector,
+    lens::Map,
+    widget::{
+        Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, RadioGroup, TextBox,
+    },
+    Data, Insets, Lens, LensExt, LocalizedString, Menu, MenuItem, Selector, Widget, WidgetExt,
+};
+
+use crate::{
+    cmd,
+    data::{
+        AppState, Config, Ctx, Nav, PlaylistTracks, RuleField, RuleMatching, RuleOp, SmartPlaylist,
+        SmartPlaylistDetail, SmartPlaylistLink, SmartPlaylistLoad, SmartPlaylistPool,
+        SmartPlaylistTracks, SmartRule, WithCtx,
+    },
+    error::Error,
+    webapi::WebApi,
+    widget::{Async, Empty, MyWidgetExt},
+};
+
+use super::{playable, theme, track, utils};
+
+pub const LOAD_DETAIL: Selector<SmartPlaylistLoad> =
+    Selector::new("app.smart-playlist.load-detail");
+
+const CREATE_PLAYLIST: Selector = Selector::new("app.smart-playlist.create");
+const DELETE_PLAYLIST: Selector<SmartPlaylistLink> = Selector::new("app.smart-playlist.delete");
+const SAVE_PLAYLIST: Selector = Selector::new("app.smart-playlist.save");
+
+const ADD_RULE: Selector = Selector::new("app.smart-playlist.add-rule");
+const REMOVE_RULE: Selector<usize> = Selector::new("app.smart-playlist.remove-rule");
+const SET_RULE_FIELD: Selector<(usize, RuleField)> =
+    Selector::new("app.smart-playlist.set-rule-field");
+const SET_RULE_OP: Selector<(usize, RuleOp)> = Selector::new("app.smart-playlist.set-rule-op");
+
+pub fn list_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            List::new(|| {
+                Label::dynamic(|playlist: &SmartPlaylist, _| playlist.name.clone())
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .expand_width()
+                    .padding(Insets::uniform_xy(theme::grid(2.0), theme::grid(0.6)))
+                    .link()
+                    .on_left_click(|ctx, _, playlist, _| {
+                        ctx.submit_command(
+                            cmd::NAVIGATE.with(Nav::SmartPlaylistDetail(playlist.link())),
+                        );
+                    })
+                    .context_menu(smart_playlist_menu)
+            })
+            .lens(AppState::config.then(Config::smart_playlists)),
+        )
+        .with_child(
+            Label::new("New Smart Playlist")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .expand_width()
+                .padding(Insets::uniform_xy(theme::grid(2.0), theme::grid(0.6)))
+                .link()
+                .on_left_click(|ctx, _, _, _| {
+                    ctx.submit_command(CREATE_PLAYLIST);
+                }),
+        )
+        .on_command(CREATE_PLAYLIST, |ctx, _, data| {
+            let playlist = SmartPlaylist::new("New Smart Playlist");
+            let link = playlist.link();
+            data.save_smart_playlist(playlist);
+            ctx.submit_command(cmd::NAVIGATE.with(Nav::SmartPlaylistDetail(link)));
+        })
+        .on_command(DELETE_PLAYLIST, |ctx, link, data| {
+            data.delete_smart_playlist(&link.id);
+            if matches!(&data.nav, Nav::SmartPlaylistDetail(shown) if shown.id == link.id) {
+                ctx.submit_command(cmd::NAVIGATE.with(Nav::Home));
+            }
+        })
+}
+
+fn smart_playlist_menu(playlist: &SmartPlaylist) -> Menu<AppState> {
+    Menu::empty().entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-delete-smart-playlist")
+                .with_placeholder("Delete Smart Playlist"),
+        )
+        .command(DELETE_PLAYLIST.with(playlist.link())),
+    )
+}
+
+pub fn detail_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            editor_widget()
+                .padding(theme::grid(1.0))
+                .lens(AppState::smart_playlist_detail.then(SmartPlaylistDetail::playlist)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(async_tracks_widget())
+        .on_command(ADD_RULE, |_, _, data| {
+            let rules = &mut data.smart_playlist_detail.playlist.rules;
+            rules.push_back(SmartRule::new(RuleField::Artist));
+        })
+        .on_command(REMOVE_RULE, |_, &index, data| {
+            let rules = &mut data.smart_playlist_detail.playlist.rules;
+            if index < rules.len() {
+                rules.remove(index);
+            }
+        })
+        .on_command(SET_RULE_FIELD, |_, &(index, field), data| {
+            if let Some(rule) = data.smart_playlist_detail.playlist.rules.get_mut(index) {
+                rule.set_field(field);
+            }
+        })
+        .on_command(SET_RULE_OP, |_, &(index, op), data| {
+            if let Some(rule) = data.smart_playlist_detail.playlist.rules.get_mut(index) {
+                rule.op = op;
+            }
+        })
+        .on_command(SAVE_PLAYLIST, |ctx, _, data| {
+            let playlist = data.smart_playlist_detail.playlist.clone();
+            data.save_smart_playlist(playlist.clone());
+            ctx.submit_command(LOAD_DETAIL.with(data.smart_playlist_load(playlist)));
+        })
+}
+
+fn editor_widget() -> impl Widget<SmartPlaylist> {
+    let buttons = Flex::row()
+        .with_child(Button::new("Add Rule").on_click(|ctx, _, _| {
+            ctx.submit_command(ADD_RULE);
+        }))
+        .with_default_spacer()
+        .with_child(Button::new("Save and Refresh").on_click(|ctx, _, _| {
+            ctx.submit_command(SAVE_PLAYLIST);
+        }));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            TextBox::new()
+                .with_placeholder("Name")
+                .with_text_size(theme::grid(2.5))
+                .expand_width()
+                .lens(SmartPlaylist::name),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            RadioGroup::row(vec![
+                ("Match all rules", RuleMatching::All),
+                ("Match any rule", RuleMatching::Any),
+            ])
+            .lens(SmartPlaylist::matching),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(List::new(rule_widget).lens(indexed_rules()))
+        .with_spacer(theme::grid(1.0))
+        .with_child(buttons)
+}
+
+/// Rule together with its position, so that the menus can refer to it.
+#[derive(Clone, Data, Lens)]
+struct RuleRow {
+    index: usize,
+    rule: SmartRule,
+}
+
+fn indexed_rules() -> impl Lens<SmartPlaylist, Vector<RuleRow>> {
+    Map::new(
+        |playlist: &SmartPlaylist| {
+            playlist
+                .rules
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(index, rule)| RuleRow { index, rule })
+                .collect()
+        },
+        |playlist: &mut SmartPlaylist, rows: Vector<RuleRow>| {
+            playlist.rules = rows.into_iter().map(|row| row.rule).collect();
+        },
+    )
+}
+
+fn rule_widget() -> impl Widget<RuleRow> {
+    Flex::row()
+        .with_child(menu_button(
+            |row| row.rule.field.name().to_string(),
+            field_menu,
+        ))
+        .with_child(menu_button(
+            |row| row.rule.op.name(row.rule.field).to_string(),
+            op_menu,
+        ))
+        .with_flex_child(
+            Either::new(
+                |row: &RuleRow, _| row.rule.field.has_value(),
+                TextBox::new()
+                    .expand_width()
+                    .lens(RuleRow::rule.then(SmartRule::value)),
+                Empty,
+            ),
+            1.0,
+        )
+        .with_child(
+            Label::dynamic(|row: &RuleRow, _| row.rule.field.unit().to_string())
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .padding_horizontal(theme::grid(1.0)),
+        )
+        .with_child(
+            Label::new("Remove")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .padding(theme::grid(1.0))
+                .link()
+                .rounded(theme::BUTTON_BORDER_RADIUS)
+                .on_left_click(|ctx, _, row, _| {
+                    ctx.submit_command(REMOVE_RULE.with(row.index));
+                }),
+        )
+        .padding((0.0, theme::grid(0.5)))
+}
+
+fn menu_button(
+    text: impl Fn(&RuleRow) -> String + 'static,
+    menu: fn(&RuleRow) -> Menu<AppState>,
+) -> impl Widget<RuleRow> {
+    Label::dynamic(move |row: &RuleRow, _| format!("{} ▾", text(row)))
+        .padding(theme::grid(1.0))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(move |ctx, event, row, _| {
+            ctx.show_context_menu(menu(row), event.window_pos);
+        })
+}
+
+fn field_menu(row: &RuleRow) -> Menu<AppState> {
+    let mut menu = Menu::empty();
+    for field in RuleField::ALL {
+        menu = menu.entry(
+            MenuItem::new(field.name())
+                .command(SET_RULE_FIELD.with((row.index, field)))
+                .selected(row.rule.field == field),
+        );
+    }
+    menu
+}
+
+fn op_menu(row: &RuleRow) -> Menu<AppState> {
+    let mut menu = Menu::empty();
+    for &op in row.rule.field.ops() {
+        menu = menu.entry(
+            MenuItem::new(op.name(row.rule.field))
+                .command(SET_RULE_OP.with((row.index, op)))
+                .selected(row.rule.op == op),
+        );
+    }
+    menu
+}
+
+fn async_tracks_widget() -> impl Widget<AppState> {
+    Async::new(utils::spinner_widget, tracks_widget, utils::error_widget)
+        .lens(
+            Ctx::make(
+                AppState::common_ctx,
+                AppState::smart_playlist_detail.then(SmartPlaylistDetail::tracks),
+            )
+            .then(Ctx::in_promise()),
+        )
+        .on_command_async(
+            LOAD_DETAIL,
+            |load| load_pool(&load),
+            |_, data, load| {
+                data.smart_playlist_detail
+                    .tracks
+                    .defer(load.playlist.link())
+            },
+            |_, data, (load, result)| {
+                let link = load.playlist.link();
+                let plays = data.listening_history.track_plays();
+                let result = result.map(|pool| SmartPlaylistTracks {
+                    link: link.clone(),
+                    tracks: pool.evaluate(&load.playlist, &plays),
+                });
+                data.smart_playlist_detail.tracks.update((link, result));
+            },
+        )
+}
+
+/// Fetch the tracks the playlist can be made of.  Saved tracks are reused from
+/// the library if loaded already.
+fn load_pool(load: &SmartPlaylistLoad) -> Result<SmartPlaylistPool, Error> {
+    let saved_tracks = match &load.saved_tracks {
+        Some(tracks) => tracks.clone(),
+        None => WebApi::global().get_saved_tracks()?,
+    };
+    let playlists = load
+        .playlists
+        .iter()
+        .map(|link| {
+            Ok(PlaylistTracks::new(
+                link,
+                WebApi::global().get_playlist_tracks(&link.id)?,
+            ))
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(SmartPlaylistPool {
+        saved_tracks,
+        playlists,
+    })
+}
+
+fn tracks_widget() -> impl Widget<WithCtx<SmartPlaylistTracks>> {
+    Either::new(
+        |tracks: &WithCtx<SmartPlaylistTracks>, _| tracks.data.tracks.is_empty(),
+        Label::new("No tracks match the rules.")
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .padding(theme::grid(1.0)),
+        playable::list_widget(playable::Display {
+            track: track::Display {
+                title: true,
+                artist: true,
+                album: true,
+                cover: true,
+                ..track::Display::empty()
+            },
+        }),
+    )
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use druid::{
+    lens::Map,
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, RadioGroup},
+    Data, Widget, WidgetExt,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, ListeningStats, Nav, PlayedItem, StatsPeriod, TopArtist, TopTrack},
+    widget::MyWidgetExt,
+};
+
+use super::theme;
+
+pub fn stats_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(period_widget())
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Either::new(
+                |stats: &ListeningStats, _| stats.plays == 0 && stats.skips == 0,
+                Label::new("Nothing has been played in this period.")
+                    .with_text_color(theme::PLACEHOLDER_COLOR),
+                stats_detail_widget(),
+            )
+            .lens(AppState::stats),
+        )
+}
+
+fn period_widget() -> impl Widget<AppState> {
+    RadioGroup::row(vec![
+        ("Last 7 Days", StatsPeriod::Week),
+        ("Last 30 Days", StatsPeriod::Month),
+        ("Last Year", StatsPeriod::Year),
+        ("All Time", StatsPeriod::AllTime),
+    ])
+    .lens(Map::new(
+        |data: &AppState| data.stats.period,
+        |data: &mut AppState, period| {
+            if data.stats.period != period {
+                data.set_stats_period(period);
+            }
+        },
+    ))
+}
+
+fn stats_detail_widget() -> impl Widget<ListeningStats> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(summary_row("Listening time", |stats| {
+            format_listening_time(stats.listening_time)
+        }))
+        .with_child(summary_row("Plays", |stats| stats.plays.to_string()))
+        .with_child(summary_row("Skipped", |stats| stats.skips.to_string()))
+        .with_spacer(theme::grid(2.0))
+        .with_child(header_widget("Top Tracks"))
+        .with_child(List::new(top_track_widget).lens(ListeningStats::top_tracks))
+        .with_spacer(theme::grid(2.0))
+        .with_child(header_widget("Top Artists"))
+        .with_child(List::new(top_artist_widget).lens(ListeningStats::top_artists))
+}
+
+fn summary_row(
+    label: &'static str,
+    value_func: impl Fn(&ListeningStats) -> String + 'static,
+) -> impl Widget<ListeningStats> {
+    Flex::row()
+        .with_child(
+            Label::new(label)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .fix_width(theme::grid(16.0)),
+        )
+        .with_child(Label::dynamic(move |stats: &ListeningStats, _| {
+            value_func(stats)
+        }))
+        .padding((0.0, theme::grid(0.5)))
+}
+
+fn header_widget(title: &str) -> impl Widget<ListeningStats> {
+    Label::new(title)
+        .with_font(theme::UI_FONT_MEDIUM)
+        .padding((0.0, theme::grid(1.0)))
+}
+
+fn top_track_widget() -> impl Widget<TopTrack> {
+    top_row_widget(
+        |track: &TopTrack| track.item.name().to_string(),
+        |track: &TopTrack| track.item.author_name().to_string(),
+        |track: &TopTrack| plays_label(track.plays, track.listening_time),
+    )
+    .on_left_click(|ctx, _, track, _| {
+        if let PlayedItem::Track {
+            id,
+            album: Some(album),
+            ..
+        } = &track.item
+        {
+            ctx.submit_command(cmd::NAVIGATE.with(Nav::AlbumDetail(album.clone(), Some(*id))));
+        }
+    })
+}
+
+fn top_artist_widget() -> impl Widget<TopArtist> {
+    top_row_widget(
+        |artist: &TopArtist| artist.artist.name.to_string(),
+        |_| String::new(),
+        |artist: &TopArtist| plays_label(artist.plays, artist.listening_time),
+    )
+    .on_left_click(|ctx, _, artist, _| {
+        ctx.submit_command(cmd::NAVIGATE.with(Nav::ArtistDetail(artist.artist.clone())));
+    })
+}
+
+fn top_row_widget<T: Data>(
+    title: impl Fn(&T) -> String + 'static,
+    subtitle: impl Fn(&T) -> String + 'static,
+    detail: impl Fn(&T) -> String + 'static,
+) -> impl Widget<T> {
+    Flex::row()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Label::dynamic(move |data, _| title(data))
+                        .with_line_break_mode(LineBreaking::Clip),
+                )
+                .with_child(
+                    Label::dynamic(move |data, _| subtitle(data))
+                        .with_text_size(theme::TEXT_SIZE_SMALL)
+                        .with_text_color(theme::PLACEHOLDER_COLOR)
+                        .with_line_break_mode(LineBreaking::Clip),
+                ),
+            1.0,
+        )
+        .with_child(
+            Label::dynamic(move |data, _| detail(data))
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+        .padding(theme::grid(1.0))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+}
+
+fn plays_label(plays: usize, listening_time: Duration) -> String {
+    let plays = if plays == 1 {
+        "1 play".to_string()
+    } else {
+        format!("{plays} plays")
+    };
+    format!("{plays} · {}", format_listening_time(listening_time))
+}
+
+fn format_listening_time(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    if minutes >= 60 {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes} min")
+    }
+}
@@ -2,7 +2,7 @@ use druid::{Color, Env, FontDescriptor, FontFamily, FontWeight, Insets, Key, Siz
 
 pub use druid::theme::*;
 
-use crate::data::{AppState, Theme};
+use crate::data::{AppState, ThemeFile};
 
 pub fn grid(m: f64) -> f64 {
     GRID * m
@@ -42,11 +42,102 @@ pub const LINK_HOT_COLOR: Key<Color> = Key::new("app.link-hot-color");
 pub const LINK_ACTIVE_COLOR: Key<Color> = Key::new("app.link-active-color");
 pub const LINK_COLD_COLOR: Key<Color> = Key::new("app.link-cold-color");
 
+/// Colors of the base palette, set before the colors derived from them.
+const PALETTE_COLORS: &[(&str, Key<Color>)] = &[
+    ("grey_000", GREY_000),
+    ("grey_100", GREY_100),
+    ("grey_200", GREY_200),
+    ("grey_300", GREY_300),
+    ("grey_400", GREY_400),
+    ("grey_500", GREY_500),
+    ("grey_600", GREY_600),
+    ("grey_700", GREY_700),
+    ("blue_100", BLUE_100),
+    ("blue_200", BLUE_200),
+    ("red", RED),
+    ("link_hot_color", LINK_HOT_COLOR),
+    ("link_active_color", LINK_ACTIVE_COLOR),
+    ("link_cold_color", LINK_COLD_COLOR),
+];
+
+const DERIVED_COLORS: &[(&str, Key<Color>)] = &[
+    ("window_background_color", WINDOW_BACKGROUND_COLOR),
+    ("text_color", TEXT_COLOR),
+    ("icon_color", ICON_COLOR),
+    ("placeholder_color", PLACEHOLDER_COLOR),
+    ("primary_light", PRIMARY_LIGHT),
+    ("primary_dark", PRIMARY_DARK),
+    ("background_light", BACKGROUND_LIGHT),
+    ("background_dark", BACKGROUND_DARK),
+    ("foreground_light", FOREGROUND_LIGHT),
+    ("foreground_dark", FOREGROUND_DARK),
+    ("button_light", BUTTON_LIGHT),
+    ("button_dark", BUTTON_DARK),
+    ("border_light", BORDER_LIGHT),
+    ("border_dark", BORDER_DARK),
+    (
+        "selected_text_background_color",
+        SELECTED_TEXT_BACKGROUND_COLOR,
+    ),
+    ("selection_text_color", SELECTION_TEXT_COLOR),
+    ("cursor_color", CURSOR_COLOR),
+    ("scrollbar_color", SCROLLBAR_COLOR),
+    ("scrollbar_border_color", SCROLLBAR_BORDER_COLOR),
+    ("menu_button_bg_active", MENU_BUTTON_BG_ACTIVE),
+    ("menu_button_bg_inactive", MENU_BUTTON_BG_INACTIVE),
+    ("menu_button_fg_active", MENU_BUTTON_FG_ACTIVE),
+    ("menu_button_fg_inactive", MENU_BUTTON_FG_INACTIVE),
+];
+
+const SIZES: &[(&str, Key<f64>)] = &[
+    ("text_size_small", TEXT_SIZE_SMALL),
+    ("text_size_normal", TEXT_SIZE_NORMAL),
+    ("text_size_large", TEXT_SIZE_LARGE),
+    ("progress_bar_radius", PROGRESS_BAR_RADIUS),
+    ("button_border_radius", BUTTON_BORDER_RADIUS),
+    ("button_border_width", BUTTON_BORDER_WIDTH),
+    ("basic_widget_height", BASIC_WIDGET_HEIGHT),
+    ("wide_widget_width", WIDE_WIDGET_WIDTH),
+    ("bordered_widget_height", BORDERED_WIDGET_HEIGHT),
+    ("textbox_border_radius", TEXTBOX_BORDER_RADIUS),
+    ("textbox_border_width", TEXTBOX_BORDER_WIDTH),
+    ("scrollbar_max_opacity", SCROLLBAR_MAX_OPACITY),
+    ("scrollbar_width", SCROLLBAR_WIDTH),
+    ("scrollbar_pad", SCROLLBAR_PAD),
+    ("scrollbar_radius", SCROLLBAR_RADIUS),
+    ("scrollbar_edge_width", SCROLLBAR_EDGE_WIDTH),
+    ("widget_padding_vertical", WIDGET_PADDING_VERTICAL),
+    ("widget_padding_horizontal", WIDGET_PADDING_HORIZONTAL),
+    (
+        "widget_control_component_padding",
+        WIDGET_CONTROL_COMPONENT_PADDING,
+    ),
+];
+
+const FONTS: &[(&str, Key<FontDescriptor>)] = &[
+    ("ui_font", UI_FONT),
+    ("ui_font_medium", UI_FONT_MEDIUM),
+    ("ui_font_mono", UI_FONT_MONO),
+];
+
 pub fn setup(env: &mut Env, state: &AppState) {
-    match state.config.theme {
-        Theme::Light => setup_light_theme(env),
-        Theme::Dark => setup_dark_theme(env),
-    };
+    let appearance = &state.appearance;
+    let theme_file = appearance.theme_file.as_deref();
+    let theme = theme_file
+        .and_then(|file| file.base)
+        .unwrap_or(state.config.theme);
+    let is_dark = appearance.is_dark(theme);
+    if is_dark {
+        setup_dark_theme(env);
+    } else {
+        setup_light_theme(env);
+    }
+    if let Some(file) = theme_file {
+        set_colors(env, file, PALETTE_COLORS);
+    }
+    if let Some(accent) = appearance.accent.filter(|_| state.config.album_accent) {
+        setup_accent(env, accent, is_dark);
+    }
 
     env.set(WINDOW_BACKGROUND_COLOR, env.get(GREY_700));
     env.set(TEXT_COLOR, env.get(GREY_100));
@@ -60,15 +151,12 @@ pub fn setup(env: &mut Env, state: &AppState) {
     env.set(FOREGROUND_LIGHT, env.get(GREY_100));
     env.set(FOREGROUND_DARK, env.get(GREY_000));
 
-    match state.config.theme {
-        Theme::Light => {
-            env.set(BUTTON_LIGHT, env.get(GREY_700));
-            env.set(BUTTON_DARK, env.get(GREY_600));
-        }
-        Theme::Dark => {
-            env.set(BUTTON_LIGHT, env.get(GREY_600));
-            env.set(BUTTON_DARK, env.get(GREY_700));
-        }
+    if is_dark {
+        env.set(BUTTON_LIGHT, env.get(GREY_600));
+        env.set(BUTTON_DARK, env.get(GREY_700));
+    } else {
+        env.set(BUTTON_LIGHT, env.get(GREY_700));
+        env.set(BUTTON_DARK, env.get(GREY_600));
     }
 
     env.set(BORDER_LIGHT, env.get(GREY_400));
@@ -126,6 +214,98 @@ pub fn setup(env: &mut Env, state: &AppState) {
     env.set(MENU_BUTTON_BG_INACTIVE, env.get(GREY_600));
     env.set(MENU_BUTTON_FG_ACTIVE, env.get(GREY_000));
     env.set(MENU_BUTTON_FG_INACTIVE, env.get(GREY_100));
+
+    if let Some(file) = theme_file {
+        set_colors(env, file, DERIVED_COLORS);
+        set_sizes(env, file);
+        set_fonts(env, file);
+    }
+}
+
+/// Keys in a theme file that don't name any themable value.
+pub fn unknown_keys(file: &ThemeFile) -> Vec<String> {
+    let colors = file.colors.keys().filter(|name| {
+        !PALETTE_COLORS
+            .iter()
+            .chain(DERIVED_COLORS)
+            .any(|(known, _)| known == name)
+    });
+    let sizes = file
+        .sizes
+        .keys()
+        .filter(|name| !SIZES.iter().any(|(known, _)| known == name));
+    let fonts = file
+        .fonts
+        .keys()
+        .filter(|name| !FONTS.iter().any(|(known, _)| known == name));
+    colors.chain(sizes).chain(fonts).cloned().collect()
+}
+
+fn set_colors(env: &mut Env, file: &ThemeFile, keys: &[(&str, Key<Color>)]) {
+    for (name, key) in keys.iter().cloned() {
+        if let Some(value) = file.colors.get(name) {
+            match Color::from_hex_str(value) {
+                Ok(color) => env.set(key, color),
+                Err(err) => log::warn!("invalid theme color {name} = {value}: {err}"),
+            }
+        }
+    }
+}
+
+fn set_sizes(env: &mut Env, file: &ThemeFile) {
+    for (name, key) in SIZES.iter().cloned() {
+        if let Some(&value) = file.sizes.get(name) {
+            env.set(key, value);
+        }
+    }
+}
+
+fn set_fonts(env: &mut Env, file: &ThemeFile) {
+    for (name, key) in FONTS.iter().cloned() {
+        if let Some(spec) = file.fonts.get(name) {
+            let mut font = env.get(&key);
+            if let Some(family) = &spec.family {
+                font.family = match family.as_str() {
+                    "system-ui" => FontFamily::SYSTEM_UI,
+                    "serif" => FontFamily::SERIF,
+                    "sans-serif" => FontFamily::SANS_SERIF,
+                    "monospace" => FontFamily::MONOSPACE,
+                    family => FontFamily::new_unchecked(family),
+                };
+            }
+            if let Some(size) = spec.size {
+                font.size = size;
+            }
+            if let Some(weight) = spec.weight {
+                font.weight = FontWeight::new(weight);
+            }
+            env.set(key, font);
+        }
+    }
+}
+
+/// Replace the blue accents with shades of `accent`.
+fn setup_accent(env: &mut Env, accent: Color, is_dark: bool) {
+    let darker = mix(&accent, &Color::BLACK, 0.2);
+    let lighter = mix(&accent, &Color::WHITE, 0.35);
+    if is_dark {
+        env.set(BLUE_100, darker);
+        env.set(BLUE_200, lighter);
+    } else {
+        env.set(BLUE_100, lighter);
+        env.set(BLUE_200, darker);
+    }
+}
+
+fn mix(a: &Color, b: &Color, t: f64) -> Color {
+    let (ar, ag, ab, aa) = a.as_rgba();
+    let (br, bg, bb, _) = b.as_rgba();
+    Color::rgba(
+        ar + (br - ar) * t,
+        ag + (bg - ag) * t,
+        ab + (bb - ab) * t,
+        aa,
+    )
 }
 
 fn setup_light_theme(env: &mut Env) {
@@ -1,22 +1,24 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use druid::{
+    im::Vector,
     widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, ViewSwitcher},
     Env, Lens, LensExt, LocalizedString, Menu, MenuItem, Size, TextAlignment, Widget, WidgetExt,
 };
 use psst_core::{
     audio::normalize::NormalizationLevel,
     item_id::{ItemId, ItemIdType},
-    player::item::PlaybackItem,
+    player::item::{PlaybackItem, PlaybackTrim},
 };
 
 use crate::{
     cmd,
     data::{
-        AppState, Library, Nav, Playable, PlaybackOrigin, PlaylistAddTrack, PlaylistRemoveTrack,
-        QueueEntry, RecommendationsRequest, Track,
+        AppState, ExportSource, Library, Nav, Playable, PlaybackOrigin, PlaylistAddTracks,
+        PlaylistLink, PlaylistRemoveTracks, QueueEntry, RecommendationsRequest, SelectMode, Track,
+        TrackSelection,
     },
-    ui::playlist,
+    ui::{playlist, playlist_file},
     widget::{fill_between::FillBetween, icons, Empty, MyWidgetExt, RemoteImage},
 };
 
@@ -177,8 +179,18 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
                 .with_child(major)
                 .with_spacer(2.0)
                 .with_child(minor)
-                .on_left_click(|ctx, _, row, _| {
-                    ctx.submit_notification(cmd::PLAY.with(row.position))
+                .on_left_click(|ctx, mouse, row, _| {
+                    if mouse.mods.shift() {
+                        ctx.submit_notification(
+                            cmd::SELECT.with((row.position, SelectMode::Extend)),
+                        )
+                    } else if mouse.mods.ctrl() || mouse.mods.meta() {
+                        ctx.submit_notification(
+                            cmd::SELECT.with((row.position, SelectMode::Toggle)),
+                        )
+                    } else {
+                        ctx.submit_notification(cmd::PLAY.with(row.position))
+                    }
                 }),
             1.0,
         )
@@ -187,6 +199,9 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
         .padding(theme::grid(1.0))
         .link()
         .active(|row: &PlayRow<Arc<Track>>, _env: &Env| {
+            if row.ctx.selection.contains(&row.item.id) {
+                return true;
+            }
             // Check if this track is the target of album detail navigation
             if let Nav::AlbumDetail(_, Some(target_id)) = &row.ctx.nav {
                 return *target_id == row.item.id;
@@ -207,6 +222,7 @@ fn cover_widget(size: f64) -> impl Widget<Arc<Track>> {
             .as_ref()
             .and_then(|al| al.image(size, size).map(|image| image.url.clone()))
     })
+    .thumbnail(size)
     .fix_size(size, size)
 }
 
@@ -232,7 +248,12 @@ fn popularity_stars(popularity: u32) -> String {
 }
 
 fn track_row_menu(row: &PlayRow<Arc<Track>>) -> Menu<AppState> {
-    track_menu(&row.item, &row.ctx.library, &row.origin)
+    let selection = &row.ctx.selection;
+    if selection.len() > 1 && selection.contains(&row.item.id) {
+        selection_menu(selection, &row.ctx.library, &row.origin)
+    } else {
+        track_menu(&row.item, &row.ctx.library, &row.origin)
+    }
 }
 
 pub fn track_menu(
@@ -310,43 +331,17 @@ pub fn track_menu(
     }
 
     if let PlaybackOrigin::Playlist(playlist) = origin {
-        // Do some (hopefully) quick checks to determine if we should give the
-        // option to remove items from this playlist, only allowing it if the
-        // playlist is collaborative or we are the owner of it
-        let should_show = {
-            if let Some(details) = library
-                .playlists
-                .resolved()
-                .and_then(|pl| pl.iter().find(|p| p.id == playlist.id))
-            {
-                if details.collaborative {
-                    true
-                } else if let Some(user) = library.user_profile.resolved() {
-                    user.id == details.owner.id
-                } else {
-                    // If we can find the playlist, but for some reason can't
-                    // resolve our own user, just show the option anyways and
-                    // we'll see an error at the bottom if it doesn't work
-                    // when they try to remove a track
-                    true
-                }
-            } else {
-                // If this playlist doesn't exist in our library,
-                // just assume that we can't edit it since we probably
-                // searched for it or something
-                false
-            }
-        };
-
-        if should_show {
+        if can_remove_from_playlist(library, playlist) {
             menu = menu.entry(
                 MenuItem::new(
                     LocalizedString::new("menu-item-remove-from-playlist")
                         .with_placeholder("Remove from Current Playlist"),
                 )
-                .command(playlist::REMOVE_TRACK.with(PlaylistRemoveTrack {
+                .command(playlist::REMOVE_TRACKS.with(PlaylistRemoveTracks {
                     link: playlist.to_owned(),
-                    track_uri: format!("spotify:track:{}", track.id.0.to_base62()).into(),
+                    track_uris: Vector::unit(
+                        format!("spotify:track:{}", track.id.0.to_base62()).into(),
+                    ),
                 })),
             );
         }
@@ -357,16 +352,7 @@ pub fn track_menu(
             LocalizedString::new("menu-item-add-to-queue").with_placeholder("Add Track to Queue"),
         )
         // PlayerCommand
-        .command(cmd::ADD_TO_QUEUE.with((
-            QueueEntry {
-                item: crate::ui::Playable::Track(track.clone()),
-                origin: origin.clone(),
-            },
-            PlaybackItem {
-                item_id: ItemId::from_base62(&String::from(track.id), ItemIdType::Track).unwrap(),
-                norm_level: NormalizationLevel::Track,
-            },
-        ))),
+        .command(cmd::ADD_TO_QUEUE.with(queue_item(track, origin))),
     );
 
     let mut playlist_menu = Menu::new(
@@ -378,13 +364,169 @@ pub fn track_menu(
                 LocalizedString::new("menu-item-save-to-playlist")
                     .with_placeholder(format!("{}", playlist.name)),
             )
-            .command(playlist::ADD_TRACK.with(PlaylistAddTrack {
+            .command(playlist::ADD_TRACKS.with(PlaylistAddTracks {
+                link: playlist.link(),
+                track_ids: Vector::unit(track.id),
+            })),
+        );
+    }
+    menu = menu.entry(playlist_menu);
+
+    menu
+}
+
+/// Menu of the actions applying to all the selected tracks at once.
+fn selection_menu(
+    selection: &TrackSelection,
+    library: &Library,
+    origin: &PlaybackOrigin,
+) -> Menu<AppState> {
+    let count = selection.len();
+    let mut menu = Menu::empty();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-copy-links")
+                .with_placeholder(format!("Copy Links to {count} Tracks")),
+        )
+        .command(cmd::COPY.with(selection.urls())),
+    );
+
+    let unsaved: Vector<_> = selection
+        .tracks
+        .iter()
+        .filter(|track| !library.contains_track(track))
+        .cloned()
+        .collect();
+    if !unsaved.is_empty() {
+        menu = menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-save-all-to-library")
+                    .with_placeholder(format!("Save {} Tracks to Library", unsaved.len())),
+            )
+            .command(library::SAVE_TRACKS.with(unsaved)),
+        );
+    }
+    let saved: Vector<_> = selection
+        .tracks
+        .iter()
+        .filter(|track| library.contains_track(track))
+        .map(|track| track.id)
+        .collect();
+    if !saved.is_empty() {
+        menu = menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-remove-all-from-library")
+                    .with_placeholder(format!("Remove {} Tracks from Library", saved.len())),
+            )
+            .command(library::UNSAVE_TRACKS.with(saved)),
+        );
+    }
+
+    if let PlaybackOrigin::Playlist(playlist) = origin {
+        if can_remove_from_playlist(library, playlist) {
+            menu = menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-remove-all-from-playlist")
+                        .with_placeholder(format!("Remove {count} Tracks from Current Playlist")),
+                )
+                .command(playlist::REMOVE_TRACKS.with(PlaylistRemoveTracks {
+                    link: playlist.to_owned(),
+                    track_uris: selection.track_uris(),
+                })),
+            );
+        }
+    }
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-all-to-queue")
+                .with_placeholder(format!("Add {count} Tracks to Queue")),
+        )
+        .command(
+            cmd::ADD_ALL_TO_QUEUE.with(
+                selection
+                    .tracks
+                    .iter()
+                    .map(|track| queue_item(track, origin))
+                    .collect(),
+            ),
+        ),
+    );
+
+    let mut playlist_menu = Menu::new(
+        LocalizedString::new("menu-item-add-all-to-playlist")
+            .with_placeholder(format!("Add {count} Tracks to Playlist")),
+    );
+    for playlist in library.writable_playlists() {
+        playlist_menu = playlist_menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-save-to-playlist")
+                    .with_placeholder(format!("{}", playlist.name)),
+            )
+            .command(playlist::ADD_TRACKS.with(PlaylistAddTracks {
                 link: playlist.link(),
-                track_id: track.id,
+                track_ids: selection.track_ids(),
             })),
         );
     }
     menu = menu.entry(playlist_menu);
 
+    menu = menu.entry(playlist_file::export_menu(ExportSource::Tracks(
+        selection.tracks.clone(),
+    )));
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-clear-selection").with_placeholder("Clear Selection"),
+        )
+        .command(cmd::SET_SELECTION.with(TrackSelection::default())),
+    );
+
     menu
 }
+
+fn queue_item(track: &Arc<Track>, origin: &PlaybackOrigin) -> (QueueEntry, PlaybackItem) {
+    (
+        QueueEntry {
+            item: crate::ui::Playable::Track(track.clone()),
+            origin: origin.clone(),
+        },
+        PlaybackItem {
+            item_id: ItemId::from_base62(&String::from(track.id), ItemIdType::Track).unwrap(),
+            norm_level: NormalizationLevel::Track,
+            trim: PlaybackTrim::default(),
+            start_position: Duration::default(),
+        },
+    )
+}
+
+fn can_remove_from_playlist(library: &Library, playlist: &PlaylistLink) -> bool {
+    // Do some (hopefully) quick checks to determine if we should give the
+    // option to remove items from this playlist, only allowing it if the
+    // playlist is collaborative or we are the owner of it
+    if let Some(details) = library
+        .playlists
+        .resolved()
+        .and_then(|pl| pl.iter().find(|p| p.id == playlist.id))
+    {
+        if details.collaborative {
+            true
+        } else if let Some(user) = library.user_profile.resolved() {
+            user.id == details.owner.id
+        } else {
+            // If we can find the playlist, but for some reason can't
+            // resolve our own user, just show the option anyways and
+            // we'll see an error at the bottom if it doesn't work
+            // when they try to remove a track
+            true
+        }
+    } else {
+        // If this playlist doesn't exist in our library,
+        // just assume that we can't edit it since we probably
+        // searched for it or something
+        false
+    }
+}
@@ -1,62 +1,195 @@
 use std::{
-    collections::hash_map::DefaultHasher,
+    fmt::Write,
     fs::{self, File},
-    hash::{Hash, Hasher},
     num::NonZeroUsize,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime},
 };
 
-use druid::image;
 use druid::ImageBuf;
 use lru::LruCache;
 use parking_lot::Mutex;
 use psst_core::cache::mkdir_if_not_exists;
+use sha1::{Digest, Sha1};
+
+/// Decoded images kept in memory.
+const IMAGE_CACHE_SIZE: usize = 512;
+
+/// Bytes of images kept on disk, originals and thumbnails together.
+const IMAGE_DISK_CACHE_LIMIT: u64 = 512 * 1024 * 1024;
+
+/// Edge lengths of the pre-scaled image variants, in pixels.
+const THUMBNAIL_SIZES: [u32; 4] = [64, 128, 256, 512];
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How the responses in a cache bucket are kept.
+#[derive(Clone, Copy, Debug)]
+pub struct CachePolicy {
+    /// Age after which a cached response is stale.
+    pub ttl: Duration,
+    /// Serve stale responses at once and refresh them in the background,
+    /// instead of refreshing them before use.  Either way, stale responses are
+    /// served when the request fails, so that we keep working offline.
+    pub stale_while_revalidate: bool,
+}
+
+impl CachePolicy {
+    pub fn for_bucket(bucket: &str) -> Self {
+        let (ttl, stale_while_revalidate) = match bucket {
+            // Catalog metadata rarely changes, show it right away.
+            "album" => (7 * DAY, true),
+            "audio-analysis" => (365 * DAY, true),
+            "artist" | "artist-albums" | "artist-info" | "related-artists" => (DAY, true),
+            "show" | "user-info" => (DAY, true),
+            "profile" => (HOUR, false),
+            // The rest is library data the user edits, always ask first.
+            _ => (Duration::ZERO, false),
+        };
+        Self {
+            ttl,
+            stale_while_revalidate,
+        }
+    }
+
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        entry
+            .cached_at
+            .elapsed()
+            .map(|age| age < self.ttl)
+            .unwrap_or(false)
+    }
+}
+
+/// Response saved in a cache bucket.
+pub struct CacheEntry {
+    pub body: Vec<u8>,
+    pub cached_at: SystemTime,
+    pub etag: Option<String>,
+}
+
+/// SHA-1 of `data` as a hex string, usable as a file name that stays the same
+/// across runs and builds.
+pub fn stable_key(data: impl AsRef<[u8]>) -> String {
+    Sha1::digest(data)
+        .iter()
+        .fold(String::with_capacity(40), |mut key, byte| {
+            let _ = write!(key, "{byte:02x}");
+            key
+        })
+}
+
+/// Size of the smallest thumbnail covering `size` pixels, or `None` if only
+/// the original is big enough.
+pub fn thumbnail_size(size: f64) -> Option<u32> {
+    THUMBNAIL_SIZES
+        .into_iter()
+        .find(|&thumbnail| f64::from(thumbnail) >= size)
+}
 
 pub struct WebApiCache {
     base: Option<PathBuf>,
-    images: Mutex<LruCache<Arc<str>, ImageBuf>>,
+    images: Mutex<LruCache<(Arc<str>, Option<u32>), ImageBuf>>,
+    // Bytes taken by the `images` bucket, measured on first write.
+    image_disk_usage: Mutex<Option<u64>>,
 }
 
 impl WebApiCache {
     pub fn new(base: Option<PathBuf>) -> Self {
-        const IMAGE_CACHE_SIZE: usize = 256;
         Self {
             base,
             images: Mutex::new(LruCache::new(NonZeroUsize::new(IMAGE_CACHE_SIZE).unwrap())),
+            image_disk_usage: Mutex::new(None),
         }
     }
 
-    pub fn get_image(&self, uri: &Arc<str>) -> Option<ImageBuf> {
-        self.images.lock().get(uri).cloned()
+    /// Decoded image, `size` being the thumbnail size or `None` for the
+    /// original.
+    pub fn get_image(&self, uri: &Arc<str>, size: Option<u32>) -> Option<ImageBuf> {
+        self.images.lock().get(&(uri.clone(), size)).cloned()
     }
 
-    pub fn set_image(&self, uri: Arc<str>, image: ImageBuf) {
-        self.images.lock().put(uri, image);
+    pub fn set_image(&self, uri: Arc<str>, size: Option<u32>, image: ImageBuf) {
+        self.images.lock().put((uri, size), image);
     }
 
-    pub fn get_image_from_disk(&self, uri: &Arc<str>) -> Option<ImageBuf> {
-        let hash = Self::hash_uri(uri);
-        self.key("images", &format!("{hash:016x}"))
-            .and_then(|path| std::fs::read(path).ok())
-            .and_then(|bytes| image::load_from_memory(&bytes).ok())
-            .map(ImageBuf::from_dynamic_image)
+    /// Encoded image saved on disk.  Marks the file as recently used, so it's
+    /// evicted last.
+    pub fn get_image_from_disk(&self, uri: &str, size: Option<u32>) -> Option<Vec<u8>> {
+        let path = self.key("images", &Self::image_key(uri, size))?;
+        let bytes = fs::read(&path).ok()?;
+        if let Err(err) = File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            log::warn!("failed to touch cached image: {err:?}");
+        }
+        Some(bytes)
     }
 
-    pub fn save_image_to_disk(&self, uri: &Arc<str>, data: &[u8]) {
-        let hash = Self::hash_uri(uri);
-        if let Some(path) = self.key("images", &format!("{hash:016x}")) {
-            if let Some(parent) = path.parent() {
-                let _ = std::fs::create_dir_all(parent);
+    pub fn save_image_to_disk(&self, uri: &str, size: Option<u32>, data: &[u8]) {
+        self.set("images", &Self::image_key(uri, size), data);
+        self.evict_images(data.len() as u64);
+    }
+
+    fn image_key(uri: &str, size: Option<u32>) -> String {
+        let key = stable_key(uri);
+        match size {
+            Some(size) => format!("{key}-{size}"),
+            None => key,
+        }
+    }
+
+    /// Remove the least recently used images once the `images` bucket grows
+    /// over the limit, down to three quarters of it.
+    fn evict_images(&self, added: u64) {
+        let Some(bucket) = self.bucket("images") else {
+            return;
+        };
+        let mut usage = self.image_disk_usage.lock();
+        let total = match *usage {
+            Some(total) => total + added,
+            None => Self::list_files(&bucket)
+                .iter()
+                .map(|(_, len, _)| len)
+                .sum(),
+        };
+        *usage = Some(total);
+        if total <= IMAGE_DISK_CACHE_LIMIT {
+            return;
+        }
+
+        let mut files = Self::list_files(&bucket);
+        files.sort_by_key(|(modified, _, _)| *modified);
+        let mut remaining: u64 = files.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in files {
+            if remaining <= IMAGE_DISK_CACHE_LIMIT / 4 * 3 {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => remaining -= len,
+                Err(err) => log::warn!("failed to evict cached image: {err:?}"),
             }
-            let _ = std::fs::write(path, data);
         }
+        log::info!("evicted cached images, {remaining} bytes left");
+        *usage = Some(remaining);
     }
 
-    fn hash_uri(uri: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        uri.hash(&mut hasher);
-        hasher.finish()
+    /// Modification time, length and path of the files in `dir`.
+    fn list_files(dir: &Path) -> Vec<(SystemTime, u64, PathBuf)> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let meta = entry.metadata().ok()?;
+                Some((meta.modified().ok()?, meta.len(), entry.path()))
+            })
+            .collect()
     }
 
     pub fn get(&self, bucket: &str, key: &str) -> Option<File> {
@@ -76,6 +209,47 @@ impl WebApiCache {
         }
     }
 
+    pub fn get_entry(&self, bucket: &str, key: &str) -> Option<CacheEntry> {
+        let path = self.key(bucket, key)?;
+        let cached_at = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        let body = fs::read(&path).ok()?;
+        let etag = self
+            .etag_key(bucket, key)
+            .and_then(|path| fs::read_to_string(path).ok());
+        Some(CacheEntry {
+            body,
+            cached_at,
+            etag,
+        })
+    }
+
+    pub fn set_entry(&self, bucket: &str, key: &str, value: &[u8], etag: Option<&str>) {
+        self.set(bucket, key, value);
+        if let Some(path) = self.etag_key(bucket, key) {
+            let result = match etag {
+                Some(etag) => fs::write(path, etag),
+                None => fs::remove_file(path).or(Ok(())),
+            };
+            if let Err(err) = result {
+                log::error!("failed to save ETag to WebAPI cache: {err:?}");
+            }
+        }
+    }
+
+    /// Mark a cached response as fresh again, after the server confirmed it
+    /// has not changed.
+    pub fn touch(&self, bucket: &str, key: &str) {
+        if let Some(path) = self.key(bucket, key) {
+            let result = File::options()
+                .append(true)
+                .open(path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            if let Err(err) = result {
+                log::error!("failed to touch WebAPI cache entry: {err:?}");
+            }
+        }
+    }
+
     fn bucket(&self, bucket: &str) -> Option<PathBuf> {
         self.base.as_ref().map(|path| path.join(bucket))
     }
@@ -83,4 +257,11 @@ impl WebApiCache {
     fn key(&self, bucket: &str, key: &str) -> Option<PathBuf> {
         self.bucket(bucket).map(|path| path.join(key))
     }
+
+    /// Sidecar file holding the ETag of an entry.  Keys can contain dots, so
+    /// the suffix is appended instead of replacing an extension, which could
+    /// make two entries share a sidecar.
+    fn etag_key(&self, bucket: &str, key: &str) -> Option<PathBuf> {
+        self.key(bucket, &format!("{key}.etag"))
+    }
 }
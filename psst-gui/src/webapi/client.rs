@@ -1,19 +1,21 @@
 use std::{
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     fmt::Display,
+    hash::{Hash, Hasher},
     io::{self, Read},
     path::PathBuf,
     sync::Arc,
     thread,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use druid::{
     im::Vector,
     image::{self, ImageFormat},
-    Data, ImageBuf,
+    Data, ExtEventSink, ImageBuf, Target,
 };
 
+use data_encoding::BASE64;
 use itertools::Itertools;
 use log::info;
 use parking_lot::Mutex;
@@ -35,21 +37,35 @@ use ureq::{
 };
 
 use crate::{
+    cmd,
     data::{
         self, utils::sanitize_html_string, Album, AlbumType, Artist, ArtistAlbums, ArtistInfo,
         ArtistLink, ArtistStats, AudioAnalysis, Cached, Episode, EpisodeId, EpisodeLink, Image,
-        MixedView, Nav, Page, Playlist, PublicUser, Range, Recommendations, RecommendationsRequest,
-        SearchResults, SearchTopic, Show, SpotifyUrl, Track, TrackLines, UserProfile,
+        LyricsIndex, LyricsMatch, MixedView, Nav, Page, Playlist, PlaylistDetails, PlaylistLink,
+        PublicUser, Range, Recommendations, RecommendationsRequest, SearchQuery, SearchResults,
+        SearchTopic, Show, SpotifyUrl, Track, TrackLines, UserProfile,
     },
     error::Error,
     ui::credits::TrackCredits,
 };
 
-use super::{cache::WebApiCache, local::LocalTrackManager};
+use super::{
+    cache::{stable_key, CachePolicy, WebApiCache},
+    local::LocalTrackManager,
+    lyrics::{self, CachedLyrics, LyricsProvider},
+    scheduler::{Attempt, Priority, Scheduler, SchedulerMetrics},
+};
 use sanitize_html::{rules::predefined::DEFAULT, sanitize_str};
 
+/// Maximum number of items added to or removed from a playlist in one request.
+pub const PLAYLIST_ITEMS_LIMIT: usize = 100;
+
+/// Maximum number of tracks saved to or removed from the library in one request.
+const LIBRARY_ITEMS_LIMIT: usize = 50;
+
 pub struct WebApi {
     agent: Agent,
+    scheduler: Scheduler,
     cache: WebApiCache,
     local_track_manager: Mutex<LocalTrackManager>,
     paginated_limit: usize,
@@ -60,6 +76,11 @@ pub struct WebApi {
     session: Mutex<Option<SessionService>>,
     login5: Login5,
     client_token_provider: ClientTokenProviderHandle,
+    lyrics_providers: Mutex<Vec<Arc<dyn LyricsProvider>>>,
+    lyrics_index: Mutex<Option<(u64, Arc<LyricsIndex>)>>,
+    // Cache entries being refreshed in the background, as `(bucket, key)`.
+    revalidating: Mutex<HashSet<(String, String)>>,
+    event_sink: Mutex<Option<ExtEventSink>>,
 }
 
 impl WebApi {
@@ -68,7 +89,10 @@ impl WebApi {
         cache_base: Option<PathBuf>,
         paginated_limit: usize,
     ) -> Self {
-        let mut agent = Agent::config_builder().timeout_global(Some(Duration::from_secs(5)));
+        // Error statuses are checked in `send()`, after looking at `Retry-After`.
+        let mut agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(5)))
+            .http_status_as_error(false);
         if let Some(proxy_url) = proxy_url {
             let proxy = ureq::Proxy::new(proxy_url).ok();
             agent = agent.proxy(proxy);
@@ -76,6 +100,7 @@ impl WebApi {
         let client_token_provider = ClientTokenProvider::new_shared(proxy_url);
         Self {
             agent: agent.build().into(),
+            scheduler: Scheduler::default(),
             cache: WebApiCache::new(cache_base),
             local_track_manager: Mutex::new(LocalTrackManager::new()),
             paginated_limit,
@@ -84,6 +109,10 @@ impl WebApi {
             session: Mutex::new(None),
             login5: Login5::new(Some(Arc::clone(&client_token_provider)), proxy_url),
             client_token_provider,
+            lyrics_providers: Mutex::new(Vec::new()),
+            lyrics_index: Mutex::new(None),
+            revalidating: Mutex::new(HashSet::new()),
+            event_sink: Mutex::new(None),
         }
     }
 
@@ -108,12 +137,27 @@ impl WebApi {
         *self.webapi_token.lock() = token;
     }
 
+    /// Set the sources to look up lyrics in, in order.
+    pub fn set_lyrics_providers(&self, providers: Vec<Arc<dyn LyricsProvider>>) {
+        *self.lyrics_providers.lock() = providers;
+    }
+
+    /// Set the sink notified about cache entries refreshed in the background.
+    pub fn set_event_sink(&self, sink: ExtEventSink) {
+        *self.event_sink.lock() = Some(sink);
+    }
+
     /// Install the authenticated core session, used to mint the first-party
     /// tokens that `api-partner.spotify.com` requires.
     pub fn set_session(&self, session: SessionService) {
         *self.session.lock() = Some(session);
     }
 
+    /// Counters of the request scheduler, for diagnostics.
+    pub fn request_metrics(&self) -> SchedulerMetrics {
+        self.scheduler.metrics()
+    }
+
     /// Mint the `(bearer, client-token)` pair accepted by `api-partner`.  Uses
     /// the Login5 access token from the core session plus a protobuf client
     /// token — the same credentials the web player and `Cdn` use.
@@ -202,9 +246,14 @@ impl WebApi {
             Method::Post => configure_request(self.agent.post(&url), &token, ct, headers)
                 .send_json(request.get_body())
                 .map_err(|err| Error::WebApiError(err.to_string())),
-            Method::Put => configure_request(self.agent.put(&url), &token, ct, headers)
-                .send_json(request.get_body())
-                .map_err(|err| Error::WebApiError(err.to_string())),
+            Method::Put => {
+                let req = configure_request(self.agent.put(&url), &token, ct, headers);
+                match &request.raw_body {
+                    Some(body) => req.send(body.as_str()),
+                    None => req.send_json(request.get_body()),
+                }
+                .map_err(|err| Error::WebApiError(err.to_string()))
+            }
             Method::Delete => configure_request(self.agent.delete(&url), &token, ct, headers)
                 .force_send_body()
                 .send_json(request.get_body())
@@ -212,67 +261,208 @@ impl WebApi {
         }
     }
 
-    fn with_retry(f: impl Fn() -> Result<Response<Body>, Error>) -> Result<Response<Body>, Error> {
-        loop {
-            let response = f()?;
-            match response.status() {
-                StatusCode::TOO_MANY_REQUESTS => {
-                    let retry_after_secs = response
-                        .headers()
-                        .get("Retry-After")
-                        .and_then(|secs| secs.to_str().ok());
-                    let secs = retry_after_secs.unwrap_or("2").parse::<u64>().unwrap_or(2);
-                    thread::sleep(Duration::from_secs(secs));
-                }
-                _ => {
-                    break Ok(response);
+    /// Send a request once the scheduler gives it a slot, waiting out the
+    /// `Retry-After` of 429 responses.  Error statuses are returned as errors.
+    fn send(&self, request: &RequestBuilder) -> Result<Response<Body>, Error> {
+        let response = self
+            .scheduler
+            .run(&request.base_uri, request.priority, || {
+                match self.request(request) {
+                    Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                        let secs = response
+                            .headers()
+                            .get("Retry-After")
+                            .and_then(|secs| secs.to_str().ok())
+                            .and_then(|secs| secs.parse().ok())
+                            .unwrap_or(2);
+                        Attempt::RetryAfter(Duration::from_secs(secs))
+                    }
+                    result => Attempt::Done(result),
                 }
-            }
+            })?;
+        let status = response.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(Error::WebApiError(format!(
+                "http status: {}",
+                status.as_u16()
+            )));
         }
+        Ok(response)
     }
 
     /// Send a request with an empty JSON object, throw away the response body.
     /// Use for POST/PUT/DELETE requests.
     fn send_empty_json(&self, request: &RequestBuilder) -> Result<(), Error> {
-        Self::with_retry(|| self.request(request)).map(|_| ())
+        self.send(request).map(|_| ())
     }
 
     /// Send a request and return the deserialized JSON body.  Use for GET
-    /// requests.
+    /// requests.  Requests with a cache bucket go through `self.load_body()`.
     fn load<T: DeserializeOwned>(&self, request: &RequestBuilder) -> Result<T, Error> {
-        let mut response = Self::with_retry(|| self.request(request))?;
-        response
-            .body_mut()
-            .read_json()
-            .map_err(|err| Error::WebApiError(err.to_string()))
+        if let Some(bucket) = request.cache_bucket {
+            let (body, _) = self.load_body(request, bucket, &request.cache_key())?;
+            return Ok(serde_json::from_slice(&body)?);
+        }
+        let (body, _) = self.fetch_new(request)?;
+        Ok(serde_json::from_slice(&body)?)
     }
 
-    /// Send a request using `self.load()`, but only if it isn't already present
-    /// in cache.
+    /// Send a request using `self.load_body()` and remember when the response
+    /// was cached.
     fn load_cached<T: Data + DeserializeOwned>(
         &self,
         request: &RequestBuilder,
         bucket: &str,
         key: &str,
     ) -> Result<Cached<T>, Error> {
-        if let Some(file) = self.cache.get(bucket, key) {
-            let cached_at = file.metadata()?.modified()?;
-            let value = serde_json::from_reader(file)?;
-            Ok(Cached::new(value, cached_at))
-        } else {
-            let response = Self::with_retry(|| self.request(request))?;
-            let body = {
-                let mut reader = response.into_body().into_reader();
-                let mut body = Vec::new();
-                reader.read_to_end(&mut body)?;
-                body
-            };
-            let value = serde_json::from_slice(&body)?;
-            self.cache.set(bucket, key, &body);
-            Ok(Cached::fresh(value))
+        let (body, cached_at) = self.load_body(request, bucket, key)?;
+        let value = serde_json::from_slice(&body)?;
+        Ok(match cached_at {
+            Some(cached_at) => Cached::new(value, cached_at),
+            None => Cached::fresh(value),
+        })
+    }
+
+    /// Return the response body of `request` from the cache while it's fresh,
+    /// following the `CachePolicy` of `bucket` once it gets stale.  Stale
+    /// responses are also returned if the request fails, so that we keep
+    /// working offline.  The second value is the time the body was cached at,
+    /// or `None` if it was just loaded.
+    fn load_body(
+        &self,
+        request: &RequestBuilder,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(Vec<u8>, Option<SystemTime>), Error> {
+        let Some(entry) = self.cache.get_entry(bucket, key) else {
+            let (body, etag) = self.fetch_new(request)?;
+            self.cache.set_entry(bucket, key, &body, etag.as_deref());
+            return Ok((body, None));
+        };
+        let policy = CachePolicy::for_bucket(bucket);
+        if policy.is_fresh(&entry) {
+            return Ok((entry.body, Some(entry.cached_at)));
+        }
+        if policy.stale_while_revalidate {
+            self.revalidate_in_background(request, bucket, key, entry.etag);
+            return Ok((entry.body, Some(entry.cached_at)));
+        }
+        match self.revalidate(request, bucket, key, entry.etag.as_deref()) {
+            Ok(Some(body)) => Ok((body, None)),
+            Ok(None) => Ok((entry.body, Some(SystemTime::now()))),
+            Err(err) => {
+                log::warn!("request failed, using stale {bucket}/{key} from cache: {err}");
+                Ok((entry.body, Some(entry.cached_at)))
+            }
+        }
+    }
+
+    /// Check a cached response with the server and update it.  Returns the new
+    /// body if it has changed.
+    fn revalidate(
+        &self,
+        request: &RequestBuilder,
+        bucket: &str,
+        key: &str,
+        etag: Option<&str>,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        match self.fetch(request, etag)? {
+            Some((body, etag)) => {
+                self.cache.set_entry(bucket, key, &body, etag.as_deref());
+                Ok(Some(body))
+            }
+            None => {
+                self.cache.touch(bucket, key);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Run `self.revalidate()` on another thread and let the UI know if the
+    /// response has changed.
+    fn revalidate_in_background(
+        &self,
+        request: &RequestBuilder,
+        bucket: &str,
+        key: &str,
+        etag: Option<String>,
+    ) {
+        let entry = (bucket.to_string(), key.to_string());
+        if !self.revalidating.lock().insert(entry.clone()) {
+            return;
+        }
+        let request = request.clone().background();
+        thread::spawn(move || {
+            let webapi = WebApi::global();
+            let (bucket, key) = &entry;
+            match webapi.revalidate(&request, bucket, key, etag.as_deref()) {
+                Ok(Some(_)) => {
+                    if let Some(sink) = webapi.event_sink.lock().as_ref() {
+                        let refreshed: (Arc<str>, Arc<str>) =
+                            (bucket.as_str().into(), key.as_str().into());
+                        if let Err(err) = sink.submit_command(
+                            cmd::WEBAPI_CACHE_REFRESHED,
+                            refreshed,
+                            Target::Auto,
+                        ) {
+                            log::error!("failed to notify about refreshed cache entry: {err}");
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    log::warn!("failed to refresh {bucket}/{key}: {err}");
+                }
+            }
+            webapi.revalidating.lock().remove(&entry);
+        });
+    }
+
+    /// Send a request, conditional on `etag` if given.  Returns the body and
+    /// ETag of the response, or `None` if the server says it's not modified.
+    /// Identical GET requests in flight are only sent once.
+    fn fetch(
+        &self,
+        request: &RequestBuilder,
+        etag: Option<&str>,
+    ) -> Result<Option<(Vec<u8>, Option<String>)>, Error> {
+        let request = match etag {
+            Some(etag) => request.clone().header("If-None-Match", etag),
+            None => request.clone(),
+        };
+        let fetch = || -> Result<Option<(Vec<u8>, Option<String>)>, Error> {
+            let response = self.send(&request)?;
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            let etag = response
+                .headers()
+                .get("ETag")
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_string);
+            let mut body = Vec::new();
+            response.into_body().into_reader().read_to_end(&mut body)?;
+            Ok(Some((body, etag)))
+        };
+        match request.get_method() {
+            Method::Get => {
+                let key = format!("{}:{}", request.cache_key(), etag.unwrap_or_default());
+                self.scheduler.run_once(&key, fetch).unwrap_or_else(|| {
+                    Err(Error::WebApiError(
+                        "Request failed in another thread".to_string(),
+                    ))
+                })
+            }
+            _ => fetch(),
         }
     }
 
+    /// Like `self.fetch()`, for requests that are not conditional.
+    fn fetch_new(&self, request: &RequestBuilder) -> Result<(Vec<u8>, Option<String>), Error> {
+        self.fetch(request, None)?
+            .ok_or_else(|| Error::WebApiError("Not modified, but nothing is cached".to_string()))
+    }
+
     /// Iterate a paginated result set by sending `request` with added
     /// pagination parameters.  Mostly used through `load_all_pages`.
     fn for_all_pages<T: DeserializeOwned + Clone>(
@@ -280,8 +470,8 @@ impl WebApi {
         request: &RequestBuilder,
         mut func: impl FnMut(Page<T>) -> Result<(), Error>,
     ) -> Result<(), Error> {
-        // TODO: Some result sets, like very long playlists and saved tracks/albums can
-        // be very big.  Implement virtualized scrolling and lazy-loading of results.
+        // TODO: Some result sets, like saved tracks/albums, can be very big.  Load
+        // them lazily, like the playlist items in `get_playlist_tracks_page`.
         let mut limit = 50;
         let mut offset = 0;
         loop {
@@ -300,6 +490,10 @@ impl WebApi {
                 limit = page_limit;
                 offset = page_offset + page_limit;
             } else {
+                if page_total > page_offset + page_limit {
+                    let loaded = page_offset + page_limit;
+                    log::warn!("results truncated to {loaded} of {page_total} items");
+                }
                 break Ok(());
             }
         }
@@ -774,7 +968,9 @@ impl WebApi {
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/get-users-profile
     pub fn get_user_profile(&self) -> Result<UserProfile, Error> {
-        let result = self.load(&RequestBuilder::new("v1/me".to_string(), Method::Get, None))?;
+        let request =
+            &RequestBuilder::new("v1/me".to_string(), Method::Get, None).cached("profile");
+        let result = self.load(request)?;
         Ok(result)
     }
 
@@ -815,7 +1011,8 @@ impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/get-an-artists-albums/
     pub fn get_artist_albums(&self, id: &str) -> Result<ArtistAlbums, Error> {
         let request = &RequestBuilder::new(format!("v1/artists/{id}/albums"), Method::Get, None)
-            .query("market", "from_token");
+            .query("market", "from_token")
+            .cached("artist-albums");
         let result: Vector<Arc<Album>> = self.load_all_pages(request)?;
 
         let mut artist_albums = ArtistAlbums {
@@ -1030,7 +1227,8 @@ impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/get-a-shows-episodes
     pub fn get_show_episodes(&self, id: &str) -> Result<Vector<Arc<Episode>>, Error> {
         let request = &RequestBuilder::new(format!("v1/shows/{id}/episodes"), Method::Get, None)
-            .query("market", "from_token");
+            .query("market", "from_token")
+            .cached("episodes");
 
         let mut results = Vector::new();
         self.for_all_pages(request, |page: Page<Option<EpisodeLink>>| {
@@ -1069,7 +1267,73 @@ impl WebApi {
         Ok(result)
     }
 
-    pub fn get_lyrics(&self, track_id: String) -> Result<Vector<TrackLines>, Error> {
+    /// Lyrics of `track` from the first provider that has them.  The lyrics
+    /// are cached in the "lyrics" bucket, whatever their source.
+    pub fn get_lyrics(&self, track: &Track) -> Result<Vector<TrackLines>, Error> {
+        if let Some(lines) = self.get_cached_lyrics(track) {
+            return Ok(lines);
+        }
+        let key = lyrics::cache_key(track);
+        // Don't hold the lock while the providers make their requests.
+        let providers = self.lyrics_providers.lock().clone();
+        for provider in providers {
+            match provider.lyrics(track) {
+                Ok(Some(lines)) if !lines.is_empty() => {
+                    let cached = CachedLyrics {
+                        provider: provider.id().to_string(),
+                        lines,
+                    };
+                    self.cache
+                        .set("lyrics", &key, &serde_json::to_vec(&cached)?);
+                    // The index is missing the new lyrics.
+                    self.lyrics_index.lock().take();
+                    return Ok(cached.lines);
+                }
+                Ok(_) => {}
+                Err(err) => log::info!("no lyrics from {}: {err}", provider.id()),
+            }
+        }
+        Err(Error::WebApiError("No lyrics found".to_string()))
+    }
+
+    /// Lyrics of `track` if they are in the cache, without asking the providers.
+    pub fn get_cached_lyrics(&self, track: &Track) -> Option<Vector<TrackLines>> {
+        let file = self.cache.get("lyrics", &lyrics::cache_key(track))?;
+        match serde_json::from_reader::<_, CachedLyrics>(file) {
+            Ok(cached) => Some(cached.lines),
+            Err(err) => {
+                log::warn!("failed to read cached lyrics: {err}");
+                None
+            }
+        }
+    }
+
+    /// Search the names and cached lyrics of `tracks`.  The index is kept
+    /// until the tracks change or new lyrics get cached.
+    pub fn search_lyrics(&self, tracks: &Vector<Arc<Track>>, query: &str) -> Vector<LyricsMatch> {
+        let mut hasher = DefaultHasher::new();
+        for track in tracks {
+            lyrics::cache_key(track).hash(&mut hasher);
+        }
+        let key = hasher.finish();
+
+        let cached = self.lyrics_index.lock().clone();
+        let index = match cached {
+            Some((cached_key, index)) if cached_key == key => index,
+            _ => {
+                let index = Arc::new(LyricsIndex::new(
+                    tracks
+                        .iter()
+                        .map(|track| (track.clone(), self.get_cached_lyrics(track))),
+                ));
+                *self.lyrics_index.lock() = Some((key, index.clone()));
+                index
+            }
+        };
+        index.search(query)
+    }
+
+    pub(super) fn get_spotify_lyrics(&self, track_id: &str) -> Result<Vector<TrackLines>, Error> {
         #[derive(Default, Debug, Clone, PartialEq, Deserialize, Data)]
         #[serde(rename_all = "camelCase")]
         pub struct Root {
@@ -1095,8 +1359,8 @@ impl WebApi {
         .query("market", "from_token")
         .header("app-platform", "WebPlayer");
 
-        let lyrics: Cached<Root> = self.load_cached(request, "lyrics", &track_id)?;
-        Ok(lyrics.data.lyrics.lines)
+        let result: Root = self.load(request)?;
+        Ok(result.lyrics.lines)
     }
 }
 
@@ -1109,8 +1373,9 @@ impl WebApi {
             album: Arc<Album>,
         }
 
-        let request =
-            &RequestBuilder::new("v1/me/albums", Method::Get, None).query("market", "from_token");
+        let request = &RequestBuilder::new("v1/me/albums", Method::Get, None)
+            .query("market", "from_token")
+            .cached("library");
 
         Ok(self
             .load_all_pages(request)?
@@ -1139,8 +1404,9 @@ impl WebApi {
         struct SavedTrack {
             track: Arc<Track>,
         }
-        let request =
-            &RequestBuilder::new("v1/me/tracks", Method::Get, None).query("market", "from_token");
+        let request = &RequestBuilder::new("v1/me/tracks", Method::Get, None)
+            .query("market", "from_token")
+            .cached("library");
         Ok(self
             .load_all_pages(request)?
             .into_iter()
@@ -1155,8 +1421,9 @@ impl WebApi {
             show: Arc<Show>,
         }
 
-        let request =
-            &RequestBuilder::new("v1/me/shows", Method::Get, None).query("market", "from_token");
+        let request = &RequestBuilder::new("v1/me/shows", Method::Get, None)
+            .query("market", "from_token")
+            .cached("library");
 
         Ok(self
             .load_all_pages(request)?
@@ -1167,16 +1434,33 @@ impl WebApi {
 
     // https://developer.spotify.com/documentation/web-api/reference/save-to-library/
     pub fn save_track(&self, id: &str) -> Result<(), Error> {
+        self.save_tracks(&[id])
+    }
+
+    pub fn save_tracks(&self, ids: &[impl AsRef<str>]) -> Result<(), Error> {
         // Spotify's /v1/me/tracks takes the base62 ids as a query param, not a
         // uris body.
-        let request = &RequestBuilder::new("v1/me/tracks", Method::Put, None).query("ids", id);
-        self.send_empty_json(request)
+        for chunk in ids.chunks(LIBRARY_ITEMS_LIMIT) {
+            let ids = chunk.iter().map(AsRef::as_ref).join(",");
+            let request = &RequestBuilder::new("v1/me/tracks", Method::Put, None).query("ids", ids);
+            self.send_empty_json(request)?;
+        }
+        Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/remove-from-library/
     pub fn unsave_track(&self, id: &str) -> Result<(), Error> {
-        let request = &RequestBuilder::new("v1/me/tracks", Method::Delete, None).query("ids", id);
-        self.send_empty_json(request)
+        self.unsave_tracks(&[id])
+    }
+
+    pub fn unsave_tracks(&self, ids: &[impl AsRef<str>]) -> Result<(), Error> {
+        for chunk in ids.chunks(LIBRARY_ITEMS_LIMIT) {
+            let ids = chunk.iter().map(AsRef::as_ref).join(",");
+            let request =
+                &RequestBuilder::new("v1/me/tracks", Method::Delete, None).query("ids", ids);
+            self.send_empty_json(request)?;
+        }
+        Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/save-to-library/
@@ -1272,12 +1556,6 @@ impl WebApi {
         self.get_section("spotify:section:0JQ5DAnM3wGh0gz1MXnu3n")
     }
 
-    // Need to make a mix of it!
-    pub fn jump_back_in(&self) -> Result<MixedView, Error> {
-        // 0JQ5DAIiKWzVFULQfUm85X -> Jump back in
-        self.get_section("spotify:section:0JQ5DAIiKWzVFULQfUm85X")
-    }
-
     // Shows
     pub fn your_shows(&self) -> Result<MixedView, Error> {
         // 0JQ5DAnM3wGh0gz1MXnu3N -> Your shows
@@ -1294,7 +1572,7 @@ impl WebApi {
 impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/get-a-list-of-current-users-playlists
     pub fn get_playlists(&self) -> Result<Vector<Playlist>, Error> {
-        let request = &RequestBuilder::new("v1/me/playlists", Method::Get, None);
+        let request = &RequestBuilder::new("v1/me/playlists", Method::Get, None).cached("library");
         let result: Vector<Playlist> = self.load_all_pages(request)?;
         Ok(result)
     }
@@ -1315,13 +1593,46 @@ impl WebApi {
 
     // https://developer.spotify.com/documentation/web-api/reference/get-playlist
     pub fn get_playlist(&self, id: &str) -> Result<Playlist, Error> {
-        let request = &RequestBuilder::new(format!("v1/playlists/{id}"), Method::Get, None);
+        let request = &RequestBuilder::new(format!("v1/playlists/{id}"), Method::Get, None)
+            .cached("playlist");
         let result: Playlist = self.load(request)?;
         Ok(result)
     }
 
+    /// Current version of the playlist, changing with every edit.
+    pub fn get_playlist_snapshot_id(&self, id: &str) -> Result<Arc<str>, Error> {
+        #[derive(Deserialize)]
+        struct Snapshot {
+            snapshot_id: Arc<str>,
+        }
+
+        let request = &RequestBuilder::new(format!("v1/playlists/{id}"), Method::Get, None)
+            .query("fields", "snapshot_id");
+        let result: Snapshot = self.load(request)?;
+        Ok(result.snapshot_id)
+    }
+
     // https://developer.spotify.com/documentation/web-api/reference/get-playlist-items
     pub fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+        let mut tracks = Vector::new();
+        let mut offset = 0;
+        loop {
+            let page = self.get_playlist_tracks_page(id, offset)?;
+            offset = page.offset + page.limit;
+            tracks.append(page.items);
+            if page.limit == 0 || offset >= page.total {
+                break Ok(tracks);
+            }
+        }
+    }
+
+    /// Load a single page of the playlist items, starting at `offset`.  Unlike
+    /// the other paginated results, this is not capped by `paginated_limit`.
+    pub fn get_playlist_tracks_page(
+        &self,
+        id: &str,
+        offset: usize,
+    ) -> Result<Page<Arc<Track>>, Error> {
         #[derive(Clone, Deserialize)]
         struct PlaylistItem {
             #[serde(default)]
@@ -1342,13 +1653,17 @@ impl WebApi {
 
         let request = &RequestBuilder::new(format!("v1/playlists/{id}/items"), Method::Get, None)
             .query("marker", "from_token")
-            .query("additional_types", "track");
+            .query("additional_types", "track")
+            .query("limit", PLAYLIST_ITEMS_LIMIT)
+            .query("offset", offset)
+            .cached("playlist");
 
-        let result: Vector<PlaylistItem> = self.load_all_pages(request)?;
+        let page: Page<PlaylistItem> = self.load(request)?;
 
         let local_track_manager = self.local_track_manager.lock();
 
-        Ok(result
+        let items = page
+            .items
             .into_iter()
             .enumerate()
             .filter_map(|(index, item)| {
@@ -1360,42 +1675,138 @@ impl WebApi {
                     }
                     None => return None,
                 };
-                Arc::make_mut(&mut track).track_pos = index;
+                Arc::make_mut(&mut track).track_pos = page.offset + index;
                 Some(track)
             })
-            .collect())
+            .collect();
+
+        Ok(Page {
+            items,
+            limit: page.limit,
+            offset: page.offset,
+            total: page.total,
+        })
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/change-playlist-details
-    pub fn change_playlist_details(&self, id: &str, name: &str) -> Result<(), Error> {
+    pub fn change_playlist_details(&self, details: &PlaylistDetails) -> Result<(), Error> {
+        let id = &details.id;
+        // Collaborative playlists can't be public.
         let request = &RequestBuilder::new(format!("v1/playlists/{id}"), Method::Put, None)
-            .set_body(Some(json!({ "name": name })));
+            .set_body(Some(json!({
+                "name": details.name,
+                "description": details.description,
+                "public": details.public && !details.collaborative,
+                "collaborative": details.collaborative,
+            })));
         self.send_empty_json(request)?;
         Ok(())
     }
 
-    // https://developer.spotify.com/documentation/web-api/reference/add-items-to-playlist
-    pub fn add_track_to_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error> {
+    // https://developer.spotify.com/documentation/web-api/reference/upload-custom-playlist-cover
+    pub fn upload_playlist_cover(&self, playlist_id: &str, image: &[u8]) -> Result<(), Error> {
+        let cover = encode_playlist_cover(image)?;
         let request = &RequestBuilder::new(
-            format!("v1/playlists/{playlist_id}/items"),
-            Method::Post,
-            Some(json!({"uris": [track_uri]})),
-        );
-        self.request(request).map(|_| ())
+            format!("v1/playlists/{playlist_id}/images"),
+            Method::Put,
+            None,
+        )
+        .set_raw_body("image/jpeg", cover);
+        self.send_empty_json(request)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/create-playlist
+    pub fn create_playlist(&self, name: &str) -> Result<PlaylistLink, Error> {
+        let request = &RequestBuilder::new("v1/me/playlists", Method::Post, None)
+            .set_body(Some(json!({ "name": name, "public": false })));
+        self.load(request)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/add-items-to-playlist
+    pub fn add_tracks_to_playlist(
+        &self,
+        playlist_id: &str,
+        track_uris: &[impl AsRef<str>],
+    ) -> Result<(), Error> {
+        for chunk in track_uris.chunks(PLAYLIST_ITEMS_LIMIT) {
+            let uris: Vec<&str> = chunk.iter().map(AsRef::as_ref).collect();
+            let request = &RequestBuilder::new(
+                format!("v1/playlists/{playlist_id}/items"),
+                Method::Post,
+                Some(json!({ "uris": uris })),
+            );
+            self.send_empty_json(request)?;
+        }
+        Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/remove-playlist-items
-    pub fn remove_track_from_playlist(
+    pub fn remove_tracks_from_playlist(
+        &self,
+        playlist_id: &str,
+        track_uris: &[impl AsRef<str>],
+    ) -> Result<(), Error> {
+        for chunk in track_uris.chunks(PLAYLIST_ITEMS_LIMIT) {
+            let items: Vec<_> = chunk
+                .iter()
+                .map(|uri| json!({ "uri": uri.as_ref() }))
+                .collect();
+            let request = &RequestBuilder::new(
+                format!("v1/playlists/{playlist_id}/items"),
+                Method::Delete,
+                Some(json!({ "items": items })),
+            );
+            self.send_empty_json(request)?;
+        }
+        Ok(())
+    }
+
+    /// Remove only the occurrences of the tracks at the given positions, in
+    /// the playlist version `snapshot_id`.  Spotify refuses the change if the
+    /// tracks are not at these positions in that version.
+    pub fn remove_playlist_positions(
+        &self,
+        playlist_id: &str,
+        snapshot_id: &str,
+        positions: &[(String, Vec<usize>)],
+    ) -> Result<(), Error> {
+        // Every chunk refers to the same version, Spotify adjusts the positions
+        // for the changes made since.
+        for chunk in positions.chunks(PLAYLIST_ITEMS_LIMIT) {
+            let items: Vec<_> = chunk
+                .iter()
+                .map(|(uri, positions)| json!({ "uri": uri, "positions": positions }))
+                .collect();
+            let request = &RequestBuilder::new(
+                format!("v1/playlists/{playlist_id}/items"),
+                Method::Delete,
+                Some(json!({ "items": items, "snapshot_id": snapshot_id })),
+            );
+            self.send_empty_json(request)?;
+        }
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/reorder-or-replace-playlists-items
+    pub fn move_playlist_track(
         &self,
         playlist_id: &str,
-        track_uri: &str,
+        from: usize,
+        to: usize,
     ) -> Result<(), Error> {
+        // The track is inserted before `insert_before`, counted before removing
+        // it from its original position.
+        let insert_before = if to > from { to + 1 } else { to };
         let request = &RequestBuilder::new(
             format!("v1/playlists/{playlist_id}/items"),
-            Method::Delete,
-            Some(json!({ "items": [{ "uri": track_uri }] })),
+            Method::Put,
+            Some(json!({
+                "range_start": from,
+                "insert_before": insert_before,
+                "range_length": 1,
+            })),
         );
-        self.request(request).map(|_| ())
+        self.send_empty_json(request)
     }
 }
 
@@ -1404,9 +1815,10 @@ impl WebApi {
     // https://developer.spotify.com/documentation/web-api/reference/search/
     pub fn search(
         &self,
-        query: &str,
+        query: &SearchQuery,
         topics: &[SearchTopic],
         limit: usize,
+        offset: usize,
     ) -> Result<SearchResults, Error> {
         #[derive(Deserialize)]
         struct ApiSearchResults {
@@ -1418,16 +1830,33 @@ impl WebApi {
             shows: Option<Page<Arc<Show>>>,
         }
 
-        let encoded_query = urlencoding::encode(query);
+        fn next_offset<T: Clone>(page: &Option<Page<T>>) -> Option<usize> {
+            let page = page.as_ref()?;
+            let next = page.offset + page.limit;
+            (next < page.total).then_some(next)
+        }
+
+        let query_string = query.to_string();
+        let encoded_query = urlencoding::encode(&query_string);
         let type_query_param = topics.iter().map(SearchTopic::as_str).join(",");
         let request = &RequestBuilder::new("v1/search", Method::Get, None)
             .query("q", encoded_query)
             .query("type", &type_query_param)
             .query("limit", limit.to_string())
+            .query("offset", offset.to_string())
             .query("marker", "from_token");
 
         let result: ApiSearchResults = self.load(request)?;
 
+        let topic = (topics.len() == 1).then_some(topics[0]);
+        let next_offset = match topic {
+            Some(SearchTopic::Artist) => next_offset(&result.artists),
+            Some(SearchTopic::Album) => next_offset(&result.albums),
+            Some(SearchTopic::Track) => next_offset(&result.tracks),
+            Some(SearchTopic::Playlist) => next_offset(&result.playlists),
+            Some(SearchTopic::Show) => next_offset(&result.shows),
+            Some(SearchTopic::Lyrics) | None => None,
+        };
         let artists = result.artists.map_or_else(Vector::new, |page| page.items);
         let albums = result.albums.map_or_else(Vector::new, |page| page.items);
         let tracks = result.tracks.map_or_else(Vector::new, |page| page.items);
@@ -1435,16 +1864,17 @@ impl WebApi {
             .playlists
             .map_or_else(Vector::new, |p| p.items.into_iter().flatten().collect());
         let shows = result.shows.map_or_else(Vector::new, |page| page.items);
-        let topic = (topics.len() == 1).then_some(topics[0]);
 
         Ok(SearchResults {
-            query: query.into(),
+            query: query_string.into(),
             topic,
             artists,
             albums,
             tracks,
             playlists,
             shows,
+            lyrics: Vector::new(),
+            next_offset,
         })
     }
 
@@ -1538,23 +1968,65 @@ impl WebApi {
 
 /// Image endpoints.
 impl WebApi {
-    pub fn get_cached_image(&self, uri: &Arc<str>) -> Option<ImageBuf> {
-        self.cache.get_image(uri)
+    /// Image decoded before, `size` being the requested thumbnail size.
+    pub fn get_cached_image(&self, uri: &Arc<str>, size: Option<u32>) -> Option<ImageBuf> {
+        self.cache.get_image(uri, size)
     }
 
-    pub fn get_image(&self, uri: Arc<str>) -> Result<ImageBuf, Error> {
-        if let Some(cached_image) = self.cache.get_image(&uri) {
+    /// Load, decode and possibly downscale an image.  Both the original and
+    /// the thumbnail are cached on disk.  Decoding is slow, don't call this
+    /// from the UI thread.
+    pub fn get_image(&self, uri: Arc<str>, size: Option<u32>) -> Result<ImageBuf, Error> {
+        if let Some(cached_image) = self.cache.get_image(&uri, size) {
             return Ok(cached_image);
         }
 
-        if let Some(disk_cached_image) = self.cache.get_image_from_disk(&uri) {
-            self.cache.set_image(uri.clone(), disk_cached_image.clone());
-            return Ok(disk_cached_image);
+        if size.is_some() {
+            if let Some(thumbnail) = self.cache.get_image_from_disk(&uri, size) {
+                let image_buf = ImageBuf::from_dynamic_image(image::load_from_memory(&thumbnail)?);
+                self.cache.set_image(uri, size, image_buf.clone());
+                return Ok(image_buf);
+            }
+        }
+
+        let body = match self.cache.get_image_from_disk(&uri, None) {
+            Some(body) => body,
+            None => {
+                let body = self.fetch_image(&uri)?;
+                self.cache.save_image_to_disk(&uri, None, &body);
+                body
+            }
+        };
+
+        let format = match infer::get(body.as_slice()) {
+            Some(kind) if kind.mime_type() == "image/jpeg" => Some(ImageFormat::Jpeg),
+            Some(kind) if kind.mime_type() == "image/png" => Some(ImageFormat::Png),
+            Some(kind) if kind.mime_type() == "image/webp" => Some(ImageFormat::WebP),
+            _ => None,
+        };
+        let mut image = if let Some(format) = format {
+            image::load_from_memory_with_format(&body, format)?
+        } else {
+            image::load_from_memory(&body)?
+        };
+
+        if let Some(size) = size {
+            if image.width() > size || image.height() > size {
+                image = image.thumbnail(size, size);
+            }
+            let mut thumbnail = Vec::new();
+            image.write_to(&mut io::Cursor::new(&mut thumbnail), ImageFormat::Png)?;
+            self.cache.save_image_to_disk(&uri, Some(size), &thumbnail);
         }
 
+        let image_buf = ImageBuf::from_dynamic_image(image);
+        self.cache.set_image(uri, size, image_buf.clone());
+        Ok(image_buf)
+    }
+
+    fn fetch_image(&self, uri: &str) -> Result<Vec<u8>, Error> {
         // Split the URI into its components
-        let uri_clone = uri.clone();
-        let parsed = url::Url::parse(&uri_clone).unwrap();
+        let parsed = url::Url::parse(uri).unwrap();
 
         let protocol = parsed.scheme();
         let base_uri = parsed.host_str().unwrap();
@@ -1567,30 +2039,11 @@ impl WebApi {
 
         let request = RequestBuilder::new(path, Method::Get, None)
             .set_protocol(protocol)
-            .set_base_uri(base_uri);
+            .set_base_uri(base_uri)
+            .background();
 
-        let response = self.request(&request)?;
-        let mut body = Vec::new();
-        response.into_body().into_reader().read_to_end(&mut body)?;
-
-        let format = match infer::get(body.as_slice()) {
-            Some(kind) if kind.mime_type() == "image/jpeg" => Some(ImageFormat::Jpeg),
-            Some(kind) if kind.mime_type() == "image/png" => Some(ImageFormat::Png),
-            Some(kind) if kind.mime_type() == "image/webp" => Some(ImageFormat::WebP),
-            _ => None,
-        };
-
-        // Save raw image data to disk cache
-        self.cache.save_image_to_disk(&uri, &body);
-
-        let image = if let Some(format) = format {
-            image::load_from_memory_with_format(&body, format)?
-        } else {
-            image::load_from_memory(&body)?
-        };
-        let image_buf = ImageBuf::from_dynamic_image(image);
-        self.cache.set_image(uri, image_buf.clone());
-        Ok(image_buf)
+        let (body, _) = self.fetch_new(&request)?;
+        Ok(body)
     }
 }
 
@@ -1612,6 +2065,40 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+/// Largest playlist cover accepted by Spotify, the size of the Base64-encoded
+/// JPEG.
+const PLAYLIST_COVER_LIMIT: usize = 256 * 1024;
+
+/// Longest side of an uploaded playlist cover, larger images are scaled down.
+const PLAYLIST_COVER_SIZE: u32 = 640;
+
+/// Convert `image`, in any supported format, into a Base64-encoded JPEG small
+/// enough to be a playlist cover.
+fn encode_playlist_cover(image: &[u8]) -> Result<String, Error> {
+    let mut image = image::load_from_memory(image)?;
+    if image.width().max(image.height()) > PLAYLIST_COVER_SIZE {
+        image = image.resize(
+            PLAYLIST_COVER_SIZE,
+            PLAYLIST_COVER_SIZE,
+            image::imageops::FilterType::Lanczos3,
+        );
+    }
+    // JPEG has no alpha channel.
+    let image = image::DynamicImage::ImageRgb8(image.to_rgb8());
+    for quality in [90, 75, 60, 45, 30] {
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+            .encode_image(&image)?;
+        let cover = BASE64.encode(&jpeg);
+        if cover.len() <= PLAYLIST_COVER_LIMIT {
+            return Ok(cover);
+        }
+    }
+    Err(Error::WebApiError(
+        "Cover image is too large, even after compression".to_string(),
+    ))
+}
+
 impl From<image::ImageError> for Error {
     fn from(err: image::ImageError) -> Self {
         Error::WebApiError(err.to_string())
@@ -1636,9 +2123,14 @@ struct RequestBuilder {
     headers: HashMap<String, String>,
     method: Method,
     body: Option<serde_json::Value>,
+    // Sent as is instead of the JSON `body`, with its own `Content-Type` header.
+    raw_body: Option<String>,
     // When set, authenticate with the first-party Login5 bearer + client-token
     // (for `api-partner.spotify.com`) instead of the Web API OAuth token.
     partner_auth: bool,
+    // When set, responses are kept in this bucket of the WebAPI cache.
+    cache_bucket: Option<&'static str>,
+    priority: Priority,
 }
 
 impl RequestBuilder {
@@ -1652,7 +2144,10 @@ impl RequestBuilder {
             headers: HashMap::new(),
             method,
             body,
+            raw_body: None,
             partner_auth: false,
+            cache_bucket: None,
+            priority: Priority::Visible,
         }
     }
 
@@ -1663,6 +2158,36 @@ impl RequestBuilder {
         self
     }
 
+    /// Keep the responses in `bucket` of the WebAPI cache, so they are
+    /// available offline.
+    fn cached(mut self, bucket: &'static str) -> Self {
+        self.cache_bucket = Some(bucket);
+        self
+    }
+
+    /// Let the requests the user is waiting for go first.
+    fn background(mut self) -> Self {
+        self.priority = Priority::Background;
+        self
+    }
+
+    /// Key of the response in the cache bucket, stable across runs.
+    fn cache_key(&self) -> String {
+        let queries = self
+            .queries
+            .iter()
+            .sorted()
+            .map(|(k, v)| format!("{k}={v}"))
+            .join("&");
+        let body = self.body.as_ref().map(|body| body.to_string());
+        stable_key(format!(
+            "{}/{}?{queries}\n{}",
+            self.base_uri,
+            self.path,
+            body.unwrap_or_default()
+        ))
+    }
+
     fn query(mut self, key: impl Display, value: impl Display) -> Self {
         self.queries.insert(key.to_string(), value.to_string());
         self
@@ -1687,6 +2212,10 @@ impl RequestBuilder {
         self.body = body;
         self
     }
+    fn set_raw_body(mut self, content_type: &str, body: String) -> Self {
+        self.raw_body = Some(body);
+        self.header("Content-Type", content_type)
+    }
     fn get_method(&self) -> &Method {
         &self.method
     }
@@ -152,6 +152,7 @@ impl LocalTrackManager {
                             id: local_album.id.unwrap_or_else(|| "null".into()), // TODO: Invalid ID
                             name: local_album.name,
                             images: local_album.images,
+                            release_year: None,
                         }
                     }),
                     artists: local_track
@@ -0,0 +1,404 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use druid::im::Vector;
+use psst_core::util::default_ureq_agent_builder;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{Config, Track, TrackLines},
+    error::Error,
+};
+
+use super::{cache::stable_key, WebApi};
+
+pub const DEFAULT_LRCLIB_URL: &str = "https://lrclib.net";
+
+/// Source of track lyrics.
+pub trait LyricsProvider: Send + Sync {
+    /// Short identifier of the source, used in logs.
+    fn id(&self) -> &'static str;
+
+    /// Lyrics of `track`, or `None` if the source doesn't have any.
+    fn lyrics(&self, track: &Track) -> Result<Option<Vector<TrackLines>>, Error>;
+}
+
+/// Providers to ask for lyrics, in order.  Local sources come first, so that
+/// the user's own lyrics take precedence.
+pub fn lyrics_providers(config: &Config) -> Vec<Arc<dyn LyricsProvider>> {
+    let mut providers: Vec<Arc<dyn LyricsProvider>> = vec![
+        Arc::new(LrcFileLyrics),
+        Arc::new(Id3Lyrics),
+        Arc::new(SpotifyLyrics),
+    ];
+    if config.lrclib_enable {
+        providers.push(Arc::new(LrclibLyrics::new(
+            config.lrclib_url.as_deref(),
+            Config::proxy().as_deref(),
+        )));
+    }
+    providers
+}
+
+/// Lyrics as stored in the "lyrics" bucket of the cache, whatever their source.
+#[derive(Serialize, Deserialize)]
+pub(super) struct CachedLyrics {
+    pub provider: String,
+    pub lines: Vector<TrackLines>,
+}
+
+/// Local tracks don't have a stable ID, key them by a digest of their path
+/// instead.  The key names a file on disk, so it must not change between runs
+/// or Rust versions, unlike the std hashers.
+pub(super) fn cache_key(track: &Track) -> String {
+    match &track.local_path {
+        Some(path) if track.is_local => format!("local-{}", stable_key(path.as_bytes())),
+        _ => track.id.0.to_base62(),
+    }
+}
+
+/// Spotify's own lyrics, mostly synced.
+pub struct SpotifyLyrics;
+
+impl LyricsProvider for SpotifyLyrics {
+    fn id(&self) -> &'static str {
+        "spotify"
+    }
+
+    fn lyrics(&self, track: &Track) -> Result<Option<Vector<TrackLines>>, Error> {
+        if track.is_local {
+            return Ok(None);
+        }
+        let lines = WebApi::global().get_spotify_lyrics(&track.id.0.to_base62())?;
+        Ok(Some(lines))
+    }
+}
+
+/// Sidecar `.lrc` file next to a local track.
+pub struct LrcFileLyrics;
+
+impl LyricsProvider for LrcFileLyrics {
+    fn id(&self) -> &'static str {
+        "lrc-file"
+    }
+
+    fn lyrics(&self, track: &Track) -> Result<Option<Vector<TrackLines>>, Error> {
+        let Some(path) = track.local_path.as_deref() else {
+            return Ok(None);
+        };
+        match fs::read_to_string(Path::new(path).with_extension("lrc")) {
+            Ok(text) => Ok(Some(parse_lrc(&text))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Lyrics embedded in the ID3v2 tag of a local track.
+pub struct Id3Lyrics;
+
+impl LyricsProvider for Id3Lyrics {
+    fn id(&self) -> &'static str {
+        "id3"
+    }
+
+    fn lyrics(&self, track: &Track) -> Result<Option<Vector<TrackLines>>, Error> {
+        let Some(path) = track.local_path.as_deref() else {
+            return Ok(None);
+        };
+        Ok(read_id3_lyrics(Path::new(path))?)
+    }
+}
+
+/// Lyrics from LRCLIB, or any server implementing its API.
+pub struct LrclibLyrics {
+    agent: ureq::Agent,
+    url: String,
+}
+
+impl LrclibLyrics {
+    /// `url` is the root of the API, `DEFAULT_LRCLIB_URL` is used if `None`.
+    pub fn new(url: Option<&str>, proxy_url: Option<&str>) -> Self {
+        Self {
+            agent: default_ureq_agent_builder(proxy_url).build().into(),
+            url: url
+                .unwrap_or(DEFAULT_LRCLIB_URL)
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+}
+
+impl LyricsProvider for LrclibLyrics {
+    fn id(&self) -> &'static str {
+        "lrclib"
+    }
+
+    fn lyrics(&self, track: &Track) -> Result<Option<Vector<TrackLines>>, Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct LrclibTrack {
+            synced_lyrics: Option<String>,
+            plain_lyrics: Option<String>,
+        }
+
+        let mut request = self
+            .agent
+            .get(format!("{}/api/get", self.url))
+            .header("User-Agent", concat!("psst/", env!("CARGO_PKG_VERSION")))
+            .query("artist_name", &*track.artist_name())
+            .query("track_name", &*track.name)
+            .query("duration", track.duration.as_secs().to_string());
+        if let Some(album) = &track.album {
+            request = request.query("album_name", &*album.name);
+        }
+        let result: LrclibTrack = match request.call() {
+            Ok(mut response) => response
+                .body_mut()
+                .read_json()
+                .map_err(|err| Error::WebApiError(err.to_string()))?,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let text = result.synced_lyrics.or(result.plain_lyrics);
+        Ok(text.map(|text| parse_lrc(&text)))
+    }
+}
+
+fn line(start: Duration, words: String) -> TrackLines {
+    TrackLines {
+        start_time_ms: start.as_millis().to_string(),
+        words,
+        end_time_ms: "0".to_string(),
+    }
+}
+
+/// Parse lyrics in the LRC format, made of `[mm:ss.xx]words` lines.  Lines
+/// without a timestamp are kept unsynced.
+fn parse_lrc(text: &str) -> Vector<TrackLines> {
+    let mut offset_ms = 0_i64;
+    let mut lines = Vec::new();
+    for raw_line in text.lines() {
+        let mut rest = raw_line.trim();
+        let mut times = Vec::new();
+        let mut has_tags = false;
+        while rest.starts_with('[') {
+            let Some(end) = rest.find(']') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+            has_tags = true;
+            if let Some(time) = parse_lrc_time(tag) {
+                times.push(time);
+            } else if let Some(offset) = tag.strip_prefix("offset:") {
+                offset_ms = offset.trim().parse().unwrap_or(0);
+            }
+            // Other tags, like `[ar:Artist]`, are metadata.
+        }
+        let words = rest.trim().to_string();
+        if times.is_empty() {
+            if !has_tags {
+                lines.push((Duration::ZERO, words));
+            }
+        } else {
+            lines.extend(times.into_iter().map(|time| (time, words.clone())));
+        }
+    }
+    // A positive offset makes the lyrics appear sooner.
+    let shift = Duration::from_millis(offset_ms.unsigned_abs());
+    lines.sort_by_key(|(time, _)| *time);
+    lines
+        .into_iter()
+        .map(|(time, words)| {
+            let time = if time.is_zero() {
+                time
+            } else if offset_ms > 0 {
+                time.saturating_sub(shift)
+            } else {
+                time + shift
+            };
+            line(time, words)
+        })
+        .collect()
+}
+
+fn parse_lrc_time(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().replace(':', ".").parse().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// Read the lyrics from the ID3v2 tag of the file at `path`, preferring the
+/// synced SYLT frame over the plain USLT one.
+fn read_id3_lyrics(path: &Path) -> io::Result<Option<Vector<TrackLines>>> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 10];
+    if file.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(None);
+    }
+    // ID3v2.2 uses different frame IDs and is rare enough to be left out.
+    let version = header[3];
+    if !(3..=4).contains(&version) {
+        return Ok(None);
+    }
+    let flags = header[5];
+    let mut tag = vec![0; syncsafe(&header[6..10])];
+    file.read_exact(&mut tag)?;
+    if flags & 0x80 != 0 {
+        tag = remove_unsynchronization(&tag);
+    }
+
+    let frame_size = |bytes: &[u8]| {
+        if version == 4 {
+            syncsafe(bytes)
+        } else {
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+        }
+    };
+    let mut pos = 0;
+    if flags & 0x40 != 0 && tag.len() >= 4 {
+        // Skip the extended header, its size includes itself only in v2.4.
+        pos = frame_size(&tag[..4]) + if version == 4 { 0 } else { 4 };
+    }
+
+    let mut plain = None;
+    let mut synced = None;
+    while pos + 10 <= tag.len() && tag[pos] != 0 {
+        let id = &tag[pos..pos + 4];
+        let size = frame_size(&tag[pos + 4..pos + 8]);
+        let format_flags = tag[pos + 9];
+        let start = pos + 10;
+        let Some(end) = start.checked_add(size).filter(|&end| end <= tag.len()) else {
+            break;
+        };
+        pos = end;
+
+        let mut body = tag[start..end].to_vec();
+        if version == 4 {
+            if format_flags & 0x02 != 0 {
+                body = remove_unsynchronization(&body);
+            }
+            if format_flags & 0x01 != 0 {
+                // Skip the data length indicator.
+                body = body.get(4..).unwrap_or_default().to_vec();
+            }
+        }
+        match id {
+            b"SYLT" if synced.is_none() => synced = parse_sylt(&body),
+            b"USLT" if plain.is_none() => plain = parse_uslt(&body),
+            _ => {}
+        }
+    }
+    Ok(synced.or(plain))
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, &byte| (size << 7) | (byte & 0x7f) as usize)
+}
+
+/// Undo the unsynchronization scheme, which inserts a zero byte after every
+/// 0xFF byte.
+fn remove_unsynchronization(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    for (index, &byte) in data.iter().enumerate() {
+        if byte == 0 && index > 0 && data[index - 1] == 0xff {
+            continue;
+        }
+        result.push(byte);
+    }
+    result
+}
+
+/// Unsynchronised lyrics: encoding, language, descriptor and the text.
+fn parse_uslt(body: &[u8]) -> Option<Vector<TrackLines>> {
+    let (&encoding, rest) = body.split_first()?;
+    let (_descriptor, text) = split_text(rest.get(3..)?, encoding);
+    // Plain lyrics are sometimes in the LRC format.
+    Some(parse_lrc(&decode_text(text, encoding)))
+}
+
+/// Synchronised lyrics: encoding, language, timestamp format, content type,
+/// descriptor and then the text of each line followed by its start time.
+fn parse_sylt(body: &[u8]) -> Option<Vector<TrackLines>> {
+    const TIMESTAMPS_IN_MS: u8 = 2;
+
+    let (&encoding, rest) = body.split_first()?;
+    // Timestamps in MPEG frames are not supported.
+    if *rest.get(3)? != TIMESTAMPS_IN_MS {
+        return None;
+    }
+    let (_descriptor, mut rest) = split_text(rest.get(5..)?, encoding);
+    let mut lines: Vector<TrackLines> = Vector::new();
+    while !rest.is_empty() {
+        let (text, after) = split_text(rest, encoding);
+        let time = after.get(..4)?;
+        let time = u32::from_be_bytes([time[0], time[1], time[2], time[3]]);
+        rest = &after[4..];
+
+        let text = decode_text(text, encoding);
+        // Syllables of the same line don't start with a line break.
+        match lines.back_mut() {
+            Some(last) if !text.starts_with(['\n', '\r']) => last.words.push_str(&text),
+            _ => lines.push_back(line(
+                Duration::from_millis(time.into()),
+                text.trim_start().to_string(),
+            )),
+        }
+    }
+    Some(lines)
+}
+
+/// Split a null-terminated string off the start of `data`.
+fn split_text(data: &[u8], encoding: u8) -> (&[u8], &[u8]) {
+    let is_wide = encoding == 1 || encoding == 2;
+    let end = if is_wide {
+        data.chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .map(|index| index * 2)
+    } else {
+        data.iter().position(|&byte| byte == 0)
+    };
+    match end {
+        Some(end) => (&data[..end], &data[end + if is_wide { 2 } else { 1 }..]),
+        None => (data, &[][..]),
+    }
+}
+
+fn decode_text(data: &[u8], encoding: u8) -> String {
+    match encoding {
+        // ISO-8859-1
+        0 => data.iter().map(|&byte| byte as char).collect(),
+        // UTF-16 with a BOM, or big endian without it.
+        1 | 2 => {
+            let (is_big_endian, data) = match data {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, data),
+            };
+            let units = data.chunks_exact(2).map(|c| {
+                if is_big_endian {
+                    u16::from_be_bytes([c[0], c[1]])
+                } else {
+                    u16::from_le_bytes([c[0], c[1]])
+                }
+            });
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        // UTF-8
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
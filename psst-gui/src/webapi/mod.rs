@@ -1,5 +1,9 @@
 mod cache;
 mod client;
 mod local;
+mod lyrics;
+mod scheduler;
 
-pub use client::WebApi;
+pub use cache::thumbnail_size;
+pub use client::{WebApi, PLAYLIST_ITEMS_LIMIT};
+pub use lyrics::{lyrics_providers, DEFAULT_LRCLIB_URL};
@@ -0,0 +1,386 @@
+use std::{
+    any::Any,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+/// Requests sent to one host at the same time.
+const REQUESTS_PER_HOST: usize = 6;
+
+/// Requests the user is waiting for are sent before the background ones.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Priority {
+    Background,
+    Visible,
+}
+
+/// Outcome of sending a request once.
+pub enum Attempt<T> {
+    Done(T),
+    /// The host asked us to slow down, send the request again after the delay.
+    RetryAfter(Duration),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SchedulerMetrics {
+    pub sent: u64,
+    pub deduplicated: u64,
+    pub rate_limited: u64,
+    pub active: usize,
+    pub waiting: usize,
+    pub longest_wait: Duration,
+}
+
+impl fmt::Display for SchedulerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} deduplicated, {} rate limited, {} active, {} waiting, longest wait {:?}",
+            self.sent,
+            self.deduplicated,
+            self.rate_limited,
+            self.active,
+            self.waiting,
+            self.longest_wait
+        )
+    }
+}
+
+#[derive(Default)]
+struct Host {
+    active: usize,
+    // Requests waiting for a slot, the highest priority and then the oldest
+    // ticket first.
+    waiting: BinaryHeap<(Priority, Reverse<u64>)>,
+    paused_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct State {
+    hosts: HashMap<String, Host>,
+    next_ticket: u64,
+    metrics: SchedulerMetrics,
+}
+
+impl State {
+    fn host(&mut self, host: &str) -> &mut Host {
+        self.hosts.entry(host.to_string()).or_default()
+    }
+}
+
+enum Outcome {
+    Done(Box<dyn Any + Send>),
+    /// The thread running the request panicked.
+    Abandoned,
+}
+
+#[derive(Default)]
+struct Pending {
+    outcome: Mutex<Option<Outcome>>,
+    done: Condvar,
+}
+
+/// Sends requests to each host through a limited number of slots, pausing the
+/// host when it responds with `Retry-After`.  The scheduler doesn't know about
+/// HTTP, requests are closures run in the calling thread once they get a slot.
+#[derive(Default)]
+pub struct Scheduler {
+    state: Mutex<State>,
+    changed: Condvar,
+    in_flight: Mutex<HashMap<String, Arc<Pending>>>,
+}
+
+impl Scheduler {
+    /// Run `send` once a slot for `host` is free, until it doesn't ask to be
+    /// retried.
+    pub fn run<T>(
+        &self,
+        host: &str,
+        priority: Priority,
+        mut send: impl FnMut() -> Attempt<T>,
+    ) -> T {
+        loop {
+            let _slot = self.acquire(host, priority);
+            match send() {
+                Attempt::Done(value) => break value,
+                Attempt::RetryAfter(delay) => self.pause(host, delay),
+            }
+        }
+    }
+
+    /// Run `run` unless another thread is already running it under the same
+    /// `key`, in which case wait for its result instead.  Use for requests
+    /// without side effects.  Returns `None` to the waiting threads if the
+    /// thread running the request panicked.
+    pub fn run_once<T: Clone + Send + 'static>(
+        &self,
+        key: &str,
+        run: impl FnOnce() -> T,
+    ) -> Option<T> {
+        let (pending, is_leader) = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(key) {
+                Some(pending) => (pending.clone(), false),
+                None => {
+                    let pending = Arc::new(Pending::default());
+                    in_flight.insert(key.to_string(), pending.clone());
+                    (pending, true)
+                }
+            }
+        };
+
+        if is_leader {
+            let leader = Leader {
+                scheduler: self,
+                key,
+                pending: &pending,
+            };
+            let value = run();
+            *pending.outcome.lock() = Some(Outcome::Done(Box::new(value.clone())));
+            drop(leader);
+            Some(value)
+        } else {
+            self.state.lock().metrics.deduplicated += 1;
+            let mut outcome = pending.outcome.lock();
+            loop {
+                match outcome.as_ref() {
+                    Some(Outcome::Done(value)) => break value.downcast_ref::<T>().cloned(),
+                    Some(Outcome::Abandoned) => break None,
+                    None => pending.done.wait(&mut outcome),
+                }
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> SchedulerMetrics {
+        let state = self.state.lock();
+        SchedulerMetrics {
+            active: state.hosts.values().map(|host| host.active).sum(),
+            waiting: state.hosts.values().map(|host| host.waiting.len()).sum(),
+            ..state.metrics.clone()
+        }
+    }
+
+    fn acquire(&self, host: &str, priority: Priority) -> Slot<'_> {
+        let started = Instant::now();
+        let mut state = self.state.lock();
+        let ticket = (priority, Reverse(state.next_ticket));
+        state.next_ticket += 1;
+        state.host(host).waiting.push(ticket);
+        loop {
+            let now = Instant::now();
+            let entry = state.host(host);
+            let paused_until = entry.paused_until.filter(|until| *until > now);
+            let is_next = entry.waiting.peek() == Some(&ticket);
+            if paused_until.is_none() && is_next && entry.active < REQUESTS_PER_HOST {
+                entry.waiting.pop();
+                entry.active += 1;
+                let metrics = &mut state.metrics;
+                metrics.sent += 1;
+                metrics.longest_wait = metrics.longest_wait.max(started.elapsed());
+                // The next request in line might fit into a free slot as well.
+                self.changed.notify_all();
+                return Slot {
+                    scheduler: self,
+                    host: host.to_string(),
+                };
+            }
+            match paused_until {
+                Some(until) => {
+                    self.changed.wait_until(&mut state, until);
+                }
+                None => {
+                    self.changed.wait(&mut state);
+                }
+            }
+        }
+    }
+
+    fn pause(&self, host: &str, delay: Duration) {
+        {
+            let mut state = self.state.lock();
+            let until = Instant::now() + delay;
+            let entry = state.host(host);
+            entry.paused_until = entry.paused_until.max(Some(until));
+            state.metrics.rate_limited += 1;
+        }
+        log::info!(
+            "rate limited by {host}, pausing for {delay:?} ({})",
+            self.metrics()
+        );
+    }
+}
+
+/// The thread running a request for `Scheduler::run_once`.  Wakes up the
+/// threads waiting for the result on drop, even when unwinding from a panic.
+struct Leader<'a> {
+    scheduler: &'a Scheduler,
+    key: &'a str,
+    pending: &'a Pending,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.pending
+            .outcome
+            .lock()
+            .get_or_insert(Outcome::Abandoned);
+        self.scheduler.in_flight.lock().remove(self.key);
+        self.pending.done.notify_all();
+    }
+}
+
+/// A taken slot of a host, freed on drop.
+struct Slot<'a> {
+    scheduler: &'a Scheduler,
+    host: String,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.scheduler.state.lock().host(&self.host).active -= 1;
+        self.scheduler.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+
+    /// Wait for another thread to reach the state checked by `cond`.
+    fn wait_until(cond: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !cond() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn visible_requests_go_first() {
+        let scheduler = Arc::new(Scheduler::default());
+        let mut slots: Vec<_> = (0..REQUESTS_PER_HOST)
+            .map(|_| scheduler.acquire("host", Priority::Background))
+            .collect();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let spawn = |priority| {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            thread::spawn(move || {
+                let _slot = scheduler.acquire("host", priority);
+                order.lock().push(priority);
+            })
+        };
+
+        let background = spawn(Priority::Background);
+        wait_until(|| scheduler.metrics().waiting == 1);
+        let visible = spawn(Priority::Visible);
+        wait_until(|| scheduler.metrics().waiting == 2);
+        drop(slots.pop());
+        visible.join().unwrap();
+        background.join().unwrap();
+
+        assert_eq!(*order.lock(), [Priority::Visible, Priority::Background]);
+    }
+
+    #[test]
+    fn limits_requests_per_host() {
+        let scheduler = Arc::new(Scheduler::default());
+        let mut slots: Vec<_> = (0..REQUESTS_PER_HOST)
+            .map(|_| scheduler.acquire("a", Priority::Visible))
+            .collect();
+        let waiting = {
+            let scheduler = scheduler.clone();
+            thread::spawn(move || drop(scheduler.acquire("a", Priority::Visible)))
+        };
+        wait_until(|| scheduler.metrics().waiting == 1);
+
+        // Other hosts have slots of their own.
+        drop(scheduler.acquire("b", Priority::Visible));
+        assert_eq!(scheduler.metrics().active, REQUESTS_PER_HOST);
+        assert_eq!(scheduler.metrics().waiting, 1);
+
+        drop(slots.pop());
+        waiting.join().unwrap();
+        assert_eq!(scheduler.metrics().sent, REQUESTS_PER_HOST as u64 + 2);
+    }
+
+    #[test]
+    fn waits_out_retry_after() {
+        let scheduler = Scheduler::default();
+        let delay = Duration::from_millis(50);
+        let started = Instant::now();
+        let mut attempts = 0;
+        let retried_at = scheduler.run("host", Priority::Visible, || {
+            attempts += 1;
+            if attempts == 1 {
+                Attempt::RetryAfter(delay)
+            } else {
+                Attempt::Done(Instant::now())
+            }
+        });
+
+        assert_eq!(attempts, 2);
+        assert!(retried_at - started >= delay);
+        assert_eq!(scheduler.metrics().rate_limited, 1);
+    }
+
+    #[test]
+    fn shares_result_of_running_request() {
+        let scheduler = Arc::new(Scheduler::default());
+        let (release, released) = mpsc::channel();
+        let leader = {
+            let scheduler = scheduler.clone();
+            thread::spawn(move || {
+                scheduler.run_once("key", || {
+                    released.recv().unwrap();
+                    1
+                })
+            })
+        };
+        wait_until(|| scheduler.in_flight.lock().contains_key("key"));
+        let follower = {
+            let scheduler = scheduler.clone();
+            thread::spawn(move || scheduler.run_once("key", || 2))
+        };
+        wait_until(|| scheduler.metrics().deduplicated == 1);
+        release.send(()).unwrap();
+
+        assert_eq!(leader.join().unwrap(), Some(1));
+        assert_eq!(follower.join().unwrap(), Some(1));
+        assert!(scheduler.in_flight.lock().is_empty());
+    }
+
+    #[test]
+    fn wakes_waiters_when_request_panics() {
+        let scheduler = Arc::new(Scheduler::default());
+        let (release, released) = mpsc::channel::<()>();
+        let leader = {
+            let scheduler = scheduler.clone();
+            thread::spawn(move || {
+                scheduler.run_once("key", || -> u32 {
+                    released.recv().unwrap();
+                    panic!("request failed");
+                })
+            })
+        };
+        wait_until(|| scheduler.in_flight.lock().contains_key("key"));
+        let follower = {
+            let scheduler = scheduler.clone();
+            thread::spawn(move || scheduler.run_once("key", || 2))
+        };
+        wait_until(|| scheduler.metrics().deduplicated == 1);
+        release.send(()).unwrap();
+
+        assert!(leader.join().is_err());
+        assert_eq!(follower.join().unwrap(), None);
+        assert!(scheduler.in_flight.lock().is_empty());
+    }
+}
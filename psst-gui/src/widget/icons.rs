@@ -26,6 +26,30 @@ pub static STORAGE: SvgIcon = SvgIcon {
     svg_size: Size::new(28.0, 28.0),
     op: PaintOp::Fill,
 };
+// Keyboard
+pub static KEYBOARD: SvgIcon = SvgIcon {
+    svg_path: "M3 7H25V21H3ZM4.5 8.5V19.5H23.5V8.5ZM6.5 10.5H8.5V12.5H6.5ZM10 10.5H12V12.5H10ZM13.5 10.5H15.5V12.5H13.5ZM17 10.5H19V12.5H17ZM20.5 10.5H22.5V12.5H20.5ZM6.5 14H8.5V16H6.5ZM10 14H12V16H10ZM13.5 14H15.5V16H13.5ZM17 14H19V16H17ZM20.5 14H22.5V16H20.5ZM9 17H19V18.5H9Z",
+    svg_size: Size::new(28.0, 28.0),
+    op: PaintOp::Fill,
+};
+// Window with a small window inside
+pub static MINI_PLAYER: SvgIcon = SvgIcon {
+    svg_path: "M3 6H25V22H3ZM4.5 7.5V20.5H23.5V7.5ZM14 13H22V19H14Z",
+    svg_size: Size::new(28.0, 28.0),
+    op: PaintOp::Fill,
+};
+// Arrows pointing to opposite corners
+pub static EXPAND: SvgIcon = SvgIcon {
+    svg_path: "M16 4H24V12H22.5V6.56L16.53 12.53L15.47 11.47L21.44 5.5H16ZM4 16H5.5V21.44L11.47 15.47L12.53 16.53L6.56 22.5H12V24H4Z",
+    svg_size: Size::new(28.0, 28.0),
+    op: PaintOp::Fill,
+};
+// Pushpin
+pub static PIN: SvgIcon = SvgIcon {
+    svg_path: "M10 4H18V5.5H16.5V12L19.5 15V16.5H14.75V23.25L14 24.5L13.25 23.25V16.5H8.5V15L11.5 12V5.5H10Z",
+    svg_size: Size::new(28.0, 28.0),
+    op: PaintOp::Fill,
+};
 
 pub static BACK: SvgIcon = SvgIcon {
     svg_path: "M9.70711 0.292893C10.0976 0.683417 10.0976 1.31658 9.70711 1.70711L2.41421 9L9.70711 16.2929C10.0976 16.6834 10.0976 17.3166 9.70711 17.7071C9.31658 18.0976 8.68342 18.0976 8.29289 17.7071L0.292893 9.70711C-0.0976311 9.31658 -0.0976311 8.68342 0.292893 8.29289L8.29289 0.292893C8.68342 -0.0976311 9.31658 -0.0976311 9.70711 0.292893Z",
@@ -151,6 +175,18 @@ pub static PODCAST: SvgIcon = SvgIcon {
     svg_size: Size::new(22.0, 22.0),
     op: PaintOp::Fill,
 };
+// Clock
+pub static HISTORY: SvgIcon = SvgIcon {
+    svg_path: "M11 3A8 8 0 1 1 11 19A8 8 0 1 1 11 3ZM11 4.5A6.5 6.5 0 1 0 11 17.5A6.5 6.5 0 1 0 11 4.5ZM10.25 6H11.75V10.25H15V11.75H10.25Z",
+    svg_size: Size::new(22.0, 22.0),
+    op: PaintOp::Fill,
+};
+// Bar chart
+pub static STATS: SvgIcon = SvgIcon {
+    svg_path: "M4 12H7V19H4ZM9.5 7H12.5V19H9.5ZM15 3H18V19H15Z",
+    svg_size: Size::new(22.0, 22.0),
+    op: PaintOp::Fill,
+};
 // Bootstrap - explicit-fill
 pub static EXPLICIT: SvgIcon = SvgIcon {
     svg_path: "M3.75 0A3.75 3.75 0 0 0 0 3.75v16.5A3.75 3.75 0 0 0 3.75 24h16.5A3.75 3.75 0 0 0 24 20.25V3.75A3.75 3.75 0 0 0 20.25 0Zm6.49 16.32h5.51V18h-7.5V6h7.5v1.68h-5.51v3.42h5.18v1.6h-5.18v3.62z",
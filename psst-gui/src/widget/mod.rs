@@ -10,6 +10,7 @@ mod promise;
 pub mod remote_image;
 mod theme;
 mod utils;
+mod virtual_list;
 
 use std::{sync::Arc, time::Duration};
 
@@ -29,6 +30,7 @@ pub use promise::Async;
 pub use remote_image::RemoteImage;
 pub use theme::ThemeScope;
 pub use utils::{Border, Clip, FadeOut, Logger};
+pub use virtual_list::VirtualList;
 
 use crate::{
     controller::{ExClick, ExCursor, ExScroll, OnCommand, OnCommandAsync, OnDebounce, OnUpdate},
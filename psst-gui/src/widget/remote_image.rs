@@ -5,9 +5,18 @@ use druid::{
     Data, ImageBuf, Point, Selector, WidgetPod,
 };
 
-pub const REQUEST_DATA: Selector<Arc<str>> = Selector::new("remote-image.request-data");
+use crate::webapi::thumbnail_size;
+
+pub const REQUEST_DATA: Selector<ImageRequest> = Selector::new("remote-image.request-data");
 pub const PROVIDE_DATA: Selector<ImagePayload> = Selector::new("remote-image.provide-data");
 
+#[derive(Clone)]
+pub struct ImageRequest {
+    pub location: Arc<str>,
+    /// Size of the thumbnail, `None` for the original image.
+    pub size: Option<u32>,
+}
+
 #[derive(Clone)]
 pub struct ImagePayload {
     pub location: Arc<str>,
@@ -19,6 +28,7 @@ pub struct RemoteImage<T> {
     image: Option<WidgetPod<T, Image>>,
     locator: Box<dyn Fn(&T, &Env) -> Option<Arc<str>>>,
     location: Option<Arc<str>>,
+    size: Option<u32>,
 }
 
 impl<T: Data> RemoteImage<T> {
@@ -30,9 +40,25 @@ impl<T: Data> RemoteImage<T> {
             placeholder: WidgetPod::new(placeholder).boxed(),
             locator: Box::new(locator),
             location: None,
+            size: None,
             image: None,
         }
     }
+
+    /// Request a thumbnail covering `size` points instead of the original
+    /// image, so big grids of covers are cheap to decode and paint.
+    pub fn thumbnail(mut self, size: f64) -> Self {
+        // Twice as big, to stay sharp on HiDPI screens.
+        self.size = thumbnail_size(size * 2.0);
+        self
+    }
+
+    fn request(&self, location: Arc<str>) -> ImageRequest {
+        ImageRequest {
+            location,
+            size: self.size,
+        }
+    }
 }
 
 impl<T: Data> Widget<T> for RemoteImage<T> {
@@ -61,7 +87,11 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
             self.image = None;
             self.location.clone_from(&location);
             if let Some(location) = location {
-                ctx.submit_command(REQUEST_DATA.with(location).to(ctx.widget_id()));
+                ctx.submit_command(
+                    REQUEST_DATA
+                        .with(self.request(location))
+                        .to(ctx.widget_id()),
+                );
             }
         }
         if let Some(image) = self.image.as_mut() {
@@ -77,7 +107,11 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
             self.image = None;
             self.location.clone_from(&location);
             if let Some(location) = location {
-                ctx.submit_command(REQUEST_DATA.with(location).to(ctx.widget_id()));
+                ctx.submit_command(
+                    REQUEST_DATA
+                        .with(self.request(location))
+                        .to(ctx.widget_id()),
+                );
             }
             ctx.children_changed();
         }
@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use druid::{
+    widget::{prelude::*, ListIter},
+    Command, Point, Rect, Selector, WidgetPod,
+};
+
+/// Rows kept around the visible area, so that scrolling doesn't reveal rows
+/// that are not laid out yet.
+const OVERSCAN: usize = 10;
+
+/// Rows created before we know which part of the list is visible.
+const INITIAL_ROWS: usize = 50;
+
+/// A list that only creates, lays out and paints the rows in the visible part
+/// of the enclosing scroll area.  Rows that were never laid out are assumed to
+/// be as high as the average of the measured ones.
+pub struct VirtualList<C> {
+    closure: Box<dyn Fn() -> Box<dyn Widget<C>>>,
+    rows: BTreeMap<usize, WidgetPod<C, Box<dyn Widget<C>>>>,
+    range: Range<usize>,
+    /// Measured height of every row that has been laid out so far.
+    heights: Vec<Option<f64>>,
+    /// Vertical position of every row, followed by the height of the list.
+    offsets: Vec<f64>,
+    visible: Option<Rect>,
+    /// Whether a command should expand the list, `None` for other commands.
+    expand_on: Option<Box<dyn Fn(&Command) -> Option<bool>>>,
+    expanded: bool,
+}
+
+impl<C: Data> VirtualList<C> {
+    pub fn new<W: Widget<C> + 'static>(closure: impl Fn() -> W + 'static) -> Self {
+        Self {
+            closure: Box::new(move || Box::new(closure())),
+            rows: BTreeMap::new(),
+            range: 0..0,
+            heights: Vec::new(),
+            offsets: Vec::new(),
+            visible: None,
+            expand_on: None,
+            expanded: false,
+        }
+    }
+
+    /// Create all the rows once `selector` is received with a payload that
+    /// `expand` accepts, so that the command reaches every row and not just the
+    /// visible ones.  A rejected payload, or a change of the list length, goes
+    /// back to the visible rows.  Used for finding in the list.
+    pub fn expand_on<U: 'static>(
+        mut self,
+        selector: Selector<U>,
+        expand: impl Fn(&U) -> bool + 'static,
+    ) -> Self {
+        self.expand_on = Some(Box::new(move |cmd: &Command| {
+            cmd.get(selector).map(&expand)
+        }));
+        self
+    }
+
+    fn visible_range(&self, len: usize) -> Range<usize> {
+        match self.visible {
+            _ if self.expanded => 0..len,
+            Some(clip) if self.offsets.len() == len + 1 && self.offsets[len] > 0.0 => {
+                let start = self
+                    .offsets
+                    .partition_point(|&y| y <= clip.y0)
+                    .saturating_sub(1);
+                let end = self.offsets.partition_point(|&y| y < clip.y1);
+                start.saturating_sub(OVERSCAN).min(len)..end.saturating_add(OVERSCAN).min(len)
+            }
+            _ => 0..len.min(INITIAL_ROWS),
+        }
+    }
+
+    /// Recompute the row positions, estimating the height of the rows that
+    /// were not measured yet.
+    fn update_offsets(&mut self) {
+        let measured: Vec<f64> = self.heights.iter().flatten().copied().collect();
+        let estimate = if measured.is_empty() {
+            0.0
+        } else {
+            measured.iter().sum::<f64>() / measured.len() as f64
+        };
+        self.offsets.clear();
+        let mut y = 0.0;
+        for height in &self.heights {
+            self.offsets.push(y);
+            y += height.unwrap_or(estimate);
+        }
+        self.offsets.push(y);
+    }
+
+    /// Create the rows that came into view and drop the ones that left it.
+    /// Returns true if the set of rows has changed.
+    fn update_rows(&mut self, len: usize) -> bool {
+        let range = self.visible_range(len);
+        if range == self.range {
+            return false;
+        }
+        self.rows.retain(|index, _| range.contains(index));
+        for index in range.clone() {
+            self.rows
+                .entry(index)
+                .or_insert_with(|| WidgetPod::new((self.closure)()));
+        }
+        self.range = range;
+        true
+    }
+}
+
+impl<C: Data, T: ListIter<C>> Widget<T> for VirtualList<C> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Command(cmd) = event {
+            let expand = self.expand_on.as_ref().and_then(|expand_on| expand_on(cmd));
+            match expand {
+                Some(true) if !self.expanded => {
+                    self.expanded = true;
+                    if self.update_rows(data.data_len()) {
+                        ctx.children_changed();
+                    }
+                    // Deliver the command again, once the new rows are added.
+                    ctx.submit_command(cmd.clone());
+                    ctx.set_handled();
+                    return;
+                }
+                Some(false) if self.expanded => {
+                    self.expanded = false;
+                    if self.update_rows(data.data_len()) {
+                        ctx.children_changed();
+                    }
+                }
+                _ => {}
+            }
+        }
+        let rows = &mut self.rows;
+        data.for_each_mut(|child_data, index| {
+            if let Some(row) = rows.get_mut(&index) {
+                if row.is_initialized() {
+                    row.event(ctx, event, child_data, env);
+                }
+            }
+        });
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::ViewContextChanged(view) = event {
+            self.visible = Some(view.clip);
+            if self.update_rows(data.data_len()) {
+                ctx.children_changed();
+            }
+        }
+        if let LifeCycle::WidgetAdded = event {
+            self.update_rows(data.data_len());
+        }
+        let rows = &mut self.rows;
+        data.for_each(|child_data, index| {
+            if let Some(row) = rows.get_mut(&index) {
+                // New rows get initialized by `WidgetAdded`, possibly routed to
+                // them through an internal event.
+                let adding = matches!(event, LifeCycle::WidgetAdded | LifeCycle::Internal(_));
+                if row.is_initialized() || adding {
+                    row.lifecycle(ctx, event, child_data, env);
+                }
+            }
+        });
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if old_data.data_len() != data.data_len() {
+            self.expanded = false;
+        }
+        if self.update_rows(data.data_len()) {
+            ctx.children_changed();
+        }
+        let rows = &mut self.rows;
+        data.for_each(|child_data, index| {
+            if let Some(row) = rows.get_mut(&index) {
+                if row.is_initialized() {
+                    row.update(ctx, child_data, env);
+                }
+            }
+        });
+        if old_data.data_len() != data.data_len() {
+            ctx.request_layout();
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let width = bc.max().width;
+        let child_bc = BoxConstraints::new(Size::new(width, 0.0), Size::new(width, f64::INFINITY));
+        let len = data.data_len();
+        self.heights.resize(len, None);
+        let rows = &mut self.rows;
+        let heights = &mut self.heights;
+        data.for_each(|child_data, index| {
+            if let Some(row) = rows.get_mut(&index) {
+                if row.is_initialized() {
+                    let size = row.layout(ctx, &child_bc, child_data, env);
+                    heights[index] = Some(size.height);
+                }
+            }
+        });
+        self.update_offsets();
+        for (index, row) in self.rows.iter_mut() {
+            if let Some(&y) = self.offsets.get(*index) {
+                if row.is_initialized() {
+                    row.set_origin(ctx, Point::new(0.0, y));
+                }
+            }
+        }
+        bc.constrain(Size::new(width, self.offsets[len]))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let rows = &mut self.rows;
+        data.for_each(|child_data, index| {
+            if let Some(row) = rows.get_mut(&index) {
+                if row.is_initialized() {
+                    row.paint(ctx, child_data, env);
+                }
+            }
+        });
+    }
+}